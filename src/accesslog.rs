@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::Error;
+
+/// Configuration for the structured (JSON) access log
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct AccessLogConfig {
+    /// File to append the access log to, logs to standard output if not set
+    file: Option<PathBuf>,
+}
+
+impl AccessLogConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+}
+
+/// A single entry in the access log
+#[derive(Debug, Serialize)]
+pub struct AccessLogEntry<'a> {
+    /// Time the request was received (RFC 3339)
+    pub timestamp: String,
+    pub request_id: Option<&'a str>,
+    /// IP address of the client
+    pub client: Option<String>,
+    pub method: &'a str,
+    /// Request path and query string
+    pub uri: &'a str,
+    /// HTTP status code of the response
+    pub status: u16,
+    /// Time taken to produce the response, in milliseconds
+    pub duration_ms: f64,
+}
+
+/// Writes an access log with one JSON object per line
+pub struct AccessLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl AccessLog {
+    pub fn new(config: &AccessLogConfig) -> Result<Self, Error> {
+        let writer: Box<dyn Write + Send> = if let Some(path) = config.file() {
+            let file: File = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| {
+                    Error::LoadError(format!(
+                        "Unable to open access log {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            Box::new(file)
+        } else {
+            Box::new(std::io::stdout())
+        };
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Append an entry to the log
+    pub fn log(&self, entry: &AccessLogEntry) {
+        if let Ok(mut line) = serde_json::to_string(entry) {
+            line.push('\n');
+            let mut writer = self.writer.lock().unwrap();
+            // failure to write the access log should never fail a request
+            let _ = writer.write_all(line.as_bytes());
+            let _ = writer.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+
+    #[test]
+    pub fn test001_accesslog_write() -> Result<(), Error> {
+        let dir = TestDir::new("access");
+        let path = dir.join("access.log");
+        let log = AccessLog::new(&AccessLogConfig::new().with_file(&path))?;
+        log.log(&AccessLogEntry {
+            timestamp: "2025-01-01T00:00:00Z".into(),
+            request_id: Some("abc"),
+            client: Some("127.0.0.1".into()),
+            method: "GET",
+            uri: "/?q=test",
+            status: 200,
+            duration_ms: 1.5,
+        });
+        let contents = std::fs::read_to_string(&path)?;
+        let value: serde_json::Value =
+            serde_json::from_str(contents.trim_end()).expect("must be valid JSON");
+        assert_eq!(value["request_id"], "abc");
+        assert_eq!(value["status"], 200);
+        assert_eq!(value["uri"], "/?q=test");
+        Ok(())
+    }
+}
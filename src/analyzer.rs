@@ -0,0 +1,159 @@
+#[cfg(feature = "stemming")]
+use rust_stemmers::{Algorithm, Stemmer};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::Error;
+
+/// Key of the analyzer chain for terms that are not restricted to a field
+pub const UNFIELDED: &str = "*";
+
+/// A step in an analyzer chain, written as `lowercase`, `asciifold` or `stem:<language>` in the configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Filter {
+    /// Converts to lowercase
+    Lowercase,
+    /// Strips diacritics and other marks, e.g. `café` becomes `cafe`
+    AsciiFold,
+    /// Reduces each word to its stem with the Snowball stemmer for a language
+    #[cfg(feature = "stemming")]
+    Stem(Algorithm),
+}
+
+impl TryFrom<String> for Filter {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Error> {
+        match s.as_str() {
+            "lowercase" => Ok(Self::Lowercase),
+            "asciifold" => Ok(Self::AsciiFold),
+            _ => {
+                let language = s.strip_prefix("stem:").ok_or_else(|| {
+                    Error::InvalidConfig(format!(
+                        "Unknown analyzer filter {}, expected lowercase, asciifold or stem:<language>",
+                        s
+                    ))
+                })?;
+                #[cfg(not(feature = "stemming"))]
+                return Err(Error::InvalidConfig(format!(
+                    "Can not stem {}: kweepeer was built without the stemming feature",
+                    language
+                )));
+                #[cfg(feature = "stemming")]
+                let algorithm = match language {
+                    "arabic" => Algorithm::Arabic,
+                    "danish" => Algorithm::Danish,
+                    "dutch" => Algorithm::Dutch,
+                    "english" => Algorithm::English,
+                    "finnish" => Algorithm::Finnish,
+                    "french" => Algorithm::French,
+                    "german" => Algorithm::German,
+                    "greek" => Algorithm::Greek,
+                    "hungarian" => Algorithm::Hungarian,
+                    "italian" => Algorithm::Italian,
+                    "norwegian" => Algorithm::Norwegian,
+                    "portuguese" => Algorithm::Portuguese,
+                    "romanian" => Algorithm::Romanian,
+                    "russian" => Algorithm::Russian,
+                    "spanish" => Algorithm::Spanish,
+                    "swedish" => Algorithm::Swedish,
+                    "tamil" => Algorithm::Tamil,
+                    "turkish" => Algorithm::Turkish,
+                    _ => {
+                        return Err(Error::InvalidConfig(format!(
+                            "No stemmer for language {}",
+                            language
+                        )))
+                    }
+                };
+                #[cfg(feature = "stemming")]
+                Ok(Self::Stem(algorithm))
+            }
+        }
+    }
+}
+
+impl Filter {
+    fn apply(&self, s: &str) -> String {
+        match self {
+            Self::Lowercase => s.to_lowercase(),
+            Self::AsciiFold => s.nfkd().filter(|c| !is_combining_mark(*c)).collect(),
+            #[cfg(feature = "stemming")]
+            Self::Stem(algorithm) => {
+                let stemmer = Stemmer::create(*algorithm);
+                s.split(' ')
+                    .map(|word| stemmer.stem(word))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+    }
+}
+
+/// Analyzer chains by search field, applied to the expansions before they are placed into the expanded query so
+/// they match the tokens in the index. The chain under `*` applies to terms that are not restricted to a field.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Analyzers(HashMap<String, Vec<Filter>>);
+
+impl Analyzers {
+    /// Returns the analyzer chain for a field, or for terms without a field if None
+    pub fn get(&self, field: Option<&str>) -> Option<&[Filter]> {
+        self.0
+            .get(field.unwrap_or(UNFIELDED))
+            .map(|filters| filters.as_slice())
+    }
+}
+
+/// Runs a string through an analyzer chain, in order
+pub fn analyze<'a>(filters: &[Filter], s: &'a str) -> Cow<'a, str> {
+    filters
+        .iter()
+        .fold(Cow::Borrowed(s), |s, filter| Cow::Owned(filter.apply(&s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, QueryExpander, TermExpansion, TermExpansions};
+
+    #[test]
+    #[cfg(feature = "stemming")]
+    pub fn test001_analyzer_filters() {
+        let filters = vec![
+            Filter::try_from("lowercase".to_string()).expect("valid filter"),
+            Filter::try_from("asciifold".to_string()).expect("valid filter"),
+            Filter::try_from("stem:dutch".to_string()).expect("valid filter"),
+        ];
+        assert_eq!(analyze(&filters, "Overijsselse Cafés"), "overijssel cafes");
+        assert_eq!(analyze(&[], "Café"), "Café");
+        assert!(Filter::try_from("stem:klingon".to_string()).is_err());
+        assert!(Filter::try_from("uppercase".to_string()).is_err());
+    }
+
+    #[test]
+    pub fn test002_analyzer_resolve() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            "[analyzers]\ntitle = [\"lowercase\", \"asciifold\"]\n\"*\" = [\"lowercase\"]",
+        )
+        .expect("valid configuration");
+        let expander = QueryExpander::new().with_config(config);
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "cafe".into(),
+            vec![TermExpansion::default().with_expansions(vec![
+                "Café".into(),
+                "cafe".into(),
+                "Koffiehuis".into(),
+            ])],
+        );
+        assert_eq!(
+            expander.resolve_query_template("title:{{cafe}} AND {{cafe}}", &terms_map)?,
+            "title:(cafe OR koffiehuis) AND (café OR cafe OR koffiehuis)"
+        );
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
+#[cfg(feature = "server")]
 use axum::{
+    body::{Body, Bytes},
     http::HeaderValue,
     http::{header, StatusCode},
     response::{IntoResponse, Json, Response},
@@ -6,10 +8,41 @@ use axum::{
 use serde::ser::SerializeStruct;
 use serde::Serialize;
 use serde_json::value::Value;
+use std::collections::HashMap;
+use std::io::{self, Write};
+#[cfg(feature = "server")]
+use tracing::error;
 
-use crate::{Error, TermExpansions};
+use crate::coverage::{Coverage, MinimumShouldMatch};
+use crate::distance;
+use crate::globalparams::GlobalParam;
+use crate::grouping::{GroupBy, Grouping};
+use crate::highlight::{self, Highlights};
+use crate::pagination::{Pagination, Totals};
+use crate::reproducibility::Reproducibility;
+use crate::suggest::{self, Suggestions};
+use crate::warnings::{self, ModuleWarning, Warnings};
+use crate::{Error, QueryContext, QueryExpander, QueryParams, Term, TermExpansions, TermStatuses};
 
-#[derive(Debug)]
+#[cfg(feature = "server")]
+tokio::task_local! {
+    /// The ID of the request currently being handled, set by the webservice for the duration of each request
+    pub static REQUEST_ID: String;
+}
+
+/// Returns the ID of the request currently being handled, if any
+#[cfg(feature = "server")]
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Returns the ID of the request currently being handled, always None without the webservice
+#[cfg(not(feature = "server"))]
+pub fn current_request_id() -> Option<String> {
+    None
+}
+
+#[derive(Debug, Clone)]
 pub enum ApiResponse {
     QueryExpansion {
         /// Terms and expansions
@@ -21,19 +54,124 @@ pub enum ApiResponse {
         query_expansion_template: String,
         /// The full expanded query
         query: String,
+        /// The status of each term, explains why a term got no expansions
+        term_status: TermStatuses,
+        /// The spans of each expansion that differ from the term, only if requested
+        highlights: Option<Highlights>,
+        /// The IDF weight of each term, only if a frequency file is configured
+        term_weights: Option<Box<HashMap<String, f64>>>,
+        /// The software version, seed and module data versions, to reproduce the expansion later
+        reproducibility: Option<Box<Reproducibility>>,
+        /// The total number of expansions of each term, only if the expansions are paginated
+        total: Option<Box<Totals>>,
+        /// The expansions of each term grouped by kind, module or language, only if requested
+        groups: Option<Box<Grouping>>,
+        /// The minimum of the alternatives of each term that should match, for the search backend to apply, only if
+        /// requested (see [`Coverage::MinimumShouldMatch`])
+        minimum_should_match: Option<MinimumShouldMatch>,
+        /// The modules that were skipped, so the response holds fewer expansions than it otherwise would
+        warnings: Option<Box<Warnings>>,
+    },
+    /// Spelling corrections ("did you mean")
+    Suggestion {
+        /// The best correction per term, terms that need no correction are absent
+        suggestions: Suggestions,
+        /// The input query
+        original_query: String,
+        /// The query with all corrections applied
+        query: String,
     },
     Modules(Vec<Value>),
+    /// A single module
+    Module(Value),
+    /// A request was accepted for processing in the background
+    Accepted(String),
+    /// Runtime statistics
+    Stats(Value),
+}
+
+/// Query expansion responses with more expansions than this are serialized incrementally into the response body,
+/// rather than into a buffer first
+#[cfg(feature = "server")]
+const STREAMING_THRESHOLD: usize = 10000;
+
+/// Size of the chunks in which large responses are sent
+#[cfg(feature = "server")]
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Sends everything written to it over a channel, in chunks. Must be used from a blocking thread.
+#[cfg(feature = "server")]
+struct ChunkWriter {
+    buffer: Vec<u8>,
+    sender: tokio::sync::mpsc::Sender<Bytes>,
 }
 
+#[cfg(feature = "server")]
+impl Write for ChunkWriter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(data);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(CHUNK_SIZE));
+            self.sender
+                .blocking_send(chunk.into())
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the bytes written to it
+#[derive(Default)]
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0 += data.len();
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns the size of a value serialized as JSON, without holding the serialization in memory
+pub fn serialized_size(value: &impl Serialize) -> usize {
+    let mut counter = ByteCounter::default();
+    serde_json::to_writer(&mut counter, value)
+        .map(|_| counter.0)
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "server")]
 impl IntoResponse for ApiResponse {
     fn into_response(self) -> Response {
         let cors = (
             header::ACCESS_CONTROL_ALLOW_ORIGIN,
             HeaderValue::from_static("*"),
         );
+        if self.expansion_count() > STREAMING_THRESHOLD {
+            let contenttype = (
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/json"),
+            );
+            return (StatusCode::OK, [cors, contenttype], self.into_body_stream()).into_response();
+        }
         match &self {
-            Self::QueryExpansion { .. } => (StatusCode::OK, [cors], Json(&self)).into_response(),
+            Self::QueryExpansion { .. } | Self::Suggestion { .. } => {
+                (StatusCode::OK, [cors], Json(&self)).into_response()
+            }
             Self::Modules(data) => (StatusCode::OK, [cors], Json(data)).into_response(),
+            Self::Module(data) => (StatusCode::OK, [cors], Json(data)).into_response(),
+            Self::Accepted(_) => (StatusCode::ACCEPTED, [cors], Json(&self)).into_response(),
+            Self::Stats(data) => (StatusCode::OK, [cors], Json(data)).into_response(),
         }
     }
 }
@@ -50,19 +188,283 @@ impl Serialize for ApiResponse {
                 original_query,
                 query_expansion_template,
                 query,
+                term_status,
+                highlights,
+                term_weights,
+                reproducibility,
+                total,
+                groups,
+                minimum_should_match,
+                warnings,
             } => {
                 state.serialize_field("terms", terms)?;
                 state.serialize_field("original_query", original_query)?;
                 state.serialize_field("query_expansion_template", query_expansion_template)?;
                 state.serialize_field("query", query)?;
+                state.serialize_field("term_status", term_status)?;
+                if let Some(highlights) = highlights {
+                    state.serialize_field("highlights", highlights)?;
+                }
+                if let Some(term_weights) = term_weights {
+                    state.serialize_field("term_weights", term_weights)?;
+                }
+                if let Some(reproducibility) = reproducibility {
+                    state.serialize_field("reproducibility", reproducibility)?;
+                }
+                if let Some(total) = total {
+                    state.serialize_field("total", total)?;
+                }
+                if let Some(groups) = groups {
+                    state.serialize_field("groups", groups)?;
+                }
+                if let Some(minimum_should_match) = minimum_should_match {
+                    state.serialize_field("minimum_should_match", minimum_should_match)?;
+                }
+                if let Some(warnings) = warnings {
+                    state.serialize_field("warnings", warnings)?;
+                }
+            }
+            Self::Suggestion {
+                suggestions,
+                original_query,
+                query,
+            } => {
+                state.serialize_field("suggestions", suggestions)?;
+                state.serialize_field("original_query", original_query)?;
+                state.serialize_field("query", query)?;
             }
             Self::Modules(v) => state.serialize_field("modules", v)?,
+            Self::Module(v) => state.serialize_field("module", v)?,
+            Self::Accepted(message) => state.serialize_field("message", message)?,
+            Self::Stats(v) => state.serialize_field("stats", v)?,
         }
         state.end()
     }
 }
 
 impl ApiResponse {
+    /// Returns the total number of expansions in a query expansion response, 0 for other responses
+    pub fn expansion_count(&self) -> usize {
+        if let Self::QueryExpansion { terms, .. } = self {
+            terms
+                .values()
+                .flatten()
+                .map(|termexpansion| termexpansion.len())
+                .sum()
+        } else {
+            0
+        }
+    }
+
+    /// Serializes the response into a body incrementally, on a blocking thread, so large responses are never
+    /// held in memory as a whole. Serialization stops if the client disconnects.
+    #[cfg(feature = "server")]
+    fn into_body_stream(self) -> Body {
+        let (sender, receiver) = tokio::sync::mpsc::channel::<Bytes>(4);
+        tokio::task::spawn_blocking(move || {
+            let mut writer = ChunkWriter {
+                buffer: Vec::with_capacity(CHUNK_SIZE),
+                sender,
+            };
+            let result = serde_json::to_writer(&mut writer, &self)
+                .map_err(io::Error::from)
+                .and_then(|_| writer.flush());
+            if let Err(e) = result {
+                error!("Unable to send response: {}", e);
+            }
+        });
+        Body::from_stream(futures_util::stream::unfold(
+            receiver,
+            |mut receiver| async move {
+                receiver
+                    .recv()
+                    .await
+                    .map(|chunk| (Ok::<_, io::Error>(chunk), receiver))
+            },
+        ))
+    }
+
+    /// Expands a query and returns the full response, as served by the webservice.
+    /// The parameters should hold the query itself as global parameter `q` (used for logging slow queries).
+    pub fn expand(
+        expander: &QueryExpander,
+        querystring: &str,
+        params: &QueryParams,
+    ) -> Result<Self, Error> {
+        expander.config().limits().check_query(querystring)?;
+        let reproducibility = Reproducibility::new(expander, params)?;
+        let coverage = Coverage::from_params(params)?;
+        let mut terms_map = TermExpansions::new();
+        let (terms, _) = Term::extract_from_query(querystring);
+        let context = QueryContext::from_query(querystring);
+        let warnings =
+            expander.expand_query_in_context(&mut terms_map, &terms, &context, params)?;
+        Ok(Self::from_expansions(
+            expander,
+            querystring,
+            terms_map,
+            params,
+            coverage,
+            reproducibility,
+        )?
+        .with_warnings(warnings))
+    }
+
+    /// Builds the full response for the expansions of a query, as served by the webservice
+    pub(crate) fn from_expansions(
+        expander: &QueryExpander,
+        querystring: &str,
+        terms_map: TermExpansions,
+        params: &QueryParams,
+        coverage: Coverage,
+        reproducibility: Reproducibility,
+    ) -> Result<Self, Error> {
+        let (terms, query_template) = Term::extract_from_query(querystring);
+        let context = QueryContext::from_query(querystring);
+        let resolved_template = expander.resolve_query_template_with_coverage(
+            query_template.as_str(),
+            &terms_map,
+            &coverage,
+        )?;
+        let term_status = expander.term_statuses_in_context(&terms, &context, &terms_map, params);
+        let mut response =
+            Self::new_queryexpansion(terms_map, querystring, query_template, resolved_template)
+                .with_term_status(term_status)
+                .with_reproducibility(reproducibility);
+        if let Some(minimum) = coverage.minimum_should_match() {
+            response = response.with_minimum_should_match(minimum);
+        }
+        if let Some(weights) = expander.term_weights() {
+            response = response.with_term_weights(weights.weigh(&terms));
+        }
+        if params.global_bool(GlobalParam::EditDistance) {
+            if let Some(terms_map) = response.expansions_mut() {
+                distance::attach_distances(terms_map);
+            }
+        }
+        if let Some(by) = GroupBy::from_params(params)? {
+            response = response.with_grouping(expander, by);
+        }
+        if params.global_bool(GlobalParam::Highlight) {
+            Ok(response.with_highlights())
+        } else {
+            Ok(response)
+        }
+    }
+
+    /// Returns the best spelling correction per term instead of expanding the query, using only the selected modules
+    /// that are spelling-oriented (see [`crate::modules::Module::is_spelling_oriented()`]), along with the corrected query
+    pub fn suggest(
+        expander: &QueryExpander,
+        querystring: &str,
+        params: &QueryParams,
+    ) -> Result<Self, Error> {
+        expander.config().limits().check_query(querystring)?;
+        let mut terms_map = TermExpansions::new();
+        let (terms, query_template) = Term::extract_from_query(querystring);
+        let context = QueryContext::from_query(querystring);
+        expander.expand_query_filtered(&mut terms_map, &terms, &context, params, |module| {
+            module.is_spelling_oriented()
+        })?;
+        if !expander
+            .selected_modules(params)
+            .any(|module| module.is_spelling_oriented())
+        {
+            return Err(Error::InvalidParameter(
+                "None of the selected modules provides spelling corrections".into(),
+            ));
+        }
+        let suggestions = suggest::best_corrections(&terms_map);
+        let query = suggest::corrected_query(&query_template, &terms, &suggestions);
+        Ok(Self::Suggestion {
+            suggestions,
+            original_query: querystring.to_owned(),
+            query,
+        })
+    }
+
+    /// Replaces the expansions of a query expansion response (e.g. after filtering them),
+    /// the expanded query and the term statuses are updated accordingly
+    pub fn with_expansions(
+        mut self,
+        expander: &QueryExpander,
+        terms_map: TermExpansions,
+        params: &QueryParams,
+    ) -> Result<Self, Error> {
+        if let Self::QueryExpansion { terms, .. } = &mut self {
+            *terms = terms_map;
+        }
+        self.refresh(expander, params)?;
+        Ok(self)
+    }
+
+    /// Updates the expanded query, the term statuses and the highlights (if any) of a query expansion response
+    /// after its expansions were modified in place, see [`Self::expansions_mut()`]
+    pub fn refresh(&mut self, expander: &QueryExpander, params: &QueryParams) -> Result<(), Error> {
+        if let Self::QueryExpansion {
+            terms,
+            original_query,
+            query_expansion_template,
+            query,
+            term_status,
+            highlights,
+            groups,
+            warnings,
+            ..
+        } = self
+        {
+            let (queryterms, _) = Term::extract_from_query(original_query);
+            let context = QueryContext::from_query(original_query);
+            *query = expander.resolve_query_template_with_coverage(
+                query_expansion_template.as_str(),
+                terms,
+                &Coverage::from_params(params)?,
+            )?;
+            *term_status = expander.term_statuses_in_context(&queryterms, &context, terms, params);
+            if let Some(warnings) = warnings {
+                warnings::apply_to_statuses(warnings, term_status);
+            }
+            if highlights.is_some() {
+                *highlights = Some(highlight::highlights(terms));
+            }
+            if let Some(grouping) = groups.as_mut() {
+                **grouping = Grouping::new(expander, terms, grouping.by());
+            }
+        }
+        Ok(())
+    }
+
+    /// Retains only the requested page of the expansions of each term and records the total number of expansions
+    /// of each term. Must be applied last, as the expanded query is not updated: it still holds all expansions.
+    pub fn paginate(&mut self, expander: &QueryExpander, pagination: Pagination) {
+        if let Self::QueryExpansion {
+            terms,
+            highlights,
+            total,
+            groups,
+            ..
+        } = self
+        {
+            *total = Some(Box::new(pagination.apply(terms)));
+            if highlights.is_some() {
+                *highlights = Some(highlight::highlights(terms));
+            }
+            if let Some(grouping) = groups.as_mut() {
+                **grouping = Grouping::new(expander, terms, grouping.by());
+            }
+        }
+    }
+
+    /// Returns the expansions of a query expansion response for modification in place (e.g. filtering them),
+    /// call [`Self::refresh()`] afterwards. Returns None for other responses.
+    pub fn expansions_mut(&mut self) -> Option<&mut TermExpansions> {
+        if let Self::QueryExpansion { terms, .. } = self {
+            Some(terms)
+        } else {
+            None
+        }
+    }
+
     pub fn new_queryexpansion(
         terms: TermExpansions,
         query: &str,
@@ -74,69 +476,233 @@ impl ApiResponse {
             terms,
             original_query: query.to_owned(),
             query: resolved_query.into(),
+            term_status: TermStatuses::new(),
+            highlights: None,
+            term_weights: None,
+            reproducibility: None,
+            total: None,
+            groups: None,
+            minimum_should_match: None,
+            warnings: None,
+        }
+    }
+
+    /// Adds the spans of each expansion that differ from its term (builder pattern), so a frontend can highlight
+    /// what changed. Only applicable to query expansion responses.
+    pub fn with_highlights(mut self) -> Self {
+        if let Self::QueryExpansion {
+            terms, highlights, ..
+        } = &mut self
+        {
+            *highlights = Some(highlight::highlights(terms));
+        }
+        self
+    }
+
+    /// Records the minimum of the alternatives of each term that should match (builder pattern), for the search
+    /// backend to apply. Only applicable to query expansion responses.
+    pub fn with_minimum_should_match(mut self, minimum: MinimumShouldMatch) -> Self {
+        if let Self::QueryExpansion {
+            minimum_should_match,
+            ..
+        } = &mut self
+        {
+            *minimum_should_match = Some(minimum);
         }
+        self
+    }
+
+    /// Groups the expansions of each term by kind, module or language (builder pattern), so a frontend need not
+    /// reorganise them. Only applicable to query expansion responses.
+    pub fn with_grouping(mut self, expander: &QueryExpander, by: GroupBy) -> Self {
+        if let Self::QueryExpansion { terms, groups, .. } = &mut self {
+            *groups = Some(Box::new(Grouping::new(expander, terms, by)));
+        }
+        self
+    }
+
+    /// Set the IDF weight of each term (builder pattern), only applicable to query expansion responses
+    pub fn with_term_weights(mut self, weights: HashMap<String, f64>) -> Self {
+        if let Self::QueryExpansion { term_weights, .. } = &mut self {
+            *term_weights = Some(Box::new(weights));
+        }
+        self
+    }
+
+    /// Set the reproducibility information (builder pattern), only applicable to query expansion responses
+    pub fn with_reproducibility(mut self, info: Reproducibility) -> Self {
+        if let Self::QueryExpansion {
+            reproducibility, ..
+        } = &mut self
+        {
+            *reproducibility = Some(Box::new(info));
+        }
+        self
+    }
+
+    /// Records the modules that were skipped (builder pattern), terms without matches get status
+    /// [`crate::TermStatus::Timeout`] if time ran out. Only applicable to query expansion responses.
+    pub fn with_warnings(mut self, skipped: Warnings) -> Self {
+        if let Self::QueryExpansion {
+            term_status,
+            warnings,
+            ..
+        } = &mut self
+        {
+            warnings::apply_to_statuses(&skipped, term_status);
+            *warnings = Some(Box::new(skipped)).filter(|skipped| !skipped.is_empty());
+        }
+        self
+    }
+
+    /// Returns the modules that were skipped, empty if none were or for other responses
+    pub fn warnings(&self) -> &[ModuleWarning] {
+        match self {
+            Self::QueryExpansion {
+                warnings: Some(warnings),
+                ..
+            } => warnings,
+            _ => &[],
+        }
+    }
+
+    /// Set the status of each term (builder pattern), only applicable to query expansion responses
+    pub fn with_term_status(mut self, statuses: TermStatuses) -> Self {
+        if let Self::QueryExpansion { term_status, .. } = &mut self {
+            *term_status = statuses;
+        }
+        self
     }
 }
 
+/// Formats a response as pretty-printed JSON, as output by the command-line tools.
+/// Also accepts any other serializable value, such as the JSON returned by a remote instance.
+pub fn to_pretty_json(value: &impl Serialize) -> Result<String, Error> {
+    serde_json::to_string_pretty(value)
+        .map_err(|e| Error::QueryExpandError(format!("Unable to serialize response: {}", e)))
+}
+
 #[derive(Debug)]
 pub enum ApiError {
     InternalError(&'static str),
     NotFound(&'static str),
     NotAcceptable(&'static str),
     PermissionDenied(&'static str),
+    Unauthorized(&'static str),
+    TooManyRequests(&'static str),
     MissingArgument(&'static str),
+    BadRequest(&'static str),
+    BadGateway(&'static str),
+    Timeout(&'static str),
+    ServiceUnavailable(&'static str),
     Error(Error),
 }
 
+impl ApiError {
+    /// Returns the name of the error
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InternalError(..) => "InternalError",
+            Self::NotFound(..) => "NotFound",
+            Self::NotAcceptable(..) => "NotAcceptable",
+            Self::PermissionDenied(..) => "PermissionDenied",
+            Self::Unauthorized(..) => "Unauthorized",
+            Self::TooManyRequests(..) => "TooManyRequests",
+            Self::MissingArgument(..) => "MissingArgument",
+            Self::BadRequest(..) => "BadRequest",
+            Self::BadGateway(..) => "BadGateway",
+            Self::Timeout(..) => "Timeout",
+            Self::ServiceUnavailable(..) => "ServiceUnavailable",
+            Self::Error(..) => "Error",
+        }
+    }
+
+    /// Returns a machine-readable code for the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InternalError(..) => "internal_error",
+            Self::NotFound(..) => "not_found",
+            Self::NotAcceptable(..) => "not_acceptable",
+            Self::PermissionDenied(..) => "permission_denied",
+            Self::Unauthorized(..) => "unauthorized",
+            Self::TooManyRequests(..) => "too_many_requests",
+            Self::MissingArgument(..) => "missing_argument",
+            Self::BadRequest(..) => "bad_request",
+            Self::BadGateway(..) => "bad_gateway",
+            Self::Timeout(..) => "timeout",
+            Self::ServiceUnavailable(..) => "service_unavailable",
+            Self::Error(e) => e.code(),
+        }
+    }
+
+    /// Returns the HTTP status code for the error
+    #[cfg(feature = "server")]
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InternalError(..) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotFound(..) => StatusCode::NOT_FOUND,
+            Self::NotAcceptable(..) => StatusCode::NOT_ACCEPTABLE,
+            Self::PermissionDenied(..) => StatusCode::FORBIDDEN,
+            Self::Unauthorized(..) => StatusCode::UNAUTHORIZED,
+            Self::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            Self::MissingArgument(..) | Self::BadRequest(..) => StatusCode::BAD_REQUEST,
+            Self::BadGateway(..) => StatusCode::BAD_GATEWAY,
+            Self::Timeout(..) => StatusCode::GATEWAY_TIMEOUT,
+            Self::ServiceUnavailable(..) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Error(e) => match e {
+                Error::InvalidParameter(..)
+                | Error::InvalidConfig(..)
+                | Error::LimitExceeded(..) => StatusCode::BAD_REQUEST,
+                Error::ModuleNotFound(..) => StatusCode::NOT_FOUND,
+                Error::Timeout(..) => StatusCode::GATEWAY_TIMEOUT,
+                // nginx' convention: the client closed the request before the response was ready
+                Error::Cancelled(..) => StatusCode::from_u16(499).expect("valid status code"),
+                Error::LoadError(..) | Error::QueryExpandError(..) => {
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }
+            },
+        }
+    }
+}
+
 impl Serialize for ApiError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("ApiError", 3)?;
+        let mut state = serializer.serialize_struct("ApiError", 5)?;
         state.serialize_field("@type", "ApiError")?;
+        state.serialize_field("name", self.name())?;
+        state.serialize_field("code", self.code())?;
         match self {
-            Self::NotFound(s) => {
-                state.serialize_field("name", "NotFound")?;
-                state.serialize_field("message", s)?;
-            }
-            Self::NotAcceptable(s) => {
-                state.serialize_field("name", "NotAcceptable")?;
-                state.serialize_field("message", s)?;
-            }
-            Self::PermissionDenied(s) => {
-                state.serialize_field("name", "PermissionDenied")?;
-                state.serialize_field("message", s)?;
-            }
-            Self::InternalError(s) => {
-                state.serialize_field("name", "InternalError")?;
-                state.serialize_field("message", s)?;
-            }
-            Self::MissingArgument(s) => {
-                state.serialize_field("name", "MissingArgument")?;
-                state.serialize_field("message", s)?;
-            }
-            Self::Error(s) => {
-                state.serialize_field("name", "Error")?;
-                state.serialize_field("message", s)?;
-            }
+            Self::InternalError(s)
+            | Self::NotFound(s)
+            | Self::NotAcceptable(s)
+            | Self::PermissionDenied(s)
+            | Self::Unauthorized(s)
+            | Self::TooManyRequests(s)
+            | Self::MissingArgument(s)
+            | Self::BadRequest(s)
+            | Self::BadGateway(s)
+            | Self::Timeout(s)
+            | Self::ServiceUnavailable(s) => state.serialize_field("message", s)?,
+            Self::Error(e) => state.serialize_field("message", e)?,
+        }
+        if let Some(request_id) = current_request_id() {
+            state.serialize_field("request_id", &request_id)?;
         }
         state.end()
     }
 }
 
+#[cfg(feature = "server")]
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        let statuscode = match self {
-            Self::InternalError(..) => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::PermissionDenied(..) => StatusCode::FORBIDDEN,
-            Self::NotAcceptable(..) => StatusCode::NOT_ACCEPTABLE,
-            _ => StatusCode::NOT_FOUND,
-        };
-        (statuscode, Json(self)).into_response()
+        (self.status(), Json(self)).into_response()
     }
 }
 
+#[cfg(feature = "server")]
 impl From<axum::Error> for ApiError {
     fn from(_value: axum::Error) -> Self {
         Self::InternalError("web framework error")
@@ -1,4 +1,50 @@
-use utoipa::ToSchema;
+use std::collections::HashMap;
+use utoipa::openapi::path::{Parameter, ParameterBuilder, ParameterIn};
+use utoipa::openapi::{ObjectBuilder, Required, Type};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::globalparams::{GlobalParam, GlobalParamType};
+use crate::grouping::Grouping;
+use crate::highlight::Highlights;
+use crate::modules::{ExpansionKind, FileChecksum};
+use crate::progress::{LoadStatus, ModuleProgress};
+use crate::reproducibility::Reproducibility;
+use crate::session::Decision;
+use crate::suggest::Suggestion;
+use crate::warnings::ModuleWarning;
+use crate::{TermExpansion, TermStatus};
+
+/// The global parameters of the main entrypoint, documented from the registry in [`GlobalParam`]
+pub struct GlobalParams;
+
+impl IntoParams for GlobalParams {
+    fn into_params(parameter_in_provider: impl Fn() -> Option<ParameterIn>) -> Vec<Parameter> {
+        GlobalParam::ALL
+            .into_iter()
+            .map(|param| {
+                let schema_type = match param.value_type() {
+                    GlobalParamType::String => Type::String,
+                    GlobalParamType::Boolean => Type::Boolean,
+                    GlobalParamType::Integer => Type::Integer,
+                };
+                ParameterBuilder::new()
+                    .name(param.name())
+                    .parameter_in(parameter_in_provider().unwrap_or(ParameterIn::Query))
+                    .required(if param == GlobalParam::Query {
+                        Required::True
+                    } else {
+                        Required::False
+                    })
+                    .description(Some(param.description()))
+                    .schema(Some(ObjectBuilder::new().schema_type(schema_type)))
+                    .allow_reserved(
+                        matches!(param.value_type(), GlobalParamType::String).then_some(true),
+                    )
+                    .build()
+            })
+            .collect()
+    }
+}
 
 #[derive(ToSchema)]
 /// An API error in JSON
@@ -8,9 +54,285 @@ pub struct ApiError {
     /// The type of error, this will be "ApiError"
     r#type: String,
 
-    /// The error name (MissingArgument, InternalError, NotFound, CustomNotFound, NotAcceptable, PermissionDenied)
+    /// The error name (MissingArgument, BadRequest, InternalError, NotFound, NotAcceptable, PermissionDenied, Unauthorized, TooManyRequests, BadGateway, Timeout, ServiceUnavailable, Error)
     name: String,
 
+    /// A machine-readable error code (missing_argument, bad_request, internal_error, not_found, not_acceptable, permission_denied, unauthorized, too_many_requests, bad_gateway, timeout, service_unavailable, load_error, query_expansion_error, invalid_parameter, invalid_config, module_not_found, limit_exceeded)
+    code: String,
+
     /// The error message
     message: String,
+
+    /// The ID of the request, to correlate errors with server logs
+    request_id: Option<String>,
+}
+
+#[derive(ToSchema)]
+/// The result of query expansion
+#[allow(dead_code)]
+pub struct QueryExpansion {
+    /// Maps each term to its expansions, with one entry per module that returned expansions
+    terms: HashMap<String, Vec<TermExpansion>>,
+
+    /// The input query
+    original_query: String,
+
+    /// A template for query expansion, expandable terms are replaced by {{term}}, which refer back to the terms
+    query_expansion_template: String,
+
+    /// The full expanded query
+    query: String,
+
+    /// The status of each term, explains why a term got no expansions
+    term_status: HashMap<String, TermStatus>,
+
+    /// Only if requested (highlight=true): maps each term to its expansions, and those to the spans of characters
+    /// in the expansion that differ from the term, as begin (inclusive) and end (exclusive) character offsets
+    #[schema(value_type = Option<HashMap<String, HashMap<String, Vec<[usize; 2]>>>>)]
+    highlights: Option<Highlights>,
+
+    /// Only if a frequency file is configured: the inverse document frequency of each term, rare terms weigh more
+    term_weights: Option<HashMap<String, f64>>,
+
+    /// The version of kweepeer, the seed (if passed) and the version of the data of each consulted module,
+    /// so the expansion can be reproduced later
+    reproducibility: Option<Reproducibility>,
+
+    /// Only if the expansions are paginated (offset or limit passed): the total number of expansions of each term
+    total: Option<HashMap<String, usize>>,
+
+    /// Only if requested (group_by): the expansions of each term grouped by the kind of expansions, the module or the
+    /// language, as configured for the modules
+    groups: Option<Grouping>,
+
+    /// Only if requested (minimum_should_match): the minimum number or percentage of the alternatives of each term
+    /// that should match, for search backends that support such a constraint
+    minimum_should_match: Option<String>,
+
+    /// Only if modules were skipped (because time ran out or their data could not be loaded): the skipped modules
+    /// and why, the response holds fewer expansions than it otherwise would
+    warnings: Option<Vec<ModuleWarning>>,
+}
+
+#[derive(ToSchema)]
+/// A module
+#[allow(dead_code)]
+pub struct ModuleInfo {
+    /// The identifier of the module
+    id: String,
+
+    /// A human-readable name for the module
+    name: String,
+
+    #[schema(rename = "type")]
+    /// The module type (e.g. lookup, fst)
+    r#type: String,
+
+    /// The version of the loaded data (a SHA-256 checksum of the files), changes when the data is reloaded.
+    /// Null if the data is not loaded yet (lazy modules).
+    data_version: Option<String>,
+
+    /// The SHA-256 checksum of each data file as it was loaded, and whether it was verified against the configured
+    /// one (`sha256`). Empty if the data is not loaded yet.
+    files: Vec<FileChecksum>,
+
+    /// The kind of expansions the module provides
+    expansion_kind: ExpansionKind,
+
+    /// The language of the expansions, if configured
+    language: Option<String>,
+
+    /// Modules with a higher priority are consulted first, their expansions come first
+    priority: i32,
+
+    /// The parameters the module accepts at query time (as `module.key=value`), passed values are validated against
+    /// these
+    parameters: Vec<ModuleParameter>,
+
+    /// Only if the data of the module could not be loaded at startup and the module is not required: the error. The
+    /// module is then unavailable, queries are expanded without it.
+    load_error: Option<String>,
+
+    /// Whether the module is available (ready), its data could not be loaded (failed) or its data is being reloaded
+    /// (loading) while the current version keeps serving
+    status: LoadStatus,
+
+    /// Only while the data is reloaded, for modules that read line-based files: the number of lines read so far
+    lines: Option<u64>,
+
+    /// Only while the data is reloaded: the fraction of the data read so far (0 to 1), if the size is known
+    fraction: Option<f64>,
+}
+
+#[derive(ToSchema)]
+/// Whether the service has started: all modules are loaded and the warm-up queries are expanded
+#[allow(dead_code)]
+pub struct Readiness {
+    ready: bool,
+
+    /// The status of each module, in the order in which they are loaded
+    modules: Vec<ModuleProgress>,
+}
+
+#[derive(ToSchema)]
+/// A parameter a module accepts at query time
+#[allow(dead_code)]
+pub struct ModuleParameter {
+    /// The name of the parameter, passed as `module.name`
+    name: String,
+
+    #[schema(rename = "type")]
+    /// The type of the value (bool, integer, float, string)
+    r#type: String,
+
+    /// The minimum value (inclusive), for integers and floats
+    min: Option<f64>,
+
+    /// The maximum value (inclusive), for integers and floats
+    max: Option<f64>,
+
+    /// The value used if the parameter is not passed, if known
+    #[schema(value_type = Option<Object>)]
+    default: Option<serde_json::Value>,
+
+    /// A description of the parameter
+    description: String,
+}
+
+#[derive(ToSchema)]
+/// A request that was accepted for processing in the background
+#[allow(dead_code)]
+pub struct Accepted {
+    /// A human-readable message
+    message: String,
+}
+
+#[derive(ToSchema)]
+/// Runtime statistics
+#[allow(dead_code)]
+pub struct Stats {
+    /// Uptime in seconds
+    uptime: u64,
+
+    /// Total number of requests handled
+    requests: u64,
+
+    /// Statistics per module (by ID)
+    modules: HashMap<String, ModuleStats>,
+
+    /// The resources each module takes (by ID)
+    resources: HashMap<String, ModuleResources>,
+
+    /// Statistics on the response cache, if enabled
+    cache: Option<CacheStats>,
+
+    /// Resident memory usage of the process in bytes, if it can be determined
+    memory_usage: Option<u64>,
+}
+
+#[derive(ToSchema)]
+/// The resources a module takes
+#[allow(dead_code)]
+pub struct ModuleResources {
+    /// Whether the data is loaded, lazy modules load it on first use
+    loaded: bool,
+
+    /// Time it took to load the data, in milliseconds
+    load_time_ms: Option<u64>,
+
+    /// Number of entries (e.g. terms) in the data, if known
+    entries: Option<usize>,
+
+    /// Rough estimate of the memory used by the data in bytes, if known
+    memory_estimate: Option<u64>,
+}
+
+#[derive(ToSchema)]
+/// Statistics for a single module
+#[allow(dead_code)]
+pub struct ModuleStats {
+    /// Number of times the module was called
+    calls: u64,
+
+    /// Number of calls that resulted in an error
+    errors: u64,
+
+    /// Average time per call, in microseconds
+    avg_time_us: u64,
+
+    /// Longest time spent in a single call, in microseconds
+    max_time_us: u64,
+}
+
+#[derive(ToSchema)]
+/// Statistics on the response cache
+#[allow(dead_code)]
+pub struct CacheStats {
+    /// Number of entries currently in the cache
+    entries: u64,
+
+    /// Number of cache hits
+    hits: u64,
+
+    /// Number of cache misses
+    misses: u64,
+}
+
+#[derive(ToSchema)]
+/// A session of interactive query expansion
+#[allow(dead_code)]
+pub struct Session {
+    /// The identifier of the session
+    id: String,
+
+    /// The parameters of the expansion request, including the query itself (q)
+    params: HashMap<String, String>,
+
+    /// The expansions of the query
+    expansion: QueryExpansion,
+
+    /// Maps terms to the decisions (accept or reject) on their expansions
+    selection: HashMap<String, HashMap<String, Decision>>,
+}
+
+#[derive(ToSchema)]
+/// A curated set of expansions, stored under a stable ID
+#[allow(dead_code)]
+pub struct ExpansionSet {
+    /// The identifier of the set, derived from its expansions
+    id: String,
+
+    /// Time at which the set was first saved (RFC 3339)
+    created: String,
+
+    /// Free-form description
+    description: Option<String>,
+
+    /// Terms and their expansions
+    terms: HashMap<String, Vec<TermExpansion>>,
+}
+
+#[derive(ToSchema)]
+/// Spelling corrections for a query
+#[allow(dead_code)]
+pub struct Suggestions {
+    /// The best correction per term, terms that need no correction are absent
+    suggestions: HashMap<String, Suggestion>,
+
+    /// The original query as passed by the user
+    original_query: String,
+
+    /// The query with all corrections applied
+    query: String,
+}
+
+#[derive(ToSchema)]
+/// Completions of a prefix
+#[allow(dead_code)]
+pub struct Completions {
+    /// The prefix as passed by the user
+    prefix: String,
+
+    /// The completions, best first
+    completions: Vec<String>,
 }
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::Error;
+
+/// Configuration for the query audit log
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// File to append the audit log to (JSON lines)
+    file: Option<PathBuf>,
+
+    /// Size in bytes after which the audit file is rotated
+    max_size: u64,
+
+    /// Number of rotated files to keep
+    keep: usize,
+
+    /// Send the audit log to the local syslog daemon instead of a file
+    syslog: bool,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            max_size: 100 * 1024 * 1024,
+            keep: 5,
+            syslog: false,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    pub fn with_rotation(mut self, max_size: u64, keep: usize) -> Self {
+        self.max_size = max_size;
+        self.keep = keep;
+        self
+    }
+
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn syslog(&self) -> bool {
+        self.syslog
+    }
+}
+
+/// A single expansion request in the audit log
+#[derive(Debug, Serialize)]
+pub struct AuditEntry<'a> {
+    /// Time the request was handled (RFC 3339)
+    pub timestamp: String,
+    pub request_id: Option<&'a str>,
+    pub query: &'a str,
+    /// All other parameters passed with the query
+    pub params: BTreeMap<&'a str, &'a str>,
+    /// IDs of the modules used for expansion
+    pub modules: Vec<&'a str>,
+    /// Size of the response body in bytes
+    pub response_size: usize,
+}
+
+enum AuditSink {
+    File {
+        path: PathBuf,
+        file: File,
+        size: u64,
+    },
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram),
+}
+
+/// Appends expansion requests to a rotating JSON lines file or to syslog
+pub struct AuditLog {
+    config: AuditConfig,
+    sink: Mutex<AuditSink>,
+}
+
+/// Syslog priority: facility user (1) and severity informational (6)
+#[cfg(unix)]
+const SYSLOG_PRIORITY: u8 = 8 + 6;
+
+impl AuditLog {
+    pub fn new(config: AuditConfig) -> Result<Self, Error> {
+        let sink = if config.syslog {
+            Self::open_syslog()?
+        } else if let Some(path) = config.file() {
+            let file = Self::open_file(path)?;
+            let size = file.metadata()?.len();
+            AuditSink::File {
+                path: path.to_owned(),
+                file,
+                size,
+            }
+        } else {
+            return Err(Error::LoadError(
+                "Audit log requires either a file or syslog to be configured".into(),
+            ));
+        };
+        Ok(Self {
+            config,
+            sink: Mutex::new(sink),
+        })
+    }
+
+    fn open_file(path: &Path) -> Result<File, Error> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| {
+                Error::LoadError(format!(
+                    "Unable to open audit log {}: {}",
+                    path.display(),
+                    e
+                ))
+            })
+    }
+
+    #[cfg(unix)]
+    fn open_syslog() -> Result<AuditSink, Error> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket
+            .connect("/dev/log")
+            .map_err(|e| Error::LoadError(format!("Unable to connect to syslog: {}", e)))?;
+        Ok(AuditSink::Syslog(socket))
+    }
+
+    #[cfg(not(unix))]
+    fn open_syslog() -> Result<AuditSink, Error> {
+        Err(Error::LoadError(
+            "Syslog is not supported on this platform".into(),
+        ))
+    }
+
+    /// Append an entry to the audit log. Failures are logged but never fail a request.
+    pub fn log(&self, entry: &AuditEntry) {
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::error!("Unable to serialize audit log entry: {}", e);
+                return;
+            }
+        };
+        let mut sink = self.sink.lock().unwrap();
+        if let Err(e) = self.write(&mut sink, line) {
+            tracing::error!("Unable to write audit log: {}", e);
+        }
+    }
+
+    fn write(&self, sink: &mut AuditSink, mut line: String) -> Result<(), Error> {
+        match sink {
+            AuditSink::File { path, file, size } => {
+                line.push('\n');
+                if *size > 0 && *size + line.len() as u64 > self.config.max_size {
+                    Self::rotate(path, self.config.keep)?;
+                    *file = Self::open_file(path)?;
+                    *size = 0;
+                }
+                file.write_all(line.as_bytes())?;
+                *size += line.len() as u64;
+            }
+            #[cfg(unix)]
+            AuditSink::Syslog(socket) => {
+                socket.send(format!("<{}>kweepeer: {}", SYSLOG_PRIORITY, line).as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotates `path` to `path.1`, `path.1` to `path.2`, etc... keeping at most `keep` rotated files
+    fn rotate(path: &Path, keep: usize) -> Result<(), Error> {
+        let rotated = |n: usize| {
+            let mut rotated = path.as_os_str().to_owned();
+            rotated.push(format!(".{}", n));
+            PathBuf::from(rotated)
+        };
+        if keep == 0 {
+            std::fs::remove_file(path)?;
+            return Ok(());
+        }
+        let _ = std::fs::remove_file(rotated(keep));
+        for n in (1..keep).rev() {
+            if rotated(n).exists() {
+                std::fs::rename(rotated(n), rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(path, rotated(1))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+
+    #[test]
+    pub fn test001_audit_rotate() -> Result<(), Error> {
+        let dir = TestDir::new("audit");
+        let path = dir.join("audit.jsonl");
+        let log = AuditLog::new(AuditConfig::new().with_file(&path).with_rotation(150, 2))?;
+        for query in ["one", "two", "three"] {
+            log.log(&AuditEntry {
+                timestamp: "2025-01-01T00:00:00Z".into(),
+                request_id: None,
+                query,
+                params: BTreeMap::new(),
+                modules: vec!["lookup"],
+                response_size: 100,
+            });
+        }
+        let current = std::fs::read_to_string(&path)?;
+        let rotated1 = std::fs::read_to_string(dir.join("audit.jsonl.1"))?;
+        let rotated2 = std::fs::read_to_string(dir.join("audit.jsonl.2"))?;
+        assert!(current.contains("\"query\":\"three\""));
+        assert!(rotated1.contains("\"query\":\"two\""));
+        assert!(rotated2.contains("\"query\":\"one\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,220 @@
+use serde::Deserialize;
+
+/// Configuration for API-key authentication. If this is not configured, all modules are publicly accessible.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Modules (by ID) that may be used without an API key. If not set, all modules are accessible anonymously.
+    anonymous_modules: Option<Vec<String>>,
+
+    /// Known API keys
+    keys: Vec<ApiKey>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiKey {
+    /// The secret key as passed by the client
+    key: String,
+
+    /// Human readable label for the client that uses this key
+    #[serde(default)]
+    name: String,
+
+    /// Modules (by ID) this key grants access to. If not set, all modules are accessible.
+    #[serde(default)]
+    modules: Option<Vec<String>>,
+
+    /// Grants access to the administrative endpoints
+    #[serde(default)]
+    admin: bool,
+}
+
+impl ApiKey {
+    pub fn new(key: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            name: name.into(),
+            modules: None,
+            admin: false,
+        }
+    }
+
+    /// Grant access to the administrative endpoints
+    pub fn with_admin(mut self) -> Self {
+        self.admin = true;
+        self
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.admin
+    }
+
+    /// Restrict this key to the specified modules
+    pub fn with_modules(mut self, modules: Vec<String>) -> Self {
+        self.modules = Some(modules);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+/// The outcome of an access check
+#[derive(Debug, PartialEq)]
+pub enum Access<'a> {
+    /// All modules may be used
+    Unrestricted,
+    /// Only the listed modules (by ID) may be used
+    Restricted(Vec<&'a str>),
+    /// The supplied API key is not valid
+    InvalidKey,
+}
+
+impl Access<'_> {
+    /// Returns whether the module (by ID) may be used
+    pub fn permits(&self, module_id: &str) -> bool {
+        match self {
+            Self::Unrestricted => true,
+            Self::Restricted(permitted) => permitted.contains(&module_id),
+            Self::InvalidKey => false,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict anonymous access to the specified modules
+    pub fn with_anonymous_modules(mut self, modules: Vec<String>) -> Self {
+        self.anonymous_modules = Some(modules);
+        self
+    }
+
+    pub fn with_key(mut self, key: ApiKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Look up an API key
+    pub fn get_key(&self, key: &str) -> Option<&ApiKey> {
+        self.keys.iter().find(|k| k.key == key)
+    }
+
+    /// Checks whether the (optional) API key passed by the client grants access to the administrative endpoints
+    pub fn is_admin(&self, key: Option<&str>) -> bool {
+        key.and_then(|key| self.get_key(key))
+            .map(|key| key.is_admin())
+            .unwrap_or(false)
+    }
+
+    /// Determine which modules may be used given the (optional) API key passed by the client.
+    /// A valid key always grants access to the anonymous modules as well.
+    pub fn access(&self, key: Option<&str>) -> Access<'_> {
+        let anonymous = match self.anonymous_modules.as_ref() {
+            None => return self.access_for_key(key, Access::Unrestricted),
+            Some(modules) => modules.iter().map(|s| s.as_str()).collect(),
+        };
+        self.access_for_key(key, Access::Restricted(anonymous))
+    }
+
+    fn access_for_key<'a>(&'a self, key: Option<&str>, anonymous: Access<'a>) -> Access<'a> {
+        match key {
+            None => anonymous,
+            Some(key) => match self.get_key(key) {
+                None => Access::InvalidKey,
+                Some(ApiKey { modules: None, .. }) => Access::Unrestricted,
+                Some(ApiKey {
+                    modules: Some(modules),
+                    ..
+                }) => match anonymous {
+                    Access::Restricted(mut permitted) => {
+                        for module in modules {
+                            if !permitted.contains(&module.as_str()) {
+                                permitted.push(module.as_str());
+                            }
+                        }
+                        Access::Restricted(permitted)
+                    }
+                    x => x,
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::overrides::TermOverrides;
+
+    fn init_test() -> AuthConfig {
+        AuthConfig::new()
+            .with_anonymous_modules(vec!["lookup".into(), "fst".into()])
+            .with_key(ApiKey::new("secret", "client").with_modules(vec!["analiticcl".into()]))
+            .with_key(ApiKey::new("admin", "admin").with_admin())
+    }
+
+    #[test]
+    pub fn test001_auth_anonymous() {
+        let auth = init_test();
+        assert_eq!(auth.access(None), Access::Restricted(vec!["lookup", "fst"]));
+    }
+
+    #[test]
+    pub fn test002_auth_restricted_key() {
+        let auth = init_test();
+        assert_eq!(
+            auth.access(Some("secret")),
+            Access::Restricted(vec!["lookup", "fst", "analiticcl"])
+        );
+    }
+
+    #[test]
+    pub fn test003_auth_unrestricted_key() {
+        let auth = init_test();
+        assert_eq!(auth.access(Some("admin")), Access::Unrestricted);
+    }
+
+    #[test]
+    pub fn test004_auth_invalid_key() {
+        let auth = init_test();
+        assert_eq!(auth.access(Some("wrong")), Access::InvalidKey);
+    }
+
+    #[test]
+    pub fn test005_auth_admin() {
+        let auth = init_test();
+        assert!(auth.is_admin(Some("admin")));
+        assert!(!auth.is_admin(Some("secret")));
+        assert!(!auth.is_admin(Some("wrong")));
+        assert!(!auth.is_admin(None));
+    }
+
+    #[test]
+    pub fn test006_auth_open() {
+        let auth = AuthConfig::new().with_key(ApiKey::new("secret", "client"));
+        assert_eq!(auth.access(None), Access::Unrestricted);
+        assert_eq!(auth.access(Some("wrong")), Access::InvalidKey);
+    }
+
+    #[test]
+    pub fn test007_auth_term_overrides() {
+        let auth = init_test();
+        let overrides: TermOverrides =
+            serde_json::from_str(r#"{"rembrandt": {"include": ["analiticcl"]}}"#)
+                .expect("overrides must parse");
+        // term_overrides can add modules to the selection, so all modules it refers to must be permitted
+        let permits = |access: Access| {
+            overrides
+                .module_ids()
+                .all(|module_id| access.permits(module_id))
+        };
+        assert!(!permits(auth.access(None)));
+        assert!(permits(auth.access(Some("secret"))));
+        assert!(permits(auth.access(Some("admin"))));
+        assert!(!permits(auth.access(Some("wrong"))));
+    }
+}
@@ -1,17 +1,52 @@
-use axum::{extract::Query, extract::State, routing::get, Router};
-use clap::Parser;
+use axum::{
+    extract::{ConnectInfo, Path, Query, Request, State},
+    http::HeaderMap,
+    middleware::{self, Next},
+    response::Response,
+    routing::{delete, get, patch, post},
+    Router,
+};
+use clap::{Parser, Subcommand};
 use std::collections::HashMap;
+use std::io::Write;
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tower_http::compression::CompressionLayer;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{error, info};
 
+use serde::Deserialize;
 use serde_json::json;
+use sha2::{Digest, Sha256};
 use toml;
 use utoipa::OpenApi;
+#[cfg(feature = "swagger-ui")]
 use utoipa_swagger_ui::SwaggerUi;
 
-use kweepeer::api::{ApiError, ApiResponse};
+use kweepeer::accesslog::{AccessLog, AccessLogEntry};
+use kweepeer::api::{current_request_id, to_pretty_json, ApiError, ApiResponse, REQUEST_ID};
+use kweepeer::audit::{AuditEntry, AuditLog};
+use kweepeer::auth::Access;
+use kweepeer::cache::ResponseCache;
+use kweepeer::cancel::Cancellation;
+#[cfg(feature = "client")]
+use kweepeer::client::{Client, ExpandRequest};
+use kweepeer::expansionset::{ExpansionSet, ExpansionSetStore};
+use kweepeer::feedback::{Feedback, FeedbackStore};
+use kweepeer::globalparams::GlobalParam;
+#[cfg(feature = "indexfilter")]
+use kweepeer::indexfilter::IndexFilter;
+use kweepeer::overrides::TermOverrides;
+use kweepeer::pagination::Pagination;
+use kweepeer::progress::{LoadProgress, LoadStatus};
+use kweepeer::ratelimit::{RateLimitBy, RateLimiter};
+use kweepeer::reexpand::Reexpansion;
+use kweepeer::session::{Selection, Session, SessionStore};
+use kweepeer::snapshot::Snapshot;
 use kweepeer::*;
 
 #[derive(Parser, Debug, Clone)]
@@ -31,115 +66,2798 @@ struct Args {
     )]
     debug: bool,
 
-    #[arg(long = "config", short, default_value = "config.toml")]
+    #[arg(long = "config", short, default_value = "config.toml", global = true)]
     config_path: PathBuf,
+
+    #[arg(
+        long,
+        requires = "tls_key",
+        help = "TLS certificate chain (PEM) to serve over HTTPS, overrides the configuration file"
+    )]
+    tls_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "tls_cert",
+        help = "TLS private key (PEM) to serve over HTTPS, overrides the configuration file"
+    )]
+    tls_key: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the process ID to this file, it is removed again when the service is stopped"
+    )]
+    pid_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Number of worker threads handling requests, defaults to the number of CPU cores"
+    )]
+    workers: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Maximum number of threads for blocking tasks, such as expansions with CPU-intensive modules (e.g. analiticcl) and module loading. Defaults to 512"
+    )]
+    blocking_threads: Option<usize>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands, the webservice is started if none is given
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Write a commented configuration skeleton to the configuration path (--config), with an example section
+    /// for each of the module types this build supports
+    Init {
+        #[arg(long, default_value_t = false, help = "Overwrite an existing file")]
+        force: bool,
+    },
+
+    /// Download all remote resources (module files referenced by URL) in the configuration, and in the tenant
+    /// configurations, into the cache directory and verify their checksums. Requires the client feature.
+    Fetch {
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Download all resources again, even if they are already in the cache"
+        )]
+        force: bool,
+    },
+
+    /// Check the configuration without loading any modules: verifies that it parses, that all referenced files exist
+    /// and are readable and that module IDs are unique. Exits with a non-zero status if there are problems.
+    ValidateConfig,
+
+    /// Convert a lexicon between formats: tsv (as used by the lookup module), solr (synonyms), jsonl,
+    /// analiticcl (weighted variant list) and wordlist (sorted, as used by the fst module)
+    Convert {
+        #[arg(long, short, help = "The input format")]
+        from: convert::LexiconFormat,
+
+        #[arg(long, short, help = "The output format")]
+        to: convert::LexiconFormat,
+
+        #[arg(long, short, help = "Input file, reads from standard input if not set")]
+        input: Option<PathBuf>,
+
+        #[arg(
+            long,
+            short,
+            help = "Output file, writes to standard output if not set"
+        )]
+        output: Option<PathBuf>,
+    },
+    /// Expand a query and print the result as JSON. By default the modules are loaded locally, with --server a
+    /// running kweepeer instance is queried instead (this requires the client feature).
+    Query {
+        #[arg(help = "The query (Lucene syntax)")]
+        query: String,
+
+        #[arg(
+            long,
+            value_name = "URL",
+            help = "Query this kweepeer instance (e.g. http://localhost:8080) rather than loading the modules locally"
+        )]
+        server: Option<String>,
+
+        #[arg(
+            long = "param",
+            short,
+            value_name = "MODULE.KEY=VALUE",
+            help = "Pass a parameter to a module (e.g. fst.distance=2), or a global parameter like include=fst without a module prefix. May be specified multiple times."
+        )]
+        params: Vec<String>,
+
+        #[arg(long, help = "API key to pass to the remote instance")]
+        api_key: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "ENGINE",
+            help = "Render the expansions as search parameters for this search engine (typesense or meilisearch) rather than outputting the full response"
+        )]
+        render: Option<render::SearchEngine>,
+    },
+
+    /// Parse a query and print the extracted terms, their types and the query template, without expanding anything.
+    /// Useful to debug why a term is not expanded as expected.
+    Parse {
+        #[arg(help = "The query (Lucene syntax)")]
+        query: String,
+    },
+
+    /// Export the loaded data of a module (e.g. the lookup entries or the vocabulary) to standard output as
+    /// tab-separated values: one term per line, followed by its variants if the module has those
+    Dump {
+        #[arg(long, short, help = "The ID of the module")]
+        module: String,
+    },
+
+    /// Evaluate the configured modules against a gold standard: reports precision, recall, F1 and the mean reciprocal
+    /// rank (MRR) of the expansions per module
+    Eval {
+        #[arg(
+            long,
+            short,
+            help = "Gold standard, a TSV file with a term followed by its expected expansions on each line"
+        )]
+        gold: PathBuf,
+
+        #[arg(
+            long,
+            short,
+            help = "File with queries to evaluate, one per line. If not set, the terms of the gold standard are used"
+        )]
+        queries: Option<PathBuf>,
+    },
+
+    /// Expand each of the given terms and export the expansions as a lexicon, e.g. to freeze the output of a slow
+    /// module into a TSV file for the lookup module, or into SKOS or a synonyms file for Solr or Elasticsearch
+    Export {
+        #[arg(
+            long,
+            short,
+            help = "File with terms to expand, one per line, reads from standard input if not set"
+        )]
+        terms: Option<PathBuf>,
+
+        #[arg(
+            long,
+            short,
+            default_value = "tsv",
+            help = "The output format (tsv, skos, solr, elasticsearch, jsonl, analiticcl or wordlist)"
+        )]
+        format: convert::LexiconFormat,
+
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Include each term itself among its variants, so synonym rules (solr, elasticsearch) don't replace the original term"
+        )]
+        keep_original: bool,
+
+        #[arg(
+            long = "module",
+            short,
+            value_name = "ID",
+            help = "Only load and use the module with this ID. May be specified multiple times, all modules are used if not set."
+        )]
+        modules: Vec<String>,
+
+        #[arg(
+            long = "param",
+            short,
+            value_name = "MODULE.KEY=VALUE",
+            help = "Pass a parameter to a module (e.g. fst.distance=2). May be specified multiple times."
+        )]
+        params: Vec<String>,
+
+        #[arg(
+            long,
+            short,
+            help = "Output file, writes to standard output if not set"
+        )]
+        output: Option<PathBuf>,
+    },
+
+    /// Write the loaded data of the modules to a snapshot file, or start the webservice restoring the data from one,
+    /// so a restart doesn't have to parse all data files again
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+
+    /// Load the configured modules and benchmark them with the given queries, reports throughput and latency
+    /// percentiles per module, as well as the memory usage
+    Bench {
+        #[arg(long, short, help = "File with queries to benchmark, one per line")]
+        queries: PathBuf,
+
+        #[arg(
+            long,
+            short = 'n',
+            default_value_t = 1,
+            help = "Number of times to run all queries"
+        )]
+        iterations: usize,
+    },
+}
+
+/// Subcommands of the snapshot command
+#[derive(Subcommand, Debug, Clone)]
+enum SnapshotCommand {
+    /// Load the configured modules (and those of the tenants) and write the data of those that support it (lookup and
+    /// fst) to a snapshot file
+    Build {
+        #[arg(long, short, help = "The snapshot file to write")]
+        output: PathBuf,
+    },
+
+    /// Start the webservice, restoring the data of the modules from the snapshot where it is up to date with the
+    /// configuration and the data files. Other modules are loaded as usual.
+    Load {
+        #[arg(help = "The snapshot file to read")]
+        file: PathBuf,
+    },
 }
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         query_entrypoint,
-        list_modules
+        broccoli_entrypoint,
+        render_entrypoint,
+        reexpand_entrypoint,
+        create_session,
+        get_session,
+        update_selection,
+        session_query,
+        save_expansionset,
+        get_expansionset,
+        record_feedback,
+        complete_entrypoint,
+        list_modules,
+        get_module,
+        readiness,
+        get_stats,
+        export_entrypoint,
+        add_module,
+        remove_module,
+        reload_module
     ),
+    nest(
+        (path = "/", api = SruApiDoc)
+    ),
+    components(schemas(apidocs::Suggestions, kweepeer::progress::ModuleProgress)),
     tags(
         (name = "kweepeer", description = "A generic webservice for interactive query expansion, expansion is provided via various modules")
     )
 )]
 pub struct ApiDoc;
 
-#[tokio::main]
-async fn main() {
+#[cfg(feature = "sru")]
+#[derive(OpenApi)]
+#[openapi(paths(sru_entrypoint))]
+pub struct SruApiDoc;
+
+#[cfg(not(feature = "sru"))]
+#[derive(OpenApi)]
+pub struct SruApiDoc;
+
+/// The state shared by all request handlers
+struct AppState {
+    /// The query expander, replaced as a whole (copy-on-write) whenever modules change at runtime
+    expander: RwLock<Arc<QueryExpander>>,
+    /// The status of the modules while the service starts, requests are only served once it is ready
+    progress: Arc<LoadProgress>,
+    ratelimiter: Option<RateLimiter>,
+    cache: Option<ResponseCache<ApiResponse>>,
+    audit: Option<AuditLog>,
+    /// Sessions of interactive query expansion
+    sessions: Option<SessionStore>,
+    /// Storage of curated expansion sets
+    expansionsets: Option<ExpansionSetStore>,
+    /// Feedback on the quality of expansions
+    feedback: Option<FeedbackStore>,
+    /// Bounds the number of queries that are expanded concurrently
+    expansions: Option<tokio::sync::Semaphore>,
+    /// Time the service was started
+    started: Instant,
+    /// Total number of requests handled
+    requests: AtomicU64,
+    /// HTTP client for the SRU backend
+    #[cfg(feature = "sru")]
+    sru_client: Option<reqwest::Client>,
+    /// Post-filter checking expansions against a search index
+    #[cfg(feature = "indexfilter")]
+    indexfilter: Option<IndexFilter>,
+}
+
+impl AppState {
+    /// Returns the current query expander
+    fn expander(&self) -> Arc<QueryExpander> {
+        self.expander.read().unwrap().clone()
+    }
+
+    /// Modify a copy of the query expander and swap it in atomically.
+    /// Requests already in progress continue using the previous one.
+    fn update_expander(
+        &self,
+        f: impl FnOnce(&mut QueryExpander) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut expander = self.expander.write().unwrap();
+        let mut updated = QueryExpander::clone(&expander);
+        f(&mut updated)?;
+        *expander = Arc::new(updated);
+        if let Some(cache) = self.cache.as_ref() {
+            cache.clear();
+        }
+        Ok(())
+    }
+}
+
+fn main() {
     let args = Args::parse();
 
-    if args.debug {
-        tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::DEBUG)
-            .init();
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+    if let Some(workers) = args.workers {
+        runtime.worker_threads(workers.max(1));
+    }
+    if let Some(blocking_threads) = args.blocking_threads {
+        runtime.max_blocking_threads(blocking_threads.max(1));
+    }
+    let code = runtime
+        .build()
+        .expect("Unable to start runtime")
+        .block_on(run(args));
+    std::process::exit(code);
+}
+
+/// Runs the command or the webservice, returns the exit code
+async fn run(args: Args) -> i32 {
+    // warnings (such as slow queries) and errors are always logged
+    tracing_subscriber::fmt()
+        .with_max_level(if args.debug {
+            tracing::Level::DEBUG
+        } else {
+            tracing::Level::WARN
+        })
+        .init();
+
+    match args.command {
+        Some(Command::Init { force }) => {
+            if let Err(e) = init_config(&args.config_path, force) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Fetch { force }) => {
+            if let Err(e) = fetch_resources(&args.config_path, force).await {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::ValidateConfig) => std::process::exit(validate_config(&args.config_path)),
+        Some(Command::Convert {
+            from,
+            to,
+            input,
+            output,
+        }) => {
+            if let Err(e) = convert_lexicon(from, to, input.as_deref(), output.as_deref()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Export {
+            terms,
+            format,
+            keep_original,
+            modules,
+            params,
+            output,
+        }) => {
+            if let Err(e) = export_terms(
+                &args.config_path,
+                terms.as_deref(),
+                format,
+                keep_original,
+                &modules,
+                &params,
+                output.as_deref(),
+            ) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Query {
+            query,
+            server,
+            params,
+            api_key,
+            render,
+        }) => {
+            let result = if let Some(server) = server {
+                query_remote(&server, &query, &params, api_key.as_deref(), render).await
+            } else {
+                query_local(&args.config_path, &query, &params, render)
+            };
+            match result {
+                Ok(output) => println!("{}", output),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Parse { query }) => {
+            parse_query(&query);
+            std::process::exit(0);
+        }
+        Some(Command::Dump { module }) => {
+            if let Err(e) = dump_module(&args.config_path, &module) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Eval { gold, queries }) => {
+            if let Err(e) = evaluate(&args.config_path, &gold, queries.as_deref()) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Bench {
+            queries,
+            iterations,
+        }) => {
+            if let Err(e) = bench(&args.config_path, &queries, iterations) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Snapshot {
+            command: SnapshotCommand::Build { ref output },
+        }) => {
+            if let Err(e) = build_snapshot(&args.config_path, output) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+        Some(Command::Snapshot {
+            command: SnapshotCommand::Load { .. },
+        })
+        | None => {}
     }
+    let snapshot_file = match args.command {
+        Some(Command::Snapshot {
+            command: SnapshotCommand::Load { ref file },
+        }) => Some(file.clone()),
+        _ => None,
+    };
 
     info!("Loading configuration from {}", &args.config_path.display());
-    let toml_string =
-        std::fs::read_to_string(&args.config_path).expect("Unable to read configuration file");
-    let config: Config = toml::from_str(&toml_string).expect("Unable to parse configuration file");
+    let config = Config::from_file(&args.config_path).expect("Unable to load configuration");
 
-    let mut state = QueryExpander::new().with_config(config);
+    let compression = CompressionLayer::new()
+        .gzip(config.compression().gzip())
+        .br(config.compression().br());
+    let tls = if let (Some(cert), Some(key)) = (args.tls_cert.as_ref(), args.tls_key.as_ref()) {
+        Some(TlsConfig::new(cert, key))
+    } else {
+        config.tls().cloned()
+    };
+    let tenants = config.tenants().to_vec();
+    let accesslog = Arc::new(
+        config
+            .accesslog()
+            .map(|accesslog| AccessLog::new(accesslog).expect("Unable to open access log")),
+    );
 
-    // Load all the modules
-    state.load().expect("Failure whilst loading modules");
+    let mut states = vec![build_state(config)];
+    let mut app = build_router(states[0].clone());
+    for tenant in tenants {
+        info!(
+            "Loading configuration for tenant {} from {}",
+            tenant.prefix(),
+            tenant.config().display()
+        );
+        let config = Config::from_file(tenant.config()).expect("Unable to load configuration");
+        let state = build_state(config);
+        app = app.nest_service(
+            &format!("/{}", tenant.prefix().trim_matches('/')),
+            build_router(state.clone()),
+        );
+        states.push(state);
+    }
+    // only written once the configuration is loaded, and removed again when the service stops (also on a panic)
+    let pid_file = args
+        .pid_file
+        .as_ref()
+        .map(|pid_file| PidFile::create(pid_file).expect("Unable to write PID file"));
 
-    let app = Router::new()
-        .route("/", get(query_entrypoint))
-        .route("/modules", get(list_modules))
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()))
-        .layer(TraceLayer::new_for_http())
-        .with_state(Arc::new(state));
+    // Load all the modules in the background while already accepting requests, so the progress of a long startup
+    // can be followed (see /readyz). Identically configured modules are shared between tenants.
+    let load = move || {
+        let mut pool = ModulePool::new();
+        if let Some(snapshot_file) = snapshot_file {
+            match Snapshot::from_file(&snapshot_file) {
+                Ok(snapshot) => pool = pool.with_snapshot(snapshot),
+                Err(e) => {
+                    error!("Unable to read snapshot: {}", e);
+                    return false;
+                }
+            }
+        }
+        for state in states.iter() {
+            if let Err(e) = load_state(state, &mut pool) {
+                error!("Failure whilst loading modules: {}", e);
+                return false;
+            }
+        }
+        info!("Ready to serve requests");
+        notify_ready();
+        true
+    };
+
+    #[cfg(feature = "swagger-ui")]
+    let app =
+        app.merge(SwaggerUi::new("/swagger-ui").url("/api-doc/openapi.json", ApiDoc::openapi()));
+    // without the swagger UI, the API documentation is still served
+    #[cfg(not(feature = "swagger-ui"))]
+    let app = app.route(
+        "/api-doc/openapi.json",
+        get(|| async { axum::Json(ApiDoc::openapi()) }),
+    );
+    let app = app
+        .layer(compression)
+        .layer(middleware::from_fn_with_state(accesslog, request_context))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &axum::http::Request<_>| {
+                tracing::info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %request.uri(),
+                    request_id = request
+                        .headers()
+                        .get("x-request-id")
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default(),
+                )
+            }),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid));
 
     //allow trailing slashes as well: (conflicts with swagger-ui!)
     //let app = NormalizePathLayer::trim_trailing_slash().layer(app);
 
-    eprintln!("[kweepeer] listening on {}", args.bind);
-    let listener = tokio::net::TcpListener::bind(args.bind).await.unwrap();
-    axum::serve(
-        listener, app,
-        //ServiceExt::<axum::http::Request<Body>>::into_make_service(app),
-    )
-    .await
-    .unwrap();
+    // the service stops gracefully (finishing the requests in progress) when it is terminated or loading fails
+    let failed = Arc::new(AtomicBool::new(false));
+    let served = if let Some(tls) = tls {
+        let shutdown = shutdown_signal(tokio::task::spawn_blocking(load), failed.clone());
+        serve_tls(&args.bind, app, tls, shutdown).await
+    } else {
+        eprintln!("[kweepeer] listening on {}", args.bind);
+        let listener = tokio::net::TcpListener::bind(args.bind).await.unwrap();
+        let shutdown = shutdown_signal(tokio::task::spawn_blocking(load), failed.clone());
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown)
+        .await
+        .unwrap();
+        true
+    };
+    drop(pid_file);
+    if served && !failed.load(Ordering::Relaxed) {
+        0
+    } else {
+        1
+    }
+}
+
+/// Resolves when the service should stop: when it is interrupted (ctrl-c) or terminated (SIGTERM), or when loading
+/// the modules failed or panicked (which sets `failed`)
+async fn shutdown_signal(loading: tokio::task::JoinHandle<bool>, failed: Arc<AtomicBool>) {
+    let terminated = async {
+        #[cfg(unix)]
+        {
+            let mut terminate =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                    .expect("Unable to install signal handler");
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {},
+                _ = terminate.recv() => {},
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    // once loaded successfully, only a signal stops the service
+    let loading_failed = async {
+        match loading.await {
+            Ok(true) => std::future::pending().await,
+            Ok(false) => {}
+            Err(e) => error!("Loading the modules panicked: {}", e),
+        }
+    };
+    tokio::select! {
+        _ = terminated => info!("Shutting down"),
+        _ = loading_failed => failed.store(true, Ordering::Relaxed),
+    }
+    let _ = kweepeer::systemd::notify("STOPPING=1");
+}
+
+/// Notifies the service manager (systemd) that all modules are loaded and requests are being accepted
+fn notify_ready() {
+    match kweepeer::systemd::notify("READY=1") {
+        Ok(true) => info!("Notified service manager"),
+        Ok(false) => {}
+        Err(e) => error!("Unable to notify service manager: {}", e),
+    }
+}
+
+/// A file holding the process ID, removed again when dropped
+struct PidFile(PathBuf);
+
+impl PidFile {
+    fn create(path: &std::path::Path) -> std::io::Result<Self> {
+        std::fs::write(path, format!("{}\n", std::process::id()))?;
+        Ok(Self(path.to_path_buf()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Checks the configuration and prints a summary of the modules, returns the exit code
+fn validate_config(config_path: &std::path::Path) -> i32 {
+    let config = match Config::from_file(config_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+    println!("Configuration: {}", config_path.display());
+    for moduleconfig in config.module_configs() {
+        println!(
+            "Module {} ({}): {}{}",
+            moduleconfig.id(),
+            moduleconfig.kind(),
+            moduleconfig.name(),
+            if !moduleconfig.common().enabled() {
+                " [disabled]"
+            } else if moduleconfig.common().lazy() {
+                " [lazy]"
+            } else {
+                ""
+            }
+        );
+        for file in moduleconfig.files() {
+            println!("    {}", file.display());
+        }
+    }
+    for tenant in config.tenants() {
+        println!("Tenant /{}: {}", tenant.prefix(), tenant.config().display());
+    }
+    let problems = config.validate();
+    if problems.is_empty() {
+        println!("Configuration is valid");
+        0
+    } else {
+        for problem in problems.iter() {
+            eprintln!("ERROR: {}", problem);
+        }
+        1
+    }
+}
+
+/// Writes a configuration skeleton
+fn init_config(config_path: &std::path::Path, force: bool) -> Result<(), Error> {
+    if config_path.exists() && !force {
+        return Err(Error::InvalidParameter(format!(
+            "{} already exists, use --force to overwrite it",
+            config_path.display()
+        )));
+    }
+    std::fs::write(config_path, Config::skeleton())?;
+    eprintln!(
+        "Wrote {}, adapt it to your data and check it with: kweepeer -c {} validate-config",
+        config_path.display(),
+        config_path.display()
+    );
+    Ok(())
+}
+
+/// Downloads all remote resources into the cache directory
+#[cfg(feature = "client")]
+async fn fetch_resources(config_path: &std::path::Path, force: bool) -> Result<(), Error> {
+    let config = Config::from_file(config_path)?;
+    let mut configs = Vec::new();
+    for tenant in config.tenants() {
+        configs.push(Config::from_file(tenant.config())?);
+    }
+    configs.insert(0, config);
+    let client = reqwest::Client::new();
+    let mut count = 0;
+    for config in configs.iter() {
+        let cache_dir = config.cache_dir();
+        for resource in config.resources() {
+            count += 1;
+            let path = resource.cache_path(&cache_dir);
+            if path.exists() && !force {
+                match resource.verify(&path) {
+                    Ok(_) => {
+                        eprintln!("{} is up to date", resource.url());
+                        continue;
+                    }
+                    Err(e) => eprintln!("{}, downloading again", e),
+                }
+            }
+            std::fs::create_dir_all(&cache_dir)?;
+            eprintln!("Downloading {} to {}", resource.url(), path.display());
+            let mut partial = path.clone().into_os_string();
+            partial.push(".part");
+            let partial = PathBuf::from(partial);
+            let download = async {
+                let mut response = client
+                    .get(resource.url())
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())?;
+                let mut file = std::fs::File::create(&partial)?;
+                while let Some(chunk) = response.chunk().await? {
+                    file.write_all(&chunk)?;
+                }
+                file.flush()?;
+                Ok::<(), Box<dyn std::error::Error>>(())
+            };
+            if let Err(e) = download.await {
+                let _ = std::fs::remove_file(&partial);
+                return Err(Error::LoadError(format!(
+                    "Unable to download {}: {}",
+                    resource.url(),
+                    e
+                )));
+            }
+            let checksum = match resource.verify(&partial) {
+                Ok(checksum) => checksum,
+                Err(e) => {
+                    let _ = std::fs::remove_file(&partial);
+                    return Err(e);
+                }
+            };
+            std::fs::rename(&partial, &path)?;
+            let mut checksum_path = path.clone().into_os_string();
+            checksum_path.push(".sha256");
+            std::fs::write(
+                checksum_path,
+                format!(
+                    "{}  {}\n",
+                    checksum,
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                ),
+            )?;
+        }
+    }
+    if count == 0 {
+        eprintln!("The configuration does not reference any remote resources");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "client"))]
+async fn fetch_resources(_config_path: &std::path::Path, _force: bool) -> Result<(), Error> {
+    Err(Error::InvalidParameter(
+        "kweepeer was built without the client feature, which is needed to download resources"
+            .into(),
+    ))
+}
+
+/// Converts a lexicon between formats, reading from/writing to standard input/output if no files are given
+fn convert_lexicon(
+    from: convert::LexiconFormat,
+    to: convert::LexiconFormat,
+    input: Option<&std::path::Path>,
+    output: Option<&std::path::Path>,
+) -> Result<(), Error> {
+    let entries = if let Some(input) = input {
+        convert::read_lexicon(from, std::io::BufReader::new(std::fs::File::open(input)?))?
+    } else {
+        convert::read_lexicon(from, std::io::stdin().lock())?
+    };
+    if let Some(output) = output {
+        convert::write_lexicon(
+            to,
+            &entries,
+            std::io::BufWriter::new(std::fs::File::create(output)?),
+        )
+    } else {
+        convert::write_lexicon(to, &entries, std::io::stdout().lock())
+    }
+}
+
+/// Loads the modules and exports the expansions for all terms in a lexicon format
+fn export_terms(
+    config_path: &std::path::Path,
+    terms_path: Option<&std::path::Path>,
+    format: convert::LexiconFormat,
+    keep_original: bool,
+    modules: &[String],
+    params: &[String],
+    output: Option<&std::path::Path>,
+) -> Result<(), Error> {
+    let terms = if let Some(terms_path) = terms_path {
+        std::fs::read_to_string(terms_path)?
+    } else {
+        std::io::read_to_string(std::io::stdin().lock())?
+    };
+    let mut config = Config::from_file(config_path)?;
+    if !modules.is_empty() {
+        config.retain_modules(|module_id| modules.iter().any(|id| id == module_id));
+    }
+    let mut expander = QueryExpander::new().with_config(config);
+    expander.load()?;
+    if let Some(id) = modules.iter().find(|id| expander.get_module(id).is_none()) {
+        return Err(Error::ModuleNotFound(format!("No such module: {}", id)));
+    }
+    let mut queryparams = QueryParams::new();
+    for param in params.iter() {
+        queryparams.insert_from_str(param)?;
+    }
+    let mut entries = export::export_expansions(&expander, terms.lines(), &queryparams)?;
+    if keep_original {
+        export::keep_original(&mut entries);
+    }
+    if let Some(output) = output {
+        convert::write_lexicon(
+            format,
+            &entries,
+            std::io::BufWriter::new(std::fs::File::create(output)?),
+        )
+    } else {
+        convert::write_lexicon(format, &entries, std::io::stdout().lock())
+    }
+}
+
+/// Loads the modules and expands a single query, returns the JSON output
+fn query_local(
+    config_path: &std::path::Path,
+    querystring: &str,
+    params: &[String],
+    render: Option<render::SearchEngine>,
+) -> Result<String, Error> {
+    let mut expander = QueryExpander::new().with_config(Config::from_file(config_path)?);
+    expander.load()?;
+    let mut queryparams =
+        QueryParams::new().with("", GlobalParam::Query.name(), querystring.into());
+    for param in params.iter() {
+        queryparams.insert_from_str(param)?;
+    }
+    let response = ApiResponse::expand(&expander, querystring, &queryparams)?;
+    if let Some(engine) = render {
+        to_pretty_json(&engine.render(&response)?)
+    } else {
+        to_pretty_json(&response)
+    }
+}
+
+/// Expands a single query using a running kweepeer instance, returns the JSON output
+#[cfg(feature = "client")]
+async fn query_remote(
+    server: &str,
+    querystring: &str,
+    params: &[String],
+    api_key: Option<&str>,
+    render: Option<render::SearchEngine>,
+) -> Result<String, Error> {
+    let mut request = ExpandRequest::new(querystring);
+    for param in params.iter() {
+        let (key, value) = param.split_once('=').ok_or_else(|| {
+            Error::InvalidParameter(format!("Expected MODULE.KEY=VALUE, got: {}", param))
+        })?;
+        request = request.with_param("", key, value);
+    }
+    let mut client = Client::new(server);
+    if let Some(api_key) = api_key {
+        client = client.with_api_key(api_key);
+    }
+    if let Some(engine) = render {
+        to_pretty_json(&client.render(engine, &request).await?)
+    } else {
+        to_pretty_json(&client.expand(&request).await?)
+    }
+}
+
+#[cfg(not(feature = "client"))]
+async fn query_remote(
+    _server: &str,
+    _querystring: &str,
+    _params: &[String],
+    _api_key: Option<&str>,
+    _render: Option<render::SearchEngine>,
+) -> Result<String, Error> {
+    Err(Error::InvalidParameter(
+        "Querying a remote instance requires kweepeer to be compiled with the client feature"
+            .into(),
+    ))
+}
+
+/// Prints the terms and query template extracted from a query
+fn parse_query(query: &str) {
+    let (_, query_template) = Term::extract_from_query(query);
+    println!("Query template: {}", query_template);
+    println!("Terms:");
+    for queryterm in Term::analyze_query(query) {
+        let mut types = vec![queryterm.term.kind().to_owned()];
+        if let Some(field) = queryterm.field {
+            types.push(format!("field={}", field));
+        }
+        if queryterm.fuzzy {
+            types.push("fuzzy".to_owned());
+        }
+        if let Some(acronym) = queryterm.term.acronym() {
+            types.push(format!("acronym={}", acronym));
+        }
+        println!("  {}\t{}", queryterm.term.as_str(), types.join(", "));
+    }
+}
+
+/// Loads only the module with the given ID and writes its data to standard output
+fn dump_module(config_path: &std::path::Path, id: &str) -> Result<(), Error> {
+    let mut config = Config::from_file(config_path)?;
+    config.retain_modules(|module_id| module_id == id);
+    let mut expander = QueryExpander::new().with_config(config);
+    expander.load()?;
+    let module = expander
+        .get_module(id)
+        .ok_or_else(|| Error::ModuleNotFound(format!("No such module: {}", id)))?;
+    let mut writer = std::io::BufWriter::new(std::io::stdout().lock());
+    module.dump(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Evaluates all configured modules against a gold standard, running each of the queries through each module separately
+fn evaluate(
+    config_path: &std::path::Path,
+    gold_path: &std::path::Path,
+    queries_path: Option<&std::path::Path>,
+) -> Result<(), Error> {
+    let gold = eval::GoldStandard::read(std::io::BufReader::new(std::fs::File::open(gold_path)?))?;
+    let queries: Vec<String> = if let Some(queries_path) = queries_path {
+        std::fs::read_to_string(queries_path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.to_owned())
+            .collect()
+    } else {
+        // quote the terms so multi-word terms are considered a single phrase
+        gold.terms()
+            .map(|term| format!("\"{}\"", term.replace('"', "")))
+            .collect()
+    };
+    let mut expander = QueryExpander::new().with_config(Config::from_file(config_path)?);
+    expander.load()?;
+
+    let params = QueryParams::new();
+    println!(
+        "{:<20} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "module", "terms", "precision", "recall", "f1", "mrr"
+    );
+    for module in expander.modules() {
+        let mut evaluation = eval::Evaluation::new();
+        for query in queries.iter() {
+            let (terms, _) = Term::extract_from_query(query);
+            let terms_map = module.expand_query(&terms, &params)?;
+            evaluation.add_expansions(&terms, &terms_map, &gold);
+        }
+        println!(
+            "{:<20} {:>8} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+            module.id(),
+            evaluation.terms(),
+            evaluation.precision(),
+            evaluation.recall(),
+            evaluation.f1(),
+            evaluation.mrr(),
+        );
+    }
+    Ok(())
+}
+
+/// Loads the modules of the configuration and of its tenants and writes a snapshot of their data
+fn build_snapshot(config_path: &std::path::Path, output: &std::path::Path) -> Result<(), Error> {
+    let config = Config::from_file(config_path)?;
+    let mut configs = Vec::new();
+    for tenant in config.tenants() {
+        configs.push(Config::from_file(tenant.config())?);
+    }
+    configs.insert(0, config);
+    // identically configured modules are shared between tenants, and only stored once
+    let mut pool = ModulePool::new();
+    let mut snapshot = Snapshot::new();
+    for config in configs {
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load_shared(&mut pool)?;
+        snapshot.add(&expander)?;
+    }
+    snapshot.save(output)?;
+    eprintln!(
+        "Wrote a snapshot of {} modules to {}: {}",
+        snapshot.len(),
+        output.display(),
+        snapshot.module_ids().collect::<Vec<_>>().join(", ")
+    );
+    Ok(())
+}
+
+/// Benchmarks all configured modules by running each of the queries through each module separately
+fn bench(
+    config_path: &std::path::Path,
+    queries_path: &std::path::Path,
+    iterations: usize,
+) -> Result<(), Error> {
+    let queries: Vec<String> = std::fs::read_to_string(queries_path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_owned())
+        .collect();
+    let memory_before = stats::memory_usage();
+    let begin = Instant::now();
+    let mut expander = QueryExpander::new().with_config(Config::from_file(config_path)?);
+    expander.load()?;
+    let load_time = begin.elapsed();
+    let memory_after = stats::memory_usage();
+
+    let params = QueryParams::new();
+    let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+    println!(
+        "{:<20} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "module", "queries", "q/s", "mean_ms", "p50_ms", "p90_ms", "p99_ms", "max_ms"
+    );
+    for module in expander.modules() {
+        let mut latencies = stats::Latencies::new();
+        for _ in 0..iterations {
+            for query in queries.iter() {
+                let (terms, _) = Term::extract_from_query(query);
+                let begin = Instant::now();
+                module.expand_query(&terms, &params)?;
+                latencies.add(begin.elapsed());
+            }
+        }
+        let mean = latencies.total() / latencies.len().max(1) as u32;
+        println!(
+            "{:<20} {:>8} {:>10.1} {:>10.3} {:>10.3} {:>10.3} {:>10.3} {:>10.3}",
+            module.id(),
+            latencies.len(),
+            latencies.throughput(),
+            ms(mean),
+            ms(latencies.percentile(50.0)),
+            ms(latencies.percentile(90.0)),
+            ms(latencies.percentile(99.0)),
+            ms(latencies.percentile(100.0)),
+        );
+    }
+    println!("Loading time: {:.1}s", load_time.as_secs_f64());
+    if let (Some(before), Some(after)) = (memory_before, memory_after) {
+        println!(
+            "Memory usage: {:.1} MiB (of which {:.1} MiB for loading modules)",
+            after as f64 / 1048576.0,
+            after.saturating_sub(before) as f64 / 1048576.0
+        );
+    }
+    Ok(())
+}
+
+/// Instantiates the state for a configuration, its modules are loaded separately (see [`load_state()`])
+fn build_state(config: Config) -> Arc<AppState> {
+    let ratelimiter = config.ratelimit().cloned().map(RateLimiter::new);
+    let cache = config.cache().cloned().map(ResponseCache::new);
+    let audit = config
+        .audit()
+        .cloned()
+        .map(|audit| AuditLog::new(audit).expect("Unable to open audit log"));
+    #[cfg(feature = "sru")]
+    let sru_client = config.sru().map(|sru| {
+        reqwest::Client::builder()
+            .timeout(sru.timeout())
+            .build()
+            .expect("Unable to instantiate HTTP client")
+    });
+    #[cfg(not(feature = "sru"))]
+    if config.sru().is_some() {
+        error!("SRU is configured but kweepeer was compiled without the 'sru' feature");
+    }
+    #[cfg(feature = "indexfilter")]
+    let indexfilter = config.indexfilter().cloned().map(|indexfilter| {
+        IndexFilter::new(indexfilter).expect("Unable to instantiate index filter")
+    });
+    #[cfg(not(feature = "indexfilter"))]
+    if config.indexfilter().is_some() {
+        error!("An index filter is configured but kweepeer was compiled without the 'indexfilter' feature");
+    }
+    let sessions = config.session().cloned().map(SessionStore::new);
+    let expansionsets = config.expansionsets().cloned().map(ExpansionSetStore::new);
+    let feedback = config
+        .feedback()
+        .cloned()
+        .map(|feedback| FeedbackStore::new(feedback).expect("Unable to open feedback file"));
+    let expansions = config
+        .limits()
+        .max_concurrent()
+        .map(tokio::sync::Semaphore::new);
+    let progress = LoadProgress::new(
+        config
+            .module_configs()
+            .into_iter()
+            .filter(|moduleconfig| moduleconfig.common().enabled())
+            .map(|moduleconfig| moduleconfig.id()),
+    );
+    Arc::new(AppState {
+        expander: RwLock::new(Arc::new(QueryExpander::new().with_config(config))),
+        progress: Arc::new(progress),
+        ratelimiter,
+        cache,
+        audit,
+        sessions,
+        expansionsets,
+        feedback,
+        expansions,
+        started: Instant::now(),
+        requests: AtomicU64::new(0),
+        #[cfg(feature = "sru")]
+        sru_client,
+        #[cfg(feature = "indexfilter")]
+        indexfilter,
+    })
+}
+
+/// Loads all the modules of the state, swaps in the loaded query expander and expands the warm-up queries, after
+/// which requests are served
+fn load_state(state: &AppState, pool: &mut ModulePool) -> Result<(), Error> {
+    let mut expander = QueryExpander::new().with_config(state.expander().config().clone());
+    expander.load_shared_with_progress(pool, &state.progress)?;
+    *state.expander.write().unwrap() = Arc::new(expander);
+    warm_up(state);
+    state.progress.set_ready();
+    Ok(())
+}
+
+/// Expands the configured warm-up queries, so the data of the modules is paged in (and lazy modules are loaded)
+/// before requests are served. If caching is enabled, the responses are cached as for requests with only a query.
+fn warm_up(state: &AppState) {
+    let expander = state.expander();
+    let queries = expander.config().warmup();
+    if queries.is_empty() {
+        return;
+    }
+    let begin = Instant::now();
+    let params = QueryParams::new();
+    for querystring in queries {
+        match ApiResponse::expand(&expander, querystring, &params) {
+            Ok(response) => {
+                if let Some(cache) = state.cache.as_ref() {
+                    let key = ResponseCache::<ApiResponse>::key(
+                        querystring,
+                        std::iter::once((GlobalParam::Query.name(), querystring.as_str())),
+                        &[GlobalParam::Query.name()],
+                    );
+                    cache.insert(key, response);
+                }
+            }
+            Err(e) => error!("Unable to expand warm-up query {}: {}", querystring, e),
+        }
+    }
+    info!(
+        "Expanded {} warm-up queries in {:.1}s",
+        queries.len(),
+        begin.elapsed().as_secs_f64()
+    );
+}
+
+/// Sets up all the routes for the given state
+fn build_router(state: Arc<AppState>) -> Router {
+    let router = Router::new()
+        .route("/", get(query_entrypoint))
+        .route("/export", post(export_entrypoint))
+        .route("/broccoli", get(broccoli_entrypoint))
+        .route("/render/{engine}", get(render_entrypoint))
+        .route("/reexpand", post(reexpand_entrypoint))
+        .route("/session", post(create_session))
+        .route("/session/{id}", get(get_session))
+        .route("/session/{id}/selection", patch(update_selection))
+        .route("/session/{id}/query", get(session_query))
+        .route("/expansionsets", post(save_expansionset))
+        .route("/expansionsets/{id}", get(get_expansionset))
+        .route("/feedback", post(record_feedback))
+        .route("/complete", get(complete_entrypoint))
+        .route("/modules/{id}", get(get_module))
+        .route("/admin/modules", post(add_module))
+        .route("/admin/modules/{id}", delete(remove_module))
+        .route("/admin/modules/{id}/reload", post(reload_module));
+    #[cfg(feature = "sru")]
+    let router = router.route("/sru", get(sru_entrypoint));
+    router
+        // all routes that do work are rate-limited
+        .layer(middleware::from_fn_with_state(state.clone(), ratelimit))
+        .layer(middleware::from_fn_with_state(state.clone(), require_ready))
+        // answered while the service starts as well
+        .route("/readyz", get(readiness))
+        .route("/modules", get(list_modules))
+        .route("/stats", get(get_stats))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            count_requests,
+        ))
+        .with_state(state)
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(
+    bind: &str,
+    app: Router,
+    tls: TlsConfig,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> bool {
+    use axum_server::tls_rustls::RustlsConfig;
+    let addr = tokio::net::lookup_host(bind)
+        .await
+        .expect("Unable to resolve bind address")
+        .next()
+        .expect("No address to bind to");
+    let rustls_config = RustlsConfig::from_pem_file(tls.cert(), tls.key())
+        .await
+        .expect("Unable to load TLS certificate and key");
+    eprintln!("[kweepeer] listening on {} (https)", addr);
+    let handle = axum_server::Handle::new();
+    let graceful = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        graceful.graceful_shutdown(None);
+    });
+    axum_server::bind_rustls(addr, rustls_config)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+        .unwrap();
+    true
+}
+
+#[cfg(not(feature = "tls"))]
+async fn serve_tls(
+    _bind: &str,
+    _app: Router,
+    _tls: TlsConfig,
+    _shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> bool {
+    eprintln!("[kweepeer] TLS is configured but kweepeer was compiled without the 'tls' feature");
+    false
 }
 
 #[utoipa::path(
     get,
     path = "/",
+    params(
+        apidocs::GlobalParams,
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag of an earlier response, to check whether it is still current"),
+    ),
+    responses(
+        (status = 200, body = apidocs::QueryExpansion, description = "Query result, or spelling suggestions (see the Suggestions schema) if suggest=true", content_type = "application/json"),
+        (status = 304, description = "Return when the ETag passed in If-None-Match is still current: neither the query nor the data of the modules changed"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the query is missing, exceeds the configured limits or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module or expansion set does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when query expansion does not complete in time", content_type = "application/json"),
+    )
+)]
+/// Receive and process a query. This is the main entrypoint
+async fn query_entrypoint(
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+
+    let etag = expansion_etag(&state, &params, &headers)?;
+    if let Some(etag) = etag.as_ref() {
+        let matches = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| {
+                value
+                    .split(',')
+                    .any(|tag| tag.trim() == etag || tag.trim() == "*")
+            });
+        if matches {
+            return Ok((
+                StatusCode::NOT_MODIFIED,
+                [
+                    (
+                        header::ETAG,
+                        HeaderValue::from_str(etag).expect("valid etag"),
+                    ),
+                    (
+                        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                        HeaderValue::from_static("*"),
+                    ),
+                ],
+            )
+                .into_response());
+        }
+    }
+    let response = expand_request(&state, params, &headers).await?;
+    // a degraded response is not current, the skipped modules may well succeed next time
+    let etag = etag.filter(|_| response.warnings().is_empty());
+    let mut response = response.into_response();
+    if let Some(etag) = etag {
+        response.headers_mut().insert(
+            header::ETAG,
+            HeaderValue::from_str(&etag).expect("valid etag"),
+        );
+    }
+    Ok(response)
+}
+
+/// Returns the entity tag for a query expansion request, which changes whenever the query, the parameters or the data
+/// of the selected modules change. Returns None if the response may also change otherwise: if it depends on external
+/// state (index filter, feedback) or if the data version of a selected module is unknown.
+fn expansion_etag(
+    state: &AppState,
+    params: &HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Result<Option<String>, ApiError> {
+    #[cfg(feature = "indexfilter")]
+    if state.indexfilter.is_some() {
+        return Ok(None);
+    }
+    if state
+        .feedback
+        .as_ref()
+        .is_some_and(|feedback| feedback.config().downrank().is_some())
+    {
+        return Ok(None);
+    }
+    let Some(querystring) = params.get(GlobalParam::Query.name()) else {
+        return Ok(None);
+    };
+    let mut params = params.clone();
+    let api_key = get_api_key(&mut params, headers);
+    let expander = state.expander();
+    check_access(&expander, api_key.as_deref(), &mut params)?;
+    let Some(data_version) = expander.data_version(&(&params).into()) else {
+        return Ok(None);
+    };
+    let key = ResponseCache::<ApiResponse>::key(
+        querystring,
+        params.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+        &[GlobalParam::Query.name()],
+    );
+    let hash = format!(
+        "{:x}",
+        Sha256::digest(format!("{}\u{1f}{}", data_version, key))
+    );
+    Ok(Some(format!("W/\"{}\"", &hash[..16])))
+}
+
+#[utoipa::path(
+    get,
+    path = "/broccoli",
     params(
         ("q" = String, Query, description = "A query in Lucene syntax", allow_reserved),
         ("include" = String, Query, description = "Comma separated list of modules to include (by ID)", allow_reserved),
         ("exclude" = String, Query, description = "Comma separated list of modules to exclude (by ID)", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
     ),
     responses(
-        (status = 200, description = "Query result",content(
-            (String = "application/json"),
-        )),
-        (status = 404, body = apidocs::ApiError, description = "Return when the query is invalid or another error occurs", content_type = "application/json"),
+        (status = 200, body = Object, description = "Query result in the form Broccoli expects from a search wrapper service: the expanded query, the original query and the variants of each term, under the field names from the configuration", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the query is missing, exceeds the configured limits or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when query expansion does not complete in time", content_type = "application/json"),
     )
 )]
-/// Receive and process a query. This is the main entrypoint
-async fn query_entrypoint(
+/// Receive and process a query, responds in the form expected by Broccoli (TextAnnoViz)
+async fn broccoli_entrypoint(
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    let response = expand_request(&state, params, &headers).await?;
+    let body = state.expander().config().broccoli().render(&response);
+    Ok((
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(body),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/render/{engine}",
+    params(
+        ("engine" = String, Path, description = "The search engine to render the expansions for: typesense or meilisearch"),
+        ("q" = String, Query, description = "A query in Lucene syntax", allow_reserved),
+        ("include" = String, Query, description = "Comma separated list of modules to include (by ID)", allow_reserved),
+        ("exclude" = String, Query, description = "Comma separated list of modules to exclude (by ID)", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    responses(
+        (status = 200, body = Object, description = "Search parameters for the search engine: the query (q) and the expansions as synonyms, in the format of the search engine (a list of synonym objects for Typesense, the synonyms setting for Meilisearch)", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the query is missing or exceeds the configured limits, the search engine is unknown or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when query expansion does not complete in time", content_type = "application/json"),
+    )
+)]
+/// Receive and process a query, responds with search parameters for a search engine that doesn't take Lucene syntax
+async fn render_entrypoint(
+    Path(engine): Path<String>,
+    Query(params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    let engine: render::SearchEngine = engine.parse()?;
+    let response = expand_request(&state, params, &headers).await?;
+    Ok((
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(engine.render(&response)?),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/reexpand",
+    params(
+        apidocs::GlobalParams,
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    request_body(content = Object, description = "An object with the earlier query expansion *response* (as returned by the main entrypoint), optionally the *terms* to expand again (default: all terms of the query) and the *modules* to run again (by ID, default: all selected modules). The parameters for the re-expansion are passed as for the main entrypoint, except for the query itself.", content_type = "application/json"),
+    responses(
+        (status = 200, body = apidocs::QueryExpansion, description = "The earlier response, with the expansions of the given modules for the given terms replaced by new ones, and the expanded query and term statuses updated accordingly", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the body is invalid, a term does not occur in the query, the query exceeds the configured limits or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when query expansion does not complete in time", content_type = "application/json"),
+    )
+)]
+/// Expand only some terms of an earlier query expansion again, with only some modules and new parameters, and merge the result into the earlier response, e.g. to tune a single module parameter interactively without a complete re-expansion
+async fn reexpand_entrypoint(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+    body: String,
+) -> Result<ApiResponse, ApiError> {
+    let reexpansion: Reexpansion = serde_json::from_str(&body)
+        .map_err(|e| Error::InvalidParameter(format!("Invalid re-expansion request: {}", e)))?;
+    let api_key = get_api_key(&mut params, &headers);
+    let expander = state.expander();
+    check_access(&expander, api_key.as_deref(), &mut params)?;
+    // the query is taken from the earlier response
+    params.remove(GlobalParam::Query.name());
+    let queryparams: QueryParams = (&params).into();
+    let cpu_intensive = expander
+        .selected_modules(&queryparams)
+        .any(|module| module.is_cpu_intensive());
+    guarded_expansion(&state, &expander, cpu_intensive, move |expander| {
+        reexpansion.apply(expander, &queryparams)
+    })
+    .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/session",
+    params(
+        ("q" = String, Query, description = "A query in Lucene syntax", allow_reserved),
+        ("include" = String, Query, description = "Comma separated list of modules to include (by ID)", allow_reserved),
+        ("exclude" = String, Query, description = "Comma separated list of modules to exclude (by ID)", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    responses(
+        (status = 201, body = apidocs::Session, description = "The new session, holding the expansions of the query", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the query is missing, exceeds the configured limits or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when sessions are not enabled or a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when query expansion does not complete in time", content_type = "application/json"),
+    )
+)]
+/// Expand a query and keep the result in a new session, in which the expansions can be accepted or rejected step by step
+async fn create_session(
     Query(params): Query<HashMap<String, String>>,
-    state: State<Arc<QueryExpander>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    let sessions = state
+        .sessions
+        .as_ref()
+        .ok_or(ApiError::NotFound("sessions are not enabled"))?;
+    let mut sessionparams = params.clone();
+    sessionparams.remove(GlobalParam::ApiKey.name());
+    let response = expand_request(&state, params, &headers).await?;
+    let session = Session::new(sessionparams, response);
+    let response = session_response(axum::http::StatusCode::CREATED, &session);
+    sessions.insert(session);
+    Ok(response)
+}
+
+#[utoipa::path(
+    get,
+    path = "/session/{id}",
+    params(
+        ("id" = String, Path, description = "The ID of the session"),
+    ),
+    responses(
+        (status = 200, body = apidocs::Session, description = "The session", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the session does not exist (anymore)", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Returns a session, with the expansions of its query and the decisions on them so far
+async fn get_session(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    let session = state
+        .sessions
+        .as_ref()
+        .and_then(|sessions| sessions.get(&id))
+        .ok_or(ApiError::NotFound("no such session"))?;
+    Ok(session_response(axum::http::StatusCode::OK, &session))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/session/{id}/selection",
+    params(
+        ("id" = String, Path, description = "The ID of the session"),
+    ),
+    request_body(content = Object, description = "Maps terms to an object that maps expansions to a decision: accept or reject. Replaces earlier decisions on the same expansions.", content_type = "application/json"),
+    responses(
+        (status = 200, body = apidocs::Session, description = "The updated session", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the body is invalid or refers to terms or expansions that are not part of the session", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the session does not exist (anymore)", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Records decisions on the expansions of a session
+async fn update_selection(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+    body: String,
+) -> Result<Response, ApiError> {
+    let selection: Selection = serde_json::from_str(&body)
+        .map_err(|e| Error::InvalidParameter(format!("Invalid selection: {}", e)))?;
+    let session = state
+        .sessions
+        .as_ref()
+        .and_then(|sessions| {
+            sessions.with_session(&id, |session| {
+                session.select(selection).map(|_| session.clone())
+            })
+        })
+        .ok_or(ApiError::NotFound("no such session"))??;
+    Ok(session_response(axum::http::StatusCode::OK, &session))
+}
+
+#[utoipa::path(
+    get,
+    path = "/session/{id}/query",
+    params(
+        ("id" = String, Path, description = "The ID of the session"),
+    ),
+    responses(
+        (status = 200, body = apidocs::QueryExpansion, description = "The query expansion with only the selected expansions: rejected expansions are left out and if any expansion of a term was accepted, only the accepted ones are kept", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the session does not exist (anymore)", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Returns the expanded query of a session, according to the decisions so far
+async fn session_query(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+) -> Result<ApiResponse, ApiError> {
+    let session = state
+        .sessions
+        .as_ref()
+        .and_then(|sessions| sessions.get(&id))
+        .ok_or(ApiError::NotFound("no such session"))?;
+    Ok(session.query(&state.expander())?)
+}
+
+#[derive(Deserialize)]
+struct ExpansionSetRequest {
+    terms: TermExpansions,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/expansionsets",
+    params(
+        ("api_key" = Option<String>, Query, description = "API key, required if authentication is configured, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    request_body(content = Object, description = "An object with the *terms* and their expansions, as in a query expansion response (which may be passed as a whole), and an optional *description*", content_type = "application/json"),
+    responses(
+        (status = 201, body = apidocs::ExpansionSet, description = "The stored expansion set. Saving the same expansions again yields the same set.", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the body is invalid or the maximum number of expansion sets is reached", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when authentication is configured and no valid API key is passed", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when expansion sets are not enabled", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when the expansion set can not be stored", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Stores a curated set of expansions under a stable ID, so it can be re-applied to later queries (parameter expansionset) and cited
+async fn save_expansionset(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+    body: String,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    let api_key = get_api_key(&mut params, &headers);
+    check_key(&state.expander(), api_key.as_deref())?;
+    let store = state
+        .expansionsets
+        .as_ref()
+        .ok_or(ApiError::NotFound("expansion sets are not enabled"))?;
+    let request: ExpansionSetRequest = serde_json::from_str(&body)
+        .map_err(|e| Error::InvalidParameter(format!("Invalid expansion set: {}", e)))?;
+    let set = store.save(ExpansionSet::new(request.terms, request.description))?;
+    Ok((
+        axum::http::StatusCode::CREATED,
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(set),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/expansionsets/{id}",
+    params(
+        ("id" = String, Path, description = "The ID of the expansion set"),
+    ),
+    responses(
+        (status = 200, body = apidocs::ExpansionSet, description = "The expansion set", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the expansion set does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Returns a stored expansion set
+async fn get_expansionset(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    let set = load_expansionset(&state, &id)?;
+    Ok((
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(set),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/feedback",
+    params(
+        ("api_key" = Option<String>, Query, description = "API key, required if authentication is configured, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    request_body(content = kweepeer::feedback::Feedback, description = "Feedback on a single expansion", content_type = "application/json"),
+    responses(
+        (status = 202, body = apidocs::Accepted, description = "The feedback was recorded. Each API key (or client, without authentication) has one vote per term and expansion, a later vote replaces the earlier one.", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the body is invalid, a value is too long or the maximum number of expansions with feedback is reached", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when authentication is configured and no valid API key is passed", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when feedback is not enabled", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Records feedback (thumbs up or down) on an expansion
+async fn record_feedback(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    state: State<Arc<AppState>>,
+    body: String,
 ) -> Result<ApiResponse, ApiError> {
-    if let Some(querystring) = params.get("q") {
-        let mut terms_map = TermExpansions::new();
-        let (terms, query_template) = Term::extract_from_query(querystring);
-        let params: QueryParams = (&params).into();
-        state.expand_query_into(&mut terms_map, &terms, &params)?;
-        let resolved_template =
-            state.resolve_query_template(query_template.as_str(), &terms_map)?;
-        Ok(ApiResponse::new_queryexpansion(
-            terms_map,
-            querystring,
-            query_template,
-            resolved_template,
+    let api_key = get_api_key(&mut params, &headers);
+    check_key(&state.expander(), api_key.as_deref())?;
+    let store = state
+        .feedback
+        .as_ref()
+        .ok_or(ApiError::NotFound("feedback is not enabled"))?;
+    let feedback: Feedback = serde_json::from_str(&body)
+        .map_err(|e| Error::InvalidParameter(format!("Invalid feedback: {}", e)))?;
+    // with authentication the (valid) API key votes, otherwise the client address
+    let voter = match api_key {
+        Some(api_key) if state.expander().config().auth().is_some() => {
+            format!("key:{}", api_key)
+        }
+        _ => format!("ip:{}", client_address(&state, addr, &headers)),
+    };
+    if store.record(feedback, &voter)? {
+        Ok(ApiResponse::Accepted("Feedback recorded".into()))
+    } else {
+        Ok(ApiResponse::Accepted(
+            "Feedback was already recorded".into(),
         ))
+    }
+}
+
+/// Maximum number of completions that can be requested at once
+const MAX_COMPLETIONS: usize = 100;
+
+#[utoipa::path(
+    get,
+    path = "/complete",
+    params(
+        ("prefix" = String, Query, description = "The prefix to complete, e.g. what the user typed so far"),
+        ("limit" = Option<usize>, Query, description = "Maximum number of completions (default 10, at most 100)"),
+        ("include" = String, Query, description = "Comma separated list of modules to include (by ID)", allow_reserved),
+        ("exclude" = String, Query, description = "Comma separated list of modules to exclude (by ID)", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    responses(
+        (status = 200, body = apidocs::Completions, description = "Ranked completions", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the prefix is missing or a parameter is invalid", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Returns ranked completions of a prefix from the modules that support prefix search (lookup, fst, finalfusion), for search-box autocompletion
+async fn complete_entrypoint(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    let api_key = get_api_key(&mut params, &headers);
+    let expander = state.expander();
+    check_access(&expander, api_key.as_deref(), &mut params)?;
+    let prefix = params
+        .get("prefix")
+        .filter(|prefix| !prefix.is_empty())
+        .ok_or(ApiError::MissingArgument("prefix"))?;
+    let limit = match params.get("limit") {
+        Some(limit) => limit
+            .parse::<usize>()
+            .map_err(|_| Error::InvalidParameter("invalid value for limit parameter".into()))?
+            .min(MAX_COMPLETIONS),
+        None => 10,
+    };
+    let completions = expander.complete(prefix, limit, &(&params).into())?;
+    Ok((
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(json!({
+            "prefix": prefix,
+            "completions": completions,
+        })),
+    )
+        .into_response())
+}
+
+/// Retrieves an expansion set from the store
+fn load_expansionset(state: &AppState, id: &str) -> Result<ExpansionSet, ApiError> {
+    state
+        .expansionsets
+        .as_ref()
+        .ok_or(ApiError::NotFound("expansion sets are not enabled"))?
+        .get(id)?
+        .ok_or(ApiError::NotFound("no such expansion set"))
+}
+
+/// Serializes a session as response
+fn session_response(status: axum::http::StatusCode, session: &Session) -> Response {
+    use axum::http::{header, HeaderValue};
+    use axum::response::{IntoResponse, Json};
+
+    (
+        status,
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        Json(session),
+    )
+        .into_response()
+}
+
+/// Expands the query in the request parameters (q), using the response cache if enabled
+async fn expand_request(
+    state: &AppState,
+    mut params: HashMap<String, String>,
+    headers: &HeaderMap,
+) -> Result<ApiResponse, ApiError> {
+    let api_key = get_api_key(&mut params, headers);
+    let expander = state.expander();
+    check_access(&expander, api_key.as_deref(), &mut params)?;
+    if let Some(querystring) = params.get(GlobalParam::Query.name()) {
+        expander.config().limits().check_query(querystring)?;
+        let pagination = Pagination::from_params(&(&params).into())?;
+        let cache_key = state.cache.as_ref().map(|_| {
+            ResponseCache::<ApiResponse>::key(
+                querystring,
+                params.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+                &[GlobalParam::Query.name()],
+            )
+        });
+        let cached =
+            if let (Some(cache), Some(cache_key)) = (state.cache.as_ref(), cache_key.as_ref()) {
+                cache.get(cache_key)
+            } else {
+                None
+            };
+        let response = if let Some(id) = params.get(GlobalParam::ExpansionSet.name()) {
+            // a stored expansion set is applied as-is, the modules play no part
+            let mut response = load_expansionset(state, id)?.apply(&expander, querystring)?;
+            if let Some(pagination) = pagination {
+                response.paginate(&expander, pagination);
+            }
+            response
+        } else if let Some(response) = cached {
+            response
+        } else {
+            let queryparams: QueryParams = (&params).into();
+            let expand = if queryparams.global_bool(GlobalParam::Suggest) {
+                ApiResponse::suggest
+            } else {
+                ApiResponse::expand
+            };
+            let cpu_intensive = expander
+                .selected_modules(&queryparams)
+                .any(|module| module.is_cpu_intensive());
+            let querystring = querystring.clone();
+            let expansionparams = queryparams.clone();
+            let mut response =
+                guarded_expansion(state, &expander, cpu_intensive, move |expander| {
+                    expand(expander, &querystring, &expansionparams)
+                })
+                .await?;
+            #[cfg(feature = "indexfilter")]
+            if let (Some(indexfilter), Some(terms_map)) =
+                (state.indexfilter.as_ref(), response.expansions_mut())
+            {
+                match indexfilter.filter(terms_map).await {
+                    Ok(()) => response.refresh(&expander, &queryparams)?,
+                    // the search itself still works, so serve the expansions unfiltered
+                    Err(e) => error!("Unable to check expansions against the index: {}", e),
+                }
+            }
+            if let (Some(feedback), Some(terms_map)) =
+                (state.feedback.as_ref(), response.expansions_mut())
+            {
+                if feedback.downrank(terms_map) {
+                    response.refresh(&expander, &queryparams)?;
+                }
+            }
+            if let Some(pagination) = pagination {
+                response.paginate(&expander, pagination);
+            }
+            if let (Some(cache), Some(cache_key)) = (state.cache.as_ref(), cache_key) {
+                // a degraded response is not cached, the skipped modules may well succeed next time
+                if response.warnings().is_empty() {
+                    cache.insert(cache_key, response.clone());
+                }
+            }
+            response
+        };
+        if let Some(audit) = state.audit.as_ref() {
+            let response_size = kweepeer::api::serialized_size(&response);
+            audit_request(audit, &expander, querystring, &params, response_size);
+        }
+        Ok(response)
     } else {
         Err(ApiError::MissingArgument("query"))
     }
 }
 
+/// Runs a query expansion within the guards of the *limits* section: it waits for its turn (`max_concurrent`), gives
+/// up after the `timeout` and is cancelled when it is no longer awaited. CPU-intensive expansions run on the
+/// dedicated thread pool (or a blocking thread), so they don't stall the worker threads.
+async fn guarded_expansion<T: Send + 'static>(
+    state: &AppState,
+    expander: &Arc<QueryExpander>,
+    cpu_intensive: bool,
+    work: impl FnOnce(&QueryExpander) -> Result<T, Error> + Send + 'static,
+) -> Result<T, ApiError> {
+    let expansion = async {
+        // if this future is dropped (the client disconnected or the timeout expired), expansions that are
+        // still running in the background are cancelled
+        let cancellation = Cancellation::new();
+        let _guard = cancellation.cancel_on_drop();
+        // waiting for a turn counts towards the timeout
+        let _permit = match state.expansions.as_ref() {
+            Some(expansions) => Some(
+                expansions
+                    .acquire()
+                    .await
+                    .map_err(|_| ApiError::InternalError("query expansion failed"))?,
+            ),
+            None => None,
+        };
+        if cpu_intensive {
+            let span = tracing::Span::current();
+            let work = move |expander: &QueryExpander| {
+                span.in_scope(|| cancellation.scope(|| work(expander)))
+            };
+            let expander = expander.clone();
+            if let Some(pool) = expander.thread_pool() {
+                // parallel searches within the modules stay in the dedicated pool too
+                let (sender, receiver) = tokio::sync::oneshot::channel();
+                let expander = expander.clone();
+                pool.spawn(move || {
+                    sender.send(work(&expander)).ok();
+                });
+                Ok(receiver
+                    .await
+                    .map_err(|_| ApiError::InternalError("query expansion failed"))??)
+            } else {
+                Ok(tokio::task::spawn_blocking(move || work(&expander))
+                    .await
+                    .map_err(|_| ApiError::InternalError("query expansion failed"))??)
+            }
+        } else {
+            Ok::<_, ApiError>(work(expander)?)
+        }
+    };
+    if let Some(timeout) = expander.config().limits().timeout() {
+        tokio::time::timeout(timeout, expansion)
+            .await
+            .map_err(|_| ApiError::Timeout("the query could not be expanded in time"))?
+    } else {
+        expansion.await
+    }
+}
+
+/// Middleware that makes the request ID available to the handlers (e.g. for error responses) and writes the access log
+async fn request_context(
+    State(accesslog): State<Arc<Option<AccessLog>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_id = request
+        .headers()
+        .get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    if let Some(accesslog) = accesslog.as_ref() {
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let begin = Instant::now();
+        let method = request.method().to_string();
+        let uri = request.uri().to_string();
+        let client = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip().to_string());
+        let response = REQUEST_ID
+            .scope(request_id.clone(), next.run(request))
+            .await;
+        accesslog.log(&AccessLogEntry {
+            timestamp,
+            request_id: Some(request_id.as_str()).filter(|id| !id.is_empty()),
+            client,
+            method: method.as_str(),
+            uri: uri.as_str(),
+            status: response.status().as_u16(),
+            duration_ms: begin.elapsed().as_secs_f64() * 1000.0,
+        });
+        response
+    } else {
+        REQUEST_ID.scope(request_id, next.run(request)).await
+    }
+}
+
+/// Appends an expansion request to the audit log
+fn audit_request(
+    audit: &AuditLog,
+    expander: &QueryExpander,
+    query: &str,
+    params: &HashMap<String, String>,
+    response_size: usize,
+) {
+    let queryparams: QueryParams = params.into();
+    let request_id = current_request_id();
+    audit.log(&AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+        request_id: request_id.as_deref(),
+        query,
+        params: params
+            .iter()
+            .filter(|(key, _)| key.as_str() != GlobalParam::Query.name())
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect(),
+        modules: expander
+            .selected_modules(&queryparams)
+            .map(|module| module.id())
+            .collect(),
+        response_size,
+    });
+}
+
+/// Middleware that counts all requests
+async fn count_requests(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    state.requests.fetch_add(1, Ordering::Relaxed);
+    next.run(request).await
+}
+
+/// Middleware that rejects requests while the service starts, until all modules are loaded
+async fn require_ready(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !state.progress.is_ready() {
+        return Err(ApiError::ServiceUnavailable(
+            "the service is starting, the modules are not loaded yet (see /readyz)",
+        ));
+    }
+    Ok(next.run(request).await)
+}
+
+/// Middleware that rejects requests from clients that exceed the configured rate limit
+async fn ratelimit(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(params): Query<HashMap<String, String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if let Some(ratelimiter) = state.ratelimiter.as_ref() {
+        let headers = request.headers();
+        let api_key = if ratelimiter.config().by() == RateLimitBy::Key {
+            params
+                .get(GlobalParam::ApiKey.name())
+                .map(|s| s.as_str())
+                .or_else(|| {
+                    headers
+                        .get("x-api-key")
+                        .and_then(|value| value.to_str().ok())
+                })
+                // only valid keys identify a client, otherwise every made-up key would get a fresh bucket
+                .filter(|api_key| {
+                    state
+                        .expander()
+                        .config()
+                        .auth()
+                        .is_some_and(|auth| auth.access(Some(*api_key)) != Access::InvalidKey)
+                })
+        } else {
+            None
+        };
+        let client = if let Some(api_key) = api_key {
+            format!("key:{}", api_key)
+        } else {
+            format!("ip:{}", client_address(&state, addr, headers))
+        };
+        if !ratelimiter.check(&client) {
+            return Err(ApiError::TooManyRequests("rate limit exceeded"));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// Returns the IP address of the client. This is taken from the X-Forwarded-For header if the rate limiting
+/// configuration trusts it, from the address the proxy appended last (the others come from the client).
+fn client_address(state: &AppState, addr: SocketAddr, headers: &HeaderMap) -> String {
+    state
+        .ratelimiter
+        .as_ref()
+        .filter(|ratelimiter| ratelimiter.config().trust_forwarded_for())
+        .and_then(|_| headers.get("x-forwarded-for"))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .map(|value| value.trim().to_owned())
+        .unwrap_or_else(|| addr.ip().to_string())
+}
+
+/// Takes the API key from the query parameters (removing it there) or from the X-Api-Key header
+fn get_api_key(params: &mut HashMap<String, String>, headers: &HeaderMap) -> Option<String> {
+    params.remove(GlobalParam::ApiKey.name()).or_else(|| {
+        headers
+            .get("x-api-key")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned())
+    })
+}
+
+/// Checks whether the API key grants access to the administrative endpoints. These are disabled if authentication is not configured.
+fn check_admin(expander: &QueryExpander, api_key: Option<&str>) -> Result<(), ApiError> {
+    match expander.config().auth() {
+        Some(auth) if auth.is_admin(api_key) => Ok(()),
+        Some(_) if api_key.is_some() => Err(ApiError::PermissionDenied(
+            "this API key does not grant administrative access",
+        )),
+        Some(_) => Err(ApiError::Unauthorized("an API key is required")),
+        None => Err(ApiError::PermissionDenied(
+            "administrative endpoints require authentication to be configured",
+        )),
+    }
+}
+
+/// Checks that a valid API key is passed if authentication is configured, for endpoints that store data
+fn check_key(expander: &QueryExpander, api_key: Option<&str>) -> Result<(), ApiError> {
+    match (expander.config().auth(), api_key) {
+        (None, _) => Ok(()),
+        (Some(auth), Some(api_key)) if auth.get_key(api_key).is_some() => Ok(()),
+        (Some(_), Some(_)) => Err(ApiError::Unauthorized("invalid API key")),
+        (Some(_), None) => Err(ApiError::Unauthorized("an API key is required")),
+    }
+}
+
+/// Checks the API key (if authentication is configured) and restricts the modules to use accordingly
+fn check_access(
+    expander: &QueryExpander,
+    api_key: Option<&str>,
+    params: &mut HashMap<String, String>,
+) -> Result<(), ApiError> {
+    if let Some(auth) = expander.config().auth() {
+        match auth.access(api_key) {
+            Access::Unrestricted => {}
+            Access::InvalidKey => return Err(ApiError::Unauthorized("invalid API key")),
+            Access::Restricted(permitted) => {
+                // term_overrides can include modules for specific terms, beyond the include parameter (an invalid
+                // value is reported when the query is expanded)
+                if let Some(overrides) = params
+                    .get(GlobalParam::TermOverrides.name())
+                    .and_then(|json| serde_json::from_str::<TermOverrides>(json).ok())
+                {
+                    if overrides.module_ids().any(|id| !permitted.contains(&id)) {
+                        return Err(ApiError::PermissionDenied(
+                            "access to one or more of the modules in term_overrides is not permitted",
+                        ));
+                    }
+                }
+                if let Some(include) = params.get(GlobalParam::Include.name()) {
+                    if include.split(',').any(|id| !permitted.contains(&id)) {
+                        return Err(ApiError::PermissionDenied(
+                            "access to one or more of the requested modules is not permitted",
+                        ));
+                    }
+                } else {
+                    // only include permitted modules that actually exist
+                    let permitted: Vec<_> = permitted
+                        .into_iter()
+                        .filter(|id| expander.get_module(id).is_some())
+                        .collect();
+                    params.insert(GlobalParam::Include.name().to_string(), permitted.join(","));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/export",
+    params(
+        ("format" = Option<String>, Query, description = "The output format: tsv (default, as read by the lookup module), skos (Turtle), solr (synonyms.txt), elasticsearch (index settings with a synonym_graph filter), jsonl, analiticcl or wordlist"),
+        ("keep_original" = Option<bool>, Query, description = "Include each term itself among its variants, so synonym rules don't replace the original term"),
+        ("include" = String, Query, description = "Comma separated list of modules to include (by ID)", allow_reserved),
+        ("exclude" = String, Query, description = "Comma separated list of modules to exclude (by ID)", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    request_body(content = String, description = "The terms to expand, one per line"),
+    responses(
+        (status = 200, body = String, description = "Returns the expansions of all terms that have any, in the requested format", content_type = "text/tab-separated-values"),
+        (status = 401, body = apidocs::ApiError, description = "Return when no valid API key is passed and access requires one", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when a module is requested that the API key does not grant access to", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the format or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when a requested module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when query expansion fails", content_type = "application/json"),
+    )
+)]
+/// Expand a list of terms and export the expansions as a lexicon, e.g. to freeze the output of a slow module into a file for the lookup module
+async fn export_entrypoint(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+    body: String,
+) -> Result<Response, ApiError> {
+    use axum::http::{header, HeaderValue};
+
+    let api_key = get_api_key(&mut params, &headers);
+    let expander = state.expander();
+    check_access(&expander, api_key.as_deref(), &mut params)?;
+    let format: convert::LexiconFormat = if let Some(format) = params.remove("format") {
+        format.parse()?
+    } else {
+        convert::LexiconFormat::Tsv
+    };
+    let keep_original = params
+        .remove("keep_original")
+        .is_some_and(|value| value == "true" || value == "1");
+    let queryparams: QueryParams = (&params).into();
+    // expanding many terms may take a while, don't block the async runtime
+    let span = tracing::Span::current();
+    let output = tokio::task::spawn_blocking(move || {
+        span.in_scope(|| {
+            let mut entries = export::export_expansions(&expander, body.lines(), &queryparams)?;
+            if keep_original {
+                export::keep_original(&mut entries);
+            }
+            let mut output = Vec::new();
+            convert::write_lexicon(format, &entries, &mut output)?;
+            Ok::<_, Error>(output)
+        })
+    })
+    .await
+    .map_err(|_| ApiError::InternalError("export failed"))??;
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.mimetype())
+        .header(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )
+        .body(output.into())
+        .map_err(|_| ApiError::InternalError("unable to construct response"))
+}
+
 #[utoipa::path(
     get,
     path = "/modules",
     params(
     ),
     responses(
-        (status = 200, description = "Returns all available modules",content(
-            (String = "application/json"),
-        )),
+        (status = 200, body = Vec<apidocs::ModuleInfo>, description = "Returns all available modules. While the service starts, returns the status of each module instead (see the ModuleProgress schema)", content_type = "application/json"),
     )
 )]
-async fn list_modules(state: State<Arc<QueryExpander>>) -> Result<ApiResponse, ApiError> {
+async fn list_modules(state: State<Arc<AppState>>) -> Result<ApiResponse, ApiError> {
     let mut modules = Vec::new();
-    for module in state.modules() {
-        modules.push(json!({"id": module.id(), "name": module.name(), "type": module.kind()}));
+    if !state.progress.is_ready() {
+        for module in state.progress.modules() {
+            modules.push(json!(module));
+        }
+        return Ok(ApiResponse::Modules(modules));
+    }
+    for module in state.expander().modules() {
+        modules.push(module_info(&state, module));
     }
     Ok(ApiResponse::Modules(modules))
 }
+
+#[utoipa::path(
+    get,
+    path = "/modules/{id}",
+    params(
+        ("id" = String, Path, description = "The ID of the module"),
+    ),
+    responses(
+        (status = 200, body = apidocs::ModuleInfo, description = "Returns the module", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Returns a single module, with the checksums of its data files
+async fn get_module(
+    Path(id): Path<String>,
+    state: State<Arc<AppState>>,
+) -> Result<ApiResponse, ApiError> {
+    let expander = state.expander();
+    let module = expander
+        .get_module(&id)
+        .ok_or(ApiError::NotFound("no such module"))?;
+    Ok(ApiResponse::Module(module_info(&state, module)))
+}
+
+/// Describes a module for the module listing
+fn module_info(state: &AppState, module: &dyn modules::Module) -> serde_json::Value {
+    // a reload in progress, while the current version keeps serving
+    let progress = state
+        .progress
+        .get(module.id())
+        .filter(|progress| progress.status() == LoadStatus::Loading);
+    json!({
+        "id": module.id(),
+        "name": module.name(),
+        "type": module.kind(),
+        "data_version": module.data_version(),
+        "files": module.file_checksums(),
+        "expansion_kind": module.expansion_kind(),
+        "language": module.common().language(),
+        "priority": module.common().priority(),
+        "parameters": module.parameters(),
+        "load_error": module.load_error(),
+        "status": if progress.is_some() {
+            LoadStatus::Loading
+        } else if module.load_error().is_some() {
+            LoadStatus::Failed
+        } else {
+            LoadStatus::Ready
+        },
+        "lines": progress.as_ref().and_then(|progress| progress.lines()),
+        "fraction": progress.as_ref().and_then(|progress| progress.fraction()),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    params(
+    ),
+    responses(
+        (status = 200, body = apidocs::Readiness, description = "Returned when the service has started: all modules are loaded", content_type = "application/json"),
+        (status = 503, body = apidocs::Readiness, description = "Returned while the service starts, with the progress of loading each module", content_type = "application/json"),
+    )
+)]
+/// Reports whether the service is ready to serve requests, and the status of each module while it starts
+async fn readiness(state: State<Arc<AppState>>) -> Response {
+    use axum::http::{header, HeaderValue, StatusCode};
+    use axum::response::IntoResponse;
+
+    let ready = state.progress.is_ready();
+    (
+        if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        [(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        )],
+        axum::Json(json!({
+            "ready": ready,
+            "modules": state.progress.modules(),
+        })),
+    )
+        .into_response()
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats",
+    params(
+    ),
+    responses(
+        (status = 200, body = apidocs::Stats, description = "Returns runtime statistics: uptime, request counts, per-module call counts and latencies, cache statistics and memory usage", content_type = "application/json"),
+    )
+)]
+async fn get_stats(state: State<Arc<AppState>>) -> Result<ApiResponse, ApiError> {
+    let expander = state.expander();
+    let mut modules = serde_json::Map::new();
+    for (id, stats) in expander.stats().modules() {
+        modules.insert(
+            id,
+            json!({
+                "calls": stats.calls(),
+                "errors": stats.errors(),
+                "avg_time_us": stats.avg_time_us(),
+                "max_time_us": stats.max_time_us(),
+            }),
+        );
+    }
+    let mut resources = serde_json::Map::new();
+    for module in expander.modules() {
+        resources.insert(
+            module.id().to_owned(),
+            json!({
+                "loaded": module.is_loaded(),
+                "load_time_ms": expander.stats().load_time(module.id()).map(|t| t.as_millis() as u64),
+                "entries": module.entries(),
+                "memory_estimate": module.memory_estimate(),
+            }),
+        );
+    }
+    let cache = state.cache.as_ref().map(|cache| {
+        json!({
+            "entries": cache.len(),
+            "hits": cache.hits(),
+            "misses": cache.misses(),
+        })
+    });
+    Ok(ApiResponse::Stats(json!({
+        "uptime": state.started.elapsed().as_secs(),
+        "requests": state.requests.load(Ordering::Relaxed),
+        "modules": modules,
+        "resources": resources,
+        "cache": cache,
+        "memory_usage": stats::memory_usage(),
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/modules",
+    params(
+        ("api_key" = Option<String>, Query, description = "API key with administrative access, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key with administrative access"),
+    ),
+    request_body(content = String, description = "The configuration of a single module in JSON or TOML, as it would appear in the configuration file, with an extra `type` key holding the module type (e.g. `lookup`)"),
+    responses(
+        (status = 200, body = Vec<apidocs::ModuleInfo>, description = "Returns the added module", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when no valid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when the API key does not grant administrative access", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the module configuration is invalid", content_type = "application/json"),
+        (status = 406, body = apidocs::ApiError, description = "Return when a module with the same ID already exists", content_type = "application/json"),
+        (status = 500, body = apidocs::ApiError, description = "Return when loading the module fails", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Load and add a module at runtime
+async fn add_module(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+    body: String,
+) -> Result<ApiResponse, ApiError> {
+    let api_key = get_api_key(&mut params, &headers);
+    check_admin(&state.expander(), api_key.as_deref())?;
+    let mut moduleconfig: serde_json::Value = if let Ok(value) = serde_json::from_str(&body) {
+        value
+    } else {
+        toml::from_str(&body).map_err(|e| {
+            Error::InvalidConfig(format!("Unable to parse module configuration: {}", e))
+        })?
+    };
+    let kind = moduleconfig
+        .as_object_mut()
+        .and_then(|moduleconfig| moduleconfig.remove("type"))
+        .ok_or(ApiError::MissingArgument("type"))?;
+    let kind = kind.as_str().ok_or(ApiError::MissingArgument("type"))?;
+    let mut module = modules::from_config(kind, moduleconfig)?;
+    if state.expander().get_module(module.id()).is_some() {
+        return Err(ApiError::NotAcceptable(
+            "a module with this ID already exists",
+        ));
+    }
+    // loading may take a while, don't block the async runtime
+    let (module, load_time) = tokio::task::spawn_blocking(move || {
+        let begin = Instant::now();
+        module.load().map(|_| (module, begin.elapsed()))
+    })
+    .await
+    .map_err(|_| ApiError::InternalError("module loading failed"))??;
+    let info = json!({
+        "id": module.id(),
+        "name": module.name(),
+        "type": module.kind(),
+        "data_version": module.data_version(),
+        "expansion_kind": module.expansion_kind(),
+        "language": module.common().language(),
+        "priority": module.common().priority(),
+        "parameters": module.parameters(),
+    });
+    let expander = state.expander();
+    expander.check_memory_budget(module.as_ref())?;
+    // the statistics are shared with the updated expander
+    expander.stats().record_load(module.id(), load_time);
+    state.update_expander(|expander| expander.register_module(module))?;
+    Ok(ApiResponse::Modules(vec![info]))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/modules/{id}",
+    params(
+        ("id" = String, Path, description = "The ID of the module to remove"),
+        ("api_key" = Option<String>, Query, description = "API key with administrative access, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key with administrative access"),
+    ),
+    responses(
+        (status = 200, body = Vec<apidocs::ModuleInfo>, description = "Returns all remaining modules", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when no valid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when the API key does not grant administrative access", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the module does not exist", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Remove a module at runtime
+async fn remove_module(
+    Path(id): Path<String>,
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<ApiResponse, ApiError> {
+    let api_key = get_api_key(&mut params, &headers);
+    check_admin(&state.expander(), api_key.as_deref())?;
+    state.update_expander(|expander| expander.remove_module(&id))?;
+    list_modules(state).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/modules/{id}/reload",
+    params(
+        ("id" = String, Path, description = "The ID of the module to reload"),
+        ("api_key" = Option<String>, Query, description = "API key with administrative access, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key with administrative access"),
+    ),
+    responses(
+        (status = 202, body = apidocs::Accepted, description = "The module is being reloaded in the background", content_type = "application/json"),
+        (status = 401, body = apidocs::ApiError, description = "Return when no valid API key is passed", content_type = "application/json"),
+        (status = 403, body = apidocs::ApiError, description = "Return when the API key does not grant administrative access", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when the module does not exist", content_type = "application/json"),
+        (status = 406, body = apidocs::ApiError, description = "Return when the module does not support reloading or is already being reloaded", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// Reload a module's data from disk in the background. The current version keeps serving until the new one is loaded and swapped in, the progress is reported in /modules.
+async fn reload_module(
+    Path(id): Path<String>,
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<ApiResponse, ApiError> {
+    let api_key = get_api_key(&mut params, &headers);
+    let expander = state.expander();
+    check_admin(&expander, api_key.as_deref())?;
+    let mut module = expander
+        .get_module(&id)
+        .ok_or(ApiError::NotFound("no such module"))?
+        .reinstantiate()
+        .ok_or(ApiError::NotAcceptable("module does not support reloading"))?;
+    if !state.progress.begin(&id) {
+        return Err(ApiError::NotAcceptable("module is already being reloaded"));
+    }
+    let state = state.0.clone();
+    let reloaded = id.clone();
+    tokio::spawn(async move {
+        let progress = state.progress.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let begin = Instant::now();
+            let id = module.id().to_owned();
+            progress
+                .scope(&id, || module.load())
+                .map(|_| (module, begin.elapsed()))
+        })
+        .await;
+        let error = match result {
+            Ok(Ok((module, load_time))) => {
+                // the statistics are shared with the updated expander
+                let expander = state.expander();
+                if let Err(e) = expander.check_memory_budget(module.as_ref()) {
+                    Some(format!("not swapping in the reloaded module: {}", e))
+                } else {
+                    expander.stats().record_load(&reloaded, load_time);
+                    let report = modules::resource_report(module.as_ref(), expander.stats());
+                    match state.update_expander(|expander| expander.replace_module(module)) {
+                        Ok(()) => {
+                            info!("Reloaded module {} ({})", reloaded, report);
+                            None
+                        }
+                        Err(e) => Some(format!("unable to swap in the reloaded module: {}", e)),
+                    }
+                }
+            }
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(e) => Some(e.to_string()),
+        };
+        let swapped = error.is_none();
+        finish_reload(&state, &reloaded, error);
+        if swapped {
+            tokio::task::spawn_blocking(move || warm_up(&state))
+                .await
+                .ok();
+        }
+    });
+    Ok(ApiResponse::Accepted(format!(
+        "Reloading module {} in the background",
+        id
+    )))
+}
+
+/// Records the end of a reload in the load progress. A module that could not be reloaded keeps serving its current
+/// version, unless that was unavailable as well.
+fn finish_reload(state: &AppState, id: &str, error: Option<String>) {
+    match error {
+        None => state.progress.set_status(id, LoadStatus::Ready),
+        Some(e) => {
+            error!("Failure whilst reloading module {}: {}", id, e);
+            if state
+                .expander()
+                .get_module(id)
+                .is_some_and(|module| module.load_error().is_none())
+            {
+                state.progress.set_status(id, LoadStatus::Ready)
+            } else {
+                state.progress.fail(id, e)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sru")]
+#[utoipa::path(
+    get,
+    path = "/sru",
+    params(
+        ("operation" = Option<String>, Query, description = "The SRU operation: `explain` or `searchRetrieve` (default if a query is passed)"),
+        ("query" = Option<String>, Query, description = "A query in CQL syntax, its search terms are expanded before it is forwarded", allow_reserved),
+        ("include" = Option<String>, Query, description = "Comma separated list of modules to include (by ID), not forwarded", allow_reserved),
+        ("exclude" = Option<String>, Query, description = "Comma separated list of modules to exclude (by ID), not forwarded", allow_reserved),
+        ("api_key" = Option<String>, Query, description = "API key, may also be passed via the X-Api-Key header"),
+        ("X-Api-Key" = Option<String>, Header, description = "API key"),
+    ),
+    responses(
+        (status = 200, description = "The response of the SRU backend, passed through unaltered",content(
+            (String = "application/xml"),
+        )),
+        (status = 401, body = apidocs::ApiError, description = "Return when an invalid API key is passed", content_type = "application/json"),
+        (status = 400, body = apidocs::ApiError, description = "Return when the query is missing, exceeds the configured limits or a parameter is invalid", content_type = "application/json"),
+        (status = 404, body = apidocs::ApiError, description = "Return when SRU is not configured or a requested module does not exist", content_type = "application/json"),
+        (status = 502, body = apidocs::ApiError, description = "Return when the SRU backend can not be reached", content_type = "application/json"),
+        (status = 504, body = apidocs::ApiError, description = "Return when the SRU backend does not respond in time", content_type = "application/json"),
+        (status = 429, body = apidocs::ApiError, description = "Return when the client exceeds the rate limit", content_type = "application/json"),
+    )
+)]
+/// SRU facade: expands the CQL query of a searchRetrieve request and forwards the request to the configured SRU backend.
+/// All other operations (e.g. explain) are forwarded as-is. Any other parameters (except those meant for kweepeer itself) are passed on to the backend.
+async fn sru_entrypoint(
+    Query(mut params): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    state: State<Arc<AppState>>,
+) -> Result<Response, ApiError> {
+    use axum::body::Body;
+    use axum::http::{header, HeaderValue};
+    use kweepeer::cql::CqlQuery;
+
+    let api_key = get_api_key(&mut params, &headers);
+    let expander = state.expander();
+    let (Some(sru), Some(client)) = (expander.config().sru(), state.sru_client.as_ref()) else {
+        return Err(ApiError::NotFound("SRU is not configured"));
+    };
+
+    // parameters for kweepeer itself (module selection and module parameters) are not forwarded
+    let (mut expansion_params, mut params): (HashMap<_, _>, HashMap<_, _>) =
+        params.into_iter().partition(|(key, _)| {
+            key == GlobalParam::Include.name()
+                || key == GlobalParam::Exclude.name()
+                || (key.contains('.') && !key.starts_with("x-"))
+        });
+
+    let operation =
+        params
+            .get("operation")
+            .map(|s| s.as_str())
+            .unwrap_or(if params.contains_key("query") {
+                "searchRetrieve"
+            } else {
+                "explain"
+            });
+    if operation == "searchRetrieve" {
+        let querystring = params
+            .get("query")
+            .ok_or(ApiError::MissingArgument("query"))?
+            .clone();
+        check_access(&expander, api_key.as_deref(), &mut expansion_params)?;
+        expander.config().limits().check_query(&querystring)?;
+        expansion_params.insert(GlobalParam::Query.name().to_string(), querystring.clone());
+        let queryparams: QueryParams = (&expansion_params).into();
+        let cpu_intensive = expander
+            .selected_modules(&queryparams)
+            .any(|module| module.is_cpu_intensive());
+        let cqlquery = querystring.clone();
+        let terms_map = guarded_expansion(&state, &expander, cpu_intensive, move |expander| {
+            let mut terms_map = TermExpansions::new();
+            expander.expand_query_into(
+                &mut terms_map,
+                &CqlQuery::parse(&cqlquery).terms(),
+                &queryparams,
+            )?;
+            Ok(terms_map)
+        })
+        .await?;
+        params.insert(
+            "query".to_string(),
+            CqlQuery::parse(&querystring).resolve(&terms_map),
+        );
+    }
+
+    let backend_response = client
+        .get(sru.backend())
+        .query(&params)
+        .send()
+        .await
+        .map_err(|e| {
+            error!("Unable to reach SRU backend: {}", e);
+            if e.is_timeout() {
+                ApiError::Timeout("the SRU backend did not respond in time")
+            } else {
+                ApiError::BadGateway("unable to reach the SRU backend")
+            }
+        })?;
+    let mut response = Response::builder()
+        .status(backend_response.status())
+        .header(
+            header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("*"),
+        );
+    if let Some(content_type) = backend_response.headers().get(header::CONTENT_TYPE) {
+        response = response.header(header::CONTENT_TYPE, content_type.clone());
+    }
+    let body = backend_response.bytes().await.map_err(|e| {
+        error!("Unable to read response from SRU backend: {}", e);
+        ApiError::BadGateway("unable to read the response from the SRU backend")
+    })?;
+    if let (Some(audit), Some(querystring)) = (
+        state.audit.as_ref(),
+        expansion_params.get(GlobalParam::Query.name()),
+    ) {
+        audit_request(audit, &expander, querystring, &expansion_params, body.len());
+    }
+    response
+        .body(Body::from(body))
+        .map_err(|_| ApiError::InternalError("unable to construct response"))
+}
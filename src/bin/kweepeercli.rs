@@ -1,13 +1,19 @@
 use clap::Parser;
-use std::io::{stdin, BufRead};
+use rayon::prelude::*;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader};
 use std::path::PathBuf;
 use tracing::info;
 
-use toml;
-
-use kweepeer::api::ApiResponse;
+use kweepeer::api::{to_pretty_json, ApiResponse};
+use kweepeer::globalparams::GlobalParam;
 use kweepeer::*;
 
+/// Number of queries that are read and expanded (in parallel) at once
+const BATCH_SIZE: usize = 1000;
+
 #[derive(Parser, Debug, Clone)]
 struct Args {
     #[arg(long, default_value_t = false, help = "Debug mode")]
@@ -15,52 +21,345 @@ struct Args {
 
     #[arg(long = "config", short, default_value = "config.toml")]
     config_path: PathBuf,
+
+    #[arg(
+        long = "param",
+        short,
+        value_name = "MODULE.KEY=VALUE",
+        help = "Pass a parameter to a module (e.g. fst.distance=2), or a global parameter like include=fst without a module prefix. May be specified multiple times."
+    )]
+    params: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "JSON",
+        help = "Pass parameters as a JSON object, either with keys like \"fst.distance\" or with module IDs mapping to objects"
+    )]
+    params_json: Option<String>,
+
+    #[arg(
+        long = "input",
+        short,
+        value_name = "FILE",
+        help = "Read queries (one per line) from this file instead of standard input. May be specified multiple times, use - for standard input."
+    )]
+    inputs: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        short,
+        default_value_t = 1,
+        help = "Number of queries to expand in parallel, the output retains the input order"
+    )]
+    jobs: usize,
+
+    #[arg(
+        long,
+        short = 'I',
+        default_value_t = false,
+        help = "Interactive mode: enter queries at a prompt, select/deselect the expansions and print the resolved query"
+    )]
+    interactive: bool,
+
+    #[arg(
+        long,
+        value_name = "MODULE_IDS",
+        help = "Only use the modules with these IDs (comma separated)"
+    )]
+    include: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "MODULE_IDS",
+        help = "Do not use the modules with these IDs (comma separated)"
+    )]
+    exclude: Option<String>,
+
+    #[arg(
+        value_name = "QUERY",
+        conflicts_with_all = ["inputs", "interactive"],
+        help = "Expand this single query, rather than reading queries from input"
+    )]
+    query: Option<String>,
 }
 
-fn main() -> Result<(), kweepeer::Error> {
+fn main() {
     let args = Args::parse();
 
-    if args.debug {
-        tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::DEBUG)
-            .init();
+    // warnings (such as slow queries) and errors are always logged, to stderr as stdout holds the output
+    tracing_subscriber::fmt()
+        .with_max_level(if args.debug {
+            tracing::Level::DEBUG
+        } else {
+            tracing::Level::WARN
+        })
+        .with_writer(std::io::stderr)
+        .init();
+
+    if let Err(e) = run(&args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
     }
+}
 
+fn run(args: &Args) -> Result<(), kweepeer::Error> {
     info!("Loading configuration from {}", &args.config_path.display());
-    let toml_string =
-        std::fs::read_to_string(&args.config_path).expect("Unable to read configuration file");
-    let config: Config = toml::from_str(&toml_string).expect("Unable to parse configuration file");
+    let config = Config::from_file(&args.config_path)?;
 
     let mut state = QueryExpander::new().with_config(config);
 
     // Load all the modules
-    state.load().expect("Failure whilst loading modules");
-
-    info!("Reading queries from standard input");
-
-    let stdin = stdin();
-    for line in stdin.lock().lines() {
-        if let Ok(querystring) = line {
-            let mut terms_map = TermExpansions::new();
-            let (terms, query_template) = Term::extract_from_query(&querystring);
-            let params = QueryParams::default(); //TODO: parse parameters from args
-            state.expand_query_into(&mut terms_map, &terms, &params)?;
-            let resolved_template =
-                state.resolve_query_template(query_template.as_str(), &terms_map)?;
-            let response = ApiResponse::new_queryexpansion(
-                terms_map,
-                &querystring,
-                query_template,
-                resolved_template,
-            );
-            match serde_json::to_string_pretty(&response) {
-                Ok(s) => println!("{}", s),
-                Err(e) => {
-                    eprintln!("{}", e);
-                    std::process::exit(2);
+    state.load()?;
+
+    let mut params = QueryParams::new();
+    for param in args.params.iter() {
+        params.insert_from_str(param)?;
+    }
+    if let Some(json) = args.params_json.as_ref() {
+        params.insert_from_json(json)?;
+    }
+    if let Some(include) = args.include.as_ref() {
+        params.insert_global(GlobalParam::Include, include.as_str().into());
+    }
+    if let Some(exclude) = args.exclude.as_ref() {
+        params.insert_global(GlobalParam::Exclude, exclude.as_str().into());
+    }
+    params.check_globals()?;
+
+    if let Some(querystring) = args.query.as_ref() {
+        print_response(expand(&state, querystring, &params)?);
+        return Ok(());
+    }
+
+    if args.interactive {
+        return interactive(&state, &params);
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.max(1))
+        .build()
+        .expect("Unable to create thread pool");
+
+    let inputs = if args.inputs.is_empty() {
+        vec![PathBuf::from("-")]
+    } else {
+        args.inputs.clone()
+    };
+    for input in inputs {
+        let reader: Box<dyn BufRead> = if input.as_os_str() == "-" {
+            info!("Reading queries from standard input");
+            Box::new(stdin().lock())
+        } else {
+            info!("Reading queries from {}", input.display());
+            Box::new(BufReader::new(File::open(&input)?))
+        };
+        let mut lines = reader.lines().peekable();
+        while lines.peek().is_some() {
+            // a read error (or invalid UTF-8) is reported rather than taken for the end of the input
+            let batch = lines
+                .by_ref()
+                .take(BATCH_SIZE)
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(|e| {
+                    kweepeer::Error::LoadError(if input.as_os_str() == "-" {
+                        format!("Unable to read queries from standard input: {}", e)
+                    } else {
+                        format!("Unable to read queries from {}: {}", input.display(), e)
+                    })
+                })?;
+            let responses: Vec<_> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|querystring| expand(&state, querystring, &params))
+                    .collect()
+            });
+            for response in responses {
+                print_response(response?);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn print_response(response: ApiResponse) {
+    match to_pretty_json(&response) {
+        Ok(s) => println!("{}", s),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Expand a single query
+fn expand(
+    state: &QueryExpander,
+    querystring: &str,
+    params: &QueryParams,
+) -> Result<ApiResponse, kweepeer::Error> {
+    let params = params
+        .clone()
+        .with("", GlobalParam::Query.name(), querystring.into());
+    ApiResponse::expand(state, querystring, &params)
+}
+
+/// An expansion as presented in interactive mode, numbered by its position
+struct Choice {
+    term: String,
+    /// Index of the term expansion (i.e. the module) for this term
+    termexpansion: usize,
+    /// Index of the expansion in the term expansion
+    index: usize,
+    selected: bool,
+}
+
+/// Interactive mode (REPL): expands queries entered by the user and lets the user toggle the expansions
+fn interactive(state: &QueryExpander, params: &QueryParams) -> Result<(), kweepeer::Error> {
+    let mut editor = DefaultEditor::new()
+        .map_err(|e| kweepeer::Error::LoadError(format!("Unable to initialise editor: {}", e)))?;
+    eprintln!("Enter a query to expand it. Enter numbers (space separated) to toggle expansions, ':a' to select all, ':n' to select none, ':q' to quit.");
+    let mut terms_map = TermExpansions::new();
+    let mut query_template = String::new();
+    let mut choices: Vec<Choice> = Vec::new();
+    loop {
+        let line = match editor.readline("kweepeer> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                return Err(kweepeer::Error::QueryExpandError(format!(
+                    "Unable to read input: {}",
+                    e
+                )))
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        let toggles: Option<Vec<usize>> = line
+            .split([' ', ','])
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse().ok())
+            .collect();
+        match (line, toggles) {
+            (":q", _) => break,
+            (":a", _) | (":n", _) => {
+                for choice in choices.iter_mut() {
+                    choice.selected = line == ":a";
+                }
+            }
+            (_, Some(toggles)) if !choices.is_empty() => {
+                for n in toggles {
+                    match choices.get_mut(n.wrapping_sub(1)) {
+                        Some(choice) => choice.selected = !choice.selected,
+                        None => eprintln!("No such expansion: {}", n),
+                    }
+                }
+            }
+            (querystring, _) => {
+                let (terms, template) = Term::extract_from_query(querystring);
+                let params = params
+                    .clone()
+                    .with("", GlobalParam::Query.name(), querystring.into());
+                terms_map.clear();
+                let context = QueryContext::from_query(querystring);
+                match state.expand_query_in_context(&mut terms_map, &terms, &context, &params) {
+                    Ok(warnings) => {
+                        for warning in warnings.iter() {
+                            eprintln!("Skipped module {}: {}", warning.module(), warning.message());
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        continue;
+                    }
+                }
+                query_template = template;
+                choices.clear();
+                for term in terms.iter() {
+                    if choices.iter().any(|choice| choice.term == term.as_str()) {
+                        // term occurs multiple times in the query
+                        continue;
+                    }
+                    for (i, termexpansion) in terms_map
+                        .get(term.as_str())
+                        .into_iter()
+                        .flatten()
+                        .enumerate()
+                    {
+                        for index in 0..termexpansion.len() {
+                            choices.push(Choice {
+                                term: term.as_str().to_owned(),
+                                termexpansion: i,
+                                index,
+                                selected: true,
+                            });
+                        }
+                    }
                 }
             }
         }
+        print_choices(&choices, &terms_map);
+        let selected = select_expansions(&choices, &terms_map);
+        println!(
+            "{}",
+            state.resolve_query_template(&query_template, &selected)?
+        );
     }
     Ok(())
 }
+
+fn print_choices(choices: &[Choice], terms_map: &TermExpansions) {
+    let mut prevterm = None;
+    for (i, choice) in choices.iter().enumerate() {
+        if prevterm != Some(choice.term.as_str()) {
+            println!("{}:", choice.term);
+            prevterm = Some(choice.term.as_str());
+        }
+        let termexpansion = &terms_map[choice.term.as_str()][choice.termexpansion];
+        println!(
+            "  [{}] {:>3}. {} ({})",
+            if choice.selected { "x" } else { " " },
+            i + 1,
+            termexpansion.expansions()[choice.index],
+            termexpansion.source_id().unwrap_or("?"),
+        );
+    }
+}
+
+/// Returns the term expansions restricted to the selected choices
+fn select_expansions(choices: &[Choice], terms_map: &TermExpansions) -> TermExpansions {
+    let mut selected = TermExpansions::new();
+    for (term, termexpansions) in terms_map.iter() {
+        let termexpansions = termexpansions
+            .iter()
+            .enumerate()
+            .map(|(i, termexpansion)| {
+                let indices: Vec<usize> = choices
+                    .iter()
+                    .filter(|choice| {
+                        choice.selected && choice.term == *term && choice.termexpansion == i
+                    })
+                    .map(|choice| choice.index)
+                    .collect();
+                termexpansion
+                    .clone()
+                    .with_expansions(
+                        indices
+                            .iter()
+                            .map(|j| termexpansion.expansions()[*j].clone())
+                            .collect(),
+                    )
+                    .with_scores(
+                        indices
+                            .iter()
+                            .filter_map(|j| termexpansion.scores().get(*j).copied())
+                            .collect(),
+                    )
+            })
+            .collect();
+        selected.insert(term.clone(), termexpansions);
+    }
+    selected
+}
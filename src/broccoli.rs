@@ -0,0 +1,120 @@
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::api::ApiResponse;
+
+/// Configuration of the Broccoli-compatible response mode, which serves the expansions in the form Broccoli
+/// (the TextAnnoViz backend) expects from a search wrapper service. Each option holds the name of a field in the
+/// response, the field is omitted if the name is empty.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct BroccoliConfig {
+    /// Field holding the full expanded query
+    query_field: String,
+
+    /// Field holding the input query
+    original_query_field: String,
+
+    /// Field holding an object mapping each term to its variants (over all modules)
+    terms_field: String,
+
+    /// Field holding the query template, in which the terms are marked with {{ and }}
+    template_field: String,
+}
+
+impl Default for BroccoliConfig {
+    fn default() -> Self {
+        Self {
+            query_field: "query".into(),
+            original_query_field: "originalQuery".into(),
+            terms_field: "terms".into(),
+            template_field: String::new(),
+        }
+    }
+}
+
+impl BroccoliConfig {
+    /// Renders a query expansion response with the configured field names.
+    /// Other responses are rendered as usual.
+    pub fn render(&self, response: &ApiResponse) -> Value {
+        if let ApiResponse::QueryExpansion {
+            terms,
+            original_query,
+            query_expansion_template,
+            query,
+            ..
+        } = response
+        {
+            let mut object = Map::new();
+            if !self.query_field.is_empty() {
+                object.insert(self.query_field.clone(), query.as_str().into());
+            }
+            if !self.original_query_field.is_empty() {
+                object.insert(
+                    self.original_query_field.clone(),
+                    original_query.as_str().into(),
+                );
+            }
+            if !self.terms_field.is_empty() {
+                let mut variants = Map::new();
+                for (term, termexpansions) in terms.iter() {
+                    let mut termvariants: Vec<&str> = Vec::new();
+                    for variant in termexpansions.iter().flat_map(|te| te.iter()) {
+                        if !termvariants.contains(&variant) {
+                            termvariants.push(variant);
+                        }
+                    }
+                    variants.insert(term.clone(), termvariants.into());
+                }
+                object.insert(self.terms_field.clone(), variants.into());
+            }
+            if !self.template_field.is_empty() {
+                object.insert(
+                    self.template_field.clone(),
+                    query_expansion_template.as_str().into(),
+                );
+            }
+            Value::Object(object)
+        } else {
+            serde_json::to_value(response).unwrap_or_default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TermExpansion, TermExpansions};
+
+    #[test]
+    pub fn test001_broccoli_render() {
+        let mut terms = TermExpansions::new();
+        terms.insert(
+            "wine".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["wijn".into()]),
+                TermExpansion::default().with_expansions(vec!["wijn".into(), "vin".into()]),
+            ],
+        );
+        let response =
+            ApiResponse::new_queryexpansion(terms, "wine", "{{wine}}", "(\"wijn\" OR \"vin\")");
+        let value = BroccoliConfig::default().render(&response);
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "query": "(\"wijn\" OR \"vin\")",
+                "originalQuery": "wine",
+                "terms": { "wine": ["wijn", "vin"] },
+            })
+        );
+
+        let config: BroccoliConfig = toml::from_str(
+            "query_field = \"expandedQuery\"\nterms_field = \"\"\ntemplate_field = \"template\"",
+        )
+        .expect("valid configuration");
+        let value = config.render(&response);
+        assert_eq!(value["expandedQuery"], "(\"wijn\" OR \"vin\")");
+        assert_eq!(value["template"], "{{wine}}");
+        assert!(value.get("terms").is_none());
+    }
+}
@@ -0,0 +1,225 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Configuration for the in-process response cache
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Time-to-live of cache entries, in seconds
+    ttl: u64,
+
+    /// Maximum number of entries in the cache
+    capacity: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 300,
+            capacity: 10000,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn new(ttl: u64, capacity: usize) -> Self {
+        Self { ttl, capacity }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// A simple in-process cache with a time-to-live, holding complete responses keyed by normalised query and parameters.
+pub struct ResponseCache<T> {
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, (Instant, T)>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<T: Clone> ResponseCache<T> {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Computes a cache key from a query and its parameters.
+    /// Whitespace in the query is normalised and parameters are sorted so equivalent requests share a key.
+    /// Parameters listed in `ignore` do not contribute to the key.
+    pub fn key<'a>(
+        query: &str,
+        params: impl Iterator<Item = (&'a str, &'a str)>,
+        ignore: &[&str],
+    ) -> String {
+        let mut params: Vec<_> = params.filter(|(k, _)| !ignore.contains(k)).collect();
+        params.sort();
+        let mut key = query.split_whitespace().collect::<Vec<_>>().join(" ");
+        for (k, v) in params {
+            key.push('\u{1f}');
+            key += k;
+            key.push('=');
+            key += v;
+        }
+        key
+    }
+
+    /// Retrieve an entry from the cache, if it exists and has not expired
+    pub fn get(&self, key: &str) -> Option<T> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &str, now: Instant) -> Option<T> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(key) {
+            Some((inserted, value)) if now.duration_since(*inserted) < self.config.ttl() => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(value.clone())
+            }
+            _ => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Add an entry to the cache, evicts expired (or otherwise the oldest) entries if the cache is full (see
+    /// [`Self::purge()`])
+    pub fn insert(&self, key: String, value: T) {
+        self.insert_at(key, value, Instant::now())
+    }
+
+    fn insert_at(&self, key: String, value: T, now: Instant) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() >= self.config.capacity && !entries.contains_key(&key) {
+            self.purge(&mut entries, now);
+        }
+        entries.insert(key, (now, value));
+    }
+
+    /// Makes room for new entries: evicts the expired entries and, if that is not enough, the oldest ones. Frees half
+    /// of the cache at once, so a stream of new entries only purges once every `capacity / 2` insertions.
+    fn purge(&self, entries: &mut HashMap<String, (Instant, T)>, now: Instant) {
+        let ttl = self.config.ttl();
+        entries.retain(|_, (inserted, _)| now.saturating_duration_since(*inserted) < ttl);
+        let keep = self.config.capacity / 2;
+        if keep == 0 {
+            entries.clear();
+        } else if entries.len() > keep {
+            let mut keys: Vec<(Instant, String)> = entries
+                .iter()
+                .map(|(key, (inserted, _))| (*inserted, key.clone()))
+                .collect();
+            let evict = keys.len() - keep;
+            keys.select_nth_unstable_by_key(evict - 1, |(inserted, _)| *inserted);
+            for (_, key) in &keys[..evict] {
+                entries.remove(key);
+            }
+        }
+    }
+
+    /// Remove all entries, this must be called whenever the underlying modules change
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// Number of entries currently in the cache (including expired ones not yet evicted)
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of cache hits so far
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of cache misses so far
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_cache_key() {
+        let params = [("include", "fst"), ("q", "foo  bar"), ("fst.distance", "1")];
+        let params2 = [("fst.distance", "1"), ("include", "fst")];
+        assert_eq!(
+            ResponseCache::<()>::key(" foo  bar", params.into_iter(), &["q"]),
+            ResponseCache::<()>::key("foo bar", params2.into_iter(), &[]),
+        );
+    }
+
+    #[test]
+    pub fn test002_cache_expiry() {
+        let cache = ResponseCache::new(CacheConfig::new(10, 10));
+        let now = Instant::now();
+        cache.insert_at("a".into(), 1, now);
+        assert_eq!(cache.get_at("a", now + Duration::from_secs(5)), Some(1));
+        assert_eq!(cache.get_at("a", now + Duration::from_secs(11)), None);
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 1);
+    }
+
+    #[test]
+    pub fn test003_cache_capacity() {
+        let cache = ResponseCache::new(CacheConfig::new(10, 2));
+        let now = Instant::now();
+        cache.insert_at("a".into(), 1, now);
+        cache.insert_at("b".into(), 2, now + Duration::from_secs(1));
+        cache.insert_at("c".into(), 3, now + Duration::from_secs(2));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get_at("a", now + Duration::from_secs(2)), None);
+        assert_eq!(cache.get_at("c", now + Duration::from_secs(2)), Some(3));
+    }
+
+    #[test]
+    pub fn test004_cache_purge() {
+        let cache = std::sync::Arc::new(ResponseCache::new(CacheConfig::new(10, 4)));
+        let now = Instant::now();
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.insert_at(key.into(), i, now + Duration::from_secs(i as u64));
+        }
+        // a full cache evicts half its entries at once, the oldest first
+        cache.insert_at("e".into(), 4, now + Duration::from_secs(4));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get_at("b", now + Duration::from_secs(4)), None);
+        assert_eq!(cache.get_at("c", now + Duration::from_secs(4)), Some(2));
+        cache.insert_at("f".into(), 5, now + Duration::from_secs(5));
+        assert_eq!(cache.len(), 4);
+        // a panic while the lock is held does not break the cache
+        let poisoner = cache.clone();
+        assert!(std::thread::spawn(move || {
+            let _entries = poisoner.entries.lock();
+            panic!("poisoning the lock");
+        })
+        .join()
+        .is_err());
+        assert_eq!(cache.get_at("f", now + Duration::from_secs(5)), Some(5));
+    }
+}
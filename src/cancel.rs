@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::Error;
+
+thread_local! {
+    /// The cancellation of the work currently running on this thread, if any
+    static CURRENT: RefCell<Option<Cancellation>> = const { RefCell::new(None) };
+}
+
+/// Signals that the result of some work is no longer needed (e.g. because the client that requested it disconnected),
+/// so it can stop early. Cancellation is cooperative: the work has to check for it in between units of work, see [`check()`].
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns a guard that cancels when it is dropped. Holding it in a future ties the cancellation to that future:
+    /// if the future is abandoned (e.g. a web request whose client disconnected), the work is cancelled.
+    pub fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop(self.clone())
+    }
+
+    /// Runs a function with this cancellation as the current one for this thread, so [`check()`] can see it
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<Cancellation>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+        let _restore = Restore(CURRENT.with(|current| current.borrow_mut().replace(self.clone())));
+        f()
+    }
+}
+
+/// Cancels when dropped, see [`Cancellation::cancel_on_drop()`]
+pub struct CancelOnDrop(Cancellation);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Returns the cancellation of the work running on this thread, or one that never cancels if there is none. Pass it
+/// on to other threads (see [`Cancellation::scope()`]) when the work is split up.
+pub fn current() -> Cancellation {
+    CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+/// Returns an error if the work running on this thread has been cancelled. Long-running work (such as modules
+/// that do expensive searches per term) should call this in between units of work.
+pub fn check() -> Result<(), Error> {
+    CURRENT.with(|current| match current.borrow().as_ref() {
+        Some(cancellation) if cancellation.is_cancelled() => {
+            Err(Error::Cancelled("Query expansion was cancelled".into()))
+        }
+        _ => Ok(()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_cancel_scope() {
+        let cancellation = Cancellation::new();
+        assert!(check().is_ok());
+        {
+            let _guard = cancellation.cancel_on_drop();
+            assert!(cancellation.scope(check).is_ok());
+        }
+        assert!(cancellation.is_cancelled());
+        assert!(matches!(
+            cancellation.scope(check),
+            Err(Error::Cancelled(..))
+        ));
+        // outside of the scope, the cancellation no longer applies
+        assert!(check().is_ok());
+    }
+}
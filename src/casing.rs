@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+
+use crate::TermExpansion;
+
+/// The casing pattern of a term
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Casing {
+    /// All cased characters are lowercase (or there are none)
+    Lower,
+    /// All cased characters are uppercase, e.g. `KAAS`
+    Upper,
+    /// The first character is uppercase, the others lowercase, e.g. `Kaas`
+    Title,
+    /// Any other pattern, e.g. `iPhone`
+    Mixed,
+}
+
+impl Casing {
+    /// Determines the casing pattern of a term
+    pub fn of(term: &str) -> Self {
+        let mut chars = term
+            .chars()
+            .filter(|c| c.is_lowercase() || c.is_uppercase());
+        let Some(first) = chars.next() else {
+            return Self::Lower;
+        };
+        let (mut lower, mut upper) = (0, 0);
+        for c in chars {
+            if c.is_uppercase() {
+                upper += 1;
+            } else {
+                lower += 1;
+            }
+        }
+        match (first.is_uppercase(), lower, upper) {
+            (false, _, 0) => Self::Lower,
+            (true, 0, _) if upper > 0 => Self::Upper,
+            (true, _, 0) => Self::Title,
+            _ => Self::Mixed,
+        }
+    }
+
+    /// Applies this casing pattern to a string. Lowercase and mixed patterns leave the string as it is,
+    /// as lexicon entries may be cased deliberately (e.g. names).
+    pub fn apply(&self, s: &str) -> String {
+        match self {
+            Self::Lower | Self::Mixed => s.to_owned(),
+            Self::Upper => s.to_uppercase(),
+            Self::Title => {
+                let mut chars = s.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Applies the casing pattern of the term to all of its expansions, so a title-cased query gets title-cased variants.
+/// Expansions that become identical are only kept once.
+pub fn preserve_case(term: &str, termexpansion: &mut TermExpansion) {
+    let casing = Casing::of(term);
+    if matches!(casing, Casing::Lower | Casing::Mixed) {
+        return;
+    }
+    for expansion in termexpansion.expansions_mut().iter_mut() {
+        *expansion = casing.apply(expansion);
+    }
+    let mut seen = HashSet::new();
+    termexpansion.retain(|expansion| seen.insert(expansion.to_owned()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_casing_of() {
+        assert_eq!(Casing::of("kaas"), Casing::Lower);
+        assert_eq!(Casing::of("Kaas"), Casing::Title);
+        assert_eq!(Casing::of("KAAS"), Casing::Upper);
+        assert_eq!(Casing::of("iPhone"), Casing::Mixed);
+        assert_eq!(Casing::of("K"), Casing::Title);
+        assert_eq!(Casing::of("'s-Hertogenbosch"), Casing::Mixed);
+        assert_eq!(Casing::of("123"), Casing::Lower);
+    }
+
+    #[test]
+    pub fn test002_casing_preserve() {
+        let mut termexpansion = TermExpansion::default()
+            .with_expansions(vec!["kaes".into(), "Kaes".into(), "ijs".into()])
+            .with_scores(vec![1.0, 0.5, 0.2]);
+        preserve_case("Kaas", &mut termexpansion);
+        assert_eq!(termexpansion.expansions(), &vec!["Kaes", "Ijs"]);
+        assert_eq!(termexpansion.scores(), &vec![1.0, 0.2]);
+        preserve_case("KAAS", &mut termexpansion);
+        assert_eq!(termexpansion.expansions(), &vec!["KAES", "IJS"]);
+    }
+}
@@ -0,0 +1,429 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::globalparams::GlobalParam;
+use crate::grouping::Grouping;
+use crate::highlight::Highlights;
+use crate::render::SearchEngine;
+use crate::reproducibility::Reproducibility;
+use crate::warnings::ModuleWarning;
+use crate::{Error, TermStatus};
+
+/// A client for the HTTP API of a running kweepeer instance
+#[derive(Debug, Clone)]
+pub struct Client {
+    base_url: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+}
+
+/// A query expansion request
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExpandRequest {
+    /// The query to expand
+    pub query: String,
+    /// Modules to include (by ID), all modules are used if empty
+    pub include: Vec<String>,
+    /// Modules to exclude (by ID)
+    pub exclude: Vec<String>,
+    /// Module parameters as `module.key` (or just `key` for global parameters) and value
+    pub params: Vec<(String, String)>,
+}
+
+impl ExpandRequest {
+    pub fn new(query: impl Into<String>) -> Self {
+        Self {
+            query: query.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_include(mut self, id: impl Into<String>) -> Self {
+        self.include.push(id.into());
+        self
+    }
+
+    pub fn with_exclude(mut self, id: impl Into<String>) -> Self {
+        self.exclude.push(id.into());
+        self
+    }
+
+    /// Sets a global parameter
+    pub fn with_global(self, param: GlobalParam, value: impl Into<String>) -> Self {
+        self.with_param("", param.name(), value)
+    }
+
+    /// Sets a parameter for a module, pass an empty module ID for global parameters
+    pub fn with_param(
+        mut self,
+        module_id: &str,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        let key = if module_id.is_empty() {
+            key.into()
+        } else {
+            format!("{}.{}", module_id, key.into())
+        };
+        self.params.push((key, value.into()));
+        self
+    }
+
+    /// The query string parameters for this request
+    fn query_params(&self) -> Vec<(String, String)> {
+        let mut params = vec![(GlobalParam::Query.name().to_owned(), self.query.clone())];
+        if !self.include.is_empty() {
+            params.push((
+                GlobalParam::Include.name().to_owned(),
+                self.include.join(","),
+            ));
+        }
+        if !self.exclude.is_empty() {
+            params.push((
+                GlobalParam::Exclude.name().to_owned(),
+                self.exclude.join(","),
+            ));
+        }
+        params.extend(self.params.iter().cloned());
+        params
+    }
+}
+
+/// The result of query expansion, as returned by the webservice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryExpansion {
+    /// Maps each term to its expansions, with one entry per module that returned expansions
+    pub terms: HashMap<String, Vec<TermExpansion>>,
+    /// The input query
+    pub original_query: String,
+    /// A template for query expansion, expandable terms are replaced by {{term}}, which refer back to the terms
+    pub query_expansion_template: String,
+    /// The full expanded query
+    pub query: String,
+    /// The status of each term, explains why a term got no expansions
+    #[serde(default)]
+    pub term_status: HashMap<String, TermStatus>,
+    /// The spans (begin and end character offsets) of each expansion that differ from the term, only if requested
+    #[serde(default)]
+    pub highlights: Option<Highlights>,
+    /// The IDF weight of each term, only if the server has a frequency file configured
+    #[serde(default)]
+    pub term_weights: Option<HashMap<String, f64>>,
+    /// The software version, seed and module data versions, to reproduce the expansion later
+    #[serde(default)]
+    pub reproducibility: Option<Reproducibility>,
+    /// The total number of expansions of each term, only if the expansions were paginated
+    #[serde(default)]
+    pub total: Option<HashMap<String, usize>>,
+    /// The expansions of each term grouped by kind, module or language, only if requested
+    #[serde(default)]
+    pub groups: Option<Grouping>,
+    /// The minimum of the alternatives of each term that should match, only if requested
+    #[serde(default)]
+    pub minimum_should_match: Option<String>,
+    /// The modules that were skipped and why, only if any were
+    #[serde(default)]
+    pub warnings: Option<Vec<ModuleWarning>>,
+}
+
+impl QueryExpansion {
+    /// Returns all expansions for a term, over all modules, in the order returned
+    pub fn expansions<'a>(&'a self, term: &str) -> impl Iterator<Item = &'a str> {
+        self.terms
+            .get(term)
+            .into_iter()
+            .flatten()
+            .flat_map(|termexpansion| termexpansion.expansions.iter().map(|s| s.as_str()))
+    }
+}
+
+/// Expansions for a single term from a single module, as returned by the webservice
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TermExpansion {
+    /// The expansions
+    pub expansions: Vec<String>,
+    /// Scores for each of the expansions (if provided by the module), in the same order
+    #[serde(default)]
+    pub scores: Vec<f64>,
+    /// The ID of the module that provided the expansions
+    pub source_id: Option<String>,
+    /// The name of the module that provided the expansions
+    pub source_name: Option<String>,
+    /// The type of the module that provided the expansions
+    #[serde(default)]
+    pub source_type: String,
+    /// A link to more information
+    pub link: Option<String>,
+}
+
+/// A module, as listed by the webservice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleInfo {
+    /// The identifier of the module
+    pub id: String,
+    /// A human-readable name for the module
+    pub name: String,
+    /// The module type (e.g. lookup, fst)
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Runtime statistics of the webservice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Uptime in seconds
+    pub uptime: u64,
+    /// Total number of requests handled
+    pub requests: u64,
+    /// Statistics per module (by ID)
+    pub modules: HashMap<String, ModuleStats>,
+    /// Statistics on the response cache, if enabled
+    pub cache: Option<CacheStats>,
+    /// Resident memory usage of the process in bytes, if it can be determined
+    pub memory_usage: Option<u64>,
+}
+
+/// Statistics for a single module
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModuleStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub avg_time_us: u64,
+    pub max_time_us: u64,
+}
+
+/// Statistics on the response cache
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// An error as returned by the webservice
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    /// The error name (e.g. NotFound)
+    pub name: String,
+    /// A machine-readable error code (e.g. module_not_found)
+    pub code: String,
+    /// The error message
+    pub message: String,
+    /// The ID of the request, to correlate errors with server logs
+    pub request_id: Option<String>,
+}
+
+impl From<ApiError> for Error {
+    /// Maps an error returned by the webservice back to the library error it originates from, where possible
+    fn from(e: ApiError) -> Self {
+        let message = if let Some(request_id) = e.request_id {
+            format!("{} (request {})", e.message, request_id)
+        } else {
+            e.message
+        };
+        match e.code.as_str() {
+            "load_error" => Self::LoadError(message),
+            "invalid_parameter" | "missing_argument" | "bad_request" => {
+                Self::InvalidParameter(message)
+            }
+            "invalid_config" => Self::InvalidConfig(message),
+            "module_not_found" | "not_found" => Self::ModuleNotFound(message),
+            "timeout" => Self::Timeout(message),
+            "limit_exceeded" => Self::LimitExceeded(message),
+            "cancelled" => Self::Cancelled(message),
+            _ => Self::QueryExpandError(format!("{}: {}", e.name, message)),
+        }
+    }
+}
+
+/// The response to a request that was accepted for processing in the background
+#[derive(Deserialize)]
+struct Accepted {
+    message: String,
+}
+
+impl Client {
+    /// Creates a client for the instance at the given base URL (e.g. `http://localhost:8080`)
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            api_key: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Sets the API key to pass with each request (builder pattern)
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Uses the given HTTP client, e.g. to configure timeouts or proxies (builder pattern)
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        self.base_url.as_str()
+    }
+
+    /// Expands a query
+    pub async fn expand(&self, request: &ExpandRequest) -> Result<QueryExpansion, Error> {
+        self.send(self.http.get(self.url("/")).query(&request.query_params()))
+            .await
+    }
+
+    /// Expands a query and renders the expansions as search parameters for the given search engine
+    pub async fn render(
+        &self,
+        engine: SearchEngine,
+        request: &ExpandRequest,
+    ) -> Result<serde_json::Value, Error> {
+        self.send(
+            self.http
+                .get(self.url(&format!("/render/{}", engine.name())))
+                .query(&request.query_params()),
+        )
+        .await
+    }
+
+    /// Lists the loaded modules
+    pub async fn modules(&self) -> Result<Vec<ModuleInfo>, Error> {
+        self.send(self.http.get(self.url("/modules"))).await
+    }
+
+    /// Returns runtime statistics
+    pub async fn stats(&self) -> Result<Stats, Error> {
+        self.send(self.http.get(self.url("/stats"))).await
+    }
+
+    /// Loads and adds a module, the configuration is JSON or TOML with an extra `type` key holding the module type.
+    /// Requires an API key with administrative access.
+    pub async fn add_module(&self, config: impl Into<String>) -> Result<ModuleInfo, Error> {
+        let modules: Vec<ModuleInfo> = self
+            .send(
+                self.http
+                    .post(self.url("/admin/modules"))
+                    .body(config.into()),
+            )
+            .await?;
+        modules
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::QueryExpandError("Empty response from server".into()))
+    }
+
+    /// Removes a module, returns the remaining modules. Requires an API key with administrative access.
+    pub async fn remove_module(&self, id: &str) -> Result<Vec<ModuleInfo>, Error> {
+        self.send(
+            self.http
+                .delete(self.url(&format!("/admin/modules/{}", id))),
+        )
+        .await
+    }
+
+    /// Reloads a module in the background, returns the message from the server. Requires an API key with administrative access.
+    pub async fn reload_module(&self, id: &str) -> Result<String, Error> {
+        let accepted: Accepted = self
+            .send(
+                self.http
+                    .post(self.url(&format!("/admin/modules/{}/reload", id))),
+            )
+            .await?;
+        Ok(accepted.message)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// Sends a request and decodes the JSON response, errors returned by the server are mapped to library errors
+    async fn send<T: DeserializeOwned>(
+        &self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<T, Error> {
+        if let Some(api_key) = self.api_key.as_deref() {
+            request = request.header("X-Api-Key", api_key);
+        }
+        let response = request.send().await.map_err(|e| {
+            Error::QueryExpandError(format!("Unable to query {}: {}", self.base_url, e))
+        })?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::QueryExpandError(format!("Unable to read response: {}", e)))?;
+        decode_response(status.as_u16(), &body)
+    }
+}
+
+/// Decodes a response body, or the error it holds if the status is not successful
+fn decode_response<T: DeserializeOwned>(status: u16, body: &str) -> Result<T, Error> {
+    if (200..300).contains(&status) {
+        serde_json::from_str(body)
+            .map_err(|e| Error::QueryExpandError(format!("Invalid response from server: {}", e)))
+    } else if let Ok(e) = serde_json::from_str::<ApiError>(body) {
+        Err(e.into())
+    } else {
+        // not every error response comes from the API itself (e.g. from a proxy)
+        Err(Error::QueryExpandError(format!(
+            "Server returned status {}: {}",
+            status,
+            body.trim()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_client_decode_expansion() -> Result<(), Error> {
+        let body = r#"{"terms":{"separate":[{"expansions":["split","apart"],"scores":[],"source_id":"lookup","source_name":"Lookup","source_type":"lookup","link":null}]},"original_query":"separate","query_expansion_template":"{{separate}}","query":"(separate OR split OR apart)","term_status":{"separate":"expanded"}}"#;
+        let expansion: QueryExpansion = decode_response(200, body)?;
+        assert_eq!(
+            expansion.expansions("separate").collect::<Vec<_>>(),
+            vec!["split", "apart"]
+        );
+        assert_eq!(
+            expansion.term_status.get("separate"),
+            Some(&TermStatus::Expanded)
+        );
+        assert_eq!(expansion.terms["separate"][0].source_type, "lookup");
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_client_decode_error() {
+        let body = r#"{"@type":"ApiError","name":"Error","code":"module_not_found","message":"No such module: x","request_id":"abc"}"#;
+        match decode_response::<QueryExpansion>(404, body) {
+            Err(Error::ModuleNotFound(message)) => {
+                assert_eq!(message, "No such module: x (request abc)")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            decode_response::<QueryExpansion>(502, "Bad Gateway"),
+            Err(Error::QueryExpandError(..))
+        ));
+    }
+
+    #[test]
+    pub fn test003_client_request_params() {
+        let request = ExpandRequest::new("wine")
+            .with_include("lookup")
+            .with_include("fst")
+            .with_param("fst", "distance", "1");
+        assert_eq!(
+            request.query_params(),
+            vec![
+                ("q".to_owned(), "wine".to_owned()),
+                ("include".to_owned(), "lookup,fst".to_owned()),
+                ("fst.distance".to_owned(), "1".to_owned()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+use crate::Error;
+
+/// Lexicon formats that can be converted between
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LexiconFormat {
+    /// Tab-separated values, a term followed by its variants, as used by the lookup module
+    Tsv,
+    /// Solr synonyms file, either `term => variant, variant` or a comma-separated list of equivalent terms
+    Solr,
+    /// JSON lines, one object with `term`, `variants` and (optionally) `scores` per line
+    Jsonl,
+    /// Analiticcl weighted variant list, a term followed by alternating variants and scores (tab-separated)
+    Analiticcl,
+    /// Plain wordlist, one word per line. When written, it contains all terms and variants, sorted lexicographically as required by the fst module.
+    Wordlist,
+    /// SKOS in Turtle syntax, each term is a concept with its variants as `skos:altLabel`. This format can only be written.
+    Skos,
+    /// Elasticsearch index settings (JSON) with a `synonym_graph` filter holding the synonym rules in Solr syntax. This format can only be written.
+    Elasticsearch,
+}
+
+impl FromStr for LexiconFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tsv" | "lookup" => Ok(Self::Tsv),
+            "solr" => Ok(Self::Solr),
+            "jsonl" => Ok(Self::Jsonl),
+            "analiticcl" => Ok(Self::Analiticcl),
+            "wordlist" | "fst" => Ok(Self::Wordlist),
+            "skos" => Ok(Self::Skos),
+            "elasticsearch" | "es" => Ok(Self::Elasticsearch),
+            _ => Err(Error::InvalidParameter(format!(
+                "Unknown lexicon format: {} (expected tsv, solr, jsonl, analiticcl, wordlist, skos or elasticsearch)",
+                s
+            ))),
+        }
+    }
+}
+
+impl LexiconFormat {
+    /// The media type of the format, as served by the webservice
+    pub fn mimetype(&self) -> &'static str {
+        match self {
+            Self::Tsv | Self::Analiticcl => "text/tab-separated-values; charset=utf-8",
+            Self::Solr | Self::Wordlist => "text/plain; charset=utf-8",
+            Self::Jsonl => "application/jsonl",
+            Self::Skos => "text/turtle",
+            Self::Elasticsearch => "application/json",
+        }
+    }
+}
+
+/// A term with its variants and (optionally) a score for each variant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LexiconEntry {
+    pub term: String,
+
+    #[serde(default)]
+    pub variants: Vec<String>,
+
+    /// Scores for each of the variants, empty if there are none
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scores: Vec<f64>,
+}
+
+impl LexiconEntry {
+    pub fn new(term: impl Into<String>, variants: Vec<String>) -> Self {
+        Self {
+            term: term.into(),
+            variants,
+            scores: Vec::new(),
+        }
+    }
+}
+
+/// Read a lexicon in the given format
+pub fn read_lexicon(
+    format: LexiconFormat,
+    reader: impl BufRead,
+) -> Result<Vec<LexiconEntry>, Error> {
+    if matches!(format, LexiconFormat::Skos | LexiconFormat::Elasticsearch) {
+        return Err(Error::InvalidParameter(format!(
+            "{:?} can only be written, not read",
+            format
+        )));
+    }
+    let mut entries = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.trim().is_empty() {
+            continue;
+        }
+        match format {
+            LexiconFormat::Tsv => {
+                let mut fields = line.split('\t');
+                if let Some(term) = fields.next() {
+                    entries.push(LexiconEntry::new(
+                        term,
+                        fields
+                            .filter(|field| !field.is_empty())
+                            .map(|field| field.to_owned())
+                            .collect(),
+                    ));
+                }
+            }
+            LexiconFormat::Analiticcl => {
+                let mut fields = line.split('\t');
+                if let Some(term) = fields.next() {
+                    let mut entry = LexiconEntry::new(term, Vec::new());
+                    let fields: Vec<&str> = fields.collect();
+                    for pair in fields.chunks(2) {
+                        entry.variants.push(pair[0].to_owned());
+                        if let Some(score) = pair.get(1) {
+                            entry.scores.push(score.parse().map_err(|_| {
+                                Error::LoadError(format!(
+                                    "Invalid score on line {}: {}",
+                                    i + 1,
+                                    score
+                                ))
+                            })?);
+                        } else {
+                            entry.scores.push(1.0);
+                        }
+                    }
+                    entries.push(entry);
+                }
+            }
+            LexiconFormat::Solr => {
+                if line.trim_start().starts_with('#') {
+                    continue;
+                }
+                if let Some((terms, variants)) = line.split_once("=>") {
+                    let variants = split_solr(variants);
+                    for term in split_solr(terms) {
+                        entries.push(LexiconEntry::new(term, variants.clone()));
+                    }
+                } else {
+                    // equivalent synonyms: every term maps to all others
+                    let terms = split_solr(line);
+                    for term in terms.iter() {
+                        entries.push(LexiconEntry::new(
+                            term.as_str(),
+                            terms.iter().filter(|t| *t != term).cloned().collect(),
+                        ));
+                    }
+                }
+            }
+            LexiconFormat::Jsonl => {
+                entries.push(serde_json::from_str(line).map_err(|e| {
+                    Error::LoadError(format!("Invalid JSON on line {}: {}", i + 1, e))
+                })?);
+            }
+            LexiconFormat::Wordlist => {
+                entries.push(LexiconEntry::new(line.trim(), Vec::new()));
+            }
+            LexiconFormat::Skos | LexiconFormat::Elasticsearch => unreachable!(),
+        }
+    }
+    Ok(entries)
+}
+
+/// Splits a comma-separated list of Solr synonyms, taking escaped commas into account
+fn split_solr(s: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(c) = chars.next() {
+                    item.push(c);
+                }
+            }
+            ',' => items.push(std::mem::take(&mut item)),
+            c => item.push(c),
+        }
+    }
+    items.push(item);
+    items
+        .into_iter()
+        .map(|item| item.trim().to_owned())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+fn escape_solr(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(',', "\\,")
+}
+
+/// Formats an entry as an explicit Solr synonym rule (`term => variant, variant`), if it has any variants
+fn solr_rule(entry: &LexiconEntry) -> Option<String> {
+    if entry.variants.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "{} => {}",
+            escape_solr(&entry.term),
+            entry
+                .variants
+                .iter()
+                .map(|variant| escape_solr(variant))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+    }
+}
+
+/// Formats a string as a Turtle string literal
+fn turtle_literal(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Write a lexicon in the given format
+pub fn write_lexicon(
+    format: LexiconFormat,
+    entries: &[LexiconEntry],
+    mut writer: impl Write,
+) -> Result<(), Error> {
+    match format {
+        LexiconFormat::Wordlist => {
+            let words: BTreeSet<&str> = entries
+                .iter()
+                .flat_map(|entry| {
+                    std::iter::once(entry.term.as_str())
+                        .chain(entry.variants.iter().map(|v| v.as_str()))
+                })
+                .collect();
+            for word in words {
+                writeln!(writer, "{}", word)?;
+            }
+        }
+        LexiconFormat::Skos => {
+            writeln!(
+                writer,
+                "@prefix skos: <http://www.w3.org/2004/02/skos/core#> ."
+            )?;
+            for entry in entries.iter().filter(|entry| !entry.variants.is_empty()) {
+                writeln!(writer)?;
+                writeln!(writer, "[] a skos:Concept ;")?;
+                writeln!(
+                    writer,
+                    "    skos:prefLabel {} ;",
+                    turtle_literal(&entry.term)
+                )?;
+                writeln!(
+                    writer,
+                    "    skos:altLabel {} .",
+                    entry
+                        .variants
+                        .iter()
+                        .map(|variant| turtle_literal(variant))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )?;
+            }
+        }
+        LexiconFormat::Elasticsearch => {
+            let synonyms: Vec<String> = entries.iter().filter_map(solr_rule).collect();
+            let settings = serde_json::json!({
+                "analysis": {
+                    "filter": {
+                        "kweepeer_synonyms": {
+                            "type": "synonym_graph",
+                            "synonyms": synonyms,
+                        }
+                    }
+                }
+            });
+            let json = serde_json::to_string_pretty(&settings)
+                .map_err(|e| Error::LoadError(format!("Unable to serialize settings: {}", e)))?;
+            writeln!(writer, "{}", json)?;
+        }
+        _ => {
+            for entry in entries {
+                match format {
+                    LexiconFormat::Tsv => {
+                        write!(writer, "{}", entry.term)?;
+                        for variant in entry.variants.iter() {
+                            write!(writer, "\t{}", variant)?;
+                        }
+                        writeln!(writer)?;
+                    }
+                    LexiconFormat::Analiticcl => {
+                        write!(writer, "{}", entry.term)?;
+                        for (i, variant) in entry.variants.iter().enumerate() {
+                            write!(
+                                writer,
+                                "\t{}\t{}",
+                                variant,
+                                entry.scores.get(i).copied().unwrap_or(1.0)
+                            )?;
+                        }
+                        writeln!(writer)?;
+                    }
+                    LexiconFormat::Solr => {
+                        if let Some(rule) = solr_rule(entry) {
+                            writeln!(writer, "{}", rule)?;
+                        }
+                    }
+                    LexiconFormat::Jsonl => {
+                        let line = serde_json::to_string(entry).map_err(|e| {
+                            Error::LoadError(format!("Unable to serialize entry: {}", e))
+                        })?;
+                        writeln!(writer, "{}", line)?;
+                    }
+                    LexiconFormat::Wordlist
+                    | LexiconFormat::Skos
+                    | LexiconFormat::Elasticsearch => unreachable!(),
+                }
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(from: LexiconFormat, to: LexiconFormat, input: &str) -> Result<String, Error> {
+        let entries = read_lexicon(from, input.as_bytes())?;
+        let mut output = Vec::new();
+        write_lexicon(to, &entries, &mut output)?;
+        Ok(String::from_utf8(output).expect("valid utf-8"))
+    }
+
+    #[test]
+    pub fn test001_convert_tsv_solr() -> Result<(), Error> {
+        let solr = convert(
+            LexiconFormat::Tsv,
+            LexiconFormat::Solr,
+            "separate\tsplit\tapart\n",
+        )?;
+        assert_eq!(solr, "separate => split, apart\n");
+        let tsv = convert(LexiconFormat::Solr, LexiconFormat::Tsv, &solr)?;
+        assert_eq!(tsv, "separate\tsplit\tapart\n");
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_convert_solr_equivalent() -> Result<(), Error> {
+        let tsv = convert(
+            LexiconFormat::Solr,
+            LexiconFormat::Tsv,
+            "# comment\nwine, wijn\n",
+        )?;
+        assert_eq!(tsv, "wine\twijn\nwijn\twine\n");
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_convert_wordlist_sorted() -> Result<(), Error> {
+        let wordlist = convert(
+            LexiconFormat::Analiticcl,
+            LexiconFormat::Wordlist,
+            "zee\tsee\t0.5\nappel\tapple\t0.9\n",
+        )?;
+        assert_eq!(wordlist, "appel\napple\nsee\nzee\n");
+        Ok(())
+    }
+
+    #[test]
+    pub fn test004_convert_jsonl() -> Result<(), Error> {
+        let jsonl = convert(
+            LexiconFormat::Analiticcl,
+            LexiconFormat::Jsonl,
+            "zee\tsee\t0.5\n",
+        )?;
+        assert_eq!(
+            jsonl,
+            "{\"term\":\"zee\",\"variants\":[\"see\"],\"scores\":[0.5]}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test005_convert_skos() -> Result<(), Error> {
+        let skos = convert(
+            LexiconFormat::Tsv,
+            LexiconFormat::Skos,
+            "wine\twijn\t\"vin\"\nunknown\n",
+        )?;
+        assert_eq!(
+            skos,
+            "@prefix skos: <http://www.w3.org/2004/02/skos/core#> .\n\n[] a skos:Concept ;\n    skos:prefLabel \"wine\" ;\n    skos:altLabel \"wijn\", \"\\\"vin\\\"\" .\n"
+        );
+        assert!(read_lexicon(LexiconFormat::Skos, skos.as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test006_convert_elasticsearch() -> Result<(), Error> {
+        let json = convert(
+            LexiconFormat::Tsv,
+            LexiconFormat::Elasticsearch,
+            "separate\tseparate\tsplit\nunknown\n",
+        )?;
+        let settings: serde_json::Value = serde_json::from_str(&json).expect("valid json");
+        let filter = &settings["analysis"]["filter"]["kweepeer_synonyms"];
+        assert_eq!(filter["type"], "synonym_graph");
+        assert_eq!(
+            filter["synonyms"],
+            serde_json::json!(["separate => separate, split"])
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+use crate::globalparams::GlobalParam;
+use crate::{Error, QueryParams};
+
+/// How the expansions of a term are combined with the term itself in the expanded query, as passed in the
+/// `coverage` parameter. Search backends differ in the trade-off between recall and precision they need.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Coverage {
+    /// The expansions replace the term, the term itself is only kept if a module returned it as expansion
+    #[default]
+    Replace,
+    /// The term is added to its expansions, so anything that matches the term is still found
+    Or,
+    /// Like `Or`, constrained by a minimum number (e.g. `2`, `-1`) or percentage (e.g. `75%`) of the alternatives
+    /// that should match. The query syntax can not express this, so the value is returned alongside the expanded
+    /// query, for search backends that support such a constraint.
+    MinimumShouldMatch(MinimumShouldMatch),
+}
+
+/// A minimum number (negative: all but this number) or percentage of alternatives that should match, serialized
+/// the way it is written, e.g. `2`, `-1` or `75%`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinimumShouldMatch {
+    value: i32,
+    percentage: bool,
+}
+
+impl MinimumShouldMatch {
+    /// Parses a number or percentage, e.g. `2`, `-1` or `75%`
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        let (number, percentage) = match s.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (s, false),
+        };
+        number
+            .parse()
+            .ok()
+            .filter(|value: &i32| !percentage || (-100..=100).contains(value))
+            .map(|value| Self { value, percentage })
+            .ok_or_else(|| {
+                Error::InvalidParameter(
+                    "minimum_should_match must be a number or a percentage".into(),
+                )
+            })
+    }
+}
+
+impl fmt::Display for MinimumShouldMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.percentage {
+            write!(f, "{}%", self.value)
+        } else {
+            write!(f, "{}", self.value)
+        }
+    }
+}
+
+impl Serialize for MinimumShouldMatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Coverage {
+    /// Reads the global `coverage` and `minimum_should_match` parameters. Passing only `minimum_should_match`
+    /// implies `coverage=minimum_should_match`.
+    pub fn from_params(params: &QueryParams) -> Result<Self, Error> {
+        let minimum = match params.global(GlobalParam::MinimumShouldMatch) {
+            None => None,
+            Some(Value::Number(value)) => Some(value.to_string()),
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(_) => Some(String::new()),
+        };
+        let coverage = params
+            .global(GlobalParam::Coverage)
+            .and_then(|value| value.as_str());
+        match (coverage, minimum) {
+            (None, None) | (Some("replace"), None) => Ok(Self::Replace),
+            (Some("or"), None) => Ok(Self::Or),
+            (None, Some(minimum)) | (Some("minimum_should_match"), Some(minimum)) => Ok(
+                Self::MinimumShouldMatch(MinimumShouldMatch::parse(&minimum)?),
+            ),
+            (Some("minimum_should_match"), None) => Err(Error::InvalidParameter(
+                "coverage minimum_should_match requires the minimum_should_match parameter".into(),
+            )),
+            (Some("replace"), Some(_)) | (Some("or"), Some(_)) => Err(Error::InvalidParameter(
+                "minimum_should_match only applies to coverage minimum_should_match".into(),
+            )),
+            (Some(_), _) => Err(Error::InvalidParameter(
+                "coverage must be replace, or or minimum_should_match".into(),
+            )),
+        }
+    }
+
+    /// Returns whether the term itself is part of its disjunction in the expanded query
+    pub fn includes_term(&self) -> bool {
+        !matches!(self, Self::Replace)
+    }
+
+    /// Returns the minimum that should match, if constrained
+    pub fn minimum_should_match(&self) -> Option<MinimumShouldMatch> {
+        match self {
+            Self::MinimumShouldMatch(minimum) => Some(*minimum),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{QueryExpander, TermExpansion, TermExpansions};
+
+    #[test]
+    pub fn test001_coverage_params() -> Result<(), Error> {
+        assert_eq!(
+            Coverage::from_params(&QueryParams::new())?,
+            Coverage::Replace
+        );
+        assert_eq!(
+            Coverage::from_params(&QueryParams::new().with("", "coverage", "or".into()))?,
+            Coverage::Or
+        );
+        assert_eq!(
+            Coverage::from_params(&QueryParams::new().with(
+                "",
+                "minimum_should_match",
+                "75%".into()
+            ))?,
+            Coverage::MinimumShouldMatch(MinimumShouldMatch::parse("75%")?)
+        );
+        assert_eq!(
+            Coverage::from_params(&QueryParams::new().with("", "minimum_should_match", 2.into()))?
+                .minimum_should_match()
+                .map(|minimum| minimum.to_string()),
+            Some("2".to_string())
+        );
+        assert_eq!(MinimumShouldMatch::parse("-1")?.to_string(), "-1");
+        assert!(MinimumShouldMatch::parse("150%").is_err());
+        assert!(
+            Coverage::from_params(&QueryParams::new().with("", "coverage", "all".into())).is_err()
+        );
+        assert!(Coverage::from_params(&QueryParams::new().with(
+            "",
+            "coverage",
+            "minimum_should_match".into()
+        ))
+        .is_err());
+        assert!(Coverage::from_params(&QueryParams::new().with(
+            "",
+            "minimum_should_match",
+            "many".into()
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_coverage_resolve() -> Result<(), Error> {
+        let expander = QueryExpander::new();
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "kaas".into(),
+            vec![TermExpansion::default().with_expansions(vec!["kaes".into(), "kaas".into()])],
+        );
+        let template = "{{kaas}} AND {{oude wijn}}";
+        assert_eq!(
+            expander.resolve_query_template_with_coverage(
+                template,
+                &terms_map,
+                &Coverage::Replace
+            )?,
+            "(kaes OR kaas) AND \"oude wijn\""
+        );
+        assert_eq!(
+            expander.resolve_query_template_with_coverage(template, &terms_map, &Coverage::Or)?,
+            "(kaas OR (kaes)) AND \"oude wijn\""
+        );
+        Ok(())
+    }
+}
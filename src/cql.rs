@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use crate::lexer::Term;
+use crate::TermExpansions;
+
+/// Boolean operators in CQL (case-insensitive)
+const BOOLEANS: &[&str] = &["and", "or", "not", "prox"];
+
+/// Relations in CQL that take the form of a word (case-insensitive)
+const RELATION_WORDS: &[&str] = &["any", "all", "adj", "within", "encloses", "exact", "scr"];
+
+/// A part of a CQL query
+#[derive(Debug, PartialEq)]
+pub enum CqlPart<'a> {
+    /// Anything that is passed through unchanged (whitespace, booleans, parentheses)
+    Literal(&'a str),
+    /// A search clause with an optional index and relation
+    Clause {
+        index: Option<&'a str>,
+        relation: Option<&'a str>,
+        term: Term<'a>,
+        /// The original text of the entire clause
+        text: &'a str,
+    },
+}
+
+/// A parsed CQL (Contextual Query Language) query, as used by SRU.
+/// This is a lightweight parser that only identifies the search terms so they can be expanded,
+/// it does not validate the query.
+#[derive(Debug, PartialEq)]
+pub struct CqlQuery<'a> {
+    parts: Vec<CqlPart<'a>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Whitespace,
+    Paren,
+    Relation,
+    Quoted,
+    Word,
+}
+
+fn tokenize(query: &str) -> Vec<(TokenKind, usize, usize)> {
+    let mut tokens = Vec::new();
+    let mut chars = query.char_indices().peekable();
+    while let Some((begin, c)) = chars.next() {
+        let kind = if c.is_whitespace() {
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+            TokenKind::Whitespace
+        } else if c == '(' || c == ')' {
+            TokenKind::Paren
+        } else if c == '=' || c == '<' || c == '>' {
+            while chars
+                .next_if(|(_, c)| *c == '=' || *c == '<' || *c == '>')
+                .is_some()
+            {}
+            TokenKind::Relation
+        } else if c == '"' {
+            let mut escaped = false;
+            for (_, c) in chars.by_ref() {
+                if c == '"' && !escaped {
+                    break;
+                }
+                escaped = c == '\\' && !escaped;
+            }
+            TokenKind::Quoted
+        } else {
+            while chars
+                .next_if(|(_, c)| {
+                    !c.is_whitespace() && !matches!(c, '(' | ')' | '=' | '<' | '>' | '"')
+                })
+                .is_some()
+            {}
+            TokenKind::Word
+        };
+        let end = chars.peek().map(|(i, _)| *i).unwrap_or(query.len());
+        tokens.push((kind, begin, end));
+    }
+    tokens
+}
+
+impl<'a> CqlQuery<'a> {
+    /// Parse a CQL query
+    pub fn parse(query: &'a str) -> Self {
+        let tokens = tokenize(query);
+        let text = |i: usize| &query[tokens[i].1..tokens[i].2];
+        let is_relation = |i: usize| {
+            tokens[i].0 == TokenKind::Relation
+                || (tokens[i].0 == TokenKind::Word
+                    && RELATION_WORDS.contains(&text(i).to_lowercase().as_str()))
+        };
+        // returns the index of the next non-whitespace token
+        let next = |i: usize| (i + 1..tokens.len()).find(|j| tokens[*j].0 != TokenKind::Whitespace);
+
+        let mut parts = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let (kind, begin, _) = tokens[i];
+            match kind {
+                TokenKind::Word if BOOLEANS.contains(&text(i).to_lowercase().as_str()) => {
+                    parts.push(CqlPart::Literal(text(i)))
+                }
+                TokenKind::Word | TokenKind::Quoted => {
+                    let (index, relation, termtoken) = match next(i) {
+                        Some(r) if is_relation(r) => match next(r) {
+                            Some(t)
+                                if matches!(tokens[t].0, TokenKind::Word | TokenKind::Quoted) =>
+                            {
+                                (Some(text(i)), Some(text(r)), t)
+                            }
+                            _ => (None, None, i),
+                        },
+                        _ => (None, None, i),
+                    };
+                    let term = if tokens[termtoken].0 == TokenKind::Quoted {
+                        let quoted = text(termtoken);
+                        Term::Phrase(&quoted[1..quoted.len().max(2) - 1])
+                    } else {
+                        Term::Singular(text(termtoken))
+                    };
+                    parts.push(CqlPart::Clause {
+                        index,
+                        relation,
+                        term,
+                        text: &query[begin..tokens[termtoken].2],
+                    });
+                    i = termtoken;
+                }
+                _ => parts.push(CqlPart::Literal(text(i))),
+            }
+            i += 1;
+        }
+        Self { parts }
+    }
+
+    pub fn parts(&self) -> &[CqlPart<'a>] {
+        &self.parts
+    }
+
+    /// Returns all search terms in the query
+    pub fn terms(&self) -> Vec<Term<'a>> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                CqlPart::Clause { term, .. } => Some(term.clone()),
+                CqlPart::Literal(_) => None,
+            })
+            .collect()
+    }
+
+    /// Returns the CQL query where each expanded search clause is substituted by a disjunction of
+    /// search clauses (with the same index and relation) for each of the expansions.
+    pub fn resolve(&self, terms_map: &TermExpansions) -> String {
+        let mut query = String::new();
+        let mut expansioncache = HashSet::<&str>::new();
+        for part in self.parts.iter() {
+            match part {
+                CqlPart::Literal(s) => query += s,
+                CqlPart::Clause {
+                    index,
+                    relation,
+                    term,
+                    text,
+                } => {
+                    expansioncache.clear();
+                    let mut disjunction = String::new();
+                    for expansion in terms_map
+                        .get(term.as_str())
+                        .into_iter()
+                        .flatten()
+                        .flat_map(|termexpansion| termexpansion.iter())
+                    {
+                        if expansioncache.insert(expansion) {
+                            if !disjunction.is_empty() {
+                                disjunction += " or ";
+                            }
+                            if let (Some(index), Some(relation)) = (index, relation) {
+                                disjunction += index;
+                                disjunction.push(' ');
+                                disjunction += relation;
+                                disjunction.push(' ');
+                            }
+                            disjunction.push('"');
+                            disjunction += &expansion.replace('\\', "\\\\").replace('"', "\\\"");
+                            disjunction.push('"');
+                        }
+                    }
+                    if disjunction.is_empty() {
+                        query += text;
+                    } else {
+                        query.push('(');
+                        query += &disjunction;
+                        query.push(')');
+                    }
+                }
+            }
+        }
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_cql_terms() {
+        let query = CqlQuery::parse("wine and title = \"trade route\" or dc.creator any voc");
+        assert_eq!(
+            query.terms(),
+            vec![
+                Term::Singular("wine"),
+                Term::Phrase("trade route"),
+                Term::Singular("voc")
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test002_cql_clause() {
+        let query = CqlQuery::parse("(title=wine)");
+        assert_eq!(
+            query.parts(),
+            &[
+                CqlPart::Literal("("),
+                CqlPart::Clause {
+                    index: Some("title"),
+                    relation: Some("="),
+                    term: Term::Singular("wine"),
+                    text: "title=wine"
+                },
+                CqlPart::Literal(")")
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test003_cql_resolve() {
+        let query = CqlQuery::parse("title = wine AND ship");
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wine".to_string(),
+            vec![TermExpansion::default().with_expansions(vec!["wijn".into(), "wyn".into()])],
+        );
+        assert_eq!(
+            query.resolve(&terms_map),
+            "(title = \"wijn\" or title = \"wyn\") AND ship"
+        );
+    }
+}
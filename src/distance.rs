@@ -0,0 +1,80 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::TermExpansions;
+
+/// Levenshtein distance between two strings, in characters. With the `simd` feature, ASCII strings are compared using
+/// SIMD instructions (if the CPU supports them), other strings character by character.
+pub fn levenshtein(a: &str, b: &str) -> u32 {
+    #[cfg(feature = "simd")]
+    if a.is_ascii() && b.is_ascii() {
+        return triple_accel::levenshtein(a.as_bytes(), b.as_bytes());
+    }
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<u32> = (0..=b.len() as u32).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i as u32 + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = prev[j] + u32::from(ca != cb);
+            current[j + 1] = substitution.min(prev[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut current);
+    }
+    prev[b.len()]
+}
+
+/// Attaches the edit distance to the term to every expansion, regardless of the module that provided it, so
+/// expansions from different modules can be compared on equal footing. With the `parallel` feature, the distances
+/// are computed in parallel.
+pub fn attach_distances(terms_map: &mut TermExpansions) {
+    #[cfg(feature = "parallel")]
+    terms_map.par_iter_mut().for_each(|(term, termexpansions)| {
+        for termexpansion in termexpansions.iter_mut() {
+            let distances = termexpansion
+                .expansions()
+                .par_iter()
+                .map(|expansion| levenshtein(term, expansion))
+                .collect();
+            termexpansion.set_distances(distances);
+        }
+    });
+    #[cfg(not(feature = "parallel"))]
+    for (term, termexpansions) in terms_map.iter_mut() {
+        for termexpansion in termexpansions.iter_mut() {
+            let distances = termexpansion
+                .expansions()
+                .iter()
+                .map(|expansion| levenshtein(term, expansion))
+                .collect();
+            termexpansion.set_distances(distances);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_distance_levenshtein() {
+        assert_eq!(levenshtein("kaas", "kaas"), 0);
+        assert_eq!(levenshtein("kaas", "kas"), 1);
+        assert_eq!(levenshtein("wijn", "vinum"), 4);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+        assert_eq!(levenshtein("", "ab"), 2);
+    }
+
+    #[test]
+    pub fn test002_distance_attach() {
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijn".into(),
+            vec![TermExpansion::default().with_expansions(vec!["wyn".into(), "wijne".into()])],
+        );
+        attach_distances(&mut terms_map);
+        assert_eq!(terms_map["wijn"][0].distances(), &vec![2, 1]);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::Error;
+
+/// Substitutes environment variables in a string: `${VAR}` is replaced by the value of `VAR`, which must be set,
+/// `${VAR:-default}` by the value of `VAR` or by the default if it is unset or empty. `$${` yields a literal `${`.
+pub fn substitute(s: &str) -> Result<String, Error> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(begin) = rest.find("${") {
+        if rest[..begin].ends_with('$') {
+            // escaped
+            result.push_str(&rest[..begin - 1]);
+            result.push_str("${");
+            rest = &rest[begin + 2..];
+            continue;
+        }
+        result.push_str(&rest[..begin]);
+        let end = rest[begin..].find('}').ok_or_else(|| {
+            Error::InvalidConfig(format!("Unterminated variable reference in: {}", s))
+        })? + begin;
+        let expression = &rest[begin + 2..end];
+        let (name, default) = match expression.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (expression, None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::InvalidConfig(format!(
+                "Invalid variable name in: {}",
+                s
+            )));
+        }
+        match (std::env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => result.push_str(default),
+            (Ok(value), _) => result.push_str(&value),
+            (Err(_), Some(default)) => result.push_str(default),
+            (Err(_), None) => {
+                return Err(Error::InvalidConfig(format!(
+                    "Environment variable {} is not set",
+                    name
+                )))
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Substitutes environment variables in all string values (not in keys) of a parsed TOML document
+pub fn substitute_toml(value: &mut toml::Value) -> Result<(), Error> {
+    match value {
+        toml::Value::String(s) if s.contains("${") => {
+            *s = substitute(s)?;
+        }
+        toml::Value::Array(array) => {
+            for value in array.iter_mut() {
+                substitute_toml(value)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                substitute_toml(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_envsubst() -> Result<(), Error> {
+        std::env::set_var("KWEEPEER_TEST_DATA", "/data");
+        std::env::set_var("KWEEPEER_TEST_EMPTY", "");
+        assert_eq!(
+            substitute("${KWEEPEER_TEST_DATA}/lexicon.tsv")?,
+            "/data/lexicon.tsv"
+        );
+        assert_eq!(
+            substitute("${KWEEPEER_TEST_UNSET:-/tmp}/${KWEEPEER_TEST_EMPTY:-x}")?,
+            "/tmp/x"
+        );
+        assert_eq!(substitute("no variables")?, "no variables");
+        assert_eq!(
+            substitute("$${KWEEPEER_TEST_DATA}")?,
+            "${KWEEPEER_TEST_DATA}"
+        );
+        assert!(substitute("${KWEEPEER_TEST_UNSET}").is_err());
+        assert!(substitute("${KWEEPEER_TEST_DATA").is_err());
+        assert!(substitute("${not a name}").is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_envsubst_toml() -> Result<(), Error> {
+        std::env::set_var("KWEEPEER_TEST_KEY", "s3cr3t");
+        let mut value: toml::Value = toml::from_str(
+            r#"
+            [[auth.keys]]
+            key = "${KWEEPEER_TEST_KEY}"
+            name = "${KWEEPEER_TEST_NAME:-client}"
+            "#,
+        )
+        .expect("valid toml");
+        substitute_toml(&mut value)?;
+        assert_eq!(value["auth"]["keys"][0]["key"].as_str(), Some("s3cr3t"));
+        assert_eq!(value["auth"]["keys"][0]["name"].as_str(), Some("client"));
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+
+use crate::convert::{read_lexicon, LexiconFormat};
+use crate::{Error, Term, TermExpansions};
+
+/// A gold standard mapping terms to their expected expansions
+#[derive(Debug, Default, Clone)]
+pub struct GoldStandard {
+    expansions: HashMap<String, HashSet<String>>,
+}
+
+impl GoldStandard {
+    /// Reads a gold standard in TSV format: a term followed by its expected expansions, as used by the lookup module
+    pub fn read(reader: impl BufRead) -> Result<Self, Error> {
+        let mut expansions: HashMap<String, HashSet<String>> = HashMap::new();
+        for entry in read_lexicon(LexiconFormat::Tsv, reader)? {
+            expansions
+                .entry(entry.term)
+                .or_default()
+                .extend(entry.variants);
+        }
+        Ok(Self { expansions })
+    }
+
+    /// Returns the expected expansions for a term
+    pub fn get(&self, term: &str) -> Option<&HashSet<String>> {
+        self.expansions.get(term)
+    }
+
+    /// Returns all terms in the gold standard
+    pub fn terms(&self) -> impl Iterator<Item = &str> {
+        self.expansions.keys().map(|term| term.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.expansions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+}
+
+/// Accumulates evaluation metrics for expansions compared against a gold standard.
+/// Precision and recall are micro-averaged over all evaluated terms, MRR is averaged over all evaluated terms.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Evaluation {
+    /// Number of evaluated terms
+    terms: usize,
+    /// Number of expansions returned
+    retrieved: usize,
+    /// Number of expansions in the gold standard
+    relevant: usize,
+    /// Number of returned expansions that are in the gold standard
+    correct: usize,
+    /// Sum of the reciprocal ranks of the first correct expansion for each term
+    reciprocal_ranks: f64,
+}
+
+impl Evaluation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates the expansions for a single term (in ranked order, duplicates are ignored)
+    pub fn add<'a>(
+        &mut self,
+        expansions: impl IntoIterator<Item = &'a str>,
+        gold: &HashSet<String>,
+    ) {
+        let mut seen = HashSet::new();
+        let mut first_correct = None;
+        for expansion in expansions {
+            if seen.insert(expansion) && gold.contains(expansion) {
+                self.correct += 1;
+                if first_correct.is_none() {
+                    first_correct = Some(seen.len());
+                }
+            }
+        }
+        self.terms += 1;
+        self.retrieved += seen.len();
+        self.relevant += gold.len();
+        if let Some(rank) = first_correct {
+            self.reciprocal_ranks += 1.0 / rank as f64;
+        }
+    }
+
+    /// Evaluates the expansions of all query terms that occur in the gold standard.
+    /// Terms without any expansions count as well.
+    pub fn add_expansions(
+        &mut self,
+        terms: &[Term],
+        terms_map: &TermExpansions,
+        gold: &GoldStandard,
+    ) {
+        for term in terms {
+            if let Some(expected) = gold.get(term.as_str()) {
+                // modules may normalise the case of the term
+                let termexpansions = terms_map
+                    .get(term.as_str())
+                    .or_else(|| terms_map.get(&term.as_str().to_lowercase()));
+                self.add(
+                    termexpansions
+                        .into_iter()
+                        .flatten()
+                        .flat_map(|termexpansion| termexpansion.iter()),
+                    expected,
+                );
+            }
+        }
+    }
+
+    /// Number of evaluated terms
+    pub fn terms(&self) -> usize {
+        self.terms
+    }
+
+    pub fn precision(&self) -> f64 {
+        if self.retrieved > 0 {
+            self.correct as f64 / self.retrieved as f64
+        } else {
+            0.0
+        }
+    }
+
+    pub fn recall(&self) -> f64 {
+        if self.relevant > 0 {
+            self.correct as f64 / self.relevant as f64
+        } else {
+            0.0
+        }
+    }
+
+    /// Harmonic mean of precision and recall
+    pub fn f1(&self) -> f64 {
+        let (precision, recall) = (self.precision(), self.recall());
+        if precision + recall > 0.0 {
+            2.0 * precision * recall / (precision + recall)
+        } else {
+            0.0
+        }
+    }
+
+    /// Mean reciprocal rank of the first correct expansion
+    pub fn mrr(&self) -> f64 {
+        if self.terms > 0 {
+            self.reciprocal_ranks / self.terms as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_eval_metrics() -> Result<(), Error> {
+        let gold = GoldStandard::read("wine\twijn\twyn\nship\tschip\n".as_bytes())?;
+        assert_eq!(gold.len(), 2);
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wine".into(),
+            vec![TermExpansion::default().with_expansions(vec!["vine".into(), "wijn".into()])],
+        );
+        terms_map.insert(
+            "ship".into(),
+            vec![TermExpansion::default().with_expansions(vec!["schip".into()])],
+        );
+        let terms = vec![
+            Term::Singular("wine"),
+            Term::Singular("ship"),
+            Term::Singular("unknown"),
+        ];
+        let mut evaluation = Evaluation::new();
+        evaluation.add_expansions(&terms, &terms_map, &gold);
+        assert_eq!(evaluation.terms(), 2);
+        assert_eq!(evaluation.precision(), 2.0 / 3.0);
+        assert_eq!(evaluation.recall(), 2.0 / 3.0);
+        assert_eq!(evaluation.mrr(), (0.5 + 1.0) / 2.0);
+
+        // a term in the gold standard without expansions lowers recall
+        let gold = GoldStandard::read(
+            "wine	wijn	wyn
+ship	schip
+sea	zee
+"
+            .as_bytes(),
+        )?;
+        let mut evaluation = Evaluation::new();
+        evaluation.add_expansions(&[Term::Singular("sea")], &terms_map, &gold);
+        assert_eq!(evaluation.terms(), 1);
+        assert_eq!(evaluation.recall(), 0.0);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_eval_no_expansions() {
+        let gold: HashSet<String> = ["wijn".to_string()].into_iter().collect();
+        let mut evaluation = Evaluation::new();
+        evaluation.add(std::iter::empty(), &gold);
+        assert_eq!(evaluation.terms(), 1);
+        assert_eq!(evaluation.precision(), 0.0);
+        assert_eq!(evaluation.recall(), 0.0);
+        assert_eq!(evaluation.mrr(), 0.0);
+        assert_eq!(evaluation.f1(), 0.0);
+    }
+}
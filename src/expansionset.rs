@@ -0,0 +1,297 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::api::ApiResponse;
+use crate::{Error, QueryExpander, Term, TermExpansion, TermExpansions, TermStatus};
+
+/// Configuration for storing expansion sets
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExpansionSetConfig {
+    /// Directory in which the expansion sets are stored, one JSON file per set
+    dir: PathBuf,
+
+    /// Maximum number of expansion sets to store, further sets are refused
+    #[serde(default = "default_max_sets")]
+    max_sets: usize,
+}
+
+fn default_max_sets() -> usize {
+    10000
+}
+
+impl ExpansionSetConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_sets: default_max_sets(),
+        }
+    }
+
+    /// Set the maximum number of expansion sets to store
+    pub fn with_max_sets(mut self, max_sets: usize) -> Self {
+        self.max_sets = max_sets;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.as_path()
+    }
+}
+
+/// A curated set of expansions, stored under a stable ID so it can be re-applied to later queries and cited.
+/// The ID is derived from the expansions, so saving the same expansions twice yields the same ID.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpansionSet {
+    id: String,
+
+    /// Time at which the set was first saved (RFC 3339)
+    created: String,
+
+    /// Free-form description
+    #[serde(default)]
+    description: Option<String>,
+
+    /// Terms and their expansions
+    terms: TermExpansions,
+}
+
+impl ExpansionSet {
+    pub fn new(terms: TermExpansions, description: Option<String>) -> Self {
+        Self {
+            id: Self::compute_id(&terms),
+            created: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            description,
+            terms,
+        }
+    }
+
+    /// Computes the ID from the expansions, independent of the order of the terms
+    fn compute_id(terms: &TermExpansions) -> String {
+        let sorted: BTreeMap<&String, &Vec<TermExpansion>> = terms.iter().collect();
+        let json = serde_json::to_vec(&sorted).unwrap_or_default();
+        Sha256::digest(json)[..16]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    pub fn created(&self) -> &str {
+        self.created.as_str()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn terms(&self) -> &TermExpansions {
+        &self.terms
+    }
+
+    /// Expands a query using only the expansions in this set, no modules are consulted.
+    /// Terms that are not in the set are not expanded.
+    pub fn apply(&self, expander: &QueryExpander, querystring: &str) -> Result<ApiResponse, Error> {
+        expander.config().limits().check_query(querystring)?;
+        let (terms, query_template) = Term::extract_from_query(querystring);
+        expander.config().limits().check_terms(&terms)?;
+        let terms_map: TermExpansions = terms
+            .iter()
+            .filter_map(|term| {
+                self.terms
+                    .get_key_value(term.as_str())
+                    .map(|(term, expansions)| (term.clone(), expansions.clone()))
+            })
+            .collect();
+        let resolved_template =
+            expander.resolve_query_template(query_template.as_str(), &terms_map)?;
+        // the modules play no part, so unlike for regular expansion, the module filter is irrelevant here
+        let term_status = terms
+            .iter()
+            .map(|term| {
+                let status = if expander.config().is_stopword(term.as_str()) {
+                    TermStatus::Stopword
+                } else if terms_map.contains_key(term.as_str()) {
+                    TermStatus::Expanded
+                } else {
+                    TermStatus::NoMatches
+                };
+                (term.as_str().to_string(), status)
+            })
+            .collect();
+        Ok(ApiResponse::new_queryexpansion(
+            terms_map,
+            querystring,
+            query_template,
+            resolved_template,
+        )
+        .with_term_status(term_status))
+    }
+}
+
+/// Stores expansion sets on disk, as `<id>.json` in the configured directory
+pub struct ExpansionSetStore {
+    config: ExpansionSetConfig,
+}
+
+impl ExpansionSetStore {
+    pub fn new(config: ExpansionSetConfig) -> Self {
+        Self { config }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.config.dir.join(format!("{}.json", id))
+    }
+
+    /// Returns the number of stored expansion sets
+    pub fn count(&self) -> Result<usize, Error> {
+        let entries = match std::fs::read_dir(&self.config.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => {
+                return Err(Error::LoadError(format!(
+                    "Unable to read directory {}: {}",
+                    self.config.dir.display(),
+                    e
+                )))
+            }
+        };
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .count())
+    }
+
+    /// Saves an expansion set, unless a set with the same expansions already exists, in which case that one is returned.
+    /// New sets are refused once `max_sets` sets are stored.
+    pub fn save(&self, set: ExpansionSet) -> Result<ExpansionSet, Error> {
+        if let Some(existing) = self.get(set.id())? {
+            return Ok(existing);
+        }
+        if self.count()? >= self.config.max_sets {
+            return Err(Error::LimitExceeded(format!(
+                "The maximum number of expansion sets ({}) is stored already",
+                self.config.max_sets
+            )));
+        }
+        std::fs::create_dir_all(&self.config.dir).map_err(|e| {
+            Error::LoadError(format!(
+                "Unable to create directory {}: {}",
+                self.config.dir.display(),
+                e
+            ))
+        })?;
+        let json = serde_json::to_vec_pretty(&set).map_err(|e| {
+            Error::QueryExpandError(format!("Unable to serialize expansion set: {}", e))
+        })?;
+        // write to a temporary file first, so a set is never read half-written
+        let tmppath = self.config.dir.join(format!(".{}.tmp", set.id()));
+        let path = self.path(set.id());
+        std::fs::write(&tmppath, json)
+            .and_then(|_| std::fs::rename(&tmppath, &path))
+            .map_err(|e| {
+                Error::LoadError(format!(
+                    "Unable to write expansion set {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        Ok(set)
+    }
+
+    /// Retrieves an expansion set by ID, returns None if it does not exist
+    pub fn get(&self, id: &str) -> Result<Option<ExpansionSet>, Error> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let path = self.path(id);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(Error::LoadError(format!(
+                    "Unable to read expansion set {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+        serde_json::from_str(&json).map(Some).map_err(|e| {
+            Error::LoadError(format!(
+                "Unable to parse expansion set {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+
+    #[test]
+    pub fn test001_expansionset_store() -> Result<(), Error> {
+        let dir = TestDir::new("expansionsets");
+        let store = ExpansionSetStore::new(ExpansionSetConfig::new(dir.path()));
+        let mut terms = TermExpansions::new();
+        terms.insert(
+            "wijn".into(),
+            vec![TermExpansion::default().with_expansions(vec!["wyn".into(), "wijne".into()])],
+        );
+        let set = store.save(ExpansionSet::new(terms.clone(), Some("wine".into())))?;
+        assert_eq!(set.id().len(), 32);
+        // the same expansions yield the same set
+        let again = store.save(ExpansionSet::new(terms, None))?;
+        assert_eq!(again.id(), set.id());
+        assert_eq!(again.description(), Some("wine"));
+        let loaded = store.get(set.id())?.expect("set must exist");
+        assert_eq!(
+            loaded.terms()["wijn"][0].expansions(),
+            &vec!["wyn".to_string(), "wijne".to_string()]
+        );
+        assert!(store.get("../config")?.is_none());
+        assert!(store.get("0123abcd")?.is_none());
+
+        let expander = QueryExpander::new();
+        let response = loaded.apply(&expander, "wijn kaas")?;
+        if let ApiResponse::QueryExpansion { terms, query, .. } = response {
+            assert_eq!(terms.len(), 1);
+            assert!(query.contains("wyn"));
+        } else {
+            panic!("expected a query expansion");
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_expansionset_max_sets() -> Result<(), Error> {
+        let dir = TestDir::new("expansionsets-max");
+        let store = ExpansionSetStore::new(ExpansionSetConfig::new(dir.path()).with_max_sets(2));
+        let set = |term: &str| {
+            let mut terms = TermExpansions::new();
+            terms.insert(
+                term.into(),
+                vec![TermExpansion::default().with_expansions(vec![format!("{}e", term)])],
+            );
+            ExpansionSet::new(terms, None)
+        };
+        assert_eq!(store.count()?, 0);
+        store.save(set("wijn"))?;
+        store.save(set("kaas"))?;
+        assert_eq!(store.count()?, 2);
+        assert!(matches!(
+            store.save(set("brood")),
+            Err(Error::LimitExceeded(_))
+        ));
+        // existing sets can still be saved again
+        store.save(set("wijn"))?;
+        assert_eq!(store.count()?, 2);
+        Ok(())
+    }
+}
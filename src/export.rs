@@ -0,0 +1,100 @@
+use crate::convert::LexiconEntry;
+use crate::{Error, QueryExpander, QueryParams, Term};
+
+/// Expands each of the terms and collects the expansions of all selected modules into lexicon entries,
+/// so the output of a (slow) module can be frozen into a file for the lookup module.
+/// Terms without expansions are omitted. Scores are only retained if all modules that returned expansions for a term provide them.
+pub fn export_expansions<'a>(
+    expander: &QueryExpander,
+    terms: impl IntoIterator<Item = &'a str>,
+    params: &QueryParams,
+) -> Result<Vec<LexiconEntry>, Error> {
+    let mut entries = Vec::new();
+    for term in terms {
+        let term = term.trim();
+        if term.is_empty() {
+            continue;
+        }
+        let query = if term.contains(char::is_whitespace) {
+            vec![Term::Phrase(term)]
+        } else {
+            vec![Term::Singular(term)]
+        };
+        let mut terms_map = expander.expand_query(&query, params)?;
+        let termexpansions = terms_map.remove(term).unwrap_or_default();
+        let scored = termexpansions
+            .iter()
+            .all(|termexpansion| termexpansion.scores().len() == termexpansion.len());
+        let mut entry = LexiconEntry::new(term, Vec::new());
+        for termexpansion in termexpansions.iter() {
+            for (i, variant) in termexpansion.iter().enumerate() {
+                if variant != term && !entry.variants.iter().any(|v| v == variant) {
+                    entry.variants.push(variant.to_owned());
+                    if scored {
+                        entry.scores.push(termexpansion.scores()[i]);
+                    }
+                }
+            }
+        }
+        if !entry.variants.is_empty() {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Includes each term itself as its first variant (with the highest score of its variants, if scored).
+/// Synonym rules (`term => variant, variant`) replace a term by its variants, this keeps the original term in the index.
+pub fn keep_original(entries: &mut [LexiconEntry]) {
+    for entry in entries.iter_mut() {
+        if !entry.variants.contains(&entry.term) {
+            entry.variants.insert(0, entry.term.clone());
+            if !entry.scores.is_empty() {
+                let max = entry.scores.iter().copied().fold(f64::MIN, f64::max);
+                entry.scores.insert(0, max);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    pub fn test001_export_expansions() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [[lookup]]
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+            "#,
+        )
+        .expect("valid configuration");
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let entries = export_expansions(&expander, ["divide", "unknown", ""], &QueryParams::new())?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].term, "divide");
+        assert!(!entries[0].variants.is_empty());
+        assert!(!entries[0].variants.iter().any(|v| v == "divide"));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_export_keep_original() {
+        let mut entries = vec![LexiconEntry {
+            term: "zee".into(),
+            variants: vec!["see".into(), "sea".into()],
+            scores: vec![0.5, 0.8],
+        }];
+        keep_original(&mut entries);
+        assert_eq!(entries[0].variants, vec!["zee", "see", "sea"]);
+        assert_eq!(entries[0].scores, vec![0.8, 0.5, 0.8]);
+        // idempotent
+        keep_original(&mut entries);
+        assert_eq!(entries[0].variants.len(), 3);
+    }
+}
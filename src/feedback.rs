@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use tracing::warn;
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::{Error, TermExpansions};
+
+/// Configuration for collecting feedback on the quality of expansions
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct FeedbackConfig {
+    /// File to append the feedback to (JSON lines), it is read back at startup
+    file: PathBuf,
+
+    /// If set, the scores of expansions with too much negative feedback are multiplied by this factor and
+    /// the expansions are moved to the end
+    downrank: Option<f64>,
+
+    /// Number of down votes (minus up votes) from which an expansion is downranked
+    min_votes: i64,
+
+    /// Maximum length (in characters) of the term, expansion and module of feedback
+    max_length: usize,
+
+    /// Maximum number of term/expansion pairs to keep a tally for, feedback on further pairs is refused
+    max_pairs: usize,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::from("feedback.jsonl"),
+            downrank: None,
+            min_votes: 3,
+            max_length: 100,
+            max_pairs: 100000,
+        }
+    }
+}
+
+impl FeedbackConfig {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_downrank(mut self, factor: f64, min_votes: i64) -> Self {
+        self.downrank = Some(factor);
+        self.min_votes = min_votes;
+        self
+    }
+
+    /// Set the maximum number of term/expansion pairs to keep a tally for
+    pub fn with_max_pairs(mut self, max_pairs: usize) -> Self {
+        self.max_pairs = max_pairs;
+        self
+    }
+
+    pub fn file(&self) -> &Path {
+        self.file.as_path()
+    }
+
+    pub fn downrank(&self) -> Option<f64> {
+        self.downrank
+    }
+}
+
+/// The verdict of a user on an expansion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Up,
+    Down,
+}
+
+/// Feedback of a user on a single expansion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Feedback {
+    /// The term from the query
+    pub term: String,
+    /// The expansion of the term
+    pub expansion: String,
+    /// The ID of the module that provided the expansion
+    #[serde(default)]
+    pub module: Option<String>,
+    pub verdict: Verdict,
+    /// Time the feedback was received (RFC 3339), set by the server
+    #[serde(default)]
+    #[cfg_attr(feature = "server", schema(read_only))]
+    pub timestamp: Option<String>,
+    /// Pseudonymous identifier of the API key or client that gave the feedback, set by the server
+    #[serde(default)]
+    #[cfg_attr(feature = "server", schema(read_only))]
+    pub voter: Option<String>,
+}
+
+/// The votes on a term and expansion, at most one per voter
+type Votes = HashMap<String, Verdict>;
+
+/// Records feedback and keeps a tally of the votes per term and expansion
+pub struct FeedbackStore {
+    config: FeedbackConfig,
+    file: Mutex<File>,
+
+    /// The votes per term and expansion
+    tally: RwLock<HashMap<(String, String), Votes>>,
+}
+
+impl FeedbackStore {
+    /// Opens the feedback file, reading back the feedback recorded earlier
+    pub fn new(config: FeedbackConfig) -> Result<Self, Error> {
+        let mut tally = HashMap::new();
+        if config.file.exists() {
+            let file = File::open(&config.file)?;
+            for (i, line) in BufReader::new(file).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<Feedback>(&line) {
+                    Ok(feedback) => Self::count(&mut tally, &feedback),
+                    Err(e) => warn!(
+                        "Skipping invalid feedback on line {} of {}: {}",
+                        i + 1,
+                        config.file.display(),
+                        e
+                    ),
+                }
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.file)
+            .map_err(|e| {
+                Error::LoadError(format!(
+                    "Unable to open feedback file {}: {}",
+                    config.file.display(),
+                    e
+                ))
+            })?;
+        Ok(Self {
+            config,
+            file: Mutex::new(file),
+            tally: RwLock::new(tally),
+        })
+    }
+
+    pub fn config(&self) -> &FeedbackConfig {
+        &self.config
+    }
+
+    /// Counts the vote of feedback, replacing an earlier vote of the same voter. Feedback without a voter counts as a
+    /// single anonymous vote.
+    fn count(tally: &mut HashMap<(String, String), Votes>, feedback: &Feedback) {
+        tally
+            .entry((feedback.term.clone(), feedback.expansion.clone()))
+            .or_default()
+            .insert(feedback.voter.clone().unwrap_or_default(), feedback.verdict);
+    }
+
+    /// Returns the number of down votes minus up votes
+    fn balance(votes: &Votes) -> i64 {
+        votes
+            .values()
+            .map(|verdict| match verdict {
+                Verdict::Up => -1,
+                Verdict::Down => 1,
+            })
+            .sum()
+    }
+
+    /// Records feedback of a voter (an API key or client address), the timestamp is set to the current time. Each
+    /// voter has one vote per term and expansion, a later vote replaces the earlier one. Returns false if the voter
+    /// already cast the same vote, which is then not recorded again.
+    pub fn record(&self, mut feedback: Feedback, voter: &str) -> Result<bool, Error> {
+        if feedback.term.is_empty() || feedback.expansion.is_empty() {
+            return Err(Error::InvalidParameter(
+                "Feedback requires a term and an expansion".into(),
+            ));
+        }
+        if [
+            feedback.term.as_str(),
+            feedback.expansion.as_str(),
+            feedback.module.as_deref().unwrap_or_default(),
+        ]
+        .iter()
+        .any(|value| value.chars().count() > self.config.max_length)
+        {
+            return Err(Error::InvalidParameter(format!(
+                "The term, expansion and module of feedback may be at most {} characters long",
+                self.config.max_length
+            )));
+        }
+        feedback.timestamp =
+            Some(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+        // the voter is pseudonymised, the feedback file should not hold API keys or client addresses
+        feedback.voter = Some(
+            Sha256::digest(voter.as_bytes())[..8]
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect(),
+        );
+
+        // the tally is locked while the feedback is written, so concurrent votes of the same voter are counted once
+        let mut tally = self.tally.write().unwrap_or_else(|e| e.into_inner());
+        match tally.get(&(feedback.term.clone(), feedback.expansion.clone())) {
+            Some(votes)
+                if votes.get(feedback.voter.as_deref().unwrap_or_default())
+                    == Some(&feedback.verdict) =>
+            {
+                return Ok(false);
+            }
+            None if tally.len() >= self.config.max_pairs => {
+                return Err(Error::InvalidParameter(format!(
+                    "The maximum number of expansions with feedback ({}) is reached",
+                    self.config.max_pairs
+                )));
+            }
+            _ => {}
+        }
+        let mut line = serde_json::to_string(&feedback)
+            .map_err(|e| Error::QueryExpandError(format!("Unable to serialize feedback: {}", e)))?;
+        line.push('\n');
+        self.file
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .write_all(line.as_bytes())?;
+        Self::count(&mut tally, &feedback);
+        Ok(true)
+    }
+
+    /// Returns the number of down votes minus up votes for an expansion of a term
+    pub fn votes(&self, term: &str, expansion: &str) -> i64 {
+        self.tally
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(term.to_owned(), expansion.to_owned()))
+            .map(Self::balance)
+            .unwrap_or_default()
+    }
+
+    /// Downranks the expansions that received too much negative feedback, if configured.
+    /// Returns true if any expansion was downranked.
+    pub fn downrank(&self, terms_map: &mut TermExpansions) -> bool {
+        let Some(factor) = self.config.downrank else {
+            return false;
+        };
+        let tally = self.tally.read().unwrap_or_else(|e| e.into_inner());
+        let mut changed = false;
+        for (term, termexpansions) in terms_map.iter_mut() {
+            let rejected = |expansion: &str| {
+                tally
+                    .get(&(term.clone(), expansion.to_owned()))
+                    .is_some_and(|votes| Self::balance(votes) >= self.config.min_votes)
+            };
+            for termexpansion in termexpansions.iter_mut() {
+                if termexpansion.iter().any(rejected) {
+                    termexpansion.downweight(factor, rejected);
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_feedback_downrank() -> Result<(), Error> {
+        let dir = TestDir::new("feedback");
+        let file = dir.join("feedback.jsonl");
+        let config = FeedbackConfig::new(&file).with_downrank(0.5, 2);
+        let store = FeedbackStore::new(config.clone())?;
+        let feedback: Feedback =
+            serde_json::from_str(r#"{"term": "wijn", "expansion": "wyn", "verdict": "down"}"#)
+                .expect("valid feedback");
+        assert!(store.record(feedback.clone(), "ip:127.0.0.1")?);
+        assert!(store.record(feedback.clone(), "ip:127.0.0.2")?);
+        // a voter has a single vote
+        assert!(!store.record(feedback, "ip:127.0.0.2")?);
+        store.record(
+            Feedback {
+                term: "wijn".into(),
+                expansion: "wijne".into(),
+                module: None,
+                verdict: Verdict::Down,
+                timestamp: None,
+                voter: None,
+            },
+            "ip:127.0.0.1",
+        )?;
+        assert_eq!(store.votes("wijn", "wyn"), 2);
+
+        // the tally is read back from the file
+        let store = FeedbackStore::new(config)?;
+        assert_eq!(store.votes("wijn", "wijne"), 1);
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijn".into(),
+            vec![TermExpansion::default()
+                .with_expansions(vec!["wyn".into(), "wijne".into()])
+                .with_scores(vec![1.0, 0.8])],
+        );
+        assert!(store.downrank(&mut terms_map));
+        assert_eq!(
+            terms_map["wijn"][0].expansions(),
+            &vec!["wijne".to_string(), "wyn".to_string()]
+        );
+        assert_eq!(terms_map["wijn"][0].scores(), &vec![0.8, 0.5]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_feedback_limits() -> Result<(), Error> {
+        let dir = TestDir::new("feedback-limits");
+        let config = FeedbackConfig::new(dir.join("feedback.jsonl")).with_max_pairs(1);
+        let store = FeedbackStore::new(config)?;
+        let feedback = |expansion: &str, verdict| Feedback {
+            term: "wijn".into(),
+            expansion: expansion.into(),
+            module: None,
+            verdict,
+            timestamp: None,
+            voter: None,
+        };
+        store.record(feedback("wyn", Verdict::Down), "ip:127.0.0.1")?;
+        // a voter can change their vote
+        store.record(feedback("wyn", Verdict::Up), "ip:127.0.0.1")?;
+        assert_eq!(store.votes("wijn", "wyn"), -1);
+        // no further pairs beyond max_pairs
+        assert!(store
+            .record(feedback("wijne", Verdict::Down), "ip:127.0.0.1")
+            .is_err());
+        assert!(store
+            .record(feedback(&"w".repeat(101), Verdict::Down), "ip:127.0.0.1")
+            .is_err());
+        // the voter is not stored as is
+        let contents = std::fs::read_to_string(dir.join("feedback.jsonl"))?;
+        assert!(!contents.contains("127.0.0.1"));
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use std::fmt;
+
+/// The type of the value of a global parameter, for documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlobalParamType {
+    String,
+    Boolean,
+    Integer,
+}
+
+/// A global query parameter, i.e. one that is not passed to a specific module (as `module.key`). This is the single
+/// registry of their names, shared by the library, the webservice, the command line tool and the API documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GlobalParam {
+    /// The query to expand
+    Query,
+    Include,
+    Exclude,
+    TermOverrides,
+    ExpansionSet,
+    Highlight,
+    EditDistance,
+    ExpandNegated,
+    GroupBy,
+    Offset,
+    Limit,
+    Coverage,
+    MinimumShouldMatch,
+    Seed,
+    Suggest,
+    ApiKey,
+}
+
+impl GlobalParam {
+    /// All global parameters, in the order in which they are documented
+    pub const ALL: [Self; 16] = [
+        Self::Query,
+        Self::Include,
+        Self::Exclude,
+        Self::TermOverrides,
+        Self::ExpansionSet,
+        Self::Highlight,
+        Self::EditDistance,
+        Self::ExpandNegated,
+        Self::GroupBy,
+        Self::Offset,
+        Self::Limit,
+        Self::Coverage,
+        Self::MinimumShouldMatch,
+        Self::Seed,
+        Self::Suggest,
+        Self::ApiKey,
+    ];
+
+    /// Returns the name of the parameter as passed in requests
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Query => "q",
+            Self::Include => "include",
+            Self::Exclude => "exclude",
+            Self::TermOverrides => "term_overrides",
+            Self::ExpansionSet => "expansionset",
+            Self::Highlight => "highlight",
+            Self::EditDistance => "edit_distance",
+            Self::ExpandNegated => "expand_negated",
+            Self::GroupBy => "group_by",
+            Self::Offset => "offset",
+            Self::Limit => "limit",
+            Self::Coverage => "coverage",
+            Self::MinimumShouldMatch => "minimum_should_match",
+            Self::Seed => "seed",
+            Self::Suggest => "suggest",
+            Self::ApiKey => "api_key",
+        }
+    }
+
+    /// Looks up a parameter by name, returns None if there is no global parameter by that name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|param| param.name() == name)
+    }
+
+    pub fn value_type(&self) -> GlobalParamType {
+        match self {
+            Self::Highlight | Self::EditDistance | Self::ExpandNegated | Self::Suggest => {
+                GlobalParamType::Boolean
+            }
+            Self::Offset | Self::Limit | Self::Seed => GlobalParamType::Integer,
+            _ => GlobalParamType::String,
+        }
+    }
+
+    /// Returns a description of the parameter, for documentation
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Query => "A query in Lucene syntax",
+            Self::Include => "Comma separated list of modules to include (by ID)",
+            Self::Exclude => "Comma separated list of modules to exclude (by ID)",
+            Self::TermOverrides => "JSON object that overrides the modules for specific terms, mapping each term to the modules to include and/or exclude (by ID) for it, e.g. {\"rembrandt\": {\"include\": [\"names\"]}}",
+            Self::ExpansionSet => "ID of a stored expansion set, expands the query with the expansions in that set instead of with the modules",
+            Self::Highlight => "Include the spans of each expansion that differ from the original term, so they can be highlighted",
+            Self::EditDistance => "Include the edit distance of each expansion to the original term, regardless of the module that provided it",
+            Self::ExpandNegated => "Also expand terms that are excluded in the query (NOT term, -term), which are not expanded by default as that broadens the exclusion",
+            Self::GroupBy => "Also return the expansions of each term grouped by the kind of expansions (kind), the module (module) or the language of the module (language)",
+            Self::Offset => "Skip this many expansions of each term (over all modules, scored expansions ordered by score), the response then holds the total number of expansions of each term",
+            Self::Limit => "Return at most this many expansions of each term (over all modules, scored expansions ordered by score), the response then holds the total number of expansions of each term",
+            Self::Coverage => "How the expansions are combined with each term in the expanded query: the expansions replace the term (replace, default), the term is added to them (or), or the term is added and the minimum_should_match parameter applies (minimum_should_match)",
+            Self::MinimumShouldMatch => "Minimum number (e.g. 2, -1) or percentage (e.g. 75%) of the alternatives of each term that should match, returned alongside the expanded query for search backends that support it; implies coverage=minimum_should_match",
+            Self::Seed => "Seed for modules with nondeterministic results, echoed in the reproducibility information of the response",
+            Self::Suggest => "Instead of expanding the query, return the best spelling correction per term and the corrected query (did you mean), using only the spelling-oriented modules (analiticcl, fst)",
+            Self::ApiKey => "API key, may also be passed via the X-Api-Key header",
+        }
+    }
+}
+
+impl fmt::Display for GlobalParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_globalparams_names() {
+        for param in GlobalParam::ALL {
+            assert_eq!(GlobalParam::from_name(param.name()), Some(param));
+        }
+        assert_eq!(
+            GlobalParam::from_name("minimum_should_match"),
+            Some(GlobalParam::MinimumShouldMatch)
+        );
+        assert_eq!(GlobalParam::from_name("distance"), None);
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::globalparams::GlobalParam;
+use crate::{Error, QueryExpander, QueryParams, TermExpansions};
+
+/// Group for expansions whose module is unknown (e.g. from a stored expansion set) or has no language configured
+const UNKNOWN: &str = "unknown";
+
+/// What to group the expansions of each term by, as passed in the `group_by` parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// The kind of expansions the module provides (spelling, synonym, translation, related)
+    Kind,
+    /// The module that provided the expansions
+    Module,
+    /// The language of the expansions, as configured for the module
+    Language,
+}
+
+impl GroupBy {
+    /// Reads the global `group_by` parameter, returns None if it is not passed
+    pub fn from_params(params: &QueryParams) -> Result<Option<Self>, Error> {
+        match params
+            .global(GlobalParam::GroupBy)
+            .and_then(|value| value.as_str())
+        {
+            None => Ok(None),
+            Some("kind") => Ok(Some(Self::Kind)),
+            Some("module") => Ok(Some(Self::Module)),
+            Some("language") => Ok(Some(Self::Language)),
+            Some(_) => Err(Error::InvalidParameter(
+                "group_by must be kind, module or language".into(),
+            )),
+        }
+    }
+}
+
+/// The expansions of each term, grouped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Grouping {
+    /// What the expansions are grouped by
+    by: GroupBy,
+    /// Maps each term to the groups, and those to their expansions (without duplicates, in order)
+    terms: HashMap<String, BTreeMap<String, Vec<String>>>,
+}
+
+impl Grouping {
+    /// Groups the expansions of each term, looking up the modules that provided them in the expander
+    pub fn new(expander: &QueryExpander, terms_map: &TermExpansions, by: GroupBy) -> Self {
+        let terms = terms_map
+            .iter()
+            .map(|(term, termexpansions)| {
+                let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                let mut seen: HashSet<(&str, &str)> = HashSet::new();
+                for termexpansion in termexpansions.iter() {
+                    let module = termexpansion
+                        .source_id()
+                        .and_then(|id| expander.get_module(id));
+                    let group = match by {
+                        GroupBy::Module => termexpansion.source_id(),
+                        GroupBy::Kind => module.map(|module| module.expansion_kind().as_str()),
+                        GroupBy::Language => module.and_then(|module| module.common().language()),
+                    }
+                    .unwrap_or(UNKNOWN);
+                    let expansions = groups.entry(group.to_owned()).or_default();
+                    for expansion in termexpansion.iter() {
+                        if seen.insert((group, expansion)) {
+                            expansions.push(expansion.to_owned());
+                        }
+                    }
+                }
+                (term.clone(), groups)
+            })
+            .collect();
+        Self { by, terms }
+    }
+
+    pub fn by(&self) -> GroupBy {
+        self.by
+    }
+
+    /// Returns the groups of a term, mapped to their expansions
+    pub fn groups(&self, term: &str) -> Option<&BTreeMap<String, Vec<String>>> {
+        self.terms.get(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::lookup::{LookupConfig, LookupModule};
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_grouping() -> Result<(), Error> {
+        assert_eq!(GroupBy::from_params(&QueryParams::new())?, None);
+        assert!(
+            GroupBy::from_params(&QueryParams::new().with("", "group_by", "x".into())).is_err()
+        );
+        let config: LookupConfig = toml::from_str(
+            "id = \"translations\"\nname = \"Translations\"\nfile = \"translations.tsv\"\nexpansion_kind = \"translation\"\nlanguage = \"en\"",
+        )
+        .expect("valid configuration");
+        let module = LookupModule::new(config);
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijn".into(),
+            vec![
+                TermExpansion::default()
+                    .with_source(&module)
+                    .with_expansions(vec!["wine".into()]),
+                TermExpansion::default().with_expansions(vec!["wyn".into(), "wine".into()]),
+            ],
+        );
+        let mut expander = QueryExpander::new();
+        expander.register_module(Box::new(module))?;
+        let grouping = Grouping::new(&expander, &terms_map, GroupBy::Kind);
+        assert_eq!(grouping.by(), GroupBy::Kind);
+        assert_eq!(grouping.terms["wijn"]["translation"], vec!["wine"]);
+        assert_eq!(grouping.terms["wijn"]["unknown"], vec!["wyn", "wine"]);
+        let grouping = Grouping::new(&expander, &terms_map, GroupBy::Language);
+        assert_eq!(grouping.terms["wijn"]["en"], vec!["wine"]);
+        Ok(())
+    }
+}
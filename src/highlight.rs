@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::TermExpansions;
+
+/// A span of characters in an expansion, as begin (inclusive) and end (exclusive) character offsets
+pub type Span = (usize, usize);
+
+/// Maps terms to their expansions, and those to the spans in the expansion that differ from the term
+pub type Highlights = HashMap<String, HashMap<String, Vec<Span>>>;
+
+/// Computes the highlights for all expansions, so a frontend can show what changed with respect to the original term
+pub fn highlights(terms_map: &TermExpansions) -> Highlights {
+    terms_map
+        .iter()
+        .map(|(term, termexpansions)| {
+            let spans = termexpansions
+                .iter()
+                .flat_map(|termexpansion| termexpansion.iter())
+                .map(|expansion| (expansion.to_owned(), changed_spans(term, expansion)))
+                .collect();
+            (term.clone(), spans)
+        })
+        .collect()
+}
+
+/// Returns the spans of the expansion that are not part of the longest common subsequence with the term
+/// (compared case-insensitively), i.e. the characters that were inserted or substituted.
+/// For example, `belangryk` against `belangrijk` yields the span of `y`.
+pub fn changed_spans(term: &str, expansion: &str) -> Vec<Span> {
+    let a: Vec<char> = term.chars().flat_map(char::to_lowercase).collect();
+    let b: Vec<char> = expansion.chars().flat_map(char::to_lowercase).collect();
+    if b.len() != expansion.chars().count() {
+        // lowercasing changed the length, so offsets would not match: consider the whole expansion changed
+        return vec![(0, expansion.chars().count())];
+    }
+    // lcs[i][j] is the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut spans: Vec<Span> = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while j < b.len() {
+        if i < a.len() && a[i] == b[j] {
+            i += 1;
+        } else if i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1] {
+            // character of the term was deleted, no span in the expansion
+            i += 1;
+            continue;
+        } else {
+            match spans.last_mut() {
+                Some((_, end)) if *end == j => *end = j + 1,
+                _ => spans.push((j, j + 1)),
+            }
+        }
+        j += 1;
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_highlight_spans() {
+        assert_eq!(changed_spans("belangrijk", "belangryk"), vec![(7, 8)]);
+        assert_eq!(changed_spans("belangryk", "belangrijk"), vec![(7, 9)]);
+        assert_eq!(changed_spans("kaas", "Kaas"), vec![]);
+        assert_eq!(changed_spans("kaas", "kaasje"), vec![(4, 6)]);
+        assert_eq!(changed_spans("wijn", "vinum"), vec![(0, 1), (3, 5)]);
+        assert_eq!(changed_spans("", "ab"), vec![(0, 2)]);
+    }
+
+    #[test]
+    pub fn test002_highlights() {
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijn".into(),
+            vec![TermExpansion::default().with_expansions(vec!["wyn".into(), "wijne".into()])],
+        );
+        let highlights = highlights(&terms_map);
+        assert_eq!(highlights["wijn"]["wyn"], vec![(1, 2)]);
+        assert_eq!(highlights["wijn"]["wijne"], vec![(4, 5)]);
+    }
+}
@@ -0,0 +1,335 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::{Error, TermExpansions};
+
+/// The type of search backend to check expansions against
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexBackend {
+    /// Document frequencies are obtained with a filters aggregation over `match_phrase` queries, so variants are analyzed like the field
+    Elasticsearch,
+    /// Document frequencies are obtained from the terms component (`/terms`), variants must match the indexed terms exactly
+    Solr,
+}
+
+/// What to do with expansions that do not occur in the index
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// Remove the expansion
+    #[default]
+    Drop,
+    /// Multiply its score by the configured weight and move it to the end
+    Downweight,
+}
+
+/// Configuration for the post-filter that checks expansions against the term dictionary of a search index,
+/// so users aren't offered expansions that return no results
+#[derive(Debug, Deserialize, Clone)]
+pub struct IndexFilterConfig {
+    backend: IndexBackend,
+
+    /// URL of the index (Elasticsearch, e.g. `http://localhost:9200/myindex`) or core/collection (Solr)
+    url: String,
+
+    /// The field to look up the expansions in
+    field: String,
+
+    #[serde(default)]
+    action: FilterAction,
+
+    /// The factor scores are multiplied with if the action is `downweight`
+    #[serde(default = "IndexFilterConfig::default_weight")]
+    weight: f64,
+
+    /// Timeout for requests to the backend, in seconds
+    #[serde(default = "IndexFilterConfig::default_timeout")]
+    timeout: u64,
+}
+
+impl IndexFilterConfig {
+    fn default_weight() -> f64 {
+        0.1
+    }
+
+    fn default_timeout() -> u64 {
+        5
+    }
+
+    pub fn backend(&self) -> IndexBackend {
+        self.backend
+    }
+
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    pub fn field(&self) -> &str {
+        self.field.as_str()
+    }
+
+    pub fn action(&self) -> FilterAction {
+        self.action
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout)
+    }
+
+    /// Drops or downweights all expansions with a document frequency of zero
+    pub fn apply(&self, terms_map: &mut TermExpansions, frequencies: &HashMap<String, u64>) {
+        // expansions the backend reported nothing about are kept
+        let absent = |expansion: &str| frequencies.get(expansion) == Some(&0);
+        for termexpansions in terms_map.values_mut() {
+            for termexpansion in termexpansions.iter_mut() {
+                match self.action {
+                    FilterAction::Drop => termexpansion.retain(|expansion| !absent(expansion)),
+                    FilterAction::Downweight => termexpansion.downweight(self.weight, absent),
+                }
+            }
+            termexpansions.retain(|termexpansion| !termexpansion.is_empty());
+        }
+    }
+}
+
+/// Builds an Elasticsearch search request that counts the documents matching each of the variants
+#[cfg_attr(not(feature = "indexfilter"), allow(dead_code))]
+fn elasticsearch_request(field: &str, variants: &[&str]) -> Value {
+    let mut filters = serde_json::Map::new();
+    for (i, variant) in variants.iter().enumerate() {
+        filters.insert(i.to_string(), json!({ "match_phrase": { field: variant } }));
+    }
+    json!({
+        "size": 0,
+        "aggs": { "df": { "filters": { "filters": filters } } }
+    })
+}
+
+/// Extracts the document frequencies from the response to a request built by `elasticsearch_request()`
+#[cfg_attr(not(feature = "indexfilter"), allow(dead_code))]
+fn parse_elasticsearch(response: &Value, variants: &[&str]) -> Result<HashMap<String, u64>, Error> {
+    let buckets = response
+        .pointer("/aggregations/df/buckets")
+        .and_then(|buckets| buckets.as_object())
+        .ok_or_else(|| Error::QueryExpandError("Unexpected response from Elasticsearch".into()))?;
+    let mut frequencies = HashMap::new();
+    for (i, variant) in variants.iter().enumerate() {
+        if let Some(count) = buckets
+            .get(&i.to_string())
+            .and_then(|bucket| bucket.get("doc_count"))
+            .and_then(|count| count.as_u64())
+        {
+            frequencies.insert(variant.to_string(), count);
+        }
+    }
+    Ok(frequencies)
+}
+
+/// Extracts the document frequencies from a Solr terms component response (with `json.nl=map`).
+/// Variants that are not in the response do not occur in the index.
+#[cfg_attr(not(feature = "indexfilter"), allow(dead_code))]
+fn parse_solr(
+    response: &Value,
+    field: &str,
+    variants: &[&str],
+) -> Result<HashMap<String, u64>, Error> {
+    let terms = response
+        .get("terms")
+        .and_then(|terms| terms.get(field))
+        .and_then(|terms| terms.as_object())
+        .ok_or_else(|| Error::QueryExpandError("Unexpected response from Solr".into()))?;
+    Ok(variants
+        .iter()
+        .map(|variant| {
+            (
+                variant.to_string(),
+                terms
+                    .get(*variant)
+                    .and_then(|count| count.as_u64())
+                    .unwrap_or(0),
+            )
+        })
+        .collect())
+}
+
+/// Checks expansions against the term dictionary of a search index
+#[cfg(feature = "indexfilter")]
+pub struct IndexFilter {
+    config: IndexFilterConfig,
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "indexfilter")]
+impl IndexFilter {
+    /// Number of variants looked up in a single request
+    const BATCH_SIZE: usize = 100;
+
+    pub fn new(config: IndexFilterConfig) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout())
+            .build()
+            .map_err(|e| Error::LoadError(format!("Unable to instantiate HTTP client: {}", e)))?;
+        Ok(Self { config, client })
+    }
+
+    pub fn config(&self) -> &IndexFilterConfig {
+        &self.config
+    }
+
+    /// Looks up the document frequencies of the variants in the index
+    pub async fn document_frequencies(
+        &self,
+        variants: &[&str],
+    ) -> Result<HashMap<String, u64>, Error> {
+        let mut frequencies = HashMap::new();
+        for batch in variants.chunks(Self::BATCH_SIZE) {
+            frequencies.extend(match self.config.backend {
+                IndexBackend::Elasticsearch => {
+                    let url = format!("{}/_search", self.config.url.trim_end_matches('/'));
+                    let request = elasticsearch_request(&self.config.field, batch);
+                    let response = self
+                        .send(
+                            self.client
+                                .post(&url)
+                                .header("Content-Type", "application/json")
+                                .body(request.to_string()),
+                        )
+                        .await?;
+                    parse_elasticsearch(&response, batch)?
+                }
+                IndexBackend::Solr => {
+                    // the terms component takes a comma separated list, commas can't be escaped
+                    let batch: Vec<&str> = batch
+                        .iter()
+                        .copied()
+                        .filter(|variant| !variant.contains(','))
+                        .collect();
+                    let url = format!("{}/terms", self.config.url.trim_end_matches('/'));
+                    let list = batch.join(",");
+                    let query = [
+                        ("terms.fl", self.config.field.as_str()),
+                        ("terms.list", list.as_str()),
+                        ("terms.limit", "-1"),
+                        ("wt", "json"),
+                        ("json.nl", "map"),
+                    ];
+                    let response = self.send(self.client.get(&url).query(&query)).await?;
+                    parse_solr(&response, &self.config.field, &batch)?
+                }
+            });
+        }
+        Ok(frequencies)
+    }
+
+    /// Drops or downweights all expansions that do not occur in the index
+    pub async fn filter(&self, terms_map: &mut TermExpansions) -> Result<(), Error> {
+        let mut variants: Vec<&str> = terms_map
+            .values()
+            .flatten()
+            .flat_map(|termexpansion| termexpansion.iter())
+            .collect();
+        variants.sort_unstable();
+        variants.dedup();
+        let frequencies = self.document_frequencies(&variants).await?;
+        self.config.apply(terms_map, &frequencies);
+        Ok(())
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value, Error> {
+        let response = request.send().await.map_err(|e| {
+            Error::QueryExpandError(format!("Unable to reach {}: {}", self.config.url, e))
+        })?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| Error::QueryExpandError(format!("Unable to read response: {}", e)))?;
+        if !status.is_success() {
+            return Err(Error::QueryExpandError(format!(
+                "{} returned {}",
+                self.config.url, status
+            )));
+        }
+        serde_json::from_str(&body).map_err(|e| {
+            Error::QueryExpandError(format!("Invalid response from {}: {}", self.config.url, e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    fn terms_map() -> TermExpansions {
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wine".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["wyn".into(), "wijn".into()]),
+                TermExpansion::default()
+                    .with_expansions(vec!["vinum".into()])
+                    .with_scores(vec![0.5]),
+            ],
+        );
+        terms_map
+    }
+
+    #[test]
+    pub fn test001_indexfilter_elasticsearch() -> Result<(), Error> {
+        let variants = ["wyn", "wijn"];
+        let request = elasticsearch_request("text", &variants);
+        assert_eq!(
+            request["aggs"]["df"]["filters"]["filters"]["1"],
+            json!({"match_phrase": {"text": "wijn"}})
+        );
+        let response = json!({"aggregations": {"df": {"buckets": {"0": {"doc_count": 0}, "1": {"doc_count": 12}}}}});
+        let frequencies = parse_elasticsearch(&response, &variants)?;
+        assert_eq!(frequencies.get("wyn"), Some(&0));
+        assert_eq!(frequencies.get("wijn"), Some(&12));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_indexfilter_drop() -> Result<(), Error> {
+        let config: IndexFilterConfig = toml::from_str(
+            "backend = \"solr\"\nurl = \"http://localhost:8983/solr/core\"\nfield = \"text\"",
+        )
+        .expect("valid configuration");
+        let response = json!({"terms": {"text": {"wijn": 3}}});
+        let frequencies = parse_solr(&response, "text", &["wyn", "wijn", "vinum"])?;
+        let mut terms_map = terms_map();
+        config.apply(&mut terms_map, &frequencies);
+        let termexpansions = &terms_map["wine"];
+        // the second module has no expansions left
+        assert_eq!(termexpansions.len(), 1);
+        assert_eq!(termexpansions[0].expansions(), &vec!["wijn".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_indexfilter_downweight() {
+        let config: IndexFilterConfig = toml::from_str(
+            "backend = \"elasticsearch\"\nurl = \"http://localhost:9200/index\"\nfield = \"text\"\naction = \"downweight\"\nweight = 0.5",
+        )
+        .expect("valid configuration");
+        let frequencies: HashMap<String, u64> =
+            [("wyn".to_string(), 0), ("wijn".to_string(), 3)].into();
+        let mut terms_map = terms_map();
+        config.apply(&mut terms_map, &frequencies);
+        let termexpansions = &terms_map["wine"];
+        assert_eq!(
+            termexpansions[0].expansions(),
+            &vec!["wijn".to_string(), "wyn".to_string()]
+        );
+        assert_eq!(termexpansions[0].scores(), &vec![1.0, 0.5]);
+        // unknown to the backend, so kept as is
+        assert_eq!(termexpansions[1].scores(), &vec![0.5]);
+    }
+}
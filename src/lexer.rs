@@ -1,8 +1,10 @@
 use logos::Logos;
 
-#[derive(Logos, Debug, PartialEq)]
+#[derive(Logos, Debug, Clone, PartialEq)]
 pub enum Term<'a> {
     #[regex(r"\w+", |lex| lex.slice())]
+    // acronyms with periods (e.g. `N.V.`) are single terms, rather than a term per letter
+    #[regex(r"\p{Lu}(\.\p{Lu})+\.?", |lex| lex.slice())]
     Singular(&'a str),
 
     // Or regular expressions.
@@ -23,6 +25,8 @@ pub enum Term<'a> {
     #[regex(r"[\s\t\n]+")]
     #[regex(r"[\+\-]")]
     #[regex(r"[\^~][0-9\.]+")]
+    #[token("~")]
+    #[regex(r"\w+:")]
     #[regex(r"\{.*\}")]
     None(&'a str),
 }
@@ -55,6 +59,82 @@ impl<'a> Term<'a> {
         (terms, query_template)
     }
 
+    /// Extract terms from a query along with information on how they occur in the query:
+    /// whether they are restricted to a field (`field:term`), whether they are marked as fuzzy (`term~`)
+    /// and whether they are excluded (`NOT term`, `!term`, `-term`, or in a group that is excluded)
+    pub fn analyze_query(query: &'a str) -> Vec<QueryTerm<'a>> {
+        let mut queryterms: Vec<QueryTerm<'a>> = Vec::new();
+        let mut field = None;
+        let mut prev_is_term = false;
+        // a negation operator applies to the next term or group
+        let mut negate_next = false;
+        // a `-` only negates at the start of a term, not within a word like `foo-bar`
+        let mut at_boundary = true;
+        let mut depth = 0;
+        // the depth of the outermost negated group we are in, if any
+        let mut negated_group: Option<usize> = None;
+        for token in Term::lexer(query).flatten() {
+            match token {
+                Term::None(s) if s.ends_with(':') => {
+                    field = Some(&s[..s.len() - 1]);
+                    prev_is_term = false;
+                    at_boundary = false;
+                }
+                Term::None(s) => {
+                    if prev_is_term && s.starts_with('~') {
+                        if let Some(queryterm) = queryterms.last_mut() {
+                            queryterm.fuzzy = true;
+                        }
+                    }
+                    match s {
+                        "NOT" | "!" => negate_next = true,
+                        "-" if at_boundary => negate_next = true,
+                        "(" => {
+                            depth += 1;
+                            if negate_next && negated_group.is_none() {
+                                negated_group = Some(depth);
+                            }
+                            negate_next = false;
+                        }
+                        ")" => {
+                            if negated_group == Some(depth) {
+                                negated_group = None;
+                            }
+                            depth = depth.saturating_sub(1);
+                            negate_next = false;
+                        }
+                        s if s.trim().is_empty() => {}
+                        _ => negate_next = false,
+                    }
+                    at_boundary = s.trim().is_empty() || s == "(";
+                    field = None;
+                    prev_is_term = false;
+                }
+                term => {
+                    queryterms.push(QueryTerm {
+                        term,
+                        field: field.take(),
+                        fuzzy: false,
+                        negated: negate_next || negated_group.is_some(),
+                    });
+                    negate_next = false;
+                    prev_is_term = true;
+                    at_boundary = false;
+                }
+            }
+        }
+        queryterms
+    }
+
+    /// Returns the type of the term
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Singular(..) => "singular",
+            Self::Phrase(..) => "phrase",
+            Self::None(..) => "none",
+        }
+    }
+
     /// Returns the term as a string
     pub fn as_str(&self) -> &'a str {
         match self {
@@ -63,6 +143,120 @@ impl<'a> Term<'a> {
             Self::None(..) => "",
         }
     }
+
+    /// Returns true if the term looks like an acronym, see [`is_acronym()`]
+    pub fn is_acronym(&self) -> bool {
+        matches!(self, Self::Singular(..)) && is_acronym(self.as_str())
+    }
+
+    /// Returns the normalised form of the term if it is an acronym, see [`normalize_acronym()`]
+    pub fn acronym(&self) -> Option<String> {
+        self.is_acronym().then(|| normalize_acronym(self.as_str()))
+    }
+}
+
+/// Returns true if a word looks like an acronym: at least two capitals and nothing else but periods, e.g. `VOC` or
+/// `N.V.`
+pub fn is_acronym(s: &str) -> bool {
+    s.chars().filter(|c| c.is_uppercase()).count() >= 2
+        && s.chars().all(|c| c.is_uppercase() || c == '.')
+}
+
+/// Returns the normalised form of an acronym: without periods, so `N.V.` and `NV` are the same
+pub fn normalize_acronym(s: &str) -> String {
+    s.chars().filter(|c| *c != '.').collect()
+}
+
+/// A term as it occurs in a query, see `Term::analyze_query()`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm<'a> {
+    pub term: Term<'a>,
+    /// The field the term is restricted to (`field:term`)
+    pub field: Option<&'a str>,
+    /// Whether the term is marked as fuzzy (`term~`)
+    pub fuzzy: bool,
+    /// Whether the term is excluded (`NOT term`, `-term`)
+    pub negated: bool,
+}
+
+/// The context of the terms in a query, passed to modules so they can take neighbouring terms and fields into
+/// account, see `Module::expand_query_in_context()`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryContext<'a> {
+    queryterms: Vec<QueryTerm<'a>>,
+}
+
+impl<'a> QueryContext<'a> {
+    /// Analyzes a query to determine the context of its terms
+    pub fn from_query(query: &'a str) -> Self {
+        Self {
+            queryterms: Term::analyze_query(query),
+        }
+    }
+
+    /// Context for terms without a query, only the neighbouring terms are known
+    pub fn from_terms(terms: &[Term<'a>]) -> Self {
+        Self {
+            queryterms: terms
+                .iter()
+                .map(|term| QueryTerm {
+                    term: term.clone(),
+                    field: None,
+                    fuzzy: false,
+                    negated: false,
+                })
+                .collect(),
+        }
+    }
+
+    /// All terms in the query, in order
+    pub fn queryterms(&self) -> &[QueryTerm<'a>] {
+        &self.queryterms
+    }
+
+    /// Returns the first occurrence of the term in the query
+    pub fn get(&self, term: &str) -> Option<&QueryTerm<'a>> {
+        self.queryterms
+            .iter()
+            .find(|queryterm| queryterm.term.as_str() == term)
+    }
+
+    /// Returns the field the term is restricted to (in its first occurrence), if any
+    pub fn field(&self, term: &str) -> Option<&'a str> {
+        self.get(term).and_then(|queryterm| queryterm.field)
+    }
+
+    /// Returns true if all occurrences of the term in the query are excluded (`NOT term`, `-term`), false if the
+    /// term does not occur
+    pub fn is_negated(&self, term: &str) -> bool {
+        let mut occurrences = self
+            .queryterms
+            .iter()
+            .filter(|queryterm| queryterm.term.as_str() == term)
+            .peekable();
+        occurrences.peek().is_some() && occurrences.all(|queryterm| queryterm.negated)
+    }
+
+    /// Returns up to `window` terms preceding and following the (first occurrence of the) term, in query order
+    pub fn neighbours(&self, term: &str, window: usize) -> (Vec<&'a str>, Vec<&'a str>) {
+        let Some(index) = self
+            .queryterms
+            .iter()
+            .position(|queryterm| queryterm.term.as_str() == term)
+        else {
+            return (Vec::new(), Vec::new());
+        };
+        let before = self.queryterms[index.saturating_sub(window)..index]
+            .iter()
+            .map(|queryterm| queryterm.term.as_str())
+            .collect();
+        let after = self.queryterms[index + 1..]
+            .iter()
+            .take(window)
+            .map(|queryterm| queryterm.term.as_str())
+            .collect();
+        (before, after)
+    }
 }
 
 #[cfg(test)]
@@ -182,4 +376,99 @@ mod tests {
             )
         )
     }
+
+    #[test]
+    pub fn test008_lexer_field() {
+        let terms = Term::extract_from_query("title:foo bar~");
+        assert_eq!(
+            terms,
+            (
+                vec!(Term::Singular("foo".into()), Term::Singular("bar".into())),
+                "title:{{foo}} {{bar}}~".into()
+            )
+        )
+    }
+
+    #[test]
+    pub fn test009_lexer_analyze() {
+        let terms = Term::analyze_query("title:\"foo bar\" AND bar~2 baz");
+        assert_eq!(
+            terms,
+            vec!(
+                QueryTerm {
+                    term: Term::Phrase("foo bar"),
+                    field: Some("title"),
+                    fuzzy: false,
+                    negated: false
+                },
+                QueryTerm {
+                    term: Term::Singular("bar"),
+                    field: None,
+                    fuzzy: true,
+                    negated: false
+                },
+                QueryTerm {
+                    term: Term::Singular("baz"),
+                    field: None,
+                    fuzzy: false,
+                    negated: false
+                }
+            )
+        )
+    }
+
+    #[test]
+    pub fn test010_lexer_context() {
+        let context = QueryContext::from_query("title:oude AND kaas OR wijn brood");
+        assert_eq!(context.field("oude"), Some("title"));
+        assert_eq!(context.field("kaas"), None);
+        assert_eq!(context.neighbours("wijn", 1), (vec!["kaas"], vec!["brood"]));
+        assert_eq!(
+            context.neighbours("oude", 5),
+            (vec![], vec!["kaas", "wijn", "brood"])
+        );
+        assert_eq!(context.neighbours("bier", 1), (vec![], vec![]));
+    }
+
+    #[test]
+    pub fn test011_lexer_negation() {
+        let context = QueryContext::from_query(
+            "kaas -wijn NOT title:bier foo-bar AND !(brood OR (ham kaas)) melk -kaas",
+        );
+        let negated: Vec<&str> = context
+            .queryterms()
+            .iter()
+            .filter(|queryterm| queryterm.negated)
+            .map(|queryterm| queryterm.term.as_str())
+            .collect();
+        assert_eq!(
+            negated,
+            vec!["wijn", "bier", "brood", "ham", "kaas", "kaas"]
+        );
+        assert!(context.is_negated("wijn"));
+        assert!(!context.is_negated("bar"));
+        assert!(!context.is_negated("melk"));
+        // kaas also occurs without negation
+        assert!(!context.is_negated("kaas"));
+        assert!(!context.is_negated("appel"));
+    }
+
+    #[test]
+    pub fn test012_lexer_acronyms() {
+        let (terms, query_template) = Term::extract_from_query("VOC N.V. WIC-schepen Voc U.S");
+        assert_eq!(
+            terms.iter().map(|term| term.as_str()).collect::<Vec<_>>(),
+            vec!["VOC", "N.V.", "WIC", "schepen", "Voc", "U.S"]
+        );
+        assert_eq!(
+            query_template,
+            "{{VOC}} {{N.V.}} {{WIC}}-{{schepen}} {{Voc}} {{U.S}}"
+        );
+        assert_eq!(terms[0].acronym(), Some("VOC".to_string()));
+        assert_eq!(terms[1].acronym(), Some("NV".to_string()));
+        assert_eq!(terms[5].acronym(), Some("US".to_string()));
+        assert!(!terms[3].is_acronym());
+        assert!(!terms[4].is_acronym());
+        assert!(!Term::Phrase("VOC WIC").is_acronym());
+    }
 }
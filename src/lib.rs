@@ -1,51 +1,1024 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use tracing::info;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
 
+pub mod accesslog;
+pub mod analyzer;
 pub mod api;
+#[cfg(feature = "server")]
 pub mod apidocs;
+pub mod audit;
+pub mod auth;
+pub mod broccoli;
+pub mod cache;
+pub mod cancel;
+pub mod casing;
+#[cfg(feature = "client")]
+pub mod client;
+pub mod convert;
+pub mod coverage;
+pub mod cql;
+pub mod distance;
+pub mod envsubst;
+pub mod eval;
+pub mod expansionset;
+pub mod export;
+pub mod feedback;
+pub mod globalparams;
+pub mod grouping;
+pub mod highlight;
+pub mod indexfilter;
 pub mod lexer;
+pub mod limits;
 pub mod modules;
+pub mod overrides;
+pub mod pagination;
+pub mod progress;
+pub mod ratelimit;
+pub mod reexpand;
+pub mod render;
+pub mod reproducibility;
+pub mod resources;
+pub mod routing;
+pub mod session;
+pub mod snapshot;
+pub mod stats;
+pub mod suggest;
+pub mod systemd;
+#[cfg(test)]
+mod testutil;
+pub mod threadpool;
+pub mod warnings;
+pub mod weights;
 
-#[cfg(feature = "analiticcl")]
-use modules::analiticcl::{AnaliticclConfig, AnaliticclModule};
+use accesslog::AccessLogConfig;
+use analyzer::Analyzers;
+use audit::AuditConfig;
+use auth::AuthConfig;
+use broccoli::BroccoliConfig;
+use cache::CacheConfig;
+use coverage::Coverage;
+use expansionset::ExpansionSetConfig;
+use feedback::FeedbackConfig;
+use globalparams::GlobalParam;
+use indexfilter::IndexFilterConfig;
+use limits::LimitsConfig;
+use modules::unavailable::UnavailableModule;
+use modules::{AnyModuleConfig, Module, ModuleConfig, ModuleConfigs};
+use overrides::TermOverrides;
+use progress::{LoadProgress, LoadStatus};
+use ratelimit::RateLimitConfig;
+use resources::Resource;
+use routing::Routes;
+use session::SessionConfig;
+use snapshot::Snapshot;
+use stats::ExpansionStats;
+use threadpool::ThreadPoolConfig;
+use warnings::{ModuleWarning, SkipReason};
+use weights::{TermWeights, WeightsConfig};
 
-#[cfg(feature = "fst")]
-use modules::fst::{FstConfig, FstModule};
-
-use modules::lookup::{LookupConfig, LookupModule};
-
-#[cfg(feature = "finalfusion")]
-use modules::finalfusion::{FinalFusionConfig, FinalFusionModule};
-
-use modules::Module;
-
-pub use lexer::Term;
+pub use lexer::{QueryContext, Term};
 
 /// Maps a term to expansions, each `TermExpansion` corresponds to one source/module and may itself contain multiple expansions
 pub type TermExpansions = HashMap<String, Vec<TermExpansion>>;
 
-#[derive(Default)]
+/// Maps a term to its status after query expansion
+pub type TermStatuses = HashMap<String, TermStatus>;
+
+/// The status of a term after query expansion, explains why a term got no expansions
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum TermStatus {
+    /// At least one module returned expansions for the term
+    Expanded,
+    /// The term was passed to the modules but none returned any expansions
+    NoMatches,
+    /// The term is a stopword and was not passed to any module
+    Stopword,
+    /// The term is excluded in the query (`NOT term`, `-term`) and was not passed to any module, as expanding
+    /// it would broaden the exclusion (unless `expand_negated` is passed)
+    Negated,
+    /// All modules were excluded by the include/exclude parameters
+    ExcludedByModuleFilter,
+    /// The term got no expansions, but modules were skipped because time ran out (see [`SkipReason::Timeout`])
+    Timeout,
+}
+
+#[derive(Default, Clone)]
 pub struct QueryExpander {
     config: Config,
-    modules: Vec<Box<dyn Module>>,
+    modules: Vec<Arc<dyn Module>>,
     initialised: bool,
+    /// Usage statistics, shared between clones
+    stats: Arc<ExpansionStats>,
+    /// Document frequencies for weighing query terms, if configured
+    weights: Option<Arc<TermWeights>>,
+    /// Dedicated thread pool for CPU-intensive modules, if configured
+    #[cfg(feature = "parallel")]
+    threadpool: Option<Arc<rayon::ThreadPool>>,
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Default, Clone)]
 #[serde(default)]
 pub struct Config {
-    lookup: Vec<LookupConfig>,
+    /// The modules, in the order in which they are used
+    #[serde(flatten)]
+    modules: ModuleConfigs,
+
+    /// Terms that are never expanded (case-insensitive)
+    stopwords: Vec<String>,
+
+    /// Rules that route classes of terms (e.g. years or acronyms) to a subset of the modules
+    #[serde(rename = "route")]
+    routes: Routes,
+
+    /// Queries taking longer than this (in milliseconds) to expand are logged, disabled if not set
+    slow_query_threshold: Option<u64>,
+
+    /// Queries that the webservice expands after loading the modules (and after each reload) to warm them up
+    warmup: Vec<String>,
+
+    /// Always include the term itself as first expansion of each module (true) or never (false), as the modules
+    /// provide it if not set. Modules may override this.
+    include_term: Option<bool>,
+
+    /// Analyzer chains by search field, applied to the expansions in the expanded query
+    analyzers: Analyzers,
+
+    /// API-key authentication, all modules are publicly accessible if not set
+    auth: Option<AuthConfig>,
+
+    /// Per-client rate limiting, no limits are imposed if not set
+    ratelimit: Option<RateLimitConfig>,
+
+    /// Limits on the size and duration of queries
+    limits: LimitsConfig,
+
+    /// Dedicated thread pool for CPU-intensive modules, the runtime's pool for blocking tasks is used if not set
+    threadpool: Option<ThreadPoolConfig>,
+
+    /// TLS certificate and key, the server speaks plain HTTP if not set
+    tls: Option<TlsConfig>,
+
+    /// HTTP response compression
+    compression: CompressionConfig,
+
+    /// In-process cache of complete query responses, disabled if not set
+    cache: Option<CacheConfig>,
+
+    /// Structured (JSON) access log, disabled if not set
+    accesslog: Option<AccessLogConfig>,
+
+    /// Audit log of all expansion requests, disabled if not set
+    audit: Option<AuditConfig>,
+
+    /// Server-side sessions for interactive query expansion, disabled if not set
+    session: Option<SessionConfig>,
+
+    /// Storage of curated expansion sets that can be re-applied to later queries, disabled if not set
+    expansionsets: Option<ExpansionSetConfig>,
+
+    /// Collection of user feedback on the quality of expansions, disabled if not set
+    feedback: Option<FeedbackConfig>,
+
+    /// Document frequencies to weigh the terms of a query by their informativeness (IDF), disabled if not set
+    weights: Option<WeightsConfig>,
+
+    /// SRU facade that expands CQL queries and forwards them to an SRU backend, disabled if not set
+    sru: Option<SruConfig>,
+
+    /// Post-filter that checks expansions against the term dictionary of a search index, disabled if not set
+    indexfilter: Option<IndexFilterConfig>,
+
+    /// Field names of the Broccoli-compatible response mode
+    broccoli: BroccoliConfig,
+
+    /// Additional independent configurations, each served under its own path prefix
+    #[serde(rename = "tenant")]
+    tenants: Vec<TenantConfig>,
+
+    /// Directory where remote resources (module files referenced by URL) are downloaded to by `kweepeer fetch`
+    cache_dir: Option<PathBuf>,
+
+    /// Glob patterns (relative to the configuration file) of additional files with module definitions
+    include: Vec<String>,
+
+    /// Settings inherited by all module sections (including those in included files) that don't set them themselves
+    defaults: toml::Table,
+
+    /// Prefix for the identifiers of all modules defined in (or included by) this file
+    id_prefix: Option<String>,
+
+    /// Directory that relative paths of module files and tenant configurations are resolved against,
+    /// defaults to the directory containing the configuration file
+    base_dir: Option<PathBuf>,
+}
+
+/// An independent configuration served under its own path prefix
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantConfig {
+    /// The path prefix (without slashes)
+    prefix: String,
+
+    /// Path to the configuration file for this tenant
+    config: PathBuf,
+}
+
+impl TenantConfig {
+    pub fn prefix(&self) -> &str {
+        self.prefix.as_str()
+    }
+
+    pub fn config(&self) -> &Path {
+        self.config.as_path()
+    }
+}
+
+/// Configuration for the SRU (Search/Retrieve via URL) facade
+#[derive(Debug, Deserialize, Clone)]
+pub struct SruConfig {
+    /// Base URL of the SRU endpoint to forward requests to
+    backend: String,
+
+    /// Timeout for requests to the backend, in seconds
+    #[serde(default = "SruConfig::default_timeout")]
+    timeout: u64,
+}
+
+impl SruConfig {
+    pub fn new(backend: impl Into<String>) -> Self {
+        Self {
+            backend: backend.into(),
+            timeout: Self::default_timeout(),
+        }
+    }
+
+    fn default_timeout() -> u64 {
+        30
+    }
+
+    pub fn backend(&self) -> &str {
+        self.backend.as_str()
+    }
 
-    #[cfg(feature = "analiticcl")]
-    analiticcl: Vec<AnaliticclConfig>,
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout)
+    }
+}
 
-    #[cfg(feature = "fst")]
-    fst: Vec<FstConfig>,
+/// Which compression algorithms may be used for HTTP responses (subject to what the client accepts)
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CompressionConfig {
+    /// Enable gzip compression
+    gzip: bool,
 
-    #[cfg(feature = "finalfusion")]
-    finalfusion: Vec<FinalFusionConfig>,
+    /// Enable brotli compression
+    br: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            gzip: true,
+            br: true,
+        }
+    }
+}
+
+impl CompressionConfig {
+    pub fn gzip(&self) -> bool {
+        self.gzip
+    }
+
+    pub fn br(&self) -> bool {
+        self.br
+    }
+}
+
+/// Certificate and private key for serving over HTTPS
+#[derive(Debug, Deserialize, Clone)]
+pub struct TlsConfig {
+    /// Path to the certificate chain (PEM)
+    cert: PathBuf,
+
+    /// Path to the private key (PEM)
+    key: PathBuf,
+}
+
+impl TlsConfig {
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            cert: cert.into(),
+            key: key.into(),
+        }
+    }
+
+    pub fn cert(&self) -> &Path {
+        self.cert.as_path()
+    }
+
+    pub fn key(&self) -> &Path {
+        self.key.as_path()
+    }
+}
+
+impl Config {
+    /// The names of the arrays of tables holding the module definitions
+    const MODULE_TABLES: &'static [&'static str] = &[
+        ModuleConfigs::TABLE,
+        "lookup",
+        "fst",
+        "analiticcl",
+        "finalfusion",
+    ];
+
+    /// Tables that may occur in included files, other than the module definitions
+    const INCLUDABLE: &'static [&'static str] = &["include", "base_dir", "id_prefix"];
+
+    /// Read and parse a configuration file, including the module definitions from the files it includes
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut config: Self = Self::parse_file(path, None)?;
+        config.resolve_paths(path);
+        let mut visited = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+        config.merge_includes(path, &mut visited)?;
+        config.apply_id_prefix()?;
+        Ok(config)
+    }
+
+    /// Merges the modules of all files included by the configuration file at `path` (recursively)
+    fn merge_includes(&mut self, path: &Path, visited: &mut Vec<PathBuf>) -> Result<(), Error> {
+        let basedir = path.parent().unwrap_or(Path::new("."));
+        for pattern in self.include.clone() {
+            // joining an absolute pattern yields the pattern itself
+            let fullpattern = basedir.join(&pattern);
+            let mut paths = include_paths(&fullpattern, &pattern)?;
+            if paths.is_empty() && !pattern.contains(['*', '?', '[']) {
+                return Err(Error::LoadError(format!(
+                    "Included file {} does not exist",
+                    fullpattern.display()
+                )));
+            }
+            paths.sort();
+            for included in paths {
+                let canonical = included.canonicalize().unwrap_or_else(|_| included.clone());
+                if visited.contains(&canonical) {
+                    return Err(Error::InvalidConfig(format!(
+                        "{} is included recursively",
+                        included.display()
+                    )));
+                }
+                let table: toml::Table = Self::parse_file(&included, Some(&self.defaults))?;
+                if let Some(key) = table.keys().find(|key| {
+                    !Self::MODULE_TABLES.contains(&key.as_str())
+                        && !Self::INCLUDABLE.contains(&key.as_str())
+                }) {
+                    return Err(Error::InvalidConfig(format!(
+                        "{}: only module definitions can be included, found {}",
+                        included.display(),
+                        key
+                    )));
+                }
+                let mut other: Self = toml::Value::Table(table).try_into().map_err(|e| {
+                    Error::LoadError(format!(
+                        "Unable to parse configuration file {}: {}",
+                        included.display(),
+                        e
+                    ))
+                })?;
+                other.resolve_paths(&included);
+                other.defaults = self.defaults.clone();
+                visited.push(canonical);
+                other.merge_includes(&included, visited)?;
+                other.apply_id_prefix()?;
+                visited.pop();
+                self.modules.append(&mut other.modules);
+            }
+        }
+        Ok(())
+    }
+
+    /// Prefixes the identifiers of all modules with the configured prefix, if any
+    fn apply_id_prefix(&mut self) -> Result<(), Error> {
+        if let Some(prefix) = self.id_prefix.take() {
+            // module IDs may not contain periods, those separate module IDs from parameters
+            if !prefix
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(Error::InvalidConfig(format!(
+                    "Invalid id_prefix {}: only alphanumeric characters, underscores and hyphens are allowed",
+                    prefix
+                )));
+            }
+            for moduleconfig in self.module_configs_mut() {
+                let id = format!("{}{}", prefix, moduleconfig.id());
+                moduleconfig.set_id(id);
+                for id in moduleconfig.common_mut().suppress_if_found_by_mut() {
+                    *id = format!("{}{}", prefix, id);
+                }
+            }
+            self.routes.prefix_ids(&prefix);
+        }
+        Ok(())
+    }
+
+    /// Resolves the relative paths of all module files and tenant configurations against the base directory,
+    /// rather than against the current working directory. `path` is the configuration file itself.
+    fn resolve_paths(&mut self, path: &Path) {
+        let configdir = path.parent().unwrap_or(Path::new("."));
+        let base_dir = match self.base_dir.as_ref() {
+            Some(base_dir) => configdir.join(base_dir),
+            None => configdir.to_path_buf(),
+        };
+        for moduleconfig in self.module_configs_mut() {
+            for file in moduleconfig.files_mut() {
+                // remote resources are resolved later, by resolve_resources()
+                if file.is_relative() && Resource::parse(file).is_none() {
+                    *file = base_dir.join(&file);
+                }
+            }
+        }
+        for tenant in self.tenants.iter_mut() {
+            tenant.config = base_dir.join(&tenant.config);
+        }
+        if let Some(weights) = self.weights.as_mut() {
+            *weights.file_mut() = base_dir.join(weights.file());
+        }
+    }
+
+    /// Reads and parses a TOML file, substituting environment variables in its values and applying the defaults
+    /// to its module sections. The defaults are taken from the file itself if not passed.
+    fn parse_file<T: serde::de::DeserializeOwned>(
+        path: &Path,
+        defaults: Option<&toml::Table>,
+    ) -> Result<T, Error> {
+        let toml_string = std::fs::read_to_string(path).map_err(|e| {
+            Error::LoadError(format!(
+                "Unable to read configuration file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let parse_error = |e: &dyn std::fmt::Display| {
+            Error::LoadError(format!(
+                "Unable to parse configuration file {}: {}",
+                path.display(),
+                e
+            ))
+        };
+        let mut table: toml::Table = toml::from_str(&toml_string).map_err(|e| parse_error(&e))?;
+        let has_defaults = match defaults {
+            Some(defaults) => !defaults.is_empty(),
+            None => table.contains_key("defaults"),
+        };
+        if !toml_string.contains("${") && !has_defaults {
+            // deserialize from the source directly, so errors refer to the line
+            return toml::from_str(&toml_string).map_err(|e| parse_error(&e));
+        }
+        // substitute environment variables in the values, not in the TOML source, so comments and quoting are unaffected
+        for (_, value) in table.iter_mut() {
+            envsubst::substitute_toml(value).map_err(|e| {
+                Error::InvalidConfig(format!("{}: {}", path.display(), e.message()))
+            })?;
+        }
+        let defaults = match defaults {
+            Some(defaults) => defaults.clone(),
+            None => match table.get("defaults") {
+                Some(toml::Value::Table(defaults)) => defaults.clone(),
+                _ => toml::Table::new(),
+            },
+        };
+        Self::apply_defaults(&mut table, &defaults);
+        toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| parse_error(&e))
+    }
+
+    /// Copies the defaults to all module sections that don't set them themselves.
+    /// Module types ignore the settings they don't know.
+    fn apply_defaults(table: &mut toml::Table, defaults: &toml::Table) {
+        for kind in Self::MODULE_TABLES {
+            if let Some(toml::Value::Array(modules)) = table.get_mut(*kind) {
+                for module in modules.iter_mut() {
+                    if let toml::Value::Table(module) = module {
+                        for (key, value) in defaults.iter() {
+                            if !module.contains_key(key) {
+                                module.insert(key.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a commented configuration skeleton with an example section for each of the module types that are
+    /// compiled in. Used by `kweepeer init`.
+    pub fn skeleton() -> String {
+        let mut s = String::from(
+            r#"# Kweepeer configuration, see kweepeer(5) for full documentation.
+# Each module is defined in a [[module]] table with its type, the modules are
+# used in the order in which they are defined and there may be multiple modules
+# of the same type. All modules take a unique identifier (id) and a
+# human-readable name. Adapt the paths to your own data
+# and run `kweepeer validate-config` to check the result.
+
+# Terms that are never expanded (case-insensitive)
+# stopwords = [ "de", "het", "een" ]
+
+# Log queries that take longer than this many milliseconds to expand
+# slow_query_threshold = 500
+
+# Expand these queries after loading the modules (and after each reload), before serving requests
+# warmup = [ "belangrijk", "wijn en kaas" ]
+
+# Always include the query term itself as first expansion of each module (modules may override this)
+# include_term = true
+
+# Merge the module definitions from other files (relative to this file)
+# include = [ "modules/*.toml" ]
+
+# Resolve relative module paths against this directory instead of the directory of this file
+# base_dir = "/data/lexica"
+
+# Module files may also be given as URLs (optionally followed by #sha256=<checksum>),
+# `kweepeer fetch` downloads these into this directory
+# cache_dir = "/var/cache/kweepeer"
+
+# A lookup module maps terms to variants explicitly, it reads a TSV file with
+# a term in the first column and its variants in the subsequent columns.
+[[module]]
+type = "lookup"
+id = "lookup"
+name = "Lookup"
+file = "variants.tsv"
+# delimiter = "\t"        # between the term and its variants
+# delimiter2 = "\t"       # between the variants
+# skipfirstline = false   # set if the first line is a header
+# casesensitive = false
+# allow_numeric = false   # numeric fields (e.g. frequencies) are ignored unless set
+# sha256 = "..."         # the checksum of the file (see sha256sum), verified at load
+# expansion_kind = "synonym"  # spelling, synonym, translation or related, to group expansions by
+# language = "nl"
+"#,
+        );
+        #[cfg(feature = "fst")]
+        s.push_str(
+            r#"
+# An fst module finds all terms in a lexicon (one term per line) within a given
+# Levenshtein distance.
+[[module]]
+type = "fst"
+id = "fst"
+name = "Lexicon (FST)"
+file = "lexicon.tsv"
+distance = 2
+# sorted = false          # set if the lexicon is sorted already, speeds up loading
+# skipfirstline = false
+# casesensitive = false
+"#,
+        );
+        #[cfg(feature = "analiticcl")]
+        s.push_str(
+            r#"
+# An analiticcl module finds spelling variants using anagram hashing, see
+# https://github.com/proycon/analiticcl for the file formats.
+[[module]]
+type = "analiticcl"
+id = "analiticcl"
+name = "Lexicon (analiticcl)"
+alphabet = "alphabet.tsv"
+
+[[module.lexicons]]
+filename = "lexicon.tsv"
+
+# [[module.variantlists]]
+# filename = "variants.tsv"
+# transparent = false
+"#,
+        );
+        #[cfg(feature = "finalfusion")]
+        s.push_str(
+            r#"
+# A finalfusion module finds similar terms using word embeddings (finalfusion format).
+[[module]]
+type = "finalfusion"
+id = "embeddings"
+name = "Word embeddings"
+file = "embeddings.fifu"
+k = 10                    # number of results
+"#,
+        );
+        s.push_str(
+            r#"
+# Restrict access to modules using API keys
+# [auth]
+# anonymous_modules = [ "lookup" ]
+#
+# [[auth.keys]]
+# key = "s3cr3t"
+# name = "My client"
+# admin = false
+
+# Rate limit clients (requests per second and burst size)
+# [ratelimit]
+# rate = 2.0
+# burst = 10
+
+# Limit the size and duration of queries, and the memory used by the modules
+# [limits]
+# max_query_length = 1000
+# max_terms = 25
+# max_expansions = 500
+# timeout = 10000
+# max_concurrent = 8
+# max_memory_mb = 4096
+
+# Run CPU-intensive modules on a dedicated thread pool, optionally pinned to certain CPUs (e.g. a single NUMA node)
+# [threadpool]
+# threads = 8
+# cpus = [ 0, 1, 2, 3, 4, 5, 6, 7 ]
+
+# Analyze the expansions in the expanded query like the search index does, per field ("*" for terms without a field)
+# [analyzers]
+# title = [ "lowercase", "asciifold" ]
+# "*" = [ "lowercase", "stem:dutch" ]
+
+# Weigh the terms of each query by their informativeness (IDF), given document frequencies (term<TAB>frequency)
+# [weights]
+# file = "frequencies.tsv"
+# documents = 100000
+
+# Cache complete responses in memory
+# [cache]
+# ttl = 300
+# capacity = 10000
+
+# Keep server-side sessions for interactive query expansion (seconds of inactivity before a session expires)
+# [session]
+# ttl = 3600
+# capacity = 1000
+
+# Store curated expansion sets (one JSON file per set) so they can be re-applied to later queries
+# [expansionsets]
+# dir = "expansionsets"
+
+# Collect feedback on expansions (POST /feedback), optionally downranking expansions with negative feedback
+# [feedback]
+# file = "feedback.jsonl"
+# downrank = 0.5
+# min_votes = 3
+
+# Compression of HTTP responses (enabled by default)
+# [compression]
+# gzip = true
+# br = true
+
+# Structured access log (JSON lines), written to standard output if no file is set
+# [accesslog]
+# file = "access.log"
+
+# Audit log of all expansion requests (JSON lines)
+# [audit]
+# file = "audit.jsonl"
+
+# Field names of the Broccoli-compatible response (/broccoli endpoint), empty to omit a field
+# [broccoli]
+# query_field = "query"
+# original_query_field = "originalQuery"
+# terms_field = "terms"
+# template_field = ""
+"#,
+        );
+        #[cfg(feature = "tls")]
+        s.push_str(
+            r#"
+# Terminate TLS
+# [tls]
+# cert = "fullchain.pem"
+# key = "privkey.pem"
+"#,
+        );
+        #[cfg(feature = "indexfilter")]
+        s.push_str(
+            r#"
+# Drop expansions that do not occur in a search index (elasticsearch or solr)
+# [indexfilter]
+# backend = "elasticsearch"
+# url = "http://localhost:9200/myindex"
+# field = "text"
+# action = "drop"
+"#,
+        );
+        #[cfg(feature = "sru")]
+        s.push_str(
+            r#"
+# Act as a facade for an SRU endpoint, expanding the terms in CQL queries
+# [sru]
+# backend = "http://localhost:8081/sru"
+# timeout = 30
+"#,
+        );
+        s.push_str(
+            r#"
+# Serve additional configurations under their own path prefix
+# [[tenant]]
+# prefix = "project"
+# config = "project.toml"
+"#,
+        );
+        // the analyzer example only stems if the stemmer is compiled in
+        if !cfg!(feature = "stemming") {
+            s = s.replace("\"stem:dutch\"", "\"asciifold\"");
+        }
+        s
+    }
+
+    /// Returns the configurations of all modules, in order
+    pub fn module_configs(&self) -> Vec<&dyn ModuleConfig> {
+        self.modules
+            .iter()
+            .map(|config| config.as_config())
+            .collect()
+    }
+
+    /// Returns mutable access to the configurations of all modules
+    fn module_configs_mut(&mut self) -> Vec<&mut dyn ModuleConfig> {
+        self.modules
+            .iter_mut()
+            .map(|config| config.as_config_mut())
+            .collect()
+    }
+
+    /// Returns the configurations of all modules, in order, with their types
+    pub fn modules(&self) -> &ModuleConfigs {
+        &self.modules
+    }
+
+    /// The directory where remote resources are cached
+    pub fn cache_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(resources::default_cache_dir)
+    }
+
+    /// Returns all remote resources (module files referenced by URL) of the enabled modules in this configuration
+    pub fn resources(&self) -> Vec<Resource> {
+        let mut resources: Vec<Resource> = Vec::new();
+        for moduleconfig in self.module_configs() {
+            if !moduleconfig.common().enabled() {
+                continue;
+            }
+            let files = moduleconfig.files();
+            for file in files.iter() {
+                let Some(mut resource) = Resource::parse(file) else {
+                    continue;
+                };
+                if resource.checksum().is_none() {
+                    if let Some(checksum) = moduleconfig
+                        .common()
+                        .sha256()
+                        .and_then(|checksums| checksums.expected(&files, file))
+                    {
+                        resource = resource.with_checksum(checksum);
+                    }
+                }
+                if !resources.contains(&resource) {
+                    resources.push(resource);
+                }
+            }
+        }
+        resources
+    }
+
+    /// Substitutes all remote resources of enabled modules by their copies in the cache directory.
+    /// Returns an error if a resource has not been downloaded yet (see `kweepeer fetch`).
+    pub fn resolve_resources(&mut self) -> Result<(), Error> {
+        let cache_dir = self.cache_dir();
+        for moduleconfig in self.module_configs_mut() {
+            if !moduleconfig.common().enabled() {
+                continue;
+            }
+            for file in moduleconfig.files_mut() {
+                if let Some(resource) = Resource::parse(file) {
+                    let path = resource.cache_path(&cache_dir);
+                    if !path.exists() && cfg!(feature = "client") {
+                        return Err(Error::LoadError(format!(
+                            "Resource {} has not been downloaded to {} yet, run kweepeer fetch first",
+                            resource.url(),
+                            cache_dir.display()
+                        )));
+                    } else if !path.exists() {
+                        return Err(Error::LoadError(format!(
+                            "Resource {} has not been downloaded to {} yet, and kweepeer was built without the client feature so it can not download it: rebuild with the client feature and run kweepeer fetch",
+                            resource.url(),
+                            cache_dir.display()
+                        )));
+                    }
+                    if resource.checksum().is_some() {
+                        // the cached copy may have been modified since it was downloaded
+                        resource.verify(&path)?;
+                    }
+                    *file = path;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes all module configurations for which the predicate (on the module ID) returns false,
+    /// so only the remaining modules will be loaded
+    pub fn retain_modules(&mut self, f: impl Fn(&str) -> bool) {
+        self.modules.retain(|config| f(config.as_config().id()));
+    }
+
+    /// Checks the configuration without loading any modules: verifies that all referenced files exist and are readable,
+    /// and that module IDs are unique. Returns a list of problems found, which is empty if the configuration is valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut ids = std::collections::HashSet::new();
+        let moduleconfigs = self.module_configs();
+        for moduleconfig in moduleconfigs.iter() {
+            if !ids.insert(moduleconfig.id()) {
+                problems.push(format!("Duplicate module ID: {}", moduleconfig.id()));
+            }
+            if !moduleconfig.common().enabled() {
+                continue;
+            }
+            for id in moduleconfig.common().suppress_if_found_by() {
+                if !moduleconfigs.iter().any(|other| other.id() == id) {
+                    problems.push(format!(
+                        "Module {}: suppress_if_found_by refers to unknown module {}",
+                        moduleconfig.id(),
+                        id
+                    ));
+                }
+            }
+            if let Some(Err(e)) = moduleconfig
+                .common()
+                .sha256()
+                .map(|checksums| checksums.validate(&moduleconfig.files()))
+            {
+                problems.push(format!("Module {}: {}", moduleconfig.id(), e.message()));
+            }
+            for file in moduleconfig.files() {
+                if let Some(resource) = Resource::parse(file) {
+                    if !resource.cache_path(&self.cache_dir()).exists() {
+                        problems.push(format!(
+                            "Module {}: resource {} has not been downloaded yet (run kweepeer fetch)",
+                            moduleconfig.id(),
+                            resource.url()
+                        ));
+                    }
+                } else if let Err(e) = std::fs::File::open(file) {
+                    problems.push(format!(
+                        "Module {}: unable to read {}: {}",
+                        moduleconfig.id(),
+                        file.display(),
+                        e
+                    ));
+                }
+            }
+        }
+        for id in self.routes.iter().flat_map(|route| route.module_ids()) {
+            if !moduleconfigs
+                .iter()
+                .any(|moduleconfig| moduleconfig.id() == id)
+            {
+                problems.push(format!("A route refers to unknown module {}", id));
+            }
+        }
+        if let Some(weights) = self.weights.as_ref() {
+            if let Err(e) = std::fs::File::open(weights.file()) {
+                problems.push(format!(
+                    "Unable to read frequency file {}: {}",
+                    weights.file().display(),
+                    e
+                ));
+            }
+        }
+        for tenant in self.tenants.iter() {
+            match Config::from_file(tenant.config()) {
+                Ok(config) => {
+                    for problem in config.validate() {
+                        problems.push(format!("Tenant {}: {}", tenant.prefix(), problem));
+                    }
+                }
+                Err(e) => problems.push(format!("Tenant {}: {}", tenant.prefix(), e)),
+            }
+        }
+        problems
+    }
+
+    /// Checks whether a term is a stopword (case-insensitive)
+    pub fn is_stopword(&self, term: &str) -> bool {
+        self.stopwords
+            .iter()
+            .any(|stopword| stopword.to_lowercase() == term.to_lowercase())
+    }
+
+    pub fn warmup(&self) -> &[String] {
+        &self.warmup
+    }
+
+    /// Returns whether the term itself is always (true) or never (false) included in the expansions of each module,
+    /// None if this is left to the modules
+    pub fn include_term(&self) -> Option<bool> {
+        self.include_term
+    }
+
+    /// Returns the analyzer chains by search field, see [`analyzer`]
+    pub fn analyzers(&self) -> &Analyzers {
+        &self.analyzers
+    }
+
+    pub fn slow_query_threshold(&self) -> Option<std::time::Duration> {
+        self.slow_query_threshold
+            .map(std::time::Duration::from_millis)
+    }
+
+    pub fn tenants(&self) -> &[TenantConfig] {
+        &self.tenants
+    }
+
+    pub fn auth(&self) -> Option<&AuthConfig> {
+        self.auth.as_ref()
+    }
+
+    pub fn ratelimit(&self) -> Option<&RateLimitConfig> {
+        self.ratelimit.as_ref()
+    }
+
+    pub fn limits(&self) -> &LimitsConfig {
+        &self.limits
+    }
+
+    pub fn routes(&self) -> &Routes {
+        &self.routes
+    }
+
+    pub fn session(&self) -> Option<&SessionConfig> {
+        self.session.as_ref()
+    }
+
+    pub fn expansionsets(&self) -> Option<&ExpansionSetConfig> {
+        self.expansionsets.as_ref()
+    }
+
+    pub fn feedback(&self) -> Option<&FeedbackConfig> {
+        self.feedback.as_ref()
+    }
+
+    pub fn threadpool(&self) -> Option<&ThreadPoolConfig> {
+        self.threadpool.as_ref()
+    }
+
+    pub fn weights(&self) -> Option<&WeightsConfig> {
+        self.weights.as_ref()
+    }
+
+    pub fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    pub fn compression(&self) -> &CompressionConfig {
+        &self.compression
+    }
+
+    pub fn cache(&self) -> Option<&CacheConfig> {
+        self.cache.as_ref()
+    }
+
+    pub fn accesslog(&self) -> Option<&AccessLogConfig> {
+        self.accesslog.as_ref()
+    }
+
+    pub fn audit(&self) -> Option<&AuditConfig> {
+        self.audit.as_ref()
+    }
+
+    pub fn sru(&self) -> Option<&SruConfig> {
+        self.sru.as_ref()
+    }
+
+    pub fn indexfilter(&self) -> Option<&IndexFilterConfig> {
+        self.indexfilter.as_ref()
+    }
+
+    pub fn broccoli(&self) -> &BroccoliConfig {
+        &self.broccoli
+    }
 }
 
 impl QueryExpander {
@@ -63,7 +1036,8 @@ impl QueryExpander {
         if self.initialised {
             panic!("Can not add modules after load()!")
         }
-        self.modules.push(module);
+        self.modules.push(Arc::from(module));
+        self.sort_modules();
     }
 
     /// Adds a new module. Only valid before call to `load()`, will panic afterwards.
@@ -72,180 +1046,824 @@ impl QueryExpander {
         self
     }
 
+    /// Returns the configuration
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns the usage statistics
+    pub fn stats(&self) -> &ExpansionStats {
+        &self.stats
+    }
+
+    /// Document frequencies for weighing query terms, if configured (available after loading)
+    pub fn term_weights(&self) -> Option<&TermWeights> {
+        self.weights.as_deref()
+    }
+
+    /// The dedicated thread pool for CPU-intensive modules, if configured (available after loading)
+    #[cfg(feature = "parallel")]
+    pub fn thread_pool(&self) -> Option<&rayon::ThreadPool> {
+        self.threadpool.as_deref()
+    }
+
     /// Returns an iterator over all the modules
     pub fn modules(&self) -> impl Iterator<Item = &dyn Module> {
         self.modules.iter().map(|x| x.as_ref())
     }
 
+    /// Returns a module by ID
+    pub fn get_module(&self, id: &str) -> Option<&dyn Module> {
+        self.modules().find(|module| module.id() == id)
+    }
+
+    /// Registers an already loaded module. Unlike `add_module()`, this may also be used after `load()`, e.g. to add modules at runtime.
+    pub fn register_module(&mut self, module: Box<dyn Module>) -> Result<(), Error> {
+        if self.get_module(module.id()).is_some() {
+            return Err(Error::LoadError(format!(
+                "A module with ID {} already exists",
+                module.id()
+            )));
+        }
+        info!("Registering module {} - {}", module.id(), module.name());
+        self.modules.push(Arc::from(module));
+        self.sort_modules();
+        Ok(())
+    }
+
+    /// Orders the modules by priority, highest first (see [`Module::priority()`]). The sort is stable, so modules
+    /// with the same priority keep the order in which they were configured or registered.
+    fn sort_modules(&mut self) {
+        self.modules
+            .sort_by_key(|module| std::cmp::Reverse(module.common().priority()));
+    }
+
+    /// Checks whether adding a loaded module (or replacing the one with the same ID) keeps the estimated memory usage
+    /// of all modules within the budget (`max_memory_mb`). Modules that are not loaded yet do not count.
+    pub fn check_memory_budget(&self, module: &dyn Module) -> Result<(), Error> {
+        let others: u64 = self
+            .modules
+            .iter()
+            .filter(|other| other.id() != module.id())
+            .filter_map(|other| other.memory_estimate())
+            .sum();
+        self.config.limits().check_memory(
+            module.id(),
+            module.memory_estimate().unwrap_or_default(),
+            others,
+        )
+    }
+
+    /// Replaces a module with another (already loaded) one with the same ID, retaining its position unless its
+    /// priority changed
+    pub fn replace_module(&mut self, module: Box<dyn Module>) -> Result<(), Error> {
+        if let Some(existing) = self.modules.iter_mut().find(|m| m.id() == module.id()) {
+            info!("Replacing module {} - {}", module.id(), module.name());
+            *existing = Arc::from(module);
+            self.sort_modules();
+            Ok(())
+        } else {
+            Err(Error::ModuleNotFound(format!(
+                "No such module: {}",
+                module.id()
+            )))
+        }
+    }
+
+    /// Removes a module by ID
+    pub fn remove_module(&mut self, id: &str) -> Result<(), Error> {
+        let len = self.modules.len();
+        self.modules.retain(|module| module.id() != id);
+        if self.modules.len() == len {
+            Err(Error::ModuleNotFound(format!("No such module: {}", id)))
+        } else {
+            info!("Removed module {}", id);
+            Ok(())
+        }
+    }
+
     /// Initialise all modules. This should be called once after all modules are loaded. Will panic if called multiple times.
     pub fn load(&mut self) -> Result<(), Error> {
+        self.load_shared(&mut ModulePool::new())
+    }
+
+    /// Initialise all modules, reusing any identically configured modules already loaded into the pool
+    /// (and adding newly loaded ones to it). This allows multiple query expanders to share resources.
+    /// Will panic if called multiple times.
+    pub fn load_shared(&mut self, pool: &mut ModulePool) -> Result<(), Error> {
+        self.load_shared_with_progress(pool, &Arc::new(LoadProgress::default()))
+    }
+
+    /// As [`Self::load_shared()`], but records the status of each module in the tracker as they are loaded, so the
+    /// progress can be followed from another thread.
+    pub fn load_shared_with_progress(
+        &mut self,
+        pool: &mut ModulePool,
+        progress: &Arc<LoadProgress>,
+    ) -> Result<(), Error> {
         if self.initialised {
             panic!("load() can only be called once");
         }
-        //MAYBE TODO: we could parallellize the loading for quicker startup time
-        for lookupconfig in self.config.lookup.iter() {
-            info!(
-                "Adding Lookup module {} - {}",
-                lookupconfig.id(),
-                lookupconfig.name()
-            );
-            let mut module = LookupModule::new(lookupconfig.clone());
-            module.load()?;
-            self.modules.push(Box::new(module));
+        let mut ids = std::collections::HashSet::new();
+        for moduleconfig in self.config.module_configs() {
+            if moduleconfig.common().enabled() && !ids.insert(moduleconfig.id()) {
+                return Err(Error::InvalidConfig(format!(
+                    "Duplicate module ID: {}",
+                    moduleconfig.id()
+                )));
+            } else if moduleconfig.common().enabled() {
+                progress.set_status(moduleconfig.id(), LoadStatus::Pending);
+            }
         }
-
-        #[cfg(feature = "fst")]
-        for fstconfig in self.config.fst.iter() {
-            info!(
-                "Adding Fst module {} - {}",
-                fstconfig.id(),
-                fstconfig.name()
-            );
-            let mut module = FstModule::new(fstconfig.clone());
-            module.load()?;
-            self.modules.push(Box::new(module));
+        self.config.resolve_resources()?;
+        if let Some(weights) = self.config.weights() {
+            self.weights = Some(Arc::new(TermWeights::load(weights)?));
         }
-
-        #[cfg(feature = "analiticcl")]
-        for analiticclconfig in self.config.analiticcl.iter() {
-            info!(
-                "Adding Analiticcl module {} - {}",
-                analiticclconfig.id(),
-                analiticclconfig.name()
-            );
-            let mut module = AnaliticclModule::new(analiticclconfig.clone());
-            module.load()?;
-            self.modules.push(Box::new(module));
+        #[cfg(feature = "parallel")]
+        if let Some(threadpool) = self.config.threadpool() {
+            self.threadpool = Some(Arc::new(threadpool.build()?));
         }
-        #[cfg(feature = "finalfusion")]
-        for finalfusionconfig in self.config.finalfusion.iter() {
+        #[cfg(not(feature = "parallel"))]
+        if self.config.threadpool().is_some() {
+            return Err(Error::InvalidConfig(
+                "A thread pool is configured, but kweepeer was built without the parallel feature"
+                    .into(),
+            ));
+        }
+        //MAYBE TODO: we could parallellize the loading for quicker startup time
+        for moduleconfig in self
+            .config
+            .modules
+            .iter()
+            .filter(|c| c.as_config().common().enabled())
+        {
             info!(
-                "Adding Finalfusion module {} - {}",
-                finalfusionconfig.id(),
-                finalfusionconfig.name()
+                "Adding {} module {} - {}",
+                moduleconfig.as_config().kind(),
+                moduleconfig.as_config().id(),
+                moduleconfig.as_config().name()
             );
-            let mut module = FinalFusionModule::new(finalfusionconfig.clone());
-            module.load()?;
-            self.modules.push(Box::new(module));
+            let id = moduleconfig.as_config().id();
+            progress.set_status(id, LoadStatus::Loading);
+            let module = match progress.scope(id, || pool.get_or_load(moduleconfig)) {
+                Ok(module) => {
+                    progress.set_status(id, LoadStatus::Ready);
+                    module
+                }
+                Err(e) if !moduleconfig.as_config().common().required() => {
+                    error!(
+                        "Module {} is unavailable, its data could not be loaded: {}",
+                        id, e
+                    );
+                    let module = UnavailableModule::new(moduleconfig.instantiate(), &e);
+                    progress.fail(id, module.load_error().unwrap_or_default());
+                    Arc::new(module)
+                }
+                Err(e) => {
+                    progress.fail(id, e.to_string());
+                    return Err(e);
+                }
+            };
+            if let Err(e) = self.check_memory_budget(module.as_ref()) {
+                progress.fail(id, e.to_string());
+                return Err(e);
+            }
+            self.modules.push(module);
         }
+        self.sort_modules();
 
+        for module in self.modules.iter() {
+            if let Some(load_time) = pool.load_time(module).filter(|_| module.is_loaded()) {
+                self.stats.record_load(module.id(), load_time);
+            }
+            info!("{}", modules::resource_report(module.as_ref(), &self.stats));
+        }
         info!("All modules loaded");
         self.initialised = true;
         Ok(())
     }
 
-    pub fn expand_query(
+    pub fn expand_query(
+        &self,
+        terms: &Vec<Term>,
+        params: &QueryParams,
+    ) -> Result<TermExpansions, Error> {
+        let mut terms_map = TermExpansions::new();
+        self.expand_query_into(&mut terms_map, terms, params)?;
+        Ok(terms_map)
+    }
+
+    /// Returns a version of the data of all modules selected by the parameters, which changes whenever the data of any
+    /// of them changes (e.g. after a reload). Returns `None` if the version of a module is unknown (e.g. a lazy module
+    /// that is not loaded yet).
+    pub fn data_version(&self, params: &QueryParams) -> Option<String> {
+        let mut hasher = Sha256::new();
+        for module in self.selected_modules(params) {
+            hasher.update(module.id());
+            hasher.update("=");
+            hasher.update(module.data_version()?);
+            hasher.update("\n");
+        }
+        Some(format!("{:x}", hasher.finalize())[..16].to_owned())
+    }
+
+    /// Returns the modules that are selected by the `include` and `exclude` parameters, along with the modules that
+    /// the `term_overrides` parameter includes for specific terms (see [`TermOverrides`])
+    pub fn selected_modules<'a>(
+        &'a self,
+        params: &QueryParams,
+    ) -> impl Iterator<Item = &'a dyn Module> {
+        let selected = module_filter(params);
+        // invalid overrides are reported by check_module_filter()
+        let overrides = TermOverrides::from_params(params).unwrap_or_default();
+        self.modules()
+            .filter(move |module| selected(module.id()) || overrides.includes(module.id()))
+    }
+
+    /// Checks that the modules referred to by the `include`, `exclude` and `term_overrides` parameters exist
+    fn check_module_filter(&self, params: &QueryParams) -> Result<(), Error> {
+        let overrides = TermOverrides::from_params(params)?;
+        for id in [GlobalParam::Include, GlobalParam::Exclude]
+            .into_iter()
+            .filter_map(|param| params.global(param))
+            .flat_map(value_to_str_array)
+            .chain(overrides.module_ids())
+        {
+            if !id.is_empty() && self.get_module(id).is_none() {
+                return Err(Error::ModuleNotFound(format!("No such module: {}", id)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the parameters passed to the modules against the parameters they accept, see
+    /// [`Module::parameters()`]. Reports all violations at once. Parameters for modules that do not exist are ignored.
+    pub fn check_module_params(&self, params: &QueryParams) -> Result<(), Error> {
+        let violations: Vec<String> = self
+            .modules
+            .iter()
+            .flat_map(|module| modules::params::violations(module.as_ref(), params))
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::InvalidParameter(violations.join("; ")))
+        }
+    }
+
+    /// Returns up to `limit` completions of a prefix from the selected modules that support this, for autocompletion.
+    /// The completions of the modules are interleaved, so the best completions of each module come first.
+    pub fn complete(
+        &self,
+        prefix: &str,
+        limit: usize,
+        params: &QueryParams,
+    ) -> Result<Vec<String>, Error> {
+        self.check_module_filter(params)?;
+        let mut ranked: Vec<Vec<String>> = Vec::new();
+        for module in self.selected_modules(params) {
+            if let Some(completions) = module.complete(prefix, limit)? {
+                ranked.push(completions);
+            }
+        }
+        let mut completions: Vec<String> = Vec::with_capacity(limit);
+        let mut rank = 0;
+        while completions.len() < limit && ranked.iter().any(|c| c.len() > rank) {
+            for completion in ranked.iter().filter_map(|c| c.get(rank)) {
+                if completions.len() < limit && !completions.contains(completion) {
+                    completions.push(completion.clone());
+                }
+            }
+            rank += 1;
+        }
+        Ok(completions)
+    }
+
+    /// Expands the terms into the map. Modules that had to be skipped (see [`SkipReason`]) are reported in the
+    /// returned warnings rather than failing the expansion.
+    pub fn expand_query_into(
+        &self,
+        terms_map: &mut TermExpansions,
+        terms: &Vec<Term>,
+        params: &QueryParams,
+    ) -> Result<Vec<ModuleWarning>, Error> {
+        let context = QueryContext::from_terms(terms);
+        self.expand_query_filtered(terms_map, terms, &context, params, |_| true)
+    }
+
+    /// Like [`Self::expand_query_into()`], but passes the context of the terms in the query (see [`QueryContext`])
+    /// to the modules, which context-aware modules may take into account
+    pub fn expand_query_in_context(
         &self,
+        terms_map: &mut TermExpansions,
         terms: &Vec<Term>,
+        context: &QueryContext,
         params: &QueryParams,
-    ) -> Result<TermExpansions, Error> {
-        let mut terms_map = TermExpansions::new();
-        self.expand_query_into(&mut terms_map, terms, params)?;
-        Ok(terms_map)
+    ) -> Result<Vec<ModuleWarning>, Error> {
+        self.expand_query_filtered(terms_map, terms, context, params, |_| true)
     }
 
-    pub fn expand_query_into(
+    /// Like [`Self::expand_query_in_context()`], but only consults the selected modules for which the filter returns true
+    pub(crate) fn expand_query_filtered(
         &self,
         terms_map: &mut TermExpansions,
         terms: &Vec<Term>,
+        context: &QueryContext,
         params: &QueryParams,
-    ) -> Result<(), Error> {
-        let excludemods: Vec<_> = if let Some(mods) = params.get("", "exclude") {
-            value_to_str_array(mods)
-        } else {
-            Vec::new()
-        };
-        let includemods: Vec<_> = if let Some(mods) = params.get("", "include") {
-            value_to_str_array(mods)
-        } else {
-            Vec::new()
-        };
-        for module in self.modules() {
-            if (excludemods.is_empty() || !excludemods.contains(&module.id()))
-                && (includemods.is_empty() || includemods.contains(&module.id()))
-            {
-                let mut expansion_map = module.expand_query(terms, params)?;
-                for term in terms.iter() {
-                    terms_map
-                        .entry(term.as_str().to_string())
-                        .and_modify(|expansions| {
-                            if let Some(expansions2) = expansion_map.remove(term.as_str()) {
-                                for expansion in expansions2 {
-                                    expansions.push(expansion);
-                                }
-                            }
-                        })
-                        .or_insert_with(|| {
-                            if let Some(expansions2) = expansion_map.remove(term.as_str()) {
-                                expansions2
-                            } else {
-                                vec![]
-                            }
-                        });
+        filter: impl Fn(&dyn Module) -> bool,
+    ) -> Result<Vec<ModuleWarning>, Error> {
+        self.check_module_filter(params)?;
+        self.check_module_params(params)?;
+        let limits = self.config.limits();
+        limits.check_terms(terms)?;
+        let expand_negated = params.global_bool(GlobalParam::ExpandNegated);
+        let expandable_terms: Vec<Term> = terms
+            .iter()
+            .filter(|term| !self.config.is_stopword(term.as_str()))
+            .filter(|term| expand_negated || !context.is_negated(term.as_str()))
+            .cloned()
+            .collect();
+        let selected = module_filter(params);
+        let overrides = TermOverrides::from_params(params)?;
+        let routes = self.config.routes();
+        let begin_query = Instant::now();
+        let mut timings = Vec::new();
+        let mut warnings = Vec::new();
+        for module in self
+            .selected_modules(params)
+            .filter(|module| filter(*module))
+        {
+            cancel::check()?;
+            let module_terms: Vec<Term> = expandable_terms
+                .iter()
+                .filter(|term| modules::accepts(module, term))
+                .filter(|term| {
+                    overrides.selects(
+                        module.id(),
+                        term.as_str(),
+                        routes.selects(module.id(), term.as_str(), selected(module.id())),
+                    )
+                })
+                .filter(|term| !suppressed(module, term.as_str(), terms_map))
+                .cloned()
+                .collect();
+            if module_terms.is_empty() {
+                continue;
+            }
+            if let Some(timeout) = limits.timeout() {
+                // modules can't be interrupted mid-term, but the remaining ones need not be consulted
+                if begin_query.elapsed() > timeout {
+                    warnings.push(ModuleWarning::new(
+                        module.id(),
+                        SkipReason::Timeout,
+                        format!(
+                            "Skipped as the query could not be expanded within {}ms",
+                            timeout.as_millis()
+                        ),
+                    ));
+                    continue;
+                }
+            }
+            let begin = Instant::now();
+            let result = module.expand_query_in_context(&module_terms, context, params);
+            let elapsed = begin.elapsed();
+            self.stats.record(module.id(), elapsed, result.is_ok());
+            timings.push(format!(
+                "{}={:.1}ms",
+                module.id(),
+                elapsed.as_secs_f64() * 1000.0
+            ));
+            let mut expansion_map = match result {
+                Ok(expansion_map) => expansion_map,
+                // the other modules can still expand the query
+                Err(Error::LoadError(e)) => {
+                    warn!("Skipping module {}: {}", module.id(), e);
+                    warnings.push(ModuleWarning::new(module.id(), SkipReason::LoadFailed, e));
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for term in module_terms.iter() {
+                // move the expansions (and the key the module allocated) rather than copying them,
+                // a new key is only allocated if the module returned nothing for a term seen for the first time
+                let mut entry = expansion_map.remove_entry(term.as_str());
+                if module.common().preserve_case() {
+                    if let Some((_, moduleexpansions)) = entry.as_mut() {
+                        for termexpansion in moduleexpansions.iter_mut() {
+                            casing::preserve_case(term.as_str(), termexpansion);
+                        }
+                    }
+                }
+                if let Some(include) = module.common().include_term().or(self.config.include_term) {
+                    if let Some((_, moduleexpansions)) = entry.as_mut() {
+                        for termexpansion in moduleexpansions.iter_mut() {
+                            termexpansion.include_term(term.as_str(), include);
+                        }
+                        moduleexpansions.retain(|termexpansion| !termexpansion.is_empty());
+                    }
+                }
+                if let Some(expansions) = terms_map.get_mut(term.as_str()) {
+                    if let Some((_, moduleexpansions)) = entry {
+                        expansions.extend(moduleexpansions);
+                    }
+                } else {
+                    let (key, moduleexpansions) =
+                        entry.unwrap_or_else(|| (term.as_str().to_owned(), Vec::new()));
+                    terms_map.insert(key, moduleexpansions);
                 }
             }
         }
-        Ok(())
+        limits.truncate(terms_map, terms);
+        // terms that were not passed to any module (stopwords or module filter) still get an entry
+        for term in terms.iter() {
+            terms_map.entry(term.as_str().to_string()).or_default();
+        }
+        if let Some(threshold) = self.config.slow_query_threshold() {
+            let elapsed = begin_query.elapsed();
+            if elapsed > threshold {
+                let query = if let Some(Value::String(query)) = params.global(GlobalParam::Query) {
+                    query.clone()
+                } else {
+                    terms
+                        .iter()
+                        .map(|term| term.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+                warn!(
+                    "Slow query ({:.1}ms): {} [{} terms, modules: {}]",
+                    elapsed.as_secs_f64() * 1000.0,
+                    query,
+                    terms.len(),
+                    timings.join(", ")
+                );
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Determines the status of each term after query expansion, see [`TermStatus`]
+    pub fn term_statuses(
+        &self,
+        terms: &[Term],
+        terms_map: &TermExpansions,
+        params: &QueryParams,
+    ) -> TermStatuses {
+        let context = QueryContext::from_terms(terms);
+        self.term_statuses_in_context(terms, &context, terms_map, params)
+    }
+
+    /// Like [`Self::term_statuses()`], but takes the context of the terms in the query into account, so excluded
+    /// terms get status [`TermStatus::Negated`]
+    pub fn term_statuses_in_context(
+        &self,
+        terms: &[Term],
+        context: &QueryContext,
+        terms_map: &TermExpansions,
+        params: &QueryParams,
+    ) -> TermStatuses {
+        let selected = module_filter(params);
+        let overrides = TermOverrides::from_params(params).unwrap_or_default();
+        let routes = self.config.routes();
+        let expand_negated = params.global_bool(GlobalParam::ExpandNegated);
+        terms
+            .iter()
+            .map(|term| {
+                let status = if self.config.is_stopword(term.as_str()) {
+                    TermStatus::Stopword
+                } else if !expand_negated && context.is_negated(term.as_str()) {
+                    TermStatus::Negated
+                } else if !self.modules().any(|module| {
+                    overrides.selects(
+                        module.id(),
+                        term.as_str(),
+                        routes.selects(module.id(), term.as_str(), selected(module.id())),
+                    )
+                }) {
+                    TermStatus::ExcludedByModuleFilter
+                } else if terms_map
+                    .get(term.as_str())
+                    .is_some_and(|expansions| expansions.iter().any(|e| !e.expansions().is_empty()))
+                {
+                    TermStatus::Expanded
+                } else {
+                    TermStatus::NoMatches
+                };
+                (term.as_str().to_string(), status)
+            })
+            .collect()
     }
 
-    /// Resolve a query template by substituting the template terms by the disjunctions from query expansion
+    /// Resolve a query template by substituting the template terms by the disjunctions from query expansion.
+    /// Expansions of multiple words are quoted as phrases, single words are left unquoted (see `push_expansion()`).
+    /// If the expansions come from multiple modules, the whole disjunction is parenthesized so operators around the
+    /// term apply to all of it. If an analyzer chain is configured for the field of the term, the expansions are
+    /// analyzed first (see [`analyzer`]), expansions that become identical are only included once. Terms without
+    /// any expansions (e.g. stopwords and excluded terms) are kept as they are.
     /// You won't really need to call this yourself.
     pub fn resolve_query_template(
         &self,
         query_template: &str,
         terms_map: &TermExpansions,
+    ) -> Result<String, Error> {
+        self.resolve_query_template_with_coverage(query_template, terms_map, &Coverage::Replace)
+    }
+
+    /// Resolve a query template like [`Self::resolve_query_template()`], combining the expansions with the terms
+    /// themselves as the coverage prescribes
+    pub fn resolve_query_template_with_coverage(
+        &self,
+        query_template: &str,
+        terms_map: &TermExpansions,
+        coverage: &Coverage,
     ) -> Result<String, Error> {
         let mut query = String::with_capacity(query_template.len());
         let mut termbegin = None;
-        let mut termend = None;
+        //offset up to which the template has been copied to the query
+        let mut copied = 0;
         let mut prevc = None;
-        let mut expansioncache = std::collections::HashSet::<&str>::new();
+        let mut expansioncache = std::collections::HashSet::<Cow<str>>::new();
         for (i, c) in query_template.char_indices() {
             if c == '{' && prevc == Some('{') {
                 termbegin = Some(i + 1);
             }
-            if c == '}' && prevc == Some('}') && termbegin.is_some() {
-                if let Some(termend) = termend {
-                    query += &query_template[termend + 2..termbegin.unwrap() - 2];
-                }
-                termend = Some(i - 1);
-                let term = &query_template[termbegin.unwrap()..termend.unwrap()];
-                if let Some(termexpansions) = terms_map.get(term) {
+            if c == '}' && prevc == Some('}') {
+                if let Some(begin) = termbegin {
+                    let preceding = &query_template[copied..begin - 2];
+                    query += preceding;
+                    copied = i + 1;
+                    let term = &query_template[begin..i - 1];
+                    let analyzer = self.config.analyzers().get(field_of(preceding));
+                    let analyze = |s| match analyzer {
+                        Some(filters) => analyzer::analyze(filters, s),
+                        None => Cow::Borrowed(s),
+                    };
                     expansioncache.clear();
-                    for termexpansion in termexpansions {
-                        let mut first = true;
-                        for expansion in termexpansion.iter() {
-                            if !expansioncache.contains(expansion) {
-                                if !first {
-                                    query += "\" OR \"";
-                                } else {
-                                    if !expansioncache.is_empty() {
-                                        query += " OR ";
+                    let mut disjunction = String::new();
+                    let mut groups = 0;
+                    if coverage.includes_term() {
+                        let term = analyze(term);
+                        push_expansion(&mut disjunction, &term);
+                        expansioncache.insert(term);
+                        groups += 1;
+                    }
+                    if let Some(termexpansions) = terms_map.get(term) {
+                        for termexpansion in termexpansions {
+                            let mut first = true;
+                            for expansion in termexpansion.iter() {
+                                let expansion = analyze(expansion);
+                                if !expansioncache.contains(&expansion) {
+                                    if !first {
+                                        disjunction += " OR ";
+                                    } else {
+                                        if !expansioncache.is_empty() {
+                                            disjunction += " OR ";
+                                        }
+                                        disjunction.push('(');
+                                        groups += 1;
                                     }
-                                    query += "(\"";
+                                    first = false;
+                                    push_expansion(&mut disjunction, &expansion);
+                                    expansioncache.insert(expansion);
                                 }
-                                first = false;
-                                query += expansion;
-                                expansioncache.insert(expansion);
+                            }
+                            if !first {
+                                disjunction.push(')');
                             }
                         }
-                        if !first {
-                            query += "\")";
-                        }
                     }
+                    if groups > 1 {
+                        query.push('(');
+                        query += &disjunction;
+                        query.push(')');
+                    } else if groups == 1 {
+                        query += &disjunction;
+                    } else {
+                        push_expansion(&mut query, term);
+                    }
+                    //reset
+                    termbegin = None;
                 }
-                //reset
-                termbegin = None;
             }
             prevc = Some(c);
         }
-        if let Some(termend) = termend {
-            query += &query_template[termend + 2..];
-        }
+        query += &query_template[copied..];
         Ok(query)
     }
 }
 
+/// Returns the field a term in a query template is restricted to, given the template text preceding it
+/// (e.g. `title` for `... AND title:`)
+fn field_of(preceding: &str) -> Option<&str> {
+    let preceding = preceding.strip_suffix(':')?;
+    let field = preceding
+        .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .next()
+        .unwrap_or(preceding);
+    (!field.is_empty()).then_some(field)
+}
+
+/// Adds an expansion to a resolved query. Single words are added as they are, anything the query syntax would not
+/// read back as a single term (multiple words, punctuation, operators like `AND`) is quoted as a phrase, with any
+/// double quotes inside escaped.
+fn push_expansion(query: &mut String, expansion: &str) {
+    let is_word = !expansion.is_empty()
+        && expansion.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && !matches!(expansion, "AND" | "OR" | "NOT");
+    if is_word {
+        query.push_str(expansion);
+    } else {
+        query.push('"');
+        query.push_str(&expansion.replace('"', "\\\""));
+        query.push('"');
+    }
+}
+
+/// Holds loaded modules so they can be shared between multiple query expanders (e.g. one per tenant).
+/// A module with an identical configuration is shared as a whole. A module whose configuration only differs in
+/// options that do not change the loaded data (e.g. its ID or priority, see [`modules::data_key()`]) shares the
+/// loaded data instead, if the module type supports it (see [`Module::share()`]; lazy modules do not).
+#[derive(Default)]
+pub struct ModulePool {
+    modules: Vec<PooledModule>,
+    /// Restore modules from this snapshot where possible, rather than loading them from their files
+    snapshot: Option<Snapshot>,
+}
+
+/// A module in the [`ModulePool`]
+struct PooledModule {
+    /// See [`modules::data_key()`]
+    data_key: String,
+    /// The full configuration
+    config: String,
+    module: Arc<dyn Module>,
+    /// The time it took to load the data of the module
+    load_time: Duration,
+}
+
+impl ModulePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restores the data of modules from a snapshot where it is up to date, see [`Snapshot::restore()`]
+    pub fn with_snapshot(mut self, snapshot: Snapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self
+    }
+
+    /// Returns the module with the given configuration from the pool, or an instance sharing the data of a module in
+    /// the pool, or instantiates, loads and adds it if its data is not in the pool yet
+    pub fn get_or_load(&mut self, config: &AnyModuleConfig) -> Result<Arc<dyn Module>, Error> {
+        let data_key = modules::data_key(config.as_config());
+        let full_config = format!("{:?}", config);
+        if let Some(pooled) = self
+            .modules
+            .iter()
+            .find(|pooled| pooled.config == full_config)
+        {
+            info!("Sharing already loaded module {}", pooled.module.id());
+            return Ok(pooled.module.clone());
+        }
+        let shared = self
+            .modules
+            .iter()
+            .filter(|pooled| pooled.data_key == data_key)
+            .find_map(|pooled| {
+                pooled
+                    .module
+                    .share(config.as_any())
+                    .map(|module| (module, pooled))
+            });
+        if let Some((module, pooled)) = shared {
+            info!(
+                "Sharing the data of already loaded module {} with module {}",
+                pooled.module.id(),
+                module.id()
+            );
+            let load_time = pooled.load_time;
+            let module: Arc<dyn Module> = Arc::from(module);
+            self.modules.push(PooledModule {
+                data_key,
+                config: full_config,
+                module: module.clone(),
+                load_time,
+            });
+            return Ok(module);
+        }
+        let mut module = config.instantiate();
+        let begin = Instant::now();
+        let restored = match self.snapshot.as_ref() {
+            Some(snapshot) => snapshot.restore(module.as_mut()).unwrap_or_else(|e| {
+                warn!(
+                    "Unable to restore module {} from snapshot, it is loaded as usual: {}",
+                    module.id(),
+                    e
+                );
+                false
+            }),
+            None => false,
+        };
+        if !restored {
+            module.load()?;
+        }
+        let module: Arc<dyn Module> = Arc::from(module);
+        self.modules.push(PooledModule {
+            data_key,
+            config: full_config,
+            module: module.clone(),
+            load_time: begin.elapsed(),
+        });
+        Ok(module)
+    }
+
+    /// Returns the time it took to load a module from the pool (or the module whose data it shares)
+    pub fn load_time(&self, module: &Arc<dyn Module>) -> Option<Duration> {
+        self.modules
+            .iter()
+            .find(|pooled| std::ptr::addr_eq(Arc::as_ptr(&pooled.module), Arc::as_ptr(module)))
+            .map(|pooled| pooled.load_time)
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+/// Returns true if a module skips a term, as the modules it defers to (see [`Module::suppress_if_found_by()`])
+/// already found enough expansions for it
+fn suppressed(module: &dyn Module, term: &str, terms_map: &TermExpansions) -> bool {
+    if module.common().suppress_if_found_by().is_empty() {
+        return false;
+    }
+    let found: usize = terms_map
+        .get(term)
+        .into_iter()
+        .flatten()
+        .filter(|termexpansion| {
+            termexpansion.source_id().is_some_and(|id| {
+                module
+                    .common()
+                    .suppress_if_found_by()
+                    .iter()
+                    .any(|other| other == id)
+            })
+        })
+        .map(|termexpansion| termexpansion.len())
+        .sum();
+    found >= module.common().suppress_min_expansions()
+}
+
+/// Returns the files matched by an include pattern (relative to the directory of the including file)
+#[cfg(feature = "glob")]
+fn include_paths(fullpattern: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    glob::glob(&fullpattern.to_string_lossy())
+        .map_err(|e| Error::InvalidConfig(format!("Invalid include pattern {}: {}", pattern, e)))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Error::LoadError(format!("Unable to include {}: {}", pattern, e)))
+}
+
+/// Returns the included file, without the `glob` feature patterns with wildcards are not supported
+#[cfg(not(feature = "glob"))]
+fn include_paths(fullpattern: &Path, pattern: &str) -> Result<Vec<PathBuf>, Error> {
+    if pattern.contains(['*', '?', '[']) {
+        return Err(Error::InvalidConfig(format!(
+            "Can not include {}: kweepeer was built without the glob feature, so wildcards are not supported",
+            pattern
+        )));
+    }
+    Ok(fullpattern
+        .exists()
+        .then(|| fullpattern.to_path_buf())
+        .into_iter()
+        .collect())
+}
+
+/// Returns a function that tells whether a module (by ID) is selected by the `include` and `exclude` parameters
+fn module_filter(params: &QueryParams) -> impl Fn(&str) -> bool {
+    let ids = |param| -> Vec<String> {
+        params
+            .global(param)
+            .map(|mods| {
+                value_to_str_array(mods)
+                    .into_iter()
+                    .map(|s| s.to_owned())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excludemods = ids(GlobalParam::Exclude);
+    let includemods = ids(GlobalParam::Include);
+    move |id| {
+        (excludemods.is_empty() || !excludemods.iter().any(|m| m == id))
+            && (includemods.is_empty() || includemods.iter().any(|m| m == id))
+    }
+}
+
 /// convert a json array of strings to a rust Vec<&str>
 fn value_to_str_array(input: &Value) -> Vec<&str> {
     if let Value::Array(array) = input {
@@ -263,13 +1881,26 @@ fn value_to_str_array(input: &Value) -> Vec<&str> {
     }
 }
 
-#[derive(Debug, Serialize, Default, Clone)]
+/// Expansions for a single term from a single source/module
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(default)]
 pub struct TermExpansion {
+    /// The expansions
     expansions: Vec<String>,
+    /// Scores for each of the expansions (if provided by the module), in the same order
     scores: Vec<f64>,
+    /// Edit distance of each of the expansions to the term (if requested), in the same order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    distances: Vec<u32>,
+    /// The ID of the module that provided the expansions
     source_id: Option<String>,
+    /// The name of the module that provided the expansions
     source_name: Option<String>,
-    source_type: &'static str,
+    /// The type of the module that provided the expansions
+    #[cfg_attr(feature = "server", schema(value_type = String))]
+    source_type: Cow<'static, str>,
+    /// A link to more information
     link: Option<String>,
 }
 
@@ -277,7 +1908,7 @@ impl TermExpansion {
     pub fn with_source(mut self, module: &impl Module) -> Self {
         self.source_id = Some(module.id().into());
         self.source_name = Some(module.name().into());
-        self.source_type = module.kind();
+        self.source_type = Cow::Borrowed(module.kind());
         self
     }
 
@@ -305,14 +1936,119 @@ impl TermExpansion {
         self.expansions.push(expansion.into());
     }
 
+    /// Sets the edit distance of each of the expansions to the term, see [`distance::attach_distances()`]
+    pub fn set_distances(&mut self, distances: Vec<u32>) {
+        self.distances = distances;
+    }
+
+    /// Retains only the expansions (and their scores and distances) for which the predicate returns true
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) {
+        let scored = self.scores.len() == self.expansions.len();
+        let measured = self.distances.len() == self.expansions.len();
+        let mut i = 0;
+        while i < self.expansions.len() {
+            if f(self.expansions[i].as_str()) {
+                i += 1;
+            } else {
+                self.expansions.remove(i);
+                if scored {
+                    self.scores.remove(i);
+                }
+                if measured {
+                    self.distances.remove(i);
+                }
+            }
+        }
+    }
+
+    /// Moves the term itself to the front of the expansions, with score 1.0 if they are scored (adding it if it is
+    /// missing) if `include` is true, removes it from the expansions otherwise. Makes the expansions of modules consistent, as some return
+    /// the term itself (e.g. if it is in their lexicon) and others don't.
+    pub fn include_term(&mut self, term: &str, include: bool) {
+        self.retain(|expansion| expansion != term);
+        if include {
+            if self.scores.len() == self.expansions.len() {
+                self.scores.insert(0, 1.0);
+            }
+            if self.distances.len() == self.expansions.len() && !self.distances.is_empty() {
+                self.distances.insert(0, 0);
+            }
+            self.expansions.insert(0, term.to_owned());
+        }
+    }
+
+    /// Multiplies the scores of the expansions for which the predicate returns true by the given factor and moves them
+    /// to the end, retaining the order otherwise. Expansions without scores get a score of 1.0 first.
+    pub fn downweight(&mut self, factor: f64, mut f: impl FnMut(&str) -> bool) {
+        if self.scores.len() != self.expansions.len() {
+            self.scores = vec![1.0; self.expansions.len()];
+        }
+        let downweighted: Vec<bool> = self.expansions.iter().map(|x| f(x)).collect();
+        for (score, _) in self
+            .scores
+            .iter_mut()
+            .zip(downweighted.iter())
+            .filter(|(_, downweighted)| **downweighted)
+        {
+            *score *= factor;
+        }
+        let order: Vec<usize> = (0..self.expansions.len())
+            .filter(|i| !downweighted[*i])
+            .chain((0..self.expansions.len()).filter(|i| downweighted[*i]))
+            .collect();
+        self.reorder(&order);
+    }
+
+    /// Orders the expansions by descending score, and those with equal scores alphabetically, so the expansions that
+    /// remain when the list is cut off are the same for identical requests. Expansions without scores are left as is.
+    pub fn sort_by_score(&mut self) {
+        if self.scores.len() != self.expansions.len() {
+            return;
+        }
+        let mut order: Vec<usize> = (0..self.expansions.len()).collect();
+        order.sort_by(|a, b| {
+            self.scores[*b]
+                .total_cmp(&self.scores[*a])
+                .then_with(|| self.expansions[*a].cmp(&self.expansions[*b]))
+        });
+        self.reorder(&order);
+    }
+
+    /// Puts the expansions (and their scores and distances) in the given order of their current indices
+    fn reorder(&mut self, order: &[usize]) {
+        if self.scores.len() == self.expansions.len() {
+            self.scores = order.iter().map(|i| self.scores[*i]).collect();
+        }
+        if self.distances.len() == self.expansions.len() {
+            self.distances = order.iter().map(|i| self.distances[*i]).collect();
+        }
+        self.expansions = order
+            .iter()
+            .map(|i| std::mem::take(&mut self.expansions[*i]))
+            .collect();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+
     pub fn expansions(&self) -> &Vec<String> {
         &self.expansions
     }
 
+    /// Returns the expansions for modification in place, the number and order must remain the same
+    pub(crate) fn expansions_mut(&mut self) -> &mut Vec<String> {
+        &mut self.expansions
+    }
+
     pub fn scores(&self) -> &Vec<f64> {
         &self.scores
     }
 
+    pub fn distances(&self) -> &Vec<u32> {
+        &self.distances
+    }
+
     pub fn source_id(&self) -> Option<&str> {
         self.source_id.as_deref()
     }
@@ -425,17 +2161,119 @@ impl QueryParams {
         }
         None
     }
+
+    /// Retrieve the value of a global parameter
+    pub fn global(&self, param: GlobalParam) -> Option<&Value> {
+        self.get("", param.name())
+    }
+
+    /// Returns true if a global flag is set, see [`Self::get_bool()`]
+    pub fn global_bool(&self, param: GlobalParam) -> bool {
+        self.get_bool("", param.name())
+    }
+
+    /// Insert a global parameter
+    pub fn insert_global(&mut self, param: GlobalParam, value: Value) {
+        self.insert("", param.name(), value);
+    }
+
+    /// Checks that all global parameters are known, see [`GlobalParam`]
+    pub fn check_globals(&self) -> Result<(), Error> {
+        for param in self.iter_for_module("") {
+            if GlobalParam::from_name(param.key()).is_none() {
+                return Err(Error::InvalidParameter(format!(
+                    "Unknown global parameter: {} (expected one of {})",
+                    param.key(),
+                    GlobalParam::ALL.map(|param| param.name()).join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a parameter as non-negative integer, None if it is not passed or not an integer. Module parameters
+    /// are validated before expansion, see [`modules::Module::parameters()`].
+    pub fn get_u64(&self, module_id: &str, key: &str) -> Option<u64> {
+        self.get(module_id, key).and_then(|value| value.as_u64())
+    }
+
+    /// Returns a parameter as number, None if it is not passed or not a number
+    pub fn get_f64(&self, module_id: &str, key: &str) -> Option<f64> {
+        self.get(module_id, key).and_then(|value| value.as_f64())
+    }
+
+    /// Returns true if a flag is set: a boolean `true`, or the string `true` or `1` as passed via the webservice
+    pub fn get_bool(&self, module_id: &str, key: &str) -> bool {
+        match self.get(module_id, key) {
+            Some(Value::Bool(value)) => *value,
+            Some(Value::String(value)) => value == "true" || value == "1",
+            Some(Value::Number(value)) => value.as_u64() == Some(1),
+            _ => false,
+        }
+    }
+}
+
+impl QueryParams {
+    /// Parse and insert a parameter of the form `module.key=value` (or `key=value` for the global scope).
+    /// The value is interpreted as JSON if possible (e.g. numbers, booleans), and as a string otherwise.
+    pub fn insert_from_str(&mut self, param: &str) -> Result<(), Error> {
+        let (key, value) = param.split_once('=').ok_or_else(|| {
+            Error::InvalidParameter(format!(
+                "Parameter must be of the form module.key=value: {}",
+                param
+            ))
+        })?;
+        let value = parse_value(value);
+        if let Some((module_id, key)) = key.split_once('.') {
+            self.insert(module_id, key, value);
+        } else {
+            self.insert("", key, value);
+        }
+        Ok(())
+    }
+
+    /// Parse and insert parameters from a JSON object. Keys are either of the form `module.key`, or a module ID mapping to an object with
+    /// keys and values for that module. Other keys are in the global scope.
+    pub fn insert_from_json(&mut self, json: &str) -> Result<(), Error> {
+        let value: Value = serde_json::from_str(json)
+            .map_err(|e| Error::InvalidParameter(format!("Invalid JSON parameters: {}", e)))?;
+        let Value::Object(map) = value else {
+            return Err(Error::InvalidParameter(
+                "JSON parameters must be an object".into(),
+            ));
+        };
+        for (key, value) in map {
+            match (key.split_once('.'), value) {
+                (Some((module_id, key)), value) => self.insert(module_id, key, value),
+                (None, Value::Object(modulemap)) => {
+                    for (modulekey, value) in modulemap {
+                        self.insert(key.as_str(), modulekey, value);
+                    }
+                }
+                (None, value) => self.insert("", key, value),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Interprets a parameter value as JSON if possible (e.g. numbers, booleans), and as a string otherwise
+fn parse_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.into()))
 }
 
+/// Parameters as passed in the query string of a GET request: `module.key=value` for module parameters (module IDs
+/// contain no periods), `key=value` for global ones. Module parameters are interpreted like
+/// [`QueryParams::insert_from_str()`] does, so e.g. `fst.distance=2` is a number. Global parameters remain strings,
+/// as a query like `q=1648` or `q="oude kaas"` must be taken literally.
 impl From<&HashMap<String, String>> for QueryParams {
     fn from(map: &HashMap<String, String>) -> Self {
         let mut result = QueryParams::new();
         for (key, value) in map.iter() {
-            let splitkey: Vec<_> = key.splitn(2, '.').collect();
-            if splitkey.len() == 1 {
-                result.insert("", key, value.to_owned().into());
+            if let Some((module_id, key)) = key.split_once('.') {
+                result.insert(module_id, key, parse_value(value));
             } else {
-                result.insert(splitkey[0], splitkey[1], value.to_owned().into());
+                result.insert("", key, value.to_owned().into());
             }
         }
         result
@@ -446,6 +2284,48 @@ impl From<&HashMap<String, String>> for QueryParams {
 pub enum Error {
     LoadError(String),
     QueryExpandError(String),
+    /// An invalid value was passed for a (module) parameter
+    InvalidParameter(String),
+    /// An invalid module configuration was passed
+    InvalidConfig(String),
+    /// The requested module does not exist
+    ModuleNotFound(String),
+    /// An operation did not complete in time
+    Timeout(String),
+    /// A query exceeds one of the configured limits
+    LimitExceeded(String),
+    /// An operation was cancelled because its result is no longer needed
+    Cancelled(String),
+}
+
+impl Error {
+    /// Returns a machine-readable code for the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::LoadError(..) => "load_error",
+            Self::QueryExpandError(..) => "query_expansion_error",
+            Self::InvalidParameter(..) => "invalid_parameter",
+            Self::InvalidConfig(..) => "invalid_config",
+            Self::ModuleNotFound(..) => "module_not_found",
+            Self::Timeout(..) => "timeout",
+            Self::LimitExceeded(..) => "limit_exceeded",
+            Self::Cancelled(..) => "cancelled",
+        }
+    }
+
+    /// Returns the error message (without prefix)
+    pub fn message(&self) -> &str {
+        match self {
+            Self::LoadError(s)
+            | Self::QueryExpandError(s)
+            | Self::InvalidParameter(s)
+            | Self::InvalidConfig(s)
+            | Self::ModuleNotFound(s)
+            | Self::Timeout(s)
+            | Self::LimitExceeded(s)
+            | Self::Cancelled(s) => s.as_str(),
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -459,10 +2339,36 @@ impl std::fmt::Display for Error {
                 f.write_str("[Query expansion error] ")?;
                 f.write_str(x)
             }
+            Self::InvalidParameter(x) => {
+                f.write_str("[Invalid parameter] ")?;
+                f.write_str(x)
+            }
+            Self::InvalidConfig(x) => {
+                f.write_str("[Invalid configuration] ")?;
+                f.write_str(x)
+            }
+            Self::ModuleNotFound(x) => {
+                f.write_str("[Module not found] ")?;
+                f.write_str(x)
+            }
+            Self::Timeout(x) => {
+                f.write_str("[Timeout] ")?;
+                f.write_str(x)
+            }
+            Self::LimitExceeded(x) => {
+                f.write_str("[Limit exceeded] ")?;
+                f.write_str(x)
+            }
+            Self::Cancelled(x) => {
+                f.write_str("[Cancelled] ")?;
+                f.write_str(x)
+            }
         }
     }
 }
 
+impl std::error::Error for Error {}
+
 impl From<std::io::Error> for Error {
     fn from(value: std::io::Error) -> Self {
         Self::LoadError(format!("{}", value))
@@ -474,8 +2380,368 @@ impl Serialize for Error {
     where
         S: serde::Serializer,
     {
-        match self {
-            Self::LoadError(s) | Self::QueryExpandError(s) => serializer.serialize_str(s.as_str()),
+        serializer.serialize_str(self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+
+    #[test]
+    pub fn test001_queryparams_from_str() -> Result<(), Error> {
+        let mut params = QueryParams::new();
+        params.insert_from_str("fst.distance=2")?;
+        params.insert_from_str("include=fst,lookup")?;
+        assert_eq!(params.get("fst", "distance"), Some(&Value::from(2)));
+        assert_eq!(
+            params.global(GlobalParam::Include),
+            Some(&Value::from("fst,lookup"))
+        );
+        assert!(params.insert_from_str("fst.distance").is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_queryparams_from_json() -> Result<(), Error> {
+        let mut params = QueryParams::new();
+        params.insert_from_json(
+            r#"{"fst": {"distance": 2}, "finalfusion.k": 5, "exclude": "lookup"}"#,
+        )?;
+        assert_eq!(params.get("fst", "distance"), Some(&Value::from(2)));
+        assert_eq!(params.get("finalfusion", "k"), Some(&Value::from(5)));
+        assert_eq!(
+            params.global(GlobalParam::Exclude),
+            Some(&Value::from("lookup"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_config_validate() {
+        let config: Config = toml::from_str(
+            r#"
+            [[lookup]]
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+
+            [[lookup]]
+            id = "lookup"
+            name = "Duplicate"
+            file = "test/nonexistent.tsv"
+            "#,
+        )
+        .expect("config must parse");
+        assert_eq!(config.module_configs().len(), 2);
+        let problems = config.validate();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].starts_with("Duplicate module ID"));
+        assert!(problems[1].contains("nonexistent.tsv"));
+    }
+
+    #[test]
+    pub fn test004_config_skeleton() -> Result<(), Error> {
+        let config: Config =
+            toml::from_str(&Config::skeleton()).map_err(|e| Error::LoadError(format!("{}", e)))?;
+        let expected = 1
+            + cfg!(feature = "fst") as usize
+            + cfg!(feature = "analiticcl") as usize
+            + cfg!(feature = "finalfusion") as usize;
+        assert_eq!(config.module_configs().len(), expected);
+
+        // the commented out examples must be valid as well
+        let uncommented: String = Config::skeleton()
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(rest)
+                    if rest.starts_with('[')
+                        || rest
+                            .split_once(" = ")
+                            .is_some_and(|(key, _)| !key.contains(' ')) =>
+                {
+                    rest
+                }
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config: Config =
+            toml::from_str(&uncommented).map_err(|e| Error::LoadError(format!("{}", e)))?;
+        assert_eq!(config.stopwords.len(), 3);
+        assert!(config.auth().is_some());
+        assert!(config.audit().is_some());
+        assert_eq!(config.include, vec!["modules/*.toml"]);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(feature = "glob")]
+    pub fn test005_config_include() -> Result<(), Error> {
+        let dir = TestDir::new("include");
+        dir.write(
+            "kweepeer.toml",
+            "include = [\"modules/*.toml\"]\n\n[[lookup]]\nid = \"main\"\nname = \"Main\"\nfile = \"main.tsv\"\n",
+        );
+        for id in ["b", "a"] {
+            dir.write(
+                format!("modules/{}.toml", id),
+                format!(
+                    "[[lookup]]\nid = \"{}\"\nname = \"{}\"\nfile = \"{}.tsv\"\n",
+                    id, id, id
+                ),
+            );
         }
+        let config = Config::from_file(dir.join("kweepeer.toml"))?;
+        let ids: Vec<&str> = config.module_configs().iter().map(|m| m.id()).collect();
+        assert_eq!(ids, vec!["main", "a", "b"]);
+        dir.write("modules/c.toml", "[auth]\n");
+        assert!(Config::from_file(dir.join("kweepeer.toml")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test006_config_relative_paths() -> Result<(), Error> {
+        let dir = TestDir::new("paths");
+        let module = "[[lookup]]\nid = \"lookup\"\nname = \"Lookup\"\nfile = \"lexicon.tsv\"\n\n[[lookup]]\nid = \"absolute\"\nname = \"Absolute\"\nfile = \"/data/lexicon.tsv\"\n";
+        let a = Config::from_file(dir.write("a.toml", module))?;
+        let b = Config::from_file(dir.write("b.toml", format!("base_dir = \"data\"\n{}", module)))?;
+        assert_eq!(a.module_configs()[0].files(), vec![dir.join("lexicon.tsv")]);
+        assert_eq!(
+            a.module_configs()[1].files(),
+            vec![Path::new("/data/lexicon.tsv")]
+        );
+        assert_eq!(
+            b.module_configs()[0].files(),
+            vec![dir.path().join("data").join("lexicon.tsv")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test007_config_defaults() -> Result<(), Error> {
+        let dir = TestDir::new("defaults");
+        dir.write(
+            "kweepeer.toml",
+            "include = [\"included.toml\"]\n\n[defaults]\nlazy = true\n\n[[lookup]]\nid = \"a\"\nname = \"A\"\nfile = \"a.tsv\"\n\n[[lookup]]\nid = \"b\"\nname = \"B\"\nfile = \"b.tsv\"\nlazy = false\n",
+        );
+        dir.write(
+            "included.toml",
+            "[[lookup]]\nid = \"c\"\nname = \"C\"\nfile = \"c.tsv\"\n",
+        );
+        let config = Config::from_file(dir.join("kweepeer.toml"))?;
+        let lazy: Vec<bool> = config
+            .module_configs()
+            .iter()
+            .map(|m| m.common().lazy())
+            .collect();
+        assert_eq!(lazy, vec![true, false, true]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test008_config_id_prefix() -> Result<(), Error> {
+        let dir = TestDir::new("prefix");
+        let module = "[[lookup]]\nid = \"lexicon\"\nname = \"Lexicon\"\nfile = \"lexicon.tsv\"\n";
+        dir.write("kweepeer.toml", "include = [\"a.toml\", \"b.toml\"]\n");
+        dir.write("a.toml", format!("id_prefix = \"a_\"\n{}", module));
+        dir.write("b.toml", module);
+        let unique = Config::from_file(dir.join("kweepeer.toml"))?;
+        let ids: Vec<&str> = unique.module_configs().iter().map(|m| m.id()).collect();
+        assert_eq!(ids, vec!["a_lexicon", "lexicon"]);
+        dir.write("a.toml", module);
+        let duplicate = Config::from_file(dir.join("kweepeer.toml"))?;
+        let mut expander = QueryExpander::new().with_config(duplicate);
+        assert!(matches!(expander.load(), Err(Error::InvalidConfig(_))));
+        dir.write("a.toml", format!("id_prefix = \"a.\"\n{}", module));
+        assert!(Config::from_file(dir.join("kweepeer.toml")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test009_termexpansion_sort_by_score() {
+        let mut termexpansion = TermExpansion::default()
+            .with_expansions(vec![
+                "wyn".into(),
+                "wine".into(),
+                "vin".into(),
+                "wijne".into(),
+            ])
+            .with_scores(vec![0.5, 1.0, 0.5, 0.8]);
+        termexpansion.set_distances(vec![1, 3, 3, 1]);
+        termexpansion.sort_by_score();
+        assert_eq!(
+            termexpansion.expansions(),
+            &vec!["wine", "wijne", "vin", "wyn"]
+        );
+        assert_eq!(termexpansion.scores(), &vec![1.0, 0.8, 0.5, 0.5]);
+        assert_eq!(termexpansion.distances(), &vec![3, 1, 3, 1]);
+        termexpansion.downweight(0.5, |expansion| expansion == "wine");
+        assert_eq!(
+            termexpansion.expansions(),
+            &vec!["wijne", "vin", "wyn", "wine"]
+        );
+        assert_eq!(termexpansion.scores(), &vec![0.8, 0.5, 0.5, 0.5]);
+        assert_eq!(termexpansion.distances(), &vec![1, 3, 1, 3]);
+    }
+
+    #[test]
+    pub fn test010_resolve_query_template_phrases() -> Result<(), Error> {
+        let expander = QueryExpander::new();
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "kaas".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["kaes".into(), "oude kaas".into()]),
+                TermExpansion::default().with_expansions(vec!["kaes".into(), "fromage".into()]),
+            ],
+        );
+        terms_map.insert(
+            "wijn".into(),
+            vec![TermExpansion::default().with_expansions(vec![
+                "wyn".into(),
+                "rode-wijn".into(),
+                "OR".into(),
+                "\"vin\"".into(),
+            ])],
+        );
+        assert_eq!(
+            expander.resolve_query_template("{{kaas}}", &terms_map)?,
+            "((kaes OR \"oude kaas\") OR (fromage))"
+        );
+        assert_eq!(
+            expander.resolve_query_template("(+{{kaas}} AND NOT ({{wijn}} OR brood))^2", &terms_map)?,
+            "(+((kaes OR \"oude kaas\") OR (fromage)) AND NOT ((wyn OR \"rode-wijn\" OR \"OR\" OR \"\\\"vin\\\"\") OR brood))^2"
+        );
+        assert_eq!(
+            expander.resolve_query_template("title:{{bier}} {{wijn}}~", &terms_map)?,
+            "title:bier (wyn OR \"rode-wijn\" OR \"OR\" OR \"\\\"vin\\\"\")~"
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test011_termexpansion_include_term() -> Result<(), Error> {
+        let mut termexpansion = TermExpansion::default()
+            .with_expansions(vec!["wyn".into(), "wijn".into(), "vin".into()])
+            .with_scores(vec![0.9, 0.8, 0.5]);
+        termexpansion.include_term("wijn", true);
+        assert_eq!(termexpansion.expansions(), &vec!["wijn", "wyn", "vin"]);
+        assert_eq!(termexpansion.scores(), &vec![1.0, 0.9, 0.5]);
+        termexpansion.include_term("wijn", false);
+        assert_eq!(termexpansion.expansions(), &vec!["wyn", "vin"]);
+        assert_eq!(termexpansion.scores(), &vec![0.9, 0.5]);
+        let mut termexpansion = TermExpansion::default().with_expansions(vec!["kaes".into()]);
+        termexpansion.include_term("kaas", true);
+        assert_eq!(termexpansion.expansions(), &vec!["kaas", "kaes"]);
+        assert!(termexpansion.scores().is_empty());
+
+        let config: Config = toml::from_str(
+            "include_term = true\n[[lookup]]\nid = \"lexicon\"\nname = \"Lexicon\"\nfile = \"lexicon.tsv\"\ninclude_term = false",
+        )
+        .expect("valid configuration");
+        assert_eq!(config.include_term(), Some(true));
+        let module = config
+            .modules()
+            .iter()
+            .next()
+            .expect("module")
+            .instantiate();
+        assert_eq!(module.common().include_term(), Some(false));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test012_queryparams_from_query_string() {
+        let map: HashMap<String, String> = [
+            ("fst.distance", "2"),
+            ("analiticcl.max_matches", "5"),
+            ("lookup.exact", "true"),
+            ("lookup.field", "title"),
+            ("q", "\"oude kaas\""),
+            ("seed", "42"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect();
+        let params = QueryParams::from(&map);
+        assert_eq!(params.get("fst", "distance"), Some(&Value::from(2)));
+        assert_eq!(
+            params.get("analiticcl", "max_matches"),
+            Some(&Value::from(5))
+        );
+        assert_eq!(params.get("lookup", "exact"), Some(&Value::from(true)));
+        assert_eq!(params.get("lookup", "field"), Some(&Value::from("title")));
+        assert_eq!(
+            params.global(GlobalParam::Query),
+            Some(&Value::from("\"oude kaas\""))
+        );
+        assert_eq!(params.get("", "seed"), Some(&Value::from("42")));
+    }
+
+    #[test]
+    pub fn test013_config_module_order() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            "[[module]]\ntype = \"lookup\"\nid = \"c\"\nname = \"C\"\nfile = \"c.tsv\"\n\n[[lookup]]\nid = \"b\"\nname = \"B\"\nfile = \"b.tsv\"\n\n[[module]]\ntype = \"lookup\"\nid = \"a\"\nname = \"A\"\nfile = \"a.tsv\"\n",
+        )
+        .expect("valid configuration");
+        let ids: Vec<&str> = config.module_configs().iter().map(|m| m.id()).collect();
+        // the tables per type come first even if written in between, TOML doesn't retain their relative order
+        assert_eq!(ids, vec!["b", "c", "a"]);
+        assert!(toml::from_str::<Config>(
+            "[[module]]\ntype = \"thesaurus\"\nid = \"t\"\nname = \"T\"\n"
+        )
+        .is_err());
+        assert!(modules::from_config(
+            "lookup",
+            serde_json::json!({"id": "d", "name": "D", "file": "d.tsv"})
+        )
+        .is_ok());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test014_module_pool_shares_data() -> Result<(), Error> {
+        let dir = TestDir::new("pool");
+        let file = dir.write("lookup.tsv", "separate\tseperate\n");
+        let config = |id: &str, extra: &str| -> Config {
+            toml::from_str(&format!(
+                "[[lookup]]\nid = \"{}\"\nname = \"Lookup\"\nfile = \"{}\"\n{}",
+                id,
+                file.display(),
+                extra
+            ))
+            .expect("valid configuration")
+        };
+        let expand = |expander: &QueryExpander, id: &str| -> Result<Vec<String>, Error> {
+            let terms = vec![Term::Singular("separate")];
+            let expansions = expander.expand_query(&terms, &QueryParams::new())?;
+            Ok(expansions["separate"]
+                .iter()
+                .filter(|expansion| expansion.source_id() == Some(id))
+                .flat_map(|expansion| expansion.iter().map(|s| s.to_owned()))
+                .collect())
+        };
+        let mut pool = ModulePool::new();
+        let mut first = QueryExpander::new().with_config(config("a", ""));
+        first.load_shared(&mut pool)?;
+        // the data changes, but modules that only differ in their ID or priority share the data already loaded
+        dir.write("lookup.tsv", "separate\tsepparate\n");
+        let mut second = QueryExpander::new().with_config(config("b", "priority = 5"));
+        second.load_shared(&mut pool)?;
+        assert_eq!(expand(&second, "b")?, vec!["seperate"]);
+        assert_eq!(
+            second
+                .get_module("b")
+                .map(|module| module.common().priority()),
+            Some(5)
+        );
+        // other options that change the loaded data do not share it
+        let mut third = QueryExpander::new().with_config(config("c", "casesensitive = true"));
+        third.load_shared(&mut pool)?;
+        assert_eq!(expand(&third, "c")?, vec!["sepparate"]);
+        assert_eq!(pool.len(), 3);
+        Ok(())
     }
 }
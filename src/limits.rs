@@ -0,0 +1,194 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::{Error, Term, TermExpansions};
+
+/// Guardrails for publicly accessible deployments. Each limit is only imposed if it is set.
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Maximum length of a query, in characters
+    max_query_length: Option<usize>,
+
+    /// Maximum number of terms in a query
+    max_terms: Option<usize>,
+
+    /// Maximum number of expansions in a response (over all terms and modules), further expansions are dropped
+    max_expansions: Option<usize>,
+
+    /// Maximum time to spend on the expansion of a query, in milliseconds
+    timeout: Option<u64>,
+
+    /// Maximum number of queries the webservice expands concurrently, further requests wait for their turn
+    max_concurrent: Option<usize>,
+
+    /// Maximum (estimated) memory used by the data of all modules together, in MiB
+    max_memory_mb: Option<u64>,
+}
+
+impl LimitsConfig {
+    pub fn max_query_length(&self) -> Option<usize> {
+        self.max_query_length
+    }
+
+    pub fn max_terms(&self) -> Option<usize> {
+        self.max_terms
+    }
+
+    pub fn max_expansions(&self) -> Option<usize> {
+        self.max_expansions
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_millis)
+    }
+
+    pub fn max_concurrent(&self) -> Option<usize> {
+        self.max_concurrent
+    }
+
+    /// Maximum memory used by the data of all modules together, in bytes
+    pub fn max_memory(&self) -> Option<u64> {
+        self.max_memory_mb.map(|mb| mb * 1024 * 1024)
+    }
+
+    /// Checks whether a module with the given estimated memory usage (in bytes) fits in the memory budget,
+    /// next to the memory already used by other modules
+    pub fn check_memory(&self, module_id: &str, estimate: u64, others: u64) -> Result<(), Error> {
+        if let Some(max) = self.max_memory() {
+            if others + estimate > max {
+                return Err(Error::LimitExceeded(format!(
+                    "Module {} takes an estimated {:.1} MiB, which exceeds the memory budget of {} MiB ({:.1} MiB is already used by other modules)",
+                    module_id,
+                    estimate as f64 / 1048576.0,
+                    max / 1048576,
+                    others as f64 / 1048576.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the length of a query
+    pub fn check_query(&self, query: &str) -> Result<(), Error> {
+        if let Some(max) = self.max_query_length {
+            let length = query.chars().count();
+            if length > max {
+                return Err(Error::LimitExceeded(format!(
+                    "Query is {} characters long, at most {} are allowed",
+                    length, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the number of terms in a query
+    pub fn check_terms(&self, terms: &[Term]) -> Result<(), Error> {
+        if let Some(max) = self.max_terms {
+            if terms.len() > max {
+                return Err(Error::LimitExceeded(format!(
+                    "Query has {} terms, at most {} are allowed",
+                    terms.len(),
+                    max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops the expansions beyond the maximum number per response. The terms are considered in query order,
+    /// so the expansions of the last terms are dropped first. Scored expansions are ordered by score (and
+    /// alphabetically on equal scores) first, see [`crate::TermExpansion::sort_by_score()`], so the best ones are kept.
+    pub fn truncate(&self, terms_map: &mut TermExpansions, terms: &[Term]) {
+        let Some(mut remaining) = self.max_expansions else {
+            return;
+        };
+        let mut seen = HashSet::new();
+        for term in terms.iter() {
+            if !seen.insert(term.as_str()) {
+                continue;
+            }
+            if let Some(termexpansions) = terms_map.get_mut(term.as_str()) {
+                for termexpansion in termexpansions.iter_mut() {
+                    termexpansion.sort_by_score();
+                    termexpansion.retain(|_| {
+                        if remaining > 0 {
+                            remaining -= 1;
+                            true
+                        } else {
+                            false
+                        }
+                    });
+                }
+                termexpansions.retain(|termexpansion| !termexpansion.is_empty());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_limits_check() {
+        let limits: LimitsConfig =
+            toml::from_str("max_query_length = 10\nmax_terms = 2").expect("valid configuration");
+        assert!(limits.check_query("wijn kaas").is_ok());
+        assert!(matches!(
+            limits.check_query("wijn en kaas"),
+            Err(Error::LimitExceeded(_))
+        ));
+        let (terms, _) = Term::extract_from_query("wijn kaas brood");
+        assert!(limits.check_terms(&terms[..2]).is_ok());
+        assert!(matches!(
+            limits.check_terms(&terms),
+            Err(Error::LimitExceeded(_))
+        ));
+        assert!(LimitsConfig::default().check_terms(&terms).is_ok());
+    }
+
+    #[test]
+    pub fn test002_limits_truncate() {
+        let limits: LimitsConfig =
+            toml::from_str("max_expansions = 3").expect("valid configuration");
+        let (terms, _) = Term::extract_from_query("wijn kaas");
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijn".into(),
+            vec![TermExpansion::default()
+                .with_expansions(vec!["wyn".into(), "wine".into()])
+                .with_scores(vec![1.0, 0.5])],
+        );
+        terms_map.insert(
+            "kaas".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["kaes".into(), "caas".into()]),
+                TermExpansion::default().with_expansions(vec!["cheese".into()]),
+            ],
+        );
+        limits.truncate(&mut terms_map, &terms);
+        assert_eq!(terms_map["wijn"][0].scores(), &vec![1.0, 0.5]);
+        assert_eq!(terms_map["kaas"].len(), 1);
+        assert_eq!(terms_map["kaas"][0].expansions(), &vec!["kaes".to_string()]);
+    }
+
+    #[test]
+    pub fn test003_limits_memory() {
+        let limits: LimitsConfig =
+            toml::from_str("max_memory_mb = 2").expect("valid configuration");
+        assert!(limits
+            .check_memory("lookup", 1024 * 1024, 512 * 1024)
+            .is_ok());
+        assert!(matches!(
+            limits.check_memory("lookup", 1024 * 1024, 1024 * 1024 + 1),
+            Err(Error::LimitExceeded(message)) if message.contains("lookup")
+        ));
+        assert!(LimitsConfig::default()
+            .check_memory("lookup", u64::MAX / 2, 0)
+            .is_ok());
+    }
+}
@@ -1,9 +1,13 @@
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::any::Any;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info};
 
 use crate::lexer::Term;
-use crate::modules::Module;
+use crate::modules::params::{ParamSpec, ParamType};
+use crate::modules::{self, CommonModuleConfig, DataVersion, FileChecksum, Module, ModuleConfig};
 use crate::{Error, QueryParams, TermExpansion, TermExpansions};
 
 use analiticcl::{SearchParameters, VariantModel, VocabParams, Weights};
@@ -56,6 +60,23 @@ pub struct AnaliticclConfig {
     /// Search parameters
     #[serde(default)]
     searchparams: SearchParameters,
+
+    /// Expand the terms of a query in parallel
+    #[serde(default)]
+    parallel: bool,
+
+    /// Expand phrases with the search mode of analiticcl: the words and word sequences in a phrase are matched
+    /// separately, rather than the phrase as a whole, see [`AnaliticclModule::expand_phrase()`]
+    #[serde(default)]
+    phrase_search: bool,
+
+    /// Phrases of more words than this are not passed to the module, as each word adds to the search time
+    #[serde(default)]
+    max_phrase_words: Option<usize>,
+
+    /// Options common to all modules
+    #[serde(flatten)]
+    common: CommonModuleConfig,
 }
 
 impl AnaliticclConfig {
@@ -73,6 +94,10 @@ impl AnaliticclConfig {
             variantlists: Vec::new(),
             confusable_lists: Vec::new(),
             searchparams: SearchParameters::default(),
+            parallel: false,
+            phrase_search: false,
+            max_phrase_words: None,
+            common: CommonModuleConfig::default(),
         }
     }
 
@@ -84,6 +109,30 @@ impl AnaliticclConfig {
         self
     }
 
+    /// Expand the terms of a query in parallel
+    pub fn with_parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Expand phrases with the search mode of analiticcl
+    pub fn with_phrase_search(mut self) -> Self {
+        self.phrase_search = true;
+        self
+    }
+
+    /// Do not pass terms longer than this (in characters) to the module
+    pub fn with_max_term_length(mut self, max_term_length: usize) -> Self {
+        self.common.max_term_length = Some(max_term_length);
+        self
+    }
+
+    /// Do not pass phrases of more words than this to the module
+    pub fn with_max_phrase_words(mut self, max_phrase_words: usize) -> Self {
+        self.max_phrase_words = Some(max_phrase_words);
+        self
+    }
+
     pub fn id(&self) -> &str {
         self.id.as_str()
     }
@@ -97,8 +146,87 @@ impl AnaliticclConfig {
 pub struct AnaliticclModule {
     config: AnaliticclConfig,
 
-    /// the Variant Model from Analiticcl. None whilst not loaded yet.
-    model: Option<VariantModel>,
+    /// the Variant Model from Analiticcl. None whilst not loaded yet. Shared with the instances for other
+    /// configurations, see [`Module::share()`]
+    model: Option<Arc<VariantModel>>,
+
+    /// Version of the loaded data, see [`Module::data_version()`]
+    data_version: Option<DataVersion>,
+}
+
+impl ModuleConfig for AnaliticclConfig {
+    fn kind(&self) -> &'static str {
+        "analiticcl"
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        std::iter::once(self.alphabet.as_path())
+            .chain(
+                self.lexicons
+                    .iter()
+                    .map(|lexicon| lexicon.filename.as_path()),
+            )
+            .chain(
+                self.variantlists
+                    .iter()
+                    .map(|variantlist| variantlist.filename.as_path()),
+            )
+            .chain(self.confusable_lists.iter().map(|path| path.as_path()))
+            .collect()
+    }
+
+    fn files_mut(&mut self) -> Vec<&mut PathBuf> {
+        std::iter::once(&mut self.alphabet)
+            .chain(
+                self.lexicons
+                    .iter_mut()
+                    .map(|lexicon| &mut lexicon.filename),
+            )
+            .chain(
+                self.variantlists
+                    .iter_mut()
+                    .map(|variantlist| &mut variantlist.filename),
+            )
+            .chain(self.confusable_lists.iter_mut())
+            .collect()
+    }
+
+    fn data_options(&self) -> String {
+        format!(
+            "{:?}",
+            (
+                &self.weights,
+                self.lexicons
+                    .iter()
+                    .map(|lexicon| &lexicon.params)
+                    .collect::<Vec<_>>(),
+                self.variantlists
+                    .iter()
+                    .map(|variantlist| (&variantlist.params, variantlist.transparent))
+                    .collect::<Vec<_>>()
+            )
+        )
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut CommonModuleConfig {
+        &mut self.common
+    }
 }
 
 impl AnaliticclModule {
@@ -106,7 +234,46 @@ impl AnaliticclModule {
         Self {
             config,
             model: None,
+            data_version: None,
+        }
+    }
+
+    /// Expands a phrase with the search mode of analiticcl: the words and word sequences (up to `max_ngram` words)
+    /// in the phrase are matched against the lexicon separately, and each variant of such a span yields the phrase
+    /// with the span replaced by it. So `Amsteldamse courant` finds `Amsterdamse courant`, even if the phrase as a
+    /// whole is not in the lexicon.
+    fn expand_phrase(
+        &self,
+        model: &VariantModel,
+        phrase: &str,
+        searchparams: &SearchParameters,
+    ) -> Option<TermExpansion> {
+        let mut termexpansion = TermExpansion::default().with_source(self);
+        for found in model.find_all_matches(phrase, searchparams) {
+            for variant in found.variants.iter().flatten() {
+                let variant_text = &model
+                    .decoder
+                    .get(variant.vocab_id as usize)
+                    .expect("vocab ID must be in decoder")
+                    .text;
+                if variant_text == found.text {
+                    continue;
+                }
+                let expansion = format!(
+                    "{}{}{}",
+                    &phrase[..found.offset.begin],
+                    variant_text,
+                    &phrase[found.offset.end..]
+                );
+                if !termexpansion.iter().any(|e| e == expansion) {
+                    termexpansion
+                        .add_variant_with_score(expansion, variant.score(searchparams.freq_weight));
+                }
+            }
         }
+        // the variants of the different matches are interleaved
+        termexpansion.sort_by_score();
+        (!termexpansion.is_empty()).then_some(termexpansion)
     }
 }
 
@@ -119,11 +286,16 @@ impl Module for AnaliticclModule {
         self.config.name.as_str()
     }
 
+    fn common(&self) -> &CommonModuleConfig {
+        &self.config.common
+    }
+
     fn kind(&self) -> &'static str {
         "analiticcl"
     }
 
     fn load(&mut self) -> Result<(), Error> {
+        let data_version = modules::verify_data(&self.config)?;
         let mut model = VariantModel::new(
             &self.config.alphabet.to_string_lossy(),
             self.config.weights.clone(),
@@ -157,10 +329,103 @@ impl Module for AnaliticclModule {
 
         model.build();
 
-        self.model = Some(model);
+        self.model = Some(Arc::new(model));
+        self.data_version = Some(data_version);
         Ok(())
     }
 
+    fn is_cpu_intensive(&self) -> bool {
+        true
+    }
+
+    fn is_spelling_oriented(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::integer(
+                "max_matches",
+                0,
+                u64::MAX,
+                "Maximum number of variants to return per term",
+            ),
+            ParamSpec::integer(
+                "edit_distance",
+                0,
+                u8::MAX as u64,
+                "Maximum (absolute) edit distance of the variants to the term",
+            ),
+            ParamSpec::integer(
+                "anagram_distance",
+                0,
+                u8::MAX as u64,
+                "Maximum (absolute) anagram distance of the variants to the term",
+            ),
+            ParamSpec::float("score_threshold", 0.0, 1.0, "Minimum score of the variants"),
+            ParamSpec::float(
+                "cutoff_threshold",
+                0.0,
+                f64::INFINITY,
+                "Cut off variants whose score is this many times lower than the best score",
+            ),
+            ParamSpec::new(
+                "phrase_search",
+                ParamType::Bool,
+                "Match the words and word sequences in phrases separately",
+            )
+            .with_default(self.config.phrase_search),
+        ]
+    }
+
+    fn handles_numbers(&self) -> bool {
+        false
+    }
+
+    fn max_phrase_words(&self) -> Option<usize> {
+        self.config.max_phrase_words
+    }
+
+    fn data_version(&self) -> Option<&str> {
+        self.data_version
+            .as_ref()
+            .map(|data_version| data_version.version())
+    }
+
+    fn file_checksums(&self) -> &[FileChecksum] {
+        self.data_version
+            .as_ref()
+            .map_or(&[], |data_version| data_version.files())
+    }
+
+    fn entries(&self) -> Option<usize> {
+        self.model.as_ref().map(|model| model.decoder.len())
+    }
+
+    fn memory_estimate(&self) -> Option<u64> {
+        // only the vocabulary is counted, the anagram index comes on top
+        self.model.as_ref().map(|model| {
+            model
+                .decoder
+                .iter()
+                .map(|value| std::mem::size_of_val(value) + value.text.capacity())
+                .sum::<usize>() as u64
+        })
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        Some(Box::new(AnaliticclModule::new(self.config.clone())))
+    }
+
+    fn share(&self, config: &dyn Any) -> Option<Box<dyn Module>> {
+        let config = config.downcast_ref::<AnaliticclConfig>()?;
+        Some(Box::new(Self {
+            config: config.clone(),
+            model: self.model.clone(),
+            data_version: self.data_version.clone(),
+        }))
+    }
+
     fn expand_query(
         &self,
         terms: &Vec<Term>,
@@ -169,89 +434,53 @@ impl Module for AnaliticclModule {
         //construct analiticcl searchparams from params
         let searchparams: Option<SearchParameters> =
             if params.iter_for_module(self.id()).next().is_some() {
+                // the parameters were validated against parameters() by the expander
                 let mut searchparams: SearchParameters = self.config.searchparams.clone();
-                for param in params.iter_for_module(self.id()) {
-                    match param.key() {
-                        "max_matches" => {
-                            searchparams = searchparams.with_max_matches(
-                                param.value().as_u64().ok_or_else(|| {
-                                    Error::QueryExpandError(
-                                        "invalid value for max_matches parameter".into(),
-                                    )
-                                })? as usize,
-                            )
-                        }
-                        "edit_distance" => {
-                            //MAYBE TODO: absolute threshold only for now
-                            searchparams = searchparams.with_edit_distance(
-                                analiticcl::DistanceThreshold::Absolute(
-                                    param.value().as_u64().ok_or_else(|| {
-                                        Error::QueryExpandError(
-                                            "invalid value for edit_distance parameter".into(),
-                                        )
-                                    })? as u8,
-                                ),
-                            )
-                        }
-                        "anagram_distance" => {
-                            //MAYBE TODO: absolute threshold only for now
-                            searchparams = searchparams.with_anagram_distance(
-                                analiticcl::DistanceThreshold::Absolute(
-                                    param.value().as_u64().ok_or_else(|| {
-                                        Error::QueryExpandError(
-                                            "invalid value for anagram_distance parameter".into(),
-                                        )
-                                    })? as u8,
-                                ),
-                            )
-                        }
-                        "score_threshold" => {
-                            searchparams = searchparams.with_score_threshold(
-                                param.value().as_f64().ok_or_else(|| {
-                                    Error::QueryExpandError(
-                                        "invalid value for edit_distance parameter".into(),
-                                    )
-                                })? as f64,
-                            )
-                        }
-                        "cutoff_threshold" => {
-                            searchparams = searchparams.with_cutoff_threshold(
-                                param.value().as_f64().ok_or_else(|| {
-                                    Error::QueryExpandError(
-                                        "invalid value for cutoff_threshold parameter".into(),
-                                    )
-                                })? as f64,
-                            )
-                        }
-                        //TODO: parse remaining analiticcl parameters
-                        x => {
-                            return Err(Error::QueryExpandError(format!(
-                                "Got unexpected parameter for analiticcl: {}",
-                                x
-                            )))
-                        }
-                    }
+                if let Some(max_matches) = params.get_u64(self.id(), "max_matches") {
+                    searchparams = searchparams.with_max_matches(max_matches as usize);
+                }
+                //MAYBE TODO: absolute thresholds only for now
+                if let Some(edit_distance) = params.get_u64(self.id(), "edit_distance") {
+                    searchparams = searchparams.with_edit_distance(
+                        analiticcl::DistanceThreshold::Absolute(edit_distance as u8),
+                    );
                 }
+                if let Some(anagram_distance) = params.get_u64(self.id(), "anagram_distance") {
+                    searchparams = searchparams.with_anagram_distance(
+                        analiticcl::DistanceThreshold::Absolute(anagram_distance as u8),
+                    );
+                }
+                if let Some(score_threshold) = params.get_f64(self.id(), "score_threshold") {
+                    searchparams = searchparams.with_score_threshold(score_threshold);
+                }
+                if let Some(cutoff_threshold) = params.get_f64(self.id(), "cutoff_threshold") {
+                    searchparams = searchparams.with_cutoff_threshold(cutoff_threshold);
+                }
+                //TODO: support remaining analiticcl parameters
                 Some(searchparams)
             } else {
                 //no search parameters specified, we fall back to borrowing the config default later
                 None
             };
 
-        let mut expansions = TermExpansions::new();
-        for term in terms {
+        let phrase_search = params
+            .get(self.id(), "phrase_search")
+            .map_or(self.config.phrase_search, |_| {
+                params.get_bool(self.id(), "phrase_search")
+            });
+
+        modules::expand_terms(terms, self.config.parallel, |term| {
             debug!("Looking up {}", term.as_str());
             if let Some(model) = self.model.as_ref() {
+                let searchparams = searchparams.as_ref().unwrap_or(&self.config.searchparams);
+                if phrase_search && matches!(term, Term::Phrase(_)) {
+                    return Ok(self
+                        .expand_phrase(model, term.as_str(), searchparams)
+                        .map(|termexpansion| (term.as_str().to_string(), termexpansion)));
+                }
                 let mut termexpansion = TermExpansion::default().with_source(self);
                 let mut found = false;
-                for variant in model.find_variants(
-                    term.as_str(),
-                    if let Some(searchparams) = searchparams.as_ref() {
-                        searchparams
-                    } else {
-                        &self.config.searchparams
-                    },
-                ) {
+                for variant in model.find_variants(term.as_str(), searchparams) {
                     found = true;
                     let variant_text = &model
                         .decoder
@@ -260,17 +489,23 @@ impl Module for AnaliticclModule {
                         .text;
                     termexpansion.add_variant_with_score(
                         variant_text,
-                        self.config.searchparams.freq_weight as f64,
+                        variant.score(searchparams.freq_weight),
                     );
                 }
-                if found {
-                    expansions.insert(term.as_str().to_string(), vec![termexpansion]);
-                }
+                Ok(found.then(|| (term.as_str().to_string(), termexpansion)))
             } else {
                 panic!("expand_query() was called before load()!");
             }
+        })
+    }
+
+    fn dump(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        if let Some(model) = self.model.as_ref() {
+            for vocabvalue in model.decoder.iter() {
+                writeln!(writer, "{}", vocabvalue.text)?;
+            }
         }
-        Ok(expansions)
+        Ok(())
     }
 }
 
@@ -347,4 +582,47 @@ mod tests {
         assert_eq!(expansions.len(), 0, "Checking number of terms returned");
         Ok(())
     }
+
+    #[test]
+    pub fn test003_analiticcl_guards() -> Result<(), Error> {
+        let module = AnaliticclModule::new(
+            init_test()?
+                .config
+                .with_max_term_length(20)
+                .with_max_phrase_words(2),
+        );
+        assert!(modules::accepts(&module, &Term::Singular("belangrijk")));
+        assert!(modules::accepts(&module, &Term::Phrase("heel belangrijk")));
+        assert!(!modules::accepts(
+            &module,
+            &Term::Phrase("heel erg belangrijk")
+        ));
+        assert!(!modules::accepts(
+            &module,
+            &Term::Singular("onwaarschijnlijkbelangrijk")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test004_analiticcl_phrase_search() -> Result<(), Error> {
+        let mut module = AnaliticclModule::new(init_test()?.config.with_phrase_search());
+        module.load()?;
+        let terms = vec![Term::Phrase("heel belangrijkk")];
+        let expansions = module.expand_query(&terms, &QueryParams::default())?;
+        let termexpansion = expansions
+            .get("heel belangrijkk")
+            .expect("term must exists")
+            .first()
+            .expect("term must have results");
+        assert!(termexpansion.iter().any(|e| e == "heel belangrijk"));
+        assert!(termexpansion.iter().all(|e| e.starts_with("heel ")));
+        // the variants are ranked by their own scores, the closest first
+        let scores = termexpansion.scores();
+        assert!(scores.iter().all(|score| *score > 0.0 && *score <= 1.0));
+        assert!(scores.windows(2).all(|pair| pair[0] >= pair[1]));
+        assert!(scores.first() > scores.last());
+        assert_eq!(termexpansion.iter().next(), Some("heel belangrijk"));
+        Ok(())
+    }
 }
@@ -1,15 +1,20 @@
 use serde::Deserialize;
+use std::any::Any;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::debug;
 
 use crate::lexer::Term;
-use crate::modules::Module;
+use crate::modules::params::ParamSpec;
+use crate::modules::{self, CommonModuleConfig, DataVersion, FileChecksum, Module, ModuleConfig};
 use crate::{Error, QueryParams, TermExpansion, TermExpansions};
 
 use finalfusion::prelude::*;
-use finalfusion::similarity::WordSimilarity;
+use finalfusion::storage::{Storage, StorageView};
+use finalfusion::vocab::Vocab;
+use ndarray::s;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct FinalFusionConfig {
@@ -24,6 +29,14 @@ pub struct FinalFusionConfig {
 
     /// Nearest Neighbours, number of results to return
     k: usize,
+
+    /// Expand the terms of a query in parallel
+    #[serde(default)]
+    parallel: bool,
+
+    /// Options common to all modules
+    #[serde(flatten)]
+    common: CommonModuleConfig,
 }
 
 impl FinalFusionConfig {
@@ -33,6 +46,8 @@ impl FinalFusionConfig {
             name: name.into(),
             file: file.into(),
             k: 10,
+            parallel: false,
+            common: CommonModuleConfig::default(),
         }
     }
 
@@ -42,6 +57,12 @@ impl FinalFusionConfig {
         self
     }
 
+    /// Expand the terms of a query in parallel
+    pub fn with_parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
     pub fn id(&self) -> &str {
         self.id.as_str()
     }
@@ -55,8 +76,51 @@ impl FinalFusionConfig {
 pub struct FinalFusionModule {
     config: FinalFusionConfig,
 
-    /// the Embedding model from FinalFrontier
-    model: Option<Embeddings<VocabWrap, StorageViewWrap>>,
+    /// the Embedding model from FinalFrontier, shared with the instances for other configurations, see
+    /// [`Module::share()`]
+    model: Option<Arc<Embeddings<VocabWrap, StorageViewWrap>>>,
+
+    /// Version of the loaded data, see [`Module::data_version()`]
+    data_version: Option<DataVersion>,
+}
+
+impl ModuleConfig for FinalFusionConfig {
+    fn kind(&self) -> &'static str {
+        "finalfusion"
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        vec![self.file.as_path()]
+    }
+
+    fn files_mut(&mut self) -> Vec<&mut PathBuf> {
+        vec![&mut self.file]
+    }
+
+    fn data_options(&self) -> String {
+        // the embeddings are read as they are
+        String::new()
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut CommonModuleConfig {
+        &mut self.common
+    }
 }
 
 impl FinalFusionModule {
@@ -64,6 +128,7 @@ impl FinalFusionModule {
         Self {
             config,
             model: None,
+            data_version: None,
         }
     }
 }
@@ -77,6 +142,10 @@ impl Module for FinalFusionModule {
         self.config.name.as_str()
     }
 
+    fn common(&self) -> &CommonModuleConfig {
+        &self.config.common
+    }
+
     fn kind(&self) -> &'static str {
         "finalfusion"
     }
@@ -89,43 +158,141 @@ impl Module for FinalFusionModule {
                 e
             ))
         })?);
+        let data_version = modules::verify_data(&self.config)?;
         let embeddings = Embeddings::read_embeddings(&mut reader)?;
-        self.model = Some(embeddings);
+        self.model = Some(Arc::new(embeddings));
+        self.data_version = Some(data_version);
         Ok(())
     }
 
+    fn is_cpu_intensive(&self) -> bool {
+        true
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![
+            ParamSpec::integer("k", 1, u64::MAX, "Number of nearest neighbours to return")
+                .with_default(self.config.k),
+        ]
+    }
+
+    fn handles_phrases(&self) -> bool {
+        // the embeddings are of single words
+        false
+    }
+
+    fn data_version(&self) -> Option<&str> {
+        self.data_version
+            .as_ref()
+            .map(|data_version| data_version.version())
+    }
+
+    fn file_checksums(&self) -> &[FileChecksum] {
+        self.data_version
+            .as_ref()
+            .map_or(&[], |data_version| data_version.files())
+    }
+
+    fn entries(&self) -> Option<usize> {
+        self.model.as_ref().map(|model| model.vocab().words_len())
+    }
+
+    fn memory_estimate(&self) -> Option<u64> {
+        // the embedding matrix dominates, assuming 32-bit floats
+        self.model.as_ref().map(|model| {
+            let (rows, dims) = model.storage().shape();
+            (rows * dims * std::mem::size_of::<f32>()) as u64
+        })
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        Some(Box::new(FinalFusionModule::new(self.config.clone())))
+    }
+
+    fn share(&self, config: &dyn Any) -> Option<Box<dyn Module>> {
+        let config = config.downcast_ref::<FinalFusionConfig>()?;
+        Some(Box::new(Self {
+            config: config.clone(),
+            model: self.model.clone(),
+            data_version: self.data_version.clone(),
+        }))
+    }
+
     fn expand_query(
         &self,
         terms: &Vec<Term>,
         params: &QueryParams,
     ) -> Result<TermExpansions, Error> {
-        let k = if let Some(param) = params.get(self.id(), "k") {
-            param.as_u64().ok_or_else(|| {
-                Error::QueryExpandError("invalid value for k (nearest-neighbours) parameter".into())
-            })? as usize
-        } else {
-            self.config.k
+        let k = params
+            .get_u64(self.id(), "k")
+            .map_or(self.config.k, |k| k as usize);
+        let Some(model) = self.model.as_ref() else {
+            panic!("expand_query() was called before load()!");
         };
-        let mut expansions = TermExpansions::new();
-        for term in terms {
+        // all terms are embedded at once and compared to the whole vocabulary in a single matrix product, rather
+        // than one by one; the embeddings are normalised, so the dot products are the cosine similarities
+        let words: Vec<&str> = terms.iter().map(|term| term.as_str()).collect();
+        let (embeddings, known) = model.embedding_batch(&words);
+        let vocab = model.vocab().words();
+        let storage = model.storage().view();
+        let similarities = embeddings.dot(&storage.slice(s![0..vocab.len(), ..]).t());
+        modules::expand_terms(terms, self.config.parallel, |term| {
             debug!("Looking up {}", term.as_str());
-            if let Some(model) = self.model.as_ref() {
-                let mut termexpansion = TermExpansion::default().with_source(self);
-
-                if let Some(results) = model.word_similarity(term.as_str(), k, None) {
-                    for variant in results {
-                        termexpansion.add_variant_with_score(
-                            variant.word(),
-                            variant.cosine_similarity() as f64,
-                        );
-                    }
-                    expansions.insert(term.as_str().to_string(), vec![termexpansion]);
-                }
-            } else {
-                panic!("expand_query() was called before load()!");
+            let row = words
+                .iter()
+                .position(|word| *word == term.as_str())
+                .expect("term must be in batch");
+            if !known[row] {
+                return Ok(None);
+            }
+            // the k nearest neighbours, other than the term itself
+            let mut neighbours: Vec<(usize, f32)> = similarities
+                .row(row)
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(index, _)| vocab[*index] != term.as_str())
+                .collect();
+            if neighbours.len() > k {
+                neighbours.select_nth_unstable_by(k, |a, b| b.1.total_cmp(&a.1));
+                neighbours.truncate(k);
+            }
+            let mut termexpansion = TermExpansion::default().with_source(self);
+            for (index, similarity) in neighbours {
+                termexpansion.add_variant_with_score(vocab[index].as_str(), similarity as f64);
+            }
+            // nearest neighbours at the same distance are returned in no particular order
+            termexpansion.sort_by_score();
+            Ok(Some((term.as_str().to_string(), termexpansion)))
+        })
+    }
+
+    fn complete(&self, prefix: &str, limit: usize) -> Result<Option<Vec<String>>, Error> {
+        // the vocabulary is ordered by frequency, so that order is kept
+        Ok(Some(
+            self.model
+                .as_ref()
+                .map(|model| {
+                    model
+                        .vocab()
+                        .words()
+                        .iter()
+                        .filter(|word| word.starts_with(prefix))
+                        .take(limit)
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn dump(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        if let Some(model) = self.model.as_ref() {
+            for word in model.vocab().words() {
+                writeln!(writer, "{}", word)?;
             }
         }
-        Ok(expansions)
+        Ok(())
     }
 }
 
@@ -134,3 +301,68 @@ impl From<finalfusion::error::Error> for Error {
         Self::LoadError(format!("{}", value))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_test() -> Result<FinalFusionModule, Error> {
+        let mut file = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        file.push("test");
+        file.push("embeddings.fifu");
+        let config = FinalFusionConfig::new("finalfusion", "finalfusion", file).with_k(3);
+        Ok(FinalFusionModule::new(config))
+    }
+
+    #[test]
+    pub fn test001_finalfusion_load() -> Result<(), Error> {
+        let mut module = init_test()?;
+        assert_eq!(module.entries(), None);
+        module.load()?;
+        // 8 words of 4 dimensions
+        assert_eq!(module.entries(), Some(8));
+        assert_eq!(module.memory_estimate(), Some(8 * 4 * 4));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_finalfusion_batched_similarities() -> Result<(), Error> {
+        use finalfusion::similarity::WordSimilarity;
+        let mut module = init_test()?;
+        module.load()?;
+        let model = module.model.as_ref().expect("model must be loaded");
+        let words = model.vocab().words().to_vec();
+        let terms: Vec<Term> = words
+            .iter()
+            .map(|word| Term::Singular(word.as_str()))
+            .chain([Term::Singular("blah")])
+            .collect();
+        for k in [1, 3, 20] {
+            let params = QueryParams::new().with("finalfusion", "k", (k as u64).into());
+            let expansions = module.expand_query(&terms, &params)?;
+            assert!(!expansions.contains_key("blah"));
+            // the batched computation returns the same neighbours as finalfusion's own one-by-one lookup
+            for word in words.iter() {
+                let expected = model
+                    .word_similarity(word, k, None)
+                    .expect("word must be known");
+                let termexpansion = expansions[word.as_str()]
+                    .first()
+                    .expect("term must have results");
+                assert_eq!(
+                    termexpansion.iter().collect::<Vec<_>>(),
+                    expected
+                        .iter()
+                        .map(|result| result.word())
+                        .collect::<Vec<_>>(),
+                    "Checking neighbours of {}",
+                    word
+                );
+                for (score, result) in termexpansion.scores().iter().zip(expected.iter()) {
+                    assert!((score - result.cosine_similarity() as f64).abs() < 1e-6);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,22 +1,31 @@
 use serde::Deserialize;
+use std::any::Any;
 use std::borrow::Cow;
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info};
 
-use fst::automaton::Levenshtein;
-use fst::{IntoStreamer, Set, SetBuilder};
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Set, SetBuilder, Streamer};
 
 use crate::lexer::Term;
-use crate::modules::Module;
+use crate::modules::params::ParamSpec;
+use crate::modules::{self, CommonModuleConfig, DataVersion, FileChecksum, Module, ModuleConfig};
+use crate::snapshot::{self, SharedBytes};
 use crate::{Error, QueryParams, TermExpansion, TermExpansions};
 
 /// A simple hash-map-based lookup module
 /// mapping keywords to variants.
 pub struct FstModule {
     config: FstConfig,
-    set: Set<Vec<u8>>,
+
+    /// The loaded data, shared with the instances for other configurations, see [`Module::share()`]
+    set: Arc<Set<SharedBytes>>,
+
+    /// Version of the loaded data, see [`Module::data_version()`]
+    data_version: Option<DataVersion>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -43,6 +52,14 @@ pub struct FstConfig {
 
     #[serde(default)]
     casesensitive: bool,
+
+    /// Expand the terms of a query in parallel
+    #[serde(default)]
+    parallel: bool,
+
+    /// Options common to all modules
+    #[serde(flatten)]
+    common: CommonModuleConfig,
 }
 
 impl FstConfig {
@@ -61,6 +78,8 @@ impl FstConfig {
             sorted,
             skipfirstline: false,
             casesensitive: false,
+            parallel: false,
+            common: CommonModuleConfig::default(),
         }
     }
 
@@ -74,6 +93,12 @@ impl FstConfig {
         self
     }
 
+    /// Expand the terms of a query in parallel
+    pub fn with_parallel(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
     pub fn id(&self) -> &str {
         self.id.as_str()
     }
@@ -83,11 +108,53 @@ impl FstConfig {
     }
 }
 
+impl ModuleConfig for FstConfig {
+    fn kind(&self) -> &'static str {
+        "fst"
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        vec![self.file.as_path()]
+    }
+
+    fn files_mut(&mut self) -> Vec<&mut PathBuf> {
+        vec![&mut self.file]
+    }
+
+    fn data_options(&self) -> String {
+        format!(
+            "{:?}",
+            (self.sorted, self.skipfirstline, self.casesensitive)
+        )
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut CommonModuleConfig {
+        &mut self.common
+    }
+}
+
 impl FstModule {
     pub fn new(config: FstConfig) -> Self {
         Self {
             config,
-            set: Set::default(),
+            set: Arc::new(shared_set(Set::default())),
+            data_version: None,
         }
     }
 }
@@ -101,11 +168,30 @@ impl Module for FstModule {
         self.config.name.as_str()
     }
 
+    fn common(&self) -> &CommonModuleConfig {
+        &self.config.common
+    }
+
     fn kind(&self) -> &'static str {
         "fst"
     }
 
+    fn is_cpu_intensive(&self) -> bool {
+        // a Levenshtein automaton has to be built and run for each term
+        true
+    }
+
+    fn is_spelling_oriented(&self) -> bool {
+        true
+    }
+
     fn load(&mut self) -> Result<(), Error> {
+        if self.config.distance > MAX_DISTANCE {
+            return Err(Error::InvalidConfig(format!(
+                "The distance of module {} is {}, it can be at most {}",
+                self.config.id, self.config.distance, MAX_DISTANCE
+            )));
+        }
         info!("Loading lexicon {}", self.config.file.as_path().display());
         let file = File::open(self.config.file.as_path()).map_err(|e| {
             Error::LoadError(format!(
@@ -114,6 +200,7 @@ impl Module for FstModule {
                 e
             ))
         })?;
+        let data_version = modules::verify_data(&self.config)?;
         let mut buffer = String::new();
         let mut reader = BufReader::new(file);
         let mut firstline = true;
@@ -163,53 +250,299 @@ impl Module for FstModule {
             }
         }
         info!("Building FST");
-        self.set = Set::new(builder.into_inner()?)?;
+        self.set = Arc::new(shared_set(Set::new(builder.into_inner()?)?));
+        self.data_version = Some(data_version);
         Ok(())
     }
 
+    fn parameters(&self) -> Vec<ParamSpec> {
+        vec![ParamSpec::integer(
+            "distance",
+            0,
+            MAX_DISTANCE as u64,
+            "Maximum Levenshtein distance of the variants to the term",
+        )
+        .with_default(self.config.distance)]
+    }
+
+    fn handles_phrases(&self) -> bool {
+        // the lexicon holds single words, a Levenshtein automaton for a phrase only costs time
+        false
+    }
+
+    fn handles_numbers(&self) -> bool {
+        false
+    }
+
+    fn data_version(&self) -> Option<&str> {
+        self.data_version
+            .as_ref()
+            .map(|data_version| data_version.version())
+    }
+
+    fn file_checksums(&self) -> &[FileChecksum] {
+        self.data_version
+            .as_ref()
+            .map_or(&[], |data_version| data_version.files())
+    }
+
+    fn entries(&self) -> Option<usize> {
+        Some(self.set.len())
+    }
+
+    fn memory_estimate(&self) -> Option<u64> {
+        Some(self.set.as_fst().as_bytes().len() as u64)
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        Some(Box::new(FstModule::new(self.config.clone())))
+    }
+
+    fn share(&self, config: &dyn Any) -> Option<Box<dyn Module>> {
+        let config = config.downcast_ref::<FstConfig>()?;
+        Some(Box::new(Self {
+            config: config.clone(),
+            set: self.set.clone(),
+            data_version: self.data_version.clone(),
+        }))
+    }
+
     fn expand_query(
         &self,
         terms: &Vec<Term>,
         params: &QueryParams,
     ) -> Result<TermExpansions, Error> {
-        let distance = if let Some(param) = params.get(self.id(), "distance") {
-            param.as_u64().ok_or_else(|| {
-                Error::QueryExpandError("invalid value for distance parameter".into())
-            })? as u32
-        } else {
-            self.config.distance as u32
-        };
-        let mut expansions = TermExpansions::new();
-        for term in terms {
+        // the parameter is validated against the maximum, see parameters()
+        let distance = params
+            .get_u64(self.id(), "distance")
+            .map_or(self.config.distance, |distance| {
+                distance.min(MAX_DISTANCE as u64) as u8
+            });
+        modules::expand_terms(terms, self.config.parallel, |original| {
             let term = if self.config.casesensitive {
-                Cow::Borrowed(term.as_str())
+                Cow::Borrowed(original.as_str())
             } else {
-                Cow::Owned(term.as_str().to_lowercase())
+                Cow::Owned(original.as_str().to_lowercase())
             };
-            match Levenshtein::new(term.as_ref(), distance) {
-                Ok(levaut) => {
-                    debug!("Looking up {}", term);
-                    let stream = self.set.search(levaut).into_stream();
-                    if let Ok(variants) = stream.into_strs() {
-                        if !variants.is_empty() {
-                            debug!("found {} expansions", variants.len());
-                            expansions.insert(
-                                term.into_owned(),
-                                vec![TermExpansion::default()
-                                    .with_source(self)
-                                    .with_expansions(variants.to_vec())],
-                            );
-                        } else {
-                            debug!("not found");
-                        }
-                    } else {
-                        debug!("UTF-8 decoding error, no results returned");
-                    }
+            debug!("Looking up {}", term);
+            let automaton = DistanceAutomaton::new(term.as_ref(), distance);
+            let mut stream = self.set.search_with_state(&automaton).into_stream();
+            let mut termexpansion = TermExpansion::default().with_source(self);
+            while let Some((variant, state)) = stream.next() {
+                match std::str::from_utf8(variant) {
+                    Ok(variant) => termexpansion.add_variant_with_score(
+                        variant,
+                        distance_score(term.as_ref(), variant, automaton.distance(&state)),
+                    ),
+                    Err(_) => debug!("UTF-8 decoding error, variant skipped"),
                 }
-                Err(e) => debug!("Can't build FST for term '{}': {}", term, e),
             }
+            if !termexpansion.is_empty() {
+                debug!("found {} expansions", termexpansion.len());
+                // the stream is in lexicographical order, the closest variants come first instead
+                termexpansion.sort_by_score();
+                // keyed by the term as it appears in the query, so the expansions are found again
+                return Ok(Some((original.as_str().to_owned(), termexpansion)));
+            }
+            debug!("not found");
+            Ok(None)
+        })
+    }
+
+    fn complete(&self, prefix: &str, limit: usize) -> Result<Option<Vec<String>>, Error> {
+        let prefix = if self.config.casesensitive {
+            Cow::Borrowed(prefix)
+        } else {
+            Cow::Owned(prefix.to_lowercase())
+        };
+        let completions = self
+            .set
+            .search(Str::new(prefix.as_ref()).starts_with())
+            .into_stream()
+            .into_strs()?;
+        Ok(Some(crate::modules::rank_completions(completions, limit)))
+    }
+
+    fn dump(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        let mut stream = self.set.stream();
+        while let Some(key) = stream.next() {
+            writer.write_all(key)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self, writer: &mut dyn Write) -> Result<bool, Error> {
+        snapshot::write_header(writer, &self.config, self.data_version.as_ref())?;
+        snapshot::write_bytes(writer, self.set.as_fst().as_bytes())?;
+        Ok(true)
+    }
+
+    fn load_snapshot(&mut self, data: &SharedBytes) -> Result<bool, Error> {
+        let reader = &mut data.as_ref();
+        let Some(data_version) = snapshot::read_header(reader, &self.config)? else {
+            return Ok(false);
+        };
+        // the FST is used as it is in the (memory-mapped) snapshot, nothing is built or copied
+        self.set = Arc::new(Set::new(snapshot::read_shared(data, reader)?)?);
+        self.data_version = Some(data_version);
+        Ok(true)
+    }
+}
+
+/// Converts an FST built in memory into one over [`SharedBytes`], the type that can also be restored from a snapshot
+/// without copying it
+fn shared_set(set: Set<Vec<u8>>) -> Set<SharedBytes> {
+    set.map_data(SharedBytes::from)
+        .expect("the FST is valid, it was just built")
+}
+
+/// Converts the edit distance between a term and a variant into a score from 0 to 1 (for the term itself): one minus
+/// the distance relative to the length of the longer of the two, so scores are comparable with those of other modules
+fn distance_score(term: &str, variant: &str, distance: u32) -> f64 {
+    let len = term.chars().count().max(variant.chars().count());
+    if len == 0 {
+        return 1.0;
+    }
+    1.0 - distance as f64 / len as f64
+}
+
+/// The largest edit distance the automaton supports, which bounds the size of its states
+const MAX_DISTANCE: u8 = 15;
+
+/// The number of cells of the dynamic programming table kept per state, see [`DistanceState`]
+const BAND: usize = 2 * MAX_DISTANCE as usize + 1;
+
+/// A Levenshtein automaton that keeps the edit distance to the term in its state, so the distance of each match is
+/// known once the FST is searched; the automaton of the fst crate compiles to a DFA that only tells whether a key is
+/// within the distance. Each state holds part of a row of the dynamic programming table of the edit distance between
+/// the term and the key so far, computed over characters (the bytes of a multi-byte character are gathered first).
+/// The states are of fixed size, so stepping through the FST does not allocate.
+struct DistanceAutomaton {
+    term: Vec<char>,
+    maxdistance: u8,
+}
+
+/// A row of the dynamic programming table, for a key of `keylen` characters. Only the cells within the maximum
+/// distance of the diagonal can be within the distance, so only those are kept: cell `k` of `band` is the distance to
+/// the first `keylen + k - maxdistance` characters of the term. Distances over the maximum are stored as the maximum
+/// plus one.
+#[derive(Clone, Copy)]
+struct DistanceState {
+    band: [u8; BAND],
+    keylen: u32,
+    /// The bytes of an incomplete UTF-8 character
+    pending: [u8; 4],
+    pending_len: u8,
+}
+
+impl DistanceAutomaton {
+    /// Will panic if the distance is over [`MAX_DISTANCE`]
+    fn new(term: &str, maxdistance: u8) -> Self {
+        assert!(maxdistance <= MAX_DISTANCE);
+        Self {
+            term: term.chars().collect(),
+            maxdistance,
+        }
+    }
+
+    /// The number of characters of the term that cell `k` of the band of a state covers, if it is within the term
+    fn term_prefix(&self, keylen: u32, k: usize) -> Option<usize> {
+        (keylen as usize + k)
+            .checked_sub(self.maxdistance as usize)
+            .filter(|i| *i <= self.term.len())
+    }
+
+    /// Returns the edit distance between the term and a matching key, given the state after the key
+    fn distance(&self, state: &Option<DistanceState>) -> u32 {
+        state
+            .as_ref()
+            .and_then(|state| {
+                // the cell that covers the whole term
+                let k = (self.term.len() + self.maxdistance as usize)
+                    .checked_sub(state.keylen as usize)
+                    .filter(|k| *k < BAND)?;
+                Some(state.band[k] as u32)
+            })
+            .filter(|distance| *distance <= self.maxdistance as u32)
+            .unwrap_or(u32::MAX)
+    }
+}
+
+impl Automaton for &DistanceAutomaton {
+    /// None if no key with the bytes so far is within the distance
+    type State = Option<DistanceState>;
+
+    fn start(&self) -> Self::State {
+        let over = self.maxdistance + 1;
+        let mut band = [over; BAND];
+        for (k, cell) in band.iter_mut().enumerate() {
+            if let Some(i) = self.term_prefix(0, k) {
+                *cell = (i as u8).min(over);
+            }
+        }
+        Some(DistanceState {
+            band,
+            keylen: 0,
+            pending: [0; 4],
+            pending_len: 0,
+        })
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        state.as_ref().is_some_and(|state| state.pending_len == 0)
+            && self.distance(state) <= self.maxdistance as u32
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        let mut state = (*state)?;
+        state.pending[state.pending_len as usize] = byte;
+        state.pending_len += 1;
+        let width = match state.pending[0] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return None,
+        };
+        if (state.pending_len as usize) < width {
+            // the character is not complete yet
+            return Some(state);
+        }
+        let c = std::str::from_utf8(&state.pending[..width])
+            .ok()?
+            .chars()
+            .next()?;
+        state.pending_len = 0;
+        let over = self.maxdistance + 1;
+        let previous = state.band;
+        // the cells further from the diagonal than the distance stay over it
+        for k in 0..=2 * self.maxdistance as usize {
+            // the cell covers the same term prefix as cell k + 1 of the previous row
+            let Some(i) = self.term_prefix(state.keylen + 1, k) else {
+                state.band[k] = over;
+                continue;
+            };
+            let deletion = previous.get(k + 1).map_or(over, |d| d + 1);
+            let insertion = if k > 0 { state.band[k - 1] + 1 } else { over };
+            let substitution = if i > 0 {
+                previous[k] + (self.term[i - 1] != c) as u8
+            } else {
+                over
+            };
+            state.band[k] = deletion.min(insertion).min(substitution).min(over);
+        }
+        state.keylen += 1;
+        // the distance never decreases as the key gets longer
+        if state.band.iter().any(|d| *d <= self.maxdistance) {
+            Some(state)
+        } else {
+            None
         }
-        Ok(expansions)
     }
 }
 
@@ -241,6 +574,8 @@ mod tests {
             sorted: false,
             skipfirstline: false,
             casesensitive: true,
+            parallel: false,
+            common: CommonModuleConfig::default(),
         };
         Ok(FstModule::new(config))
     }
@@ -275,8 +610,8 @@ mod tests {
             [
                 "belangrijk",
                 "belangrijke",
-                "belangrijker",
                 "belangrijks",
+                "belangrijker",
                 "belangrijkst",
                 "onbelangrijk"
             ],
@@ -294,4 +629,211 @@ mod tests {
         assert_eq!(expansions.len(), 0, "Checking number of terms returned");
         Ok(())
     }
+
+    #[test]
+    pub fn test003_lookup_query_parallel() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let terms = vec![
+            Term::Singular("belangrijk"),
+            Term::Singular("blah"),
+            Term::Singular("belangrijke"),
+        ];
+        let expansions = module.expand_query(&terms, &QueryParams::new())?;
+        module.config = module.config.clone().with_parallel();
+        let parallel_expansions = module.expand_query(&terms, &QueryParams::new())?;
+        assert_eq!(
+            parallel_expansions.len(),
+            2,
+            "Checking number of terms returned"
+        );
+        for (term, termexpansions) in expansions.iter() {
+            assert_eq!(
+                parallel_expansions[term][0].expansions(),
+                termexpansions[0].expansions(),
+                "Checking returned expansions"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test004_fst_capabilities() -> Result<(), Error> {
+        let mut module = init_test()?;
+        assert!(modules::accepts(&module, &Term::Singular("belangrijk")));
+        assert!(!modules::accepts(&module, &Term::Phrase("heel belangrijk")));
+        assert!(!modules::accepts(&module, &Term::Singular("1648")));
+        assert!(!modules::accepts(&module, &Term::Phrase("belang*")));
+        module.config.common.max_term_length = Some(5);
+        assert!(!modules::accepts(&module, &Term::Singular("belangrijk")));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test005_fst_parameters() -> Result<(), Error> {
+        let module = init_test()?;
+        let params = QueryParams::new().with("fst", "distance", 1.into());
+        assert!(modules::params::violations(&module, &params).is_empty());
+        assert_eq!(params.get_u64("fst", "distance"), Some(1));
+        let params = QueryParams::new()
+            .with("fst", "distance", 300.into())
+            .with("fst", "k", 10.into())
+            .with("other", "distance", "far".into());
+        assert_eq!(
+            modules::params::violations(&module, &params),
+            vec![
+                "fst.distance: must be an integer from 0 to 15",
+                "fst.k: unknown parameter for this module"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test006_fst_scores() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let terms = vec![Term::Singular("belangrijk")];
+        let expansions = module.expand_query(&terms, &QueryParams::new())?;
+        let termexpansion = &expansions["belangrijk"][0];
+        let scores: Vec<(&str, f64)> = termexpansion
+            .iter()
+            .zip(termexpansion.scores().iter().copied())
+            .collect();
+        assert_eq!(scores[0], ("belangrijk", 1.0));
+        assert_eq!(scores[1], ("belangrijke", 1.0 - 1.0 / 11.0));
+        assert_eq!(scores[2], ("belangrijks", 1.0 - 1.0 / 11.0));
+        assert_eq!(scores[5], ("onbelangrijk", 1.0 - 2.0 / 12.0));
+        assert_eq!(distance_score("", "", 0), 1.0);
+        // the distances reported by the automaton are those of the full comparison
+        for (variant, score) in scores {
+            let distance = crate::distance::levenshtein("belangrijk", variant);
+            assert_eq!(score, distance_score("belangrijk", variant, distance));
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test007_fst_snapshot() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let mut data = Vec::new();
+        assert!(module.snapshot(&mut data)?);
+        let mut restored = init_test()?;
+        assert!(restored.load_snapshot(&data.into())?);
+        assert_eq!(restored.entries(), module.entries());
+        assert_eq!(restored.data_version(), module.data_version());
+        let terms = vec![Term::Singular("belangrijk")];
+        let expansions = module.expand_query(&terms, &QueryParams::new())?;
+        let restored_expansions = restored.expand_query(&terms, &QueryParams::new())?;
+        assert_eq!(
+            restored_expansions["belangrijk"][0]
+                .iter()
+                .collect::<Vec<_>>(),
+            expansions["belangrijk"][0].iter().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test008_distance_automaton() -> Result<(), Error> {
+        let mut keys = vec![
+            "",
+            "a",
+            "ab",
+            "ba",
+            "cafe",
+            "café",
+            "caffè",
+            "ça",
+            "çava",
+            "kaffee",
+            "belangrijk",
+            "onbelangrijk",
+            "straße",
+            "strasse",
+            "日本",
+            "日本語",
+        ];
+        keys.sort();
+        let set = Set::from_iter(keys.iter())?;
+        for term in ["", "a", "cafe", "café", "strasse", "日本", "belangrijke"] {
+            for maxdistance in [0, 1, 2, 3, MAX_DISTANCE] {
+                let automaton = DistanceAutomaton::new(term, maxdistance);
+                let mut stream = set.search_with_state(&automaton).into_stream();
+                let mut found = Vec::new();
+                while let Some((key, state)) = stream.next() {
+                    let key = std::str::from_utf8(key).expect("valid UTF-8").to_owned();
+                    found.push((key, automaton.distance(&state)));
+                }
+                let expected: Vec<(String, u32)> = keys
+                    .iter()
+                    .map(|key| (key.to_string(), crate::distance::levenshtein(term, key)))
+                    .filter(|(_, distance)| *distance <= maxdistance as u32)
+                    .collect();
+                assert_eq!(found, expected, "term {} distance {}", term, maxdistance);
+            }
+        }
+        Ok(())
+    }
+
+    /// Compares the search with the automaton to that with the Levenshtein automaton of the fst crate, which it
+    /// replaced. Run it with `cargo test --release --lib bench_distance_automaton -- --ignored --nocapture`.
+    #[test]
+    #[ignore = "benchmark"]
+    pub fn test009_bench_distance_automaton() -> Result<(), Error> {
+        use fst::automaton::Levenshtein;
+        use std::time::Instant;
+
+        // pseudo-random words, so the benchmark needs no large lexicon
+        let mut seed: u64 = 0x2545f4914f6cdd1d;
+        let mut random = move |n: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        };
+        let mut words: Vec<String> = (0..200_000)
+            .map(|_| {
+                (0..4 + random(8))
+                    .map(|_| b"aeioubdklmnrst"[random(14) as usize] as char)
+                    .collect()
+            })
+            .collect();
+        words.sort();
+        words.dedup();
+        let set = Set::from_iter(words.iter())?;
+        let terms: Vec<&String> = (0..500)
+            .map(|_| &words[random(words.len() as u64) as usize])
+            .collect();
+        for distance in [1, 2] {
+            let begin = Instant::now();
+            let mut found = 0;
+            for term in terms.iter() {
+                let automaton = DistanceAutomaton::new(term, distance);
+                let mut stream = set.search_with_state(&automaton).into_stream();
+                while let Some((_, state)) = stream.next() {
+                    found += 1;
+                    assert!(automaton.distance(&state) <= distance as u32);
+                }
+            }
+            let elapsed = begin.elapsed();
+            let begin = Instant::now();
+            let mut levenshtein_found = 0;
+            for term in terms.iter() {
+                let automaton = Levenshtein::new(term, distance as u32)?;
+                levenshtein_found += set.search(automaton).into_stream().into_strs()?.len();
+            }
+            let levenshtein_elapsed = begin.elapsed();
+            assert_eq!(found, levenshtein_found);
+            println!(
+                "distance {}: {} keys found, distance automaton {:.1}ms, Levenshtein {:.1}ms",
+                distance,
+                found,
+                elapsed.as_secs_f64() * 1000.0,
+                levenshtein_elapsed.as_secs_f64() * 1000.0
+            );
+        }
+        Ok(())
+    }
 }
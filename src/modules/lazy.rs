@@ -0,0 +1,236 @@
+use std::io::Write;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::info;
+
+use crate::lexer::{QueryContext, Term};
+use crate::modules::params::ParamSpec;
+use crate::modules::{CommonModuleConfig, FileChecksum, Module};
+use crate::{Error, QueryParams, TermExpansions};
+
+type Instantiate = dyn Fn() -> Box<dyn Module> + Send + Sync;
+
+/// Wraps a module whose data is loaded on first use rather than at startup.
+/// Requests that arrive while the data is being loaded wait for it. If loading fails, the error
+/// is returned (the query expander then skips the module) and the next request tries again.
+pub struct LazyModule {
+    kind: &'static str,
+    id: String,
+    name: String,
+    common: CommonModuleConfig,
+    spelling_oriented: bool,
+    handles_phrases: bool,
+    handles_wildcards: bool,
+    handles_numbers: bool,
+    max_phrase_words: Option<usize>,
+    parameters: Vec<ParamSpec>,
+
+    /// Returns a new, not yet loaded, instance of the wrapped module
+    instantiate: Arc<Instantiate>,
+
+    /// The loaded module
+    module: OnceLock<Box<dyn Module>>,
+
+    /// Held while loading, so the data is only loaded once
+    loading: Mutex<()>,
+}
+
+impl LazyModule {
+    pub fn new(instantiate: impl Fn() -> Box<dyn Module> + Send + Sync + 'static) -> Self {
+        Self::from_arc(Arc::new(instantiate))
+    }
+
+    fn from_arc(instantiate: Arc<Instantiate>) -> Self {
+        let module = instantiate();
+        Self {
+            kind: module.kind(),
+            id: module.id().to_owned(),
+            name: module.name().to_owned(),
+            common: module.common().clone(),
+            spelling_oriented: module.is_spelling_oriented(),
+            handles_phrases: module.handles_phrases(),
+            handles_wildcards: module.handles_wildcards(),
+            handles_numbers: module.handles_numbers(),
+            max_phrase_words: module.max_phrase_words(),
+            parameters: module.parameters(),
+            instantiate,
+            module: OnceLock::new(),
+            loading: Mutex::new(()),
+        }
+    }
+
+    /// Returns the wrapped module, loading it first if needed
+    fn get(&self) -> Result<&dyn Module, Error> {
+        if let Some(module) = self.module.get() {
+            return Ok(module.as_ref());
+        }
+        let _guard = self.loading.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(module) = self.module.get() {
+            // loaded by another request while we were waiting
+            return Ok(module.as_ref());
+        }
+        info!("Loading lazy module {} - {}", self.id, self.name);
+        let mut module = (self.instantiate)();
+        module.load()?;
+        Ok(self.module.get_or_init(|| module).as_ref())
+    }
+}
+
+impl Module for LazyModule {
+    fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        &self.common
+    }
+
+    fn load(&mut self) -> Result<(), Error> {
+        info!(
+            "Module {} - {} will be loaded on first use",
+            self.id, self.name
+        );
+        Ok(())
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        Some(Box::new(Self::from_arc(self.instantiate.clone())))
+    }
+
+    fn expand_query(
+        &self,
+        terms: &Vec<Term>,
+        queryparams: &QueryParams,
+    ) -> Result<TermExpansions, Error> {
+        self.get()?.expand_query(terms, queryparams)
+    }
+
+    fn expand_query_in_context(
+        &self,
+        terms: &Vec<Term>,
+        context: &QueryContext,
+        queryparams: &QueryParams,
+    ) -> Result<TermExpansions, Error> {
+        self.get()?
+            .expand_query_in_context(terms, context, queryparams)
+    }
+
+    fn is_cpu_intensive(&self) -> bool {
+        // loading is, so the first request should not run on the async runtime either
+        self.module
+            .get()
+            .is_none_or(|module| module.is_cpu_intensive())
+    }
+
+    fn data_version(&self) -> Option<&str> {
+        self.module.get().and_then(|module| module.data_version())
+    }
+
+    fn file_checksums(&self) -> &[FileChecksum] {
+        self.module
+            .get()
+            .map_or(&[], |module| module.file_checksums())
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.module.get().is_some()
+    }
+
+    fn entries(&self) -> Option<usize> {
+        self.module.get().and_then(|module| module.entries())
+    }
+
+    fn memory_estimate(&self) -> Option<u64> {
+        self.module
+            .get()
+            .and_then(|module| module.memory_estimate())
+    }
+
+    fn is_spelling_oriented(&self) -> bool {
+        self.spelling_oriented
+    }
+
+    fn handles_phrases(&self) -> bool {
+        self.handles_phrases
+    }
+
+    fn handles_wildcards(&self) -> bool {
+        self.handles_wildcards
+    }
+
+    fn handles_numbers(&self) -> bool {
+        self.handles_numbers
+    }
+
+    fn max_phrase_words(&self) -> Option<usize> {
+        self.max_phrase_words
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        self.parameters.clone()
+    }
+
+    fn complete(&self, prefix: &str, limit: usize) -> Result<Option<Vec<String>>, Error> {
+        self.get()?.complete(prefix, limit)
+    }
+
+    fn dump(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        self.get()?.dump(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warnings::SkipReason;
+    use crate::{Config, Error, QueryExpander, QueryParams, Term, TermExpansions};
+
+    #[test]
+    pub fn test001_lazy_load() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [[lookup]]
+            id = "lazy"
+            name = "Lazy"
+            file = "test/lookup.tsv"
+            lazy = true
+
+            [[lookup]]
+            id = "disabled"
+            name = "Disabled"
+            file = "test/nonexistent.tsv"
+            enabled = false
+
+            [[lookup]]
+            id = "broken"
+            name = "Broken"
+            file = "test/nonexistent.tsv"
+            lazy = true
+            "#,
+        )
+        .expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        // neither the disabled nor the broken module is loaded yet
+        expander.load()?;
+        assert_eq!(expander.modules().count(), 2);
+        let terms = vec![Term::Singular("separate")];
+        let params = QueryParams::new().with("", "include", "lazy".into());
+        let terms_map = expander.expand_query(&terms, &params)?;
+        assert!(!terms_map["separate"].is_empty());
+        // the broken module is skipped with a warning, rather than failing the expansion
+        let params = QueryParams::new().with("", "include", "broken".into());
+        let mut terms_map = TermExpansions::new();
+        let warnings = expander.expand_query_into(&mut terms_map, &terms, &params)?;
+        assert!(terms_map["separate"].is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].module(), "broken");
+        assert_eq!(warnings[0].reason(), SkipReason::LoadFailed);
+        Ok(())
+    }
+}
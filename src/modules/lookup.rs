@@ -1,20 +1,37 @@
 use serde::Deserialize;
+use std::any::Any;
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info};
 
+#[cfg(feature = "fst")]
+use fst::automaton::Levenshtein;
+#[cfg(feature = "fst")]
+use fst::{IntoStreamer, Set};
+
+use crate::analyzer::{self, Filter};
+use crate::distance;
 use crate::lexer::Term;
-use crate::modules::Module;
+use crate::modules::params::ParamSpec;
+use crate::modules::{self, CommonModuleConfig, DataVersion, FileChecksum, Module, ModuleConfig};
+use crate::progress;
+use crate::snapshot::{self, SharedBytes};
 use crate::{Error, QueryParams, TermExpansion, TermExpansions};
 
 /// A simple hash-map-based lookup module
 /// mapping keywords to variants.
 pub struct LookupModule {
     config: LookupConfig,
-    data: LookupData,
+
+    /// The loaded data, shared with the instances for other configurations, see [`Module::share()`]
+    data: Arc<LookupData>,
+
+    /// Version of the loaded data, see [`Module::data_version()`]
+    data_version: Option<DataVersion>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -47,6 +64,20 @@ pub struct LookupConfig {
     /// Allow numeric fields, otherwise they will be ignored (which is useful to filter out frequency/score information from input files)
     #[serde(default)]
     allow_numeric: bool,
+
+    /// If a term is not found, look it up again with case, diacritics and punctuation ignored (see [`fold_key()`])
+    #[serde(default)]
+    fuzzy: bool,
+
+    /// If a term is still not found with `fuzzy`, fall back to the keys within this edit distance of the folded term
+    /// (0 to disable), can be changed per query. The keys are searched with a Levenshtein automaton over an FST of
+    /// the folded keys if the `fst` feature is enabled, otherwise all keys are scanned.
+    #[serde(default)]
+    fuzzy_distance: u32,
+
+    /// Options common to all modules
+    #[serde(flatten)]
+    common: CommonModuleConfig,
 }
 
 impl LookupConfig {
@@ -63,18 +94,184 @@ fn tab() -> char {
     '\t'
 }
 
+/// The keys and variants are interned: every distinct string is stored once and shared by all keys it occurs with,
+/// as variant lists tend to repeat the same strings over and over
 #[derive(Default)]
 pub struct LookupData {
-    variants: HashMap<String, Vec<String>>,
+    variants: HashMap<Arc<str>, Box<[Arc<str>]>>,
+
+    /// Maps folded keys to the keys that fold to them, only built if `fuzzy` is set
+    folded: HashMap<String, Vec<Arc<str>>>,
+
+    /// The folded keys, to search those within an edit distance, only built if `fuzzy` is set
+    #[cfg(feature = "fst")]
+    folded_keys: Set<Vec<u8>>,
+
+    /// The heap size of the distinct strings, for the memory estimate
+    string_bytes: usize,
+}
+
+impl LookupData {
+    /// Builds the FST of the folded keys, once these are known
+    fn build_folded_keys(&mut self) -> Result<(), Error> {
+        #[cfg(feature = "fst")]
+        {
+            let mut folded: Vec<&String> = self.folded.keys().collect();
+            folded.sort();
+            self.folded_keys = Set::from_iter(folded)?;
+        }
+        Ok(())
+    }
+}
+
+/// Stores every distinct string once while the data is loaded, see [`LookupData`]
+#[derive(Default)]
+struct Interner {
+    strings: HashSet<Arc<str>>,
+    bytes: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(s);
+        // the reference counts precede the string on the heap
+        self.bytes += s.len() + 2 * std::mem::size_of::<usize>();
+        self.strings.insert(interned.clone());
+        interned
+    }
+}
+
+/// Folds a key or term for fuzzy matching: lowercased, without diacritics and without punctuation or other symbols
+pub fn fold_key(s: &str) -> String {
+    analyzer::analyze(&[Filter::Lowercase, Filter::AsciiFold], s)
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace())
+        .collect()
+}
+
+impl ModuleConfig for LookupConfig {
+    fn kind(&self) -> &'static str {
+        "lookup"
+    }
+
+    fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    fn set_id(&mut self, id: String) {
+        self.id = id;
+    }
+
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn files(&self) -> Vec<&Path> {
+        vec![self.file.as_path()]
+    }
+
+    fn files_mut(&mut self) -> Vec<&mut PathBuf> {
+        vec![&mut self.file]
+    }
+
+    fn data_options(&self) -> String {
+        format!(
+            "{:?}",
+            (
+                self.delimiter,
+                self.delimiter2,
+                self.skipfirstline,
+                self.casesensitive,
+                self.allow_numeric,
+                self.fuzzy
+            )
+        )
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        &self.common
+    }
+
+    fn common_mut(&mut self) -> &mut CommonModuleConfig {
+        &mut self.common
+    }
 }
 
 impl LookupModule {
     pub fn new(config: LookupConfig) -> Self {
         Self {
             config,
-            data: LookupData::default(),
+            data: Arc::default(),
+            data_version: None,
+        }
+    }
+
+    /// Returns the keys that match a term that was not found as is, if `fuzzy` is set: the keys that are equal
+    /// when folded, or otherwise those closest to it within the given edit distance
+    fn fuzzy_keys(&self, term: &str, maxdistance: u32) -> Vec<&str> {
+        if !self.config.fuzzy {
+            return Vec::new();
+        }
+        let term = fold_key(term);
+        if let Some(keys) = self.data.folded.get(term.as_str()) {
+            return keys.iter().map(|key| &**key).collect();
+        }
+        if maxdistance == 0 || term.is_empty() {
+            return Vec::new();
+        }
+        let mut best = maxdistance + 1;
+        let mut matches: Vec<&str> = Vec::new();
+        for folded in self.candidates(term.as_str(), maxdistance) {
+            let distance = distance::levenshtein(term.as_str(), folded.as_ref());
+            if distance < best {
+                best = distance;
+                matches.clear();
+            }
+            if distance == best {
+                if let Some(keys) = self.data.folded.get(folded.as_ref()) {
+                    matches.extend(keys.iter().map(|key| &**key));
+                }
+            }
+        }
+        matches.sort();
+        matches
+    }
+
+    /// Returns the folded keys that may be within the edit distance of a folded term
+    #[cfg(feature = "fst")]
+    fn candidates(&self, term: &str, maxdistance: u32) -> Vec<Cow<'_, str>> {
+        match Levenshtein::new(term, maxdistance) {
+            Ok(levaut) => self
+                .data
+                .folded_keys
+                .search(levaut)
+                .into_stream()
+                .into_strs()
+                .unwrap_or_default()
+                .into_iter()
+                .map(Cow::Owned)
+                .collect(),
+            Err(e) => {
+                debug!("Can't build FST for term '{}': {}", term, e);
+                Vec::new()
+            }
         }
     }
+
+    /// Returns the folded keys that may be within the edit distance of a folded term
+    #[cfg(not(feature = "fst"))]
+    fn candidates(&self, term: &str, maxdistance: u32) -> Vec<Cow<'_, str>> {
+        let length = term.chars().count();
+        self.data
+            .folded
+            .keys()
+            .filter(|folded| folded.chars().count().abs_diff(length) <= maxdistance as usize)
+            .map(|folded| Cow::Borrowed(folded.as_str()))
+            .collect()
+    }
 }
 
 impl Module for LookupModule {
@@ -86,6 +283,10 @@ impl Module for LookupModule {
         self.config.name.as_str()
     }
 
+    fn common(&self) -> &CommonModuleConfig {
+        &self.config.common
+    }
+
     fn kind(&self) -> &'static str {
         "lookup"
     }
@@ -99,14 +300,24 @@ impl Module for LookupModule {
                 e
             ))
         })?;
+        let data_version = modules::verify_data(&self.config)?;
+        let total = file.metadata().ok().map(|metadata| metadata.len());
         let mut buffer = String::new();
+        let mut interner = Interner::default();
+        let mut data = LookupData::default();
         let mut reader = BufReader::new(file);
         let mut firstline = true;
+        let (mut lines, mut read) = (0, 0);
         while let Ok(bytes) = reader.read_line(&mut buffer) {
             if bytes == 0 {
                 //EOF
                 break;
             }
+            lines += 1;
+            read += bytes as u64;
+            if lines % progress::REPORT_INTERVAL == 0 {
+                progress::report(lines, read, total);
+            }
             if firstline {
                 firstline = false;
                 if self.config.skipfirstline {
@@ -117,23 +328,23 @@ impl Module for LookupModule {
             if buffer.chars().next() != Some('#') {
                 let mut iter = buffer.trim().splitn(2, self.config.delimiter);
                 if let (Some(keyword), Some(variants)) = (iter.next(), iter.next()) {
-                    let variants: Vec<_> = variants
+                    let variants: Box<[Arc<str>]> = variants
                         .split(self.config.delimiter2)
                         .filter_map(|s| {
                             //check if field is not purely numeric, ignore if it is
                             if self.config.allow_numeric || s.parse::<f64>().is_err() {
-                                Some(s.to_owned())
+                                Some(interner.intern(s))
                             } else {
                                 None
                             }
                         })
                         .collect();
                     if !variants.is_empty() {
-                        self.data.variants.insert(
+                        data.variants.insert(
                             if self.config.casesensitive {
-                                keyword.to_owned()
+                                interner.intern(keyword)
                             } else {
-                                keyword.to_lowercase()
+                                interner.intern(&keyword.to_lowercase())
                             },
                             variants,
                         );
@@ -142,42 +353,269 @@ impl Module for LookupModule {
             }
             buffer.clear();
         }
-        info!("Loaded {} terms", self.data.variants.len());
+        progress::report(lines, read, total);
+        data.string_bytes = interner.bytes;
+        if self.config.fuzzy {
+            for keyword in data.variants.keys() {
+                data.folded
+                    .entry(fold_key(keyword))
+                    .or_default()
+                    .push(keyword.clone());
+            }
+            data.build_folded_keys()?;
+        }
+        info!("Loaded {} terms", data.variants.len());
+        self.data = Arc::new(data);
+        self.data_version = Some(data_version);
         Ok(())
     }
 
+    fn parameters(&self) -> Vec<ParamSpec> {
+        if !self.config.fuzzy {
+            return Vec::new();
+        }
+        vec![ParamSpec::integer(
+            "fuzzy_distance",
+            0,
+            u8::MAX as u64,
+            "Maximum edit distance of the keys to a term that is not found, with case, diacritics and punctuation ignored",
+        )
+        .with_default(self.config.fuzzy_distance)]
+    }
+
+    fn data_version(&self) -> Option<&str> {
+        self.data_version
+            .as_ref()
+            .map(|data_version| data_version.version())
+    }
+
+    fn file_checksums(&self) -> &[FileChecksum] {
+        self.data_version
+            .as_ref()
+            .map_or(&[], |data_version| data_version.files())
+    }
+
+    fn entries(&self) -> Option<usize> {
+        Some(self.data.variants.len())
+    }
+
+    fn memory_estimate(&self) -> Option<u64> {
+        // the strings themselves are shared, so they are counted once
+        let shared = std::mem::size_of::<Arc<str>>();
+        let entry = shared + std::mem::size_of::<Box<[Arc<str>]>>();
+        let folded = self
+            .data
+            .folded
+            .iter()
+            .map(|(folded, keys)| {
+                std::mem::size_of::<String>()
+                    + std::mem::size_of::<Vec<Arc<str>>>()
+                    + folded.capacity()
+                    + keys.capacity() * shared
+            })
+            .sum::<usize>();
+        let variants = self
+            .data
+            .variants
+            .values()
+            .map(|variants| entry + variants.len() * shared)
+            .sum::<usize>();
+        #[cfg(feature = "fst")]
+        let folded = folded + self.data.folded_keys.as_fst().as_bytes().len();
+        Some((self.data.string_bytes + variants + folded) as u64)
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        Some(Box::new(LookupModule::new(self.config.clone())))
+    }
+
+    fn share(&self, config: &dyn Any) -> Option<Box<dyn Module>> {
+        let config = config.downcast_ref::<LookupConfig>()?;
+        Some(Box::new(Self {
+            config: config.clone(),
+            data: self.data.clone(),
+            data_version: self.data_version.clone(),
+        }))
+    }
+
     fn expand_query(
         &self,
         terms: &Vec<Term>,
-        _params: &QueryParams,
+        params: &QueryParams,
     ) -> Result<TermExpansions, Error> {
+        let fuzzy_distance = params
+            .get_u64(self.id(), "fuzzy_distance")
+            .map_or(self.config.fuzzy_distance, |distance| distance as u32);
         let mut expansions = TermExpansions::new();
-        for term in terms {
-            debug!("Looking up {}", term.as_str());
+        for original in terms {
+            debug!("Looking up {}", original.as_str());
             let term = if self.config.casesensitive {
-                Cow::Borrowed(term.as_str())
+                Cow::Borrowed(original.as_str())
             } else {
-                Cow::Owned(term.as_str().to_lowercase())
+                Cow::Owned(original.as_str().to_lowercase())
             };
-            if let Some(variants) = self.data.variants.get(term.as_ref()) {
+            // acronyms are also looked up in their normalised form, so `N.V.` finds the key `NV`
+            let variants = self.data.variants.get(term.as_ref()).or_else(|| {
+                let acronym = original.acronym()?;
+                if self.config.casesensitive {
+                    self.data.variants.get(acronym.as_str())
+                } else {
+                    self.data.variants.get(acronym.to_lowercase().as_str())
+                }
+            });
+            if let Some(variants) = variants {
                 debug!("found {} expansions", variants.len());
+                // keyed by the term as it appears in the query, so the expansions are found again
+                expansions.insert(
+                    original.as_str().to_owned(),
+                    vec![TermExpansion::default().with_source(self).with_expansions(
+                        variants.iter().map(|variant| variant.to_string()).collect(),
+                    )],
+                );
+            } else {
+                let keys = self.fuzzy_keys(original.as_str(), fuzzy_distance);
+                if keys.is_empty() {
+                    debug!("not found");
+                    continue;
+                }
+                debug!("found {} keys by fuzzy matching", keys.len());
+                // the matched keys are the spellings found in the lexicon, so they come first
+                let mut variants: Vec<String> = Vec::new();
+                for key in keys {
+                    for variant in std::iter::once(key)
+                        .chain(self.data.variants[key].iter().map(|variant| &**variant))
+                    {
+                        if variant != term.as_ref() && !variants.iter().any(|v| v == variant) {
+                            variants.push(variant.to_owned());
+                        }
+                    }
+                }
                 expansions.insert(
-                    term.into_owned(),
+                    original.as_str().to_owned(),
                     vec![TermExpansion::default()
                         .with_source(self)
-                        .with_expansions(variants.to_vec())],
+                        .with_expansions(variants)],
                 );
-            } else {
-                debug!("not found");
             }
         }
         Ok(expansions)
     }
+
+    fn complete(&self, prefix: &str, limit: usize) -> Result<Option<Vec<String>>, Error> {
+        let prefix = if self.config.casesensitive {
+            Cow::Borrowed(prefix)
+        } else {
+            Cow::Owned(prefix.to_lowercase())
+        };
+        let completions = self
+            .data
+            .variants
+            .keys()
+            .filter(|keyword| keyword.starts_with(prefix.as_ref()))
+            .map(|keyword| keyword.to_string())
+            .collect();
+        Ok(Some(crate::modules::rank_completions(completions, limit)))
+    }
+
+    fn dump(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        let mut keywords: Vec<&Arc<str>> = self.data.variants.keys().collect();
+        keywords.sort();
+        for keyword in keywords {
+            write!(writer, "{}", keyword)?;
+            for variant in self.data.variants[keyword].iter() {
+                write!(writer, "\t{}", variant)?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self, writer: &mut dyn Write) -> Result<bool, Error> {
+        snapshot::write_header(writer, &self.config, self.data_version.as_ref())?;
+        // every distinct string is stored once, the keys and variants refer to it by its index in this table
+        let mut table: HashMap<&str, u64> = HashMap::new();
+        let mut strings: Vec<&str> = Vec::new();
+        for (keyword, variants) in self.data.variants.iter() {
+            for s in std::iter::once(keyword).chain(variants.iter()) {
+                table.entry(s).or_insert_with(|| {
+                    strings.push(s);
+                    strings.len() as u64 - 1
+                });
+            }
+        }
+        snapshot::write_u64(writer, strings.len() as u64)?;
+        for s in strings {
+            snapshot::write_str(writer, s)?;
+        }
+        snapshot::write_u64(writer, self.data.variants.len() as u64)?;
+        for (keyword, variants) in self.data.variants.iter() {
+            snapshot::write_u64(writer, table[keyword.as_ref()])?;
+            snapshot::write_u64(writer, variants.len() as u64)?;
+            for variant in variants.iter() {
+                snapshot::write_u64(writer, table[variant.as_ref()])?;
+            }
+        }
+        // the folded keys are stored too, as folding all keys again takes most of the time of loading
+        snapshot::write_u64(writer, self.data.folded.len() as u64)?;
+        for (folded, keywords) in self.data.folded.iter() {
+            snapshot::write_str(writer, folded)?;
+            snapshot::write_u64(writer, keywords.len() as u64)?;
+            for keyword in keywords.iter() {
+                snapshot::write_u64(writer, table[keyword.as_ref()])?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn load_snapshot(&mut self, data: &SharedBytes) -> Result<bool, Error> {
+        let reader = &mut data.as_ref();
+        let Some(data_version) = snapshot::read_header(reader, &self.config)? else {
+            return Ok(false);
+        };
+        let mut lookupdata = LookupData::default();
+        // the strings are distinct already, they need not be interned again
+        let strings = (0..snapshot::read_u64(reader)?)
+            .map(|_| {
+                let s = snapshot::read_string(reader)?;
+                lookupdata.string_bytes += s.len() + 2 * std::mem::size_of::<usize>();
+                Ok(Arc::from(s))
+            })
+            .collect::<Result<Vec<Arc<str>>, Error>>()?;
+        let string = |reader: &mut &[u8]| -> Result<Arc<str>, Error> {
+            let index = snapshot::read_u64(reader)?;
+            strings
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| Error::LoadError("Snapshot is corrupt: invalid string index".into()))
+        };
+        for _ in 0..snapshot::read_u64(reader)? {
+            let keyword = string(reader)?;
+            let variants = (0..snapshot::read_u64(reader)?)
+                .map(|_| string(reader))
+                .collect::<Result<_, Error>>()?;
+            lookupdata.variants.insert(keyword, variants);
+        }
+        for _ in 0..snapshot::read_u64(reader)? {
+            let folded = snapshot::read_string(reader)?;
+            let keywords = (0..snapshot::read_u64(reader)?)
+                .map(|_| string(reader))
+                .collect::<Result<_, Error>>()?;
+            lookupdata.folded.insert(folded, keywords);
+        }
+        lookupdata.build_folded_keys()?;
+        info!("Restored {} terms", lookupdata.variants.len());
+        self.data = Arc::new(lookupdata);
+        self.data_version = Some(data_version);
+        Ok(true)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::resources::Checksums;
+    use crate::testutil::TestDir;
+    use crate::{Config, QueryExpander, TermStatus};
 
     fn init_test() -> Result<LookupModule, Error> {
         let mut testfile = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -192,6 +630,9 @@ mod tests {
             skipfirstline: false,
             casesensitive: false,
             allow_numeric: false,
+            fuzzy: false,
+            fuzzy_distance: 0,
+            common: CommonModuleConfig::default(),
         }))
     }
 
@@ -199,6 +640,8 @@ mod tests {
     pub fn test001_lookup_load() -> Result<(), Error> {
         let mut module = init_test()?;
         module.load()?;
+        assert!(module.entries().is_some_and(|entries| entries > 0));
+        assert!(module.memory_estimate().is_some_and(|bytes| bytes > 0));
         Ok(())
     }
 
@@ -248,4 +691,279 @@ mod tests {
         assert_eq!(expansions.len(), 0, "Checking number of terms returned");
         Ok(())
     }
+
+    #[test]
+    pub fn test003_lookup_term_statuses() -> Result<(), Error> {
+        let config: Config = toml::from_str("stopwords = [\"The\"]").expect("config must parse");
+        let mut module = init_test()?;
+        module.load()?;
+        let expander = QueryExpander::new()
+            .with_config(config)
+            .with_module(Box::new(module));
+        let terms = vec![
+            Term::Singular("separate"),
+            Term::Singular("blah"),
+            Term::Singular("the"),
+        ];
+        let params = QueryParams::default();
+        let terms_map = expander.expand_query(&terms, &params)?;
+        let statuses = expander.term_statuses(&terms, &terms_map, &params);
+        assert_eq!(statuses.get("separate"), Some(&TermStatus::Expanded));
+        assert_eq!(statuses.get("blah"), Some(&TermStatus::NoMatches));
+        assert_eq!(statuses.get("the"), Some(&TermStatus::Stopword));
+        assert_eq!(terms_map.get("the").map(|e| e.len()), Some(0));
+
+        let params = QueryParams::new().with("", "exclude", "lookup".into());
+        let terms_map = expander.expand_query(&terms, &params)?;
+        let statuses = expander.term_statuses(&terms, &terms_map, &params);
+        assert_eq!(
+            statuses.get("separate"),
+            Some(&TermStatus::ExcludedByModuleFilter)
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test004_lookup_unknown_module() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let expander = QueryExpander::new().with_module(Box::new(module));
+        let terms = vec![Term::Singular("separate")];
+        let params = QueryParams::new().with("", "include", "nonexistent".into());
+        assert!(matches!(
+            expander.expand_query(&terms, &params),
+            Err(Error::ModuleNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test005_lookup_dump() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let mut output = Vec::new();
+        module.dump(&mut output)?;
+        let output = String::from_utf8(output).expect("valid utf-8");
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "separate\tseparated\tseparates\tsplit\tapart\tdivide\tdivided"
+        );
+        assert!(lines[0].starts_with("divide\t"), "output must be sorted");
+        Ok(())
+    }
+
+    #[test]
+    pub fn test006_lookup_complete() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        assert_eq!(
+            module.complete("SEP", 10)?,
+            Some(vec!["separate".to_string(), "seperate".to_string()])
+        );
+        let expander = QueryExpander::new().with_module(Box::new(module));
+        assert_eq!(
+            expander.complete("se", 1, &QueryParams::new())?,
+            vec!["separate".to_string()]
+        );
+        assert!(expander.complete("x", 10, &QueryParams::new())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test007_lookup_data_version() -> Result<(), Error> {
+        let mut module = init_test()?;
+        assert_eq!(module.data_version(), None);
+        module.load()?;
+        assert_eq!(
+            module.data_version(),
+            Some(crate::resources::sha256_file(&module.config.file)?.as_str())
+        );
+        let expander = QueryExpander::new().with_module(Box::new(module));
+        let version = expander.data_version(&QueryParams::new());
+        assert_eq!(version.as_ref().map(|v| v.len()), Some(16));
+        assert_ne!(
+            expander.data_version(&QueryParams::new().with("", "exclude", "lookup".into())),
+            version
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test008_lookup_priority() -> Result<(), Error> {
+        let mut first = init_test()?;
+        first.config.id = "first".into();
+        first.load()?;
+        let mut second = init_test()?;
+        second.config.id = "second".into();
+        second.config.common.priority = 1;
+        second.load()?;
+        let expander = QueryExpander::new()
+            .with_module(Box::new(first))
+            .with_module(Box::new(second));
+        let ids: Vec<&str> = expander.modules().map(|module| module.id()).collect();
+        assert_eq!(ids, vec!["second", "first"]);
+        let terms = vec![Term::Singular("separate")];
+        let terms_map = expander.expand_query(&terms, &QueryParams::default())?;
+        let sources: Vec<Option<&str>> = terms_map["separate"]
+            .iter()
+            .map(|termexpansion| termexpansion.source_id())
+            .collect();
+        assert_eq!(sources, vec![Some("second"), Some("first")]);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test009_lookup_checksum() -> Result<(), Error> {
+        let mut module = init_test()?;
+        let checksum = crate::resources::sha256_file(&module.config.file)?;
+        module.config.common.sha256 = Some(Checksums::Single(checksum.clone()));
+        module.load()?;
+        assert_eq!(module.file_checksums().len(), 1);
+        assert_eq!(module.file_checksums()[0].sha256(), checksum);
+        assert!(module.file_checksums()[0].verified());
+        let mut module = init_test()?;
+        module.config.common.sha256 = Some(Checksums::Single("00".into()));
+        assert!(
+            matches!(module.load(), Err(Error::LoadError(e)) if e.contains("Checksum mismatch"))
+        );
+        assert_eq!(module.data_version(), None);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test010_lookup_fuzzy() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let terms = vec![Term::Singular("Sépàrate!")];
+        assert!(module
+            .expand_query(&terms, &QueryParams::default())?
+            .is_empty());
+        let mut module = init_test()?;
+        module.config.fuzzy = true;
+        module.load()?;
+        let expansions = module.expand_query(&terms, &QueryParams::default())?;
+        assert_eq!(
+            expansions["Sépàrate!"][0]
+                .iter()
+                .take(2)
+                .collect::<Vec<_>>(),
+            vec!["separate", "separated"]
+        );
+        // exact matches do not include the key
+        let expansions =
+            module.expand_query(&vec![Term::Singular("divide")], &QueryParams::default())?;
+        assert_eq!(expansions["divide"][0].iter().next(), Some("separated"));
+        let terms = vec![Term::Singular("seperat")];
+        assert!(module
+            .expand_query(&terms, &QueryParams::default())?
+            .is_empty());
+        module.config.fuzzy_distance = 1;
+        let expansions = module.expand_query(&terms, &QueryParams::default())?;
+        assert_eq!(expansions["seperat"][0].iter().next(), Some("seperate"));
+        // beyond the distance
+        let terms = vec![Term::Singular("sepa")];
+        assert!(module
+            .expand_query(&terms, &QueryParams::default())?
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test011_lookup_interned() -> Result<(), Error> {
+        let mut module = init_test()?;
+        module.load()?;
+        let variants = &module.data.variants;
+        assert!(Arc::ptr_eq(
+            &variants["separate"][0],
+            &variants["seperate"][0]
+        ));
+        // keys and variants share strings too
+        let (divide, _) = variants.get_key_value("divide").expect("key must exist");
+        assert!(Arc::ptr_eq(divide, &variants["separate"][4]));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test012_lookup_fuzzy_distance_param() -> Result<(), Error> {
+        let mut module = init_test()?;
+        assert!(module.parameters().is_empty());
+        module.config.fuzzy = true;
+        module.load()?;
+        assert_eq!(module.parameters()[0].name(), "fuzzy_distance");
+        let terms = vec![Term::Singular("deviDe")];
+        assert!(module
+            .expand_query(&terms, &QueryParams::default())?
+            .is_empty());
+        let params = QueryParams::new().with("lookup", "fuzzy_distance", 2.into());
+        let expansions = module.expand_query(&terms, &params)?;
+        assert_eq!(expansions["deviDe"][0].iter().next(), Some("divide"));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test013_lookup_suppress_if_found_by() -> Result<(), Error> {
+        let toml = |min: usize| {
+            format!(
+                r#"
+                [[lookup]]
+                id = "cheap"
+                name = "Cheap"
+                file = "test/lookup.tsv"
+
+                [[lookup]]
+                id = "expensive"
+                name = "Expensive"
+                file = "test/lookup.tsv"
+                suppress_if_found_by = ["cheap"]
+                suppress_min_expansions = {}
+                "#,
+                min
+            )
+        };
+        let sources = |min: usize| -> Result<Vec<String>, Error> {
+            let config: Config = toml::from_str(&toml(min)).expect("config must parse");
+            assert!(config.validate().is_empty());
+            let mut expander = QueryExpander::new().with_config(config);
+            expander.load()?;
+            let terms = vec![Term::Singular("separate")];
+            let terms_map = expander.expand_query(&terms, &QueryParams::default())?;
+            Ok(terms_map["separate"]
+                .iter()
+                .filter_map(|termexpansion| termexpansion.source_id().map(str::to_owned))
+                .collect())
+        };
+        assert_eq!(sources(1)?, vec!["cheap"]);
+        // the cheap module finds 6 expansions, not enough
+        assert_eq!(sources(10)?, vec!["cheap", "expensive"]);
+
+        let config: Config = toml::from_str(&toml(1).replace(r#"["cheap"]"#, r#"["nonexistent"]"#))
+            .expect("config must parse");
+        assert!(config
+            .validate()
+            .iter()
+            .any(|problem| problem.contains("unknown module nonexistent")));
+        Ok(())
+    }
+
+    #[test]
+    pub fn test014_lookup_acronym() -> Result<(), Error> {
+        let dir = TestDir::new("acronyms");
+        let mut module = init_test()?;
+        module.config.file = dir.write(
+            "acronyms.tsv",
+            "NV\tnaamloze vennootschap\nVOC\tVereenigde Oostindische Compagnie\n",
+        );
+        module.load()?;
+        let (terms, _) = Term::extract_from_query("N.V. VOC V.O.C.");
+        let expansions = module.expand_query(&terms, &QueryParams::default())?;
+        assert_eq!(
+            expansions["N.V."][0].iter().collect::<Vec<_>>(),
+            vec!["naamloze vennootschap"]
+        );
+        assert!(expansions.contains_key("VOC"));
+        assert!(expansions.contains_key("V.O.C."));
+        Ok(())
+    }
 }
@@ -1,4 +1,7 @@
+pub mod lazy;
 pub mod lookup;
+pub mod params;
+pub mod unavailable;
 
 #[cfg(feature = "analiticcl")]
 pub mod analiticcl;
@@ -9,8 +12,265 @@ pub mod fst;
 #[cfg(feature = "finalfusion")]
 pub mod finalfusion;
 
-use crate::lexer::Term;
-use crate::{Error, QueryParams, TermExpansions};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::any::Any;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::cancel;
+use crate::lexer::{QueryContext, Term};
+use crate::resources::{self, Checksums};
+use crate::snapshot::SharedBytes;
+use crate::stats::ExpansionStats;
+use crate::{Error, QueryParams, TermExpansion, TermExpansions};
+use lazy::LazyModule;
+use params::ParamSpec;
+
+/// This trait is implemented for the configuration of all modules, it provides access to common properties without loading the module
+pub trait ModuleConfig {
+    /// Get the module type
+    fn kind(&self) -> &'static str;
+
+    /// Get the module identifier
+    fn id(&self) -> &str;
+
+    /// Changes the module identifier, used to namespace the modules of included configuration files
+    fn set_id(&mut self, id: String);
+
+    /// Get the module name, a human-readable label
+    fn name(&self) -> &str;
+
+    /// Returns all files the module reads when it is loaded
+    fn files(&self) -> Vec<&Path>;
+
+    /// Mutable access to all files the module reads, used to substitute remote resources by their cached copies
+    fn files_mut(&mut self) -> Vec<&mut PathBuf>;
+
+    /// Describes the options that change the data the module loads from its files, as opposed to how the data is
+    /// used (e.g. the edit distance of lookups), see [`data_key()`]
+    fn data_options(&self) -> String;
+
+    /// The options common to all modules
+    fn common(&self) -> &CommonModuleConfig;
+
+    /// Mutable access to the options common to all modules
+    fn common_mut(&mut self) -> &mut CommonModuleConfig;
+}
+
+/// Identifies the data a module loads: its type, its files (canonicalised, so different paths to the same file
+/// match), their expected checksums and the options that change the loaded data (see
+/// [`ModuleConfig::data_options()`]). Modules with the same data key can share the loaded data, see [`Module::share()`].
+pub fn data_key(config: &dyn ModuleConfig) -> String {
+    let files: Vec<PathBuf> = config
+        .files()
+        .iter()
+        .map(|file| file.canonicalize().unwrap_or_else(|_| file.to_path_buf()))
+        .collect();
+    format!(
+        "{}:{:?}:{:?}:{}",
+        config.kind(),
+        files,
+        config.common().sha256(),
+        config.data_options()
+    )
+}
+
+/// The options common to all modules, embedded in the configuration of each module type
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct CommonModuleConfig {
+    /// Set to false to skip this module, it is then not loaded at all
+    enabled: bool,
+
+    /// Load the data on first use rather than at startup
+    lazy: bool,
+
+    /// Set to false to start without this module (listed as unavailable) if its data can not be loaded
+    required: bool,
+
+    /// The expected SHA-256 checksum of the file, or a table mapping file names to checksums, verified at load
+    sha256: Option<Checksums>,
+
+    /// The kind of expansions the module provides (spelling, synonym, translation or related), used for grouping
+    expansion_kind: Option<ExpansionKind>,
+
+    /// The language of the expansions (e.g. an ISO 639 code), used for grouping
+    language: Option<String>,
+
+    /// Apply the casing of the term to its expansions (e.g. title case), see [`crate::casing`]
+    preserve_case: bool,
+
+    /// Always include the term as first expansion (true) or never (false), overrides the global setting
+    include_term: Option<bool>,
+
+    /// Terms longer than this (in characters) are not passed to the module
+    max_term_length: Option<usize>,
+
+    /// Modules with a higher priority are consulted first and their expansions come first, modules with the same
+    /// priority keep the order of the configuration
+    priority: i32,
+
+    /// Skip the terms that one of these (cheaper) modules, consulted before this one, already expanded
+    suppress_if_found_by: Vec<String>,
+
+    /// The number of expansions the modules in `suppress_if_found_by` must have found for a term to be skipped
+    suppress_min_expansions: usize,
+}
+
+impl Default for CommonModuleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            lazy: false,
+            required: true,
+            sha256: None,
+            expansion_kind: None,
+            language: None,
+            preserve_case: false,
+            include_term: None,
+            max_term_length: None,
+            priority: 0,
+            suppress_if_found_by: Vec::new(),
+            suppress_min_expansions: 1,
+        }
+    }
+}
+
+impl CommonModuleConfig {
+    /// Returns false if the module is disabled, it is then not loaded at all
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns true if the data of the module is loaded on first use rather than at startup
+    pub fn lazy(&self) -> bool {
+        self.lazy
+    }
+
+    /// Whether the service refuses to start if the data of this module can not be loaded. If not, the module is
+    /// listed as unavailable instead and queries are expanded without it.
+    pub fn required(&self) -> bool {
+        self.required
+    }
+
+    /// The expected SHA-256 checksums of the files the module reads, verified whenever the data is loaded
+    pub fn sha256(&self) -> Option<&Checksums> {
+        self.sha256.as_ref()
+    }
+
+    /// The configured kind of expansions, see [`Module::expansion_kind()`] for the kind with the default applied
+    pub fn expansion_kind(&self) -> Option<ExpansionKind> {
+        self.expansion_kind
+    }
+
+    /// Returns the language of the expansions (e.g. an ISO 639 code), if known
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Returns true if the casing of each term should be applied to its expansions, see [`crate::casing`]
+    pub fn preserve_case(&self) -> bool {
+        self.preserve_case
+    }
+
+    /// Returns whether the term itself should always (true) or never (false) be included in its expansions, or
+    /// None to follow the global setting, see [`crate::Config::include_term()`]
+    pub fn include_term(&self) -> Option<bool> {
+        self.include_term
+    }
+
+    /// Returns the maximum length (in characters) of the terms passed to the module, if limited, see [`accepts()`]
+    pub fn max_term_length(&self) -> Option<usize> {
+        self.max_term_length
+    }
+
+    /// Returns the priority of the module: modules with a higher priority are consulted first, so their expansions
+    /// come first for each term
+    pub fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    /// Returns the IDs of the modules whose expansions of a term make this module skip the term, so an expensive
+    /// module only handles the terms that cheaper ones (consulted before it, see [`Self::priority()`]) could not
+    pub fn suppress_if_found_by(&self) -> &[String] {
+        &self.suppress_if_found_by
+    }
+
+    /// Mutable access to the IDs in [`Self::suppress_if_found_by()`], used to namespace them along with the modules
+    pub fn suppress_if_found_by_mut(&mut self) -> &mut Vec<String> {
+        &mut self.suppress_if_found_by
+    }
+
+    /// Returns the number of expansions the modules in [`Self::suppress_if_found_by()`] must have found for a term
+    /// (together) for this module to skip it
+    pub fn suppress_min_expansions(&self) -> usize {
+        self.suppress_min_expansions
+    }
+}
+
+/// The kind of expansions a module provides, so responses can group them (see [`crate::grouping`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExpansionKind {
+    /// Spelling variants and corrections
+    Spelling,
+    /// Terms with the same meaning
+    Synonym,
+    /// The term in other languages
+    Translation,
+    /// Otherwise related terms, such as nearest neighbours in word embeddings
+    Related,
+}
+
+impl ExpansionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Spelling => "spelling",
+            Self::Synonym => "synonym",
+            Self::Translation => "translation",
+            Self::Related => "related",
+        }
+    }
+}
+
+/// Returns true if the module is capable of expanding the term (see [`Module::handles_phrases()`] and the like).
+/// The expander does not pass other terms to the module, as that would only cost time.
+pub fn accepts(module: &(impl Module + ?Sized), term: &Term) -> bool {
+    let term = term.as_str();
+    let is_phrase = term.contains(char::is_whitespace);
+    let has_wildcards = term.contains(['*', '?']);
+    let is_number = term.chars().any(|c| c.is_numeric())
+        && term.chars().all(|c| c.is_numeric() || c == '.' || c == ',');
+    (module.handles_phrases() || !is_phrase)
+        && (module.handles_wildcards() || !has_wildcards)
+        && (module.handles_numbers() || !is_number)
+        && module
+            .common()
+            .max_term_length()
+            .is_none_or(|max| term.chars().count() <= max)
+        && (!is_phrase
+            || module
+                .max_phrase_words()
+                .is_none_or(|max| term.split_whitespace().count() <= max))
+}
+
+/// The kind of expansions of a module that does not configure it: spelling variants for spelling-oriented modules,
+/// related terms otherwise
+pub fn default_expansion_kind(module: &(impl Module + ?Sized)) -> ExpansionKind {
+    if module.is_spelling_oriented() {
+        ExpansionKind::Spelling
+    } else {
+        ExpansionKind::Related
+    }
+}
 
 /// This trait is implemented for all query expansions modules
 pub trait Module: Send + Sync {
@@ -23,13 +283,568 @@ pub trait Module: Send + Sync {
     /// Get the module name, a human-readable label
     fn name(&self) -> &str;
 
+    /// The options common to all modules, from the configuration of the module
+    fn common(&self) -> &CommonModuleConfig;
+
     /// Load the module. This *MUST* be called (once) prior to calling *expand_query()*.
     fn load(&mut self) -> Result<(), Error>;
 
+    /// Returns a new, not yet loaded, instance of this module with the same configuration.
+    /// This is used to reload the module's data. Returns `None` if the module does not support this.
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        None
+    }
+
+    /// Returns an instance of this (loaded) module with another configuration of the same type that shares the loaded
+    /// data, rather than loading it again. The configuration must have the same [`data_key()`], it may differ in all
+    /// other options (e.g. the ID or the priority). Returns `None` if the module does not support this.
+    fn share(&self, _config: &dyn Any) -> Option<Box<dyn Module>> {
+        None
+    }
+
     /// Expands a (decomposed) query. Note that `load()` *MUST* be called (once) prior to calling this for the first time, otherwise it will result in a panic.
     fn expand_query(
         &self,
         terms: &Vec<Term>,
         queryparams: &QueryParams,
     ) -> Result<TermExpansions, Error>;
+
+    /// Expands a (decomposed) query, given the context of the terms in the query: their neighbouring terms and the
+    /// fields they are restricted to. Context-aware modules implement this, by default it calls `expand_query()`.
+    fn expand_query_in_context(
+        &self,
+        terms: &Vec<Term>,
+        _context: &QueryContext,
+        queryparams: &QueryParams,
+    ) -> Result<TermExpansions, Error> {
+        self.expand_query(terms, queryparams)
+    }
+
+    /// Returns true if expansion with this module is CPU-intensive. The webservice runs such
+    /// expansions on a separate thread pool (the configured one, if any), so they don't stall other requests.
+    fn is_cpu_intensive(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the module corrects spelling (e.g. by fuzzy matching against a lexicon), rather than providing
+    /// semantically related terms. Only such modules are used for spelling suggestions.
+    fn is_spelling_oriented(&self) -> bool {
+        false
+    }
+
+    /// Returns the kind of expansions the module provides: the configured one, otherwise the default (see
+    /// [`default_expansion_kind()`])
+    fn expansion_kind(&self) -> ExpansionKind {
+        self.common()
+            .expansion_kind()
+            .unwrap_or_else(|| default_expansion_kind(self))
+    }
+
+    /// Returns true if the module can expand phrases (terms of multiple words), see [`accepts()`]
+    fn handles_phrases(&self) -> bool {
+        true
+    }
+
+    /// Returns true if the module can expand terms with wildcards (`*`, `?`), see [`accepts()`]
+    fn handles_wildcards(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the module can expand numbers (e.g. `1648`), see [`accepts()`]
+    fn handles_numbers(&self) -> bool {
+        true
+    }
+
+    /// Returns the maximum number of words of the phrases the module can expand, if limited, see [`accepts()`]
+    fn max_phrase_words(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the parameters the module accepts at query time. The expander validates the passed parameters against
+    /// these before calling `expand_query()` (see [`params::violations()`]), so modules can read them as typed
+    /// values (e.g. with [`QueryParams::get_u64()`]).
+    fn parameters(&self) -> Vec<ParamSpec> {
+        Vec::new()
+    }
+
+    /// Returns a version of the loaded data, so an expansion can be reproduced later with the exact same data.
+    /// Returns `None` if the data is not loaded (yet). Modules with nondeterministic results should
+    /// derive their randomness from the global `seed` parameter, if it is passed.
+    fn data_version(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns true if the data of the module has been loaded, false if loading is deferred (see [`LazyModule`])
+    fn is_loaded(&self) -> bool {
+        true
+    }
+
+    /// Returns the error if the data of the module could not be loaded at startup, the module is then unavailable
+    /// (see [`unavailable::UnavailableModule`])
+    fn load_error(&self) -> Option<&str> {
+        None
+    }
+
+    /// Returns the SHA-256 checksum of each data file as it was loaded, empty if the data is not loaded (yet)
+    fn file_checksums(&self) -> &[FileChecksum] {
+        &[]
+    }
+
+    /// Returns the number of entries (e.g. terms in the lexicon) in the loaded data, if known
+    fn entries(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns a rough estimate of the memory used by the loaded data in bytes, if known
+    fn memory_estimate(&self) -> Option<u64> {
+        None
+    }
+
+    /// Returns up to `limit` terms from the loaded data that start with the prefix, best first, for autocompletion.
+    /// Returns `None` if the module does not support this.
+    fn complete(&self, _prefix: &str, _limit: usize) -> Result<Option<Vec<String>>, Error> {
+        Ok(None)
+    }
+
+    /// Writes the loaded data of the module as tab-separated values: one term per line, optionally followed by its variants.
+    /// Returns an error if the module does not support this.
+    fn dump(&self, _writer: &mut dyn Write) -> Result<(), Error> {
+        Err(Error::InvalidParameter(format!(
+            "Module {} ({}) does not support dumping its data",
+            self.id(),
+            self.kind()
+        )))
+    }
+
+    /// Writes the loaded data of the module for a snapshot (see [`crate::snapshot::Snapshot`]), so it can be restored
+    /// with [`Self::load_snapshot()`] rather than loaded from its files again. Returns false if the module does not
+    /// support this.
+    fn snapshot(&self, _writer: &mut dyn Write) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Restores the data written by [`Self::snapshot()`], instead of calling `load()`. Returns false if the module does
+    /// not support this or if the snapshot is out of date (its configuration or data files changed), the module
+    /// should then be loaded as usual.
+    fn load_snapshot(&mut self, _data: &SharedBytes) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+/// The SHA-256 checksum of a data file of a module
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct FileChecksum {
+    #[cfg_attr(feature = "server", schema(value_type = String))]
+    file: PathBuf,
+    sha256: String,
+    /// Whether the checksum was verified against the one in the configuration (`sha256`)
+    verified: bool,
+    /// The size and modification time of the file when the checksum was computed
+    #[serde(skip)]
+    stamp: Option<FileStamp>,
+}
+
+impl FileChecksum {
+    pub fn file(&self) -> &Path {
+        self.file.as_path()
+    }
+
+    pub fn sha256(&self) -> &str {
+        self.sha256.as_str()
+    }
+
+    pub fn verified(&self) -> bool {
+        self.verified
+    }
+
+    pub(crate) fn new(file: PathBuf, sha256: String, verified: bool, stamp: FileStamp) -> Self {
+        Self {
+            file,
+            sha256,
+            verified,
+            stamp: Some(stamp),
+        }
+    }
+
+    pub(crate) fn stamp(&self) -> Option<FileStamp> {
+        self.stamp
+    }
+}
+
+/// The size and modification time of a file, to tell whether it changed since its checksum was computed without
+/// computing it again (see [`crate::snapshot`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FileStamp {
+    pub(crate) size: u64,
+    /// Nanoseconds since the Unix epoch
+    pub(crate) modified: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn of(path: &Path) -> Result<Self, Error> {
+        let error = |e: std::io::Error| {
+            Error::LoadError(format!("Unable to read {}: {}", path.display(), e))
+        };
+        let metadata = std::fs::metadata(path).map_err(error)?;
+        let modified = metadata
+            .modified()
+            .map_err(error)?
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |modified| modified.as_nanos() as u64);
+        Ok(Self {
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// The version of the loaded data of a module, see [`verify_data()`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataVersion {
+    version: String,
+    files: Vec<FileChecksum>,
+}
+
+impl DataVersion {
+    /// The version of data files with known checksums, see [`verify_data()`]
+    pub(crate) fn new(files: Vec<FileChecksum>) -> Self {
+        let version = if let [checksum] = files.as_slice() {
+            checksum.sha256.clone()
+        } else {
+            let joined: Vec<&str> = files.iter().map(|c| c.sha256.as_str()).collect();
+            format!("{:x}", Sha256::digest(joined.join("\n")))
+        };
+        Self { version, files }
+    }
+
+    /// The version of the data as a whole, see [`Module::data_version()`]
+    pub fn version(&self) -> &str {
+        self.version.as_str()
+    }
+
+    pub fn files(&self) -> &[FileChecksum] {
+        &self.files
+    }
+}
+
+/// Computes the checksums of the files of a module and verifies them against the configured ones (`sha256`), so the
+/// module refuses to load corrupted or truncated data. The version of the data is the SHA-256 checksum of the file if
+/// there is only one (so it can be verified with `sha256sum`), otherwise the SHA-256 checksum of the checksums of all
+/// files.
+pub(crate) fn verify_data(config: &dyn ModuleConfig) -> Result<DataVersion, Error> {
+    let files = config.files();
+    if let Some(expected) = config.common().sha256() {
+        expected.validate(&files)?;
+    }
+    let mut checksums = Vec::with_capacity(files.len());
+    for file in files.iter() {
+        // taken before the checksum, so a change while it is computed shows in a later comparison
+        let stamp = FileStamp::of(file).ok();
+        let (sha256, verified) = match config.common().sha256() {
+            Some(expected) => expected.verify(&files, file)?,
+            None => (resources::sha256_file(file)?, false),
+        };
+        checksums.push(FileChecksum {
+            file: file.to_path_buf(),
+            sha256,
+            verified,
+            stamp,
+        });
+    }
+    Ok(DataVersion::new(checksums))
+}
+
+/// Describes the resources a loaded module takes (load time, entries, estimated memory), for logging
+pub fn resource_report(module: &dyn Module, stats: &ExpansionStats) -> String {
+    if !module.is_loaded() {
+        return format!("Module {}: not loaded yet", module.id());
+    }
+    let mut report = format!("Module {}:", module.id());
+    if let Some(load_time) = stats.load_time(module.id()) {
+        report += &format!(" loaded in {:.1}s,", load_time.as_secs_f64());
+    }
+    match module.entries() {
+        Some(entries) => report += &format!(" {} entries,", entries),
+        None => report += " unknown number of entries,",
+    }
+    match module.memory_estimate() {
+        Some(bytes) => report += &format!(" ~{:.1} MiB", bytes as f64 / 1048576.0),
+        None => report += " unknown memory usage",
+    }
+    report
+}
+
+/// Expands each of the terms with the given function, which returns the key and the expansion for a term (if any).
+/// With `parallel`, the terms are expanded at the same time on the current thread pool, which pays off for queries
+/// with many terms (this requires the `parallel` feature). Cancellation is checked before each term in either case.
+#[cfg_attr(
+    not(any(feature = "analiticcl", feature = "fst", feature = "finalfusion")),
+    allow(dead_code)
+)]
+pub(crate) fn expand_terms<F>(
+    terms: &[Term],
+    parallel: bool,
+    expand: F,
+) -> Result<TermExpansions, Error>
+where
+    F: Fn(&Term) -> Result<Option<(String, TermExpansion)>, Error> + Sync,
+{
+    let expand = |term: &Term| {
+        cancel::check()?;
+        expand(term)
+    };
+    #[cfg(feature = "parallel")]
+    let results: Vec<_> = if parallel {
+        let cancellation = cancel::current();
+        terms
+            .par_iter()
+            .map(|term| cancellation.scope(|| expand(term)))
+            .collect::<Result<_, _>>()?
+    } else {
+        terms.iter().map(expand).collect::<Result<_, _>>()?
+    };
+    #[cfg(not(feature = "parallel"))]
+    let results: Vec<_> = {
+        let _ = parallel;
+        terms.iter().map(expand).collect::<Result<_, _>>()?
+    };
+    Ok(results
+        .into_iter()
+        .flatten()
+        .map(|(key, termexpansion)| (key, vec![termexpansion]))
+        .collect())
+}
+
+/// Ranks completions for modules without a notion of frequency: shorter terms first, then alphabetically
+pub(crate) fn rank_completions(mut completions: Vec<String>, limit: usize) -> Vec<String> {
+    completions.sort_by(|a, b| {
+        a.chars()
+            .count()
+            .cmp(&b.chars().count())
+            .then_with(|| a.cmp(b))
+    });
+    completions.dedup();
+    completions.truncate(limit);
+    completions
+}
+
+/// Instantiates a module from its configuration, wrapped in a [`LazyModule`] if it is configured to load lazily.
+/// The module is not loaded yet, call `load()` on the result.
+pub fn instantiate<C, M>(config: &C, new: fn(C) -> M) -> Box<dyn Module>
+where
+    C: ModuleConfig + Clone + Send + Sync + 'static,
+    M: Module + 'static,
+{
+    if config.common().lazy() {
+        let config = config.clone();
+        Box::new(LazyModule::new(move || Box::new(new(config.clone()))))
+    } else {
+        Box::new(new(config.clone()))
+    }
+}
+
+/// Instantiates a module of the given type (e.g. `lookup`) from its configuration, as it would appear in the configuration file.
+/// The module is not loaded yet, call `load()` on the result.
+pub fn from_config(kind: &str, config: Value) -> Result<Box<dyn Module>, Error> {
+    if !ModuleConfigs::TYPES.contains(&kind) {
+        return Err(Error::InvalidConfig(format!(
+            "Unknown or unsupported module type: {}",
+            kind
+        )));
+    }
+    let Value::Object(mut config) = config else {
+        return Err(Error::InvalidConfig(
+            "Invalid module configuration: expected an object".into(),
+        ));
+    };
+    config.insert("type".into(), kind.into());
+    let config: AnyModuleConfig = serde_json::from_value(Value::Object(config))
+        .map_err(|e| Error::InvalidConfig(format!("Invalid module configuration: {}", e)))?;
+    Ok(config.instantiate())
+}
+
+/// The configuration of a module of any of the types that are compiled in, tagged with its `type`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum AnyModuleConfig {
+    Lookup(lookup::LookupConfig),
+    #[cfg(feature = "fst")]
+    Fst(fst::FstConfig),
+    #[cfg(feature = "analiticcl")]
+    Analiticcl(analiticcl::AnaliticclConfig),
+    #[cfg(feature = "finalfusion")]
+    FinalFusion(finalfusion::FinalFusionConfig),
+}
+
+impl AnyModuleConfig {
+    /// Access to the properties common to all module configurations
+    pub fn as_config(&self) -> &dyn ModuleConfig {
+        match self {
+            Self::Lookup(config) => config,
+            #[cfg(feature = "fst")]
+            Self::Fst(config) => config,
+            #[cfg(feature = "analiticcl")]
+            Self::Analiticcl(config) => config,
+            #[cfg(feature = "finalfusion")]
+            Self::FinalFusion(config) => config,
+        }
+    }
+
+    /// Mutable access to the properties common to all module configurations
+    pub fn as_config_mut(&mut self) -> &mut dyn ModuleConfig {
+        match self {
+            Self::Lookup(config) => config,
+            #[cfg(feature = "fst")]
+            Self::Fst(config) => config,
+            #[cfg(feature = "analiticcl")]
+            Self::Analiticcl(config) => config,
+            #[cfg(feature = "finalfusion")]
+            Self::FinalFusion(config) => config,
+        }
+    }
+
+    /// The configuration of the module type, to downcast it (see [`Module::share()`])
+    pub fn as_any(&self) -> &dyn Any {
+        match self {
+            Self::Lookup(config) => config,
+            #[cfg(feature = "fst")]
+            Self::Fst(config) => config,
+            #[cfg(feature = "analiticcl")]
+            Self::Analiticcl(config) => config,
+            #[cfg(feature = "finalfusion")]
+            Self::FinalFusion(config) => config,
+        }
+    }
+
+    /// Instantiates the module, see [`instantiate()`]
+    pub fn instantiate(&self) -> Box<dyn Module> {
+        match self {
+            Self::Lookup(config) => instantiate(config, lookup::LookupModule::new),
+            #[cfg(feature = "fst")]
+            Self::Fst(config) => instantiate(config, fst::FstModule::new),
+            #[cfg(feature = "analiticcl")]
+            Self::Analiticcl(config) => instantiate(config, analiticcl::AnaliticclModule::new),
+            #[cfg(feature = "finalfusion")]
+            Self::FinalFusion(config) => instantiate(config, finalfusion::FinalFusionModule::new),
+        }
+    }
+}
+
+/// The configurations of all modules, in the order in which they are used. Modules are defined in `[[module]]`
+/// tables with a `type`, which keeps them in the order in which they are written. The older arrays of tables per
+/// type (`[[lookup]]`, `[[fst]]`, ...) are still accepted, these come first, by type: the order between different
+/// arrays of tables is not retained by TOML (nor by [`toml::Table`]), so mixing both syntaxes loses the order in which
+/// the modules are written.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleConfigs(Vec<AnyModuleConfig>);
+
+impl ModuleConfigs {
+    /// The module types, i.e. the values of `type` and the names of the arrays of tables per type
+    pub const TYPES: &'static [&'static str] = &["lookup", "fst", "analiticcl", "finalfusion"];
+
+    /// The name of the array of tables holding the module definitions of any type
+    pub const TABLE: &'static str = "module";
+
+    pub fn iter(&self) -> impl Iterator<Item = &AnyModuleConfig> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AnyModuleConfig> {
+        self.0.iter_mut()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Adds the modules of another configuration after these
+    pub fn append(&mut self, other: &mut Self) {
+        self.0.append(&mut other.0);
+    }
+
+    /// Removes all modules for which the predicate returns false
+    pub fn retain(&mut self, f: impl Fn(&AnyModuleConfig) -> bool) {
+        self.0.retain(f);
+    }
+}
+
+/// Deserializes the module definitions from the configuration, other keys are ignored. Module types that are not
+/// compiled in are ignored in the per-type arrays, and are an error in `[[module]]`.
+impl<'de> Deserialize<'de> for ModuleConfigs {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ModuleConfigsVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ModuleConfigsVisitor {
+            type Value = ModuleConfigs;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("module definitions")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<ModuleConfigs, A::Error> {
+                let mut modules: Vec<AnyModuleConfig> = Vec::new();
+                let mut by_type: Vec<(usize, AnyModuleConfig)> = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    let position = ModuleConfigs::TYPES.iter().position(|kind| *kind == key);
+                    let configs: Vec<AnyModuleConfig> = match key.as_str() {
+                        ModuleConfigs::TABLE => {
+                            modules.extend(map.next_value::<Vec<AnyModuleConfig>>()?);
+                            continue;
+                        }
+                        "lookup" => map
+                            .next_value::<Vec<lookup::LookupConfig>>()?
+                            .into_iter()
+                            .map(AnyModuleConfig::Lookup)
+                            .collect(),
+                        #[cfg(feature = "fst")]
+                        "fst" => map
+                            .next_value::<Vec<fst::FstConfig>>()?
+                            .into_iter()
+                            .map(AnyModuleConfig::Fst)
+                            .collect(),
+                        #[cfg(feature = "analiticcl")]
+                        "analiticcl" => map
+                            .next_value::<Vec<analiticcl::AnaliticclConfig>>()?
+                            .into_iter()
+                            .map(AnyModuleConfig::Analiticcl)
+                            .collect(),
+                        #[cfg(feature = "finalfusion")]
+                        "finalfusion" => map
+                            .next_value::<Vec<finalfusion::FinalFusionConfig>>()?
+                            .into_iter()
+                            .map(AnyModuleConfig::FinalFusion)
+                            .collect(),
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                            continue;
+                        }
+                    };
+                    let position = position.expect("module type");
+                    by_type.extend(configs.into_iter().map(|config| (position, config)));
+                }
+                if !by_type.is_empty() && !modules.is_empty() {
+                    warn!(
+                        "The configuration mixes [[{}]] tables with tables per module type, the latter are used first (by type) regardless of where they are written",
+                        ModuleConfigs::TABLE
+                    );
+                }
+                // stable, so the modules of each type keep their order
+                by_type.sort_by_key(|(position, _)| *position);
+                let mut configs: Vec<AnyModuleConfig> =
+                    by_type.into_iter().map(|(_, config)| config).collect();
+                configs.append(&mut modules);
+                Ok(ModuleConfigs(configs))
+            }
+        }
+
+        deserializer.deserialize_map(ModuleConfigsVisitor)
+    }
 }
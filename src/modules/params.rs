@@ -0,0 +1,125 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use super::Module;
+use crate::QueryParams;
+
+/// The type of a module parameter, along with its valid range
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParamType {
+    Bool,
+    /// A non-negative integer within the range (inclusive)
+    Integer {
+        min: u64,
+        max: u64,
+    },
+    /// A number within the range (inclusive)
+    Float {
+        min: f64,
+        max: f64,
+    },
+    String,
+}
+
+/// Declares a parameter that a module accepts at query time (`module.key=value`), see [`Module::parameters()`].
+/// Passed parameters are validated against these declarations before a query is expanded.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParamSpec {
+    name: &'static str,
+    #[serde(flatten)]
+    kind: ParamType,
+    /// The value used if the parameter is not passed, typically from the module configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    default: Option<Value>,
+    description: &'static str,
+}
+
+impl ParamSpec {
+    pub fn new(name: &'static str, kind: ParamType, description: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            default: None,
+            description,
+        }
+    }
+
+    pub fn integer(name: &'static str, min: u64, max: u64, description: &'static str) -> Self {
+        Self::new(name, ParamType::Integer { min, max }, description)
+    }
+
+    pub fn float(name: &'static str, min: f64, max: f64, description: &'static str) -> Self {
+        Self::new(name, ParamType::Float { min, max }, description)
+    }
+
+    pub fn with_default(mut self, default: impl Into<Value>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn kind(&self) -> ParamType {
+        self.kind
+    }
+
+    pub fn default(&self) -> Option<&Value> {
+        self.default.as_ref()
+    }
+
+    /// Checks a passed value against the type and range, returns what is wrong with it if it is invalid
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let valid = match self.kind {
+            ParamType::Bool => value.is_boolean(),
+            ParamType::Integer { min, max } => value
+                .as_u64()
+                .is_some_and(|value| (min..=max).contains(&value)),
+            ParamType::Float { min, max } => value
+                .as_f64()
+                .is_some_and(|value| (min..=max).contains(&value)),
+            ParamType::String => value.is_string(),
+        };
+        if valid {
+            return Ok(());
+        }
+        Err(match self.kind {
+            ParamType::Bool => "must be true or false".to_owned(),
+            ParamType::Integer { min, max: u64::MAX } => {
+                format!("must be an integer of at least {}", min)
+            }
+            ParamType::Integer { min, max } => {
+                format!("must be an integer from {} to {}", min, max)
+            }
+            ParamType::Float { min, max } if max.is_infinite() => {
+                format!("must be a number of at least {}", min)
+            }
+            ParamType::Float { min, max } => format!("must be a number from {} to {}", min, max),
+            ParamType::String => "must be a string".to_owned(),
+        })
+    }
+}
+
+/// Validates the parameters passed to a module against the parameters it declares, returns all violations
+/// (empty if the parameters are valid)
+pub fn violations(module: &(impl Module + ?Sized), params: &QueryParams) -> Vec<String> {
+    let specs = module.parameters();
+    params
+        .iter_for_module(module.id())
+        .filter_map(
+            |param| match specs.iter().find(|spec| spec.name() == param.key()) {
+                None => Some(format!(
+                    "{}.{}: unknown parameter for this module",
+                    module.id(),
+                    param.key()
+                )),
+                Some(spec) => spec
+                    .check(param.value())
+                    .err()
+                    .map(|problem| format!("{}.{}: {}", module.id(), param.key(), problem)),
+            },
+        )
+        .collect()
+}
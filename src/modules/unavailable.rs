@@ -0,0 +1,138 @@
+use crate::lexer::Term;
+use crate::modules::params::ParamSpec;
+use crate::modules::{CommonModuleConfig, Module};
+use crate::{Error, QueryParams, TermExpansions};
+
+/// Stands in for a module whose data could not be loaded at startup, if the module is not required (see
+/// `required` in the module configuration). The module is still listed, with the error, but every expansion
+/// fails with the error, so the query expander skips it with a warning. Reloading the module may bring it back.
+pub struct UnavailableModule {
+    /// The module that failed to load, only used for its properties
+    module: Box<dyn Module>,
+    error: String,
+}
+
+impl UnavailableModule {
+    pub fn new(module: Box<dyn Module>, error: &Error) -> Self {
+        let error = match error {
+            Error::LoadError(e) => e.clone(),
+            e => e.to_string(),
+        };
+        Self { module, error }
+    }
+}
+
+impl Module for UnavailableModule {
+    fn kind(&self) -> &'static str {
+        self.module.kind()
+    }
+
+    fn id(&self) -> &str {
+        self.module.id()
+    }
+
+    fn name(&self) -> &str {
+        self.module.name()
+    }
+
+    fn common(&self) -> &CommonModuleConfig {
+        self.module.common()
+    }
+
+    fn load(&mut self) -> Result<(), Error> {
+        Err(Error::LoadError(self.error.clone()))
+    }
+
+    fn reinstantiate(&self) -> Option<Box<dyn Module>> {
+        self.module.reinstantiate()
+    }
+
+    fn expand_query(
+        &self,
+        _terms: &Vec<Term>,
+        _params: &QueryParams,
+    ) -> Result<TermExpansions, Error> {
+        Err(Error::LoadError(self.error.clone()))
+    }
+
+    fn is_spelling_oriented(&self) -> bool {
+        self.module.is_spelling_oriented()
+    }
+
+    fn handles_phrases(&self) -> bool {
+        self.module.handles_phrases()
+    }
+
+    fn handles_wildcards(&self) -> bool {
+        self.module.handles_wildcards()
+    }
+
+    fn handles_numbers(&self) -> bool {
+        self.module.handles_numbers()
+    }
+
+    fn max_phrase_words(&self) -> Option<usize> {
+        self.module.max_phrase_words()
+    }
+
+    fn parameters(&self) -> Vec<ParamSpec> {
+        self.module.parameters()
+    }
+
+    fn is_loaded(&self) -> bool {
+        false
+    }
+
+    fn load_error(&self) -> Option<&str> {
+        Some(self.error.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warnings::SkipReason;
+    use crate::{Config, Error, QueryExpander, QueryParams, Term, TermExpansions};
+
+    const CONFIG: &str = r#"
+        [[module]]
+        type = "lookup"
+        id = "lookup"
+        name = "Lookup"
+        file = "test/lookup.tsv"
+
+        [[module]]
+        type = "lookup"
+        id = "broken"
+        name = "Broken"
+        file = "test/nonexistent.tsv"
+        "#;
+
+    #[test]
+    pub fn test001_unavailable_required() {
+        let config: Config = toml::from_str(CONFIG).expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        assert!(matches!(expander.load(), Err(Error::LoadError(_))));
+    }
+
+    #[test]
+    pub fn test002_unavailable_optional() -> Result<(), Error> {
+        let config: Config =
+            toml::from_str(&format!("{}required = false\n", CONFIG)).expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let broken = expander.get_module("broken").expect("module is listed");
+        assert!(!broken.is_loaded());
+        assert!(broken
+            .load_error()
+            .is_some_and(|e| e.contains("nonexistent.tsv")));
+        assert!(broken.reinstantiate().is_some());
+        let terms = vec![Term::Singular("separate")];
+        let mut terms_map = TermExpansions::new();
+        let warnings = expander.expand_query_into(&mut terms_map, &terms, &QueryParams::new())?;
+        assert!(!terms_map["separate"].is_empty());
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].module(), "broken");
+        assert_eq!(warnings[0].reason(), SkipReason::LoadFailed);
+        Ok(())
+    }
+}
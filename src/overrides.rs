@@ -0,0 +1,181 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::globalparams::GlobalParam;
+use crate::{Error, QueryParams};
+
+/// Modules to include or exclude (by ID) for a specific term, as passed in the `term_overrides` parameter
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TermOverride {
+    /// Only these modules expand the term, regardless of the `include` and `exclude` parameters
+    include: Vec<String>,
+    /// These modules do not expand the term
+    exclude: Vec<String>,
+}
+
+impl TermOverride {
+    /// Returns whether a module expands the term, given whether the `include` and `exclude` parameters select it
+    pub fn selects(&self, module_id: &str, selected: bool) -> bool {
+        let included = if self.include.is_empty() {
+            selected
+        } else {
+            self.include.iter().any(|id| id == module_id)
+        };
+        included && !self.exclude.iter().any(|id| id == module_id)
+    }
+
+    /// Returns the IDs of all modules the override refers to
+    pub fn module_ids(&self) -> impl Iterator<Item = &str> {
+        self.include
+            .iter()
+            .chain(self.exclude.iter())
+            .map(|id| id.as_str())
+    }
+}
+
+/// Forces or forbids modules for specific terms, so a single term can be expanded with other modules than the rest
+/// of the query. Passed as a JSON object in the `term_overrides` parameter, mapping terms (as they appear in the
+/// query) to the modules to include and/or exclude, e.g. `{"rembrandt": {"include": ["names"]}}`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct TermOverrides(HashMap<String, TermOverride>);
+
+impl TermOverrides {
+    /// Reads the global `term_overrides` parameter, a JSON object or a string holding one
+    pub fn from_params(params: &QueryParams) -> Result<Self, Error> {
+        let overrides = match params.global(GlobalParam::TermOverrides) {
+            None => return Ok(Self::default()),
+            Some(Value::String(json)) => serde_json::from_str(json),
+            Some(value) => serde_json::from_value(value.clone()),
+        };
+        overrides.map_err(|e| {
+            Error::InvalidParameter(format!(
+                "term_overrides must map terms to modules to include and/or exclude: {}",
+                e
+            ))
+        })
+    }
+
+    /// Returns the override for a term, if any
+    pub fn get(&self, term: &str) -> Option<&TermOverride> {
+        self.0.get(term)
+    }
+
+    /// Returns whether a module expands a term, given whether the `include` and `exclude` parameters select it
+    pub fn selects(&self, module_id: &str, term: &str, selected: bool) -> bool {
+        match self.get(term) {
+            Some(termoverride) => termoverride.selects(module_id, selected),
+            None => selected,
+        }
+    }
+
+    /// Returns whether the override of any term includes the module
+    pub fn includes(&self, module_id: &str) -> bool {
+        self.0
+            .values()
+            .any(|termoverride| termoverride.include.iter().any(|id| id == module_id))
+    }
+
+    /// Returns the IDs of all modules the overrides refer to
+    pub fn module_ids(&self) -> impl Iterator<Item = &str> {
+        self.0
+            .values()
+            .flat_map(|termoverride| termoverride.module_ids())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::lookup::{LookupConfig, LookupModule};
+    use crate::modules::Module;
+    use crate::{QueryExpander, Term, TermStatus};
+
+    fn lookup(id: &str) -> Result<Box<dyn Module>, Error> {
+        let config: LookupConfig = toml::from_str(&format!(
+            "id = \"{}\"\nname = \"{}\"\nfile = \"{}/test/lookup.tsv\"",
+            id,
+            id,
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("valid configuration");
+        let mut module = LookupModule::new(config);
+        module.load()?;
+        Ok(Box::new(module))
+    }
+
+    #[test]
+    pub fn test001_overrides_params() -> Result<(), Error> {
+        assert_eq!(
+            TermOverrides::from_params(&QueryParams::new())?,
+            TermOverrides::default()
+        );
+        let overrides = TermOverrides::from_params(&QueryParams::new().with(
+            "",
+            "term_overrides",
+            r#"{"rembrandt": {"include": ["names"]}, "kaas": {"exclude": ["fst"]}}"#.into(),
+        ))?;
+        assert!(overrides.selects("names", "rembrandt", false));
+        assert!(!overrides.selects("fst", "rembrandt", true));
+        assert!(!overrides.selects("fst", "kaas", true));
+        assert!(overrides.selects("lookup", "kaas", true));
+        assert!(!overrides.selects("lookup", "kaas", false));
+        assert!(overrides.selects("fst", "wijn", true));
+        assert!(overrides.includes("names"));
+        assert!(!overrides.includes("fst"));
+        assert!(TermOverrides::from_params(&QueryParams::new().with(
+            "",
+            "term_overrides",
+            r#"{"kaas": {"only": ["fst"]}}"#.into(),
+        ))
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_overrides_expand() -> Result<(), Error> {
+        let expander = QueryExpander::new()
+            .with_module(lookup("lookup")?)
+            .with_module(lookup("names")?);
+        let terms = vec![Term::Singular("separate"), Term::Singular("divide")];
+        let params = QueryParams::new()
+            .with("", "include", "lookup".into())
+            .with(
+                "",
+                "term_overrides",
+                r#"{"divide": {"include": ["names"]}}"#.into(),
+            );
+        let terms_map = expander.expand_query(&terms, &params)?;
+        let sources = |term: &str| -> Vec<Option<&str>> {
+            terms_map[term]
+                .iter()
+                .map(|termexpansion| termexpansion.source_id())
+                .collect()
+        };
+        assert_eq!(sources("separate"), vec![Some("lookup")]);
+        assert_eq!(sources("divide"), vec![Some("names")]);
+
+        let params = QueryParams::new().with(
+            "",
+            "term_overrides",
+            r#"{"divide": {"exclude": ["lookup", "names"]}}"#.into(),
+        );
+        let terms_map = expander.expand_query(&terms, &params)?;
+        let statuses = expander.term_statuses(&terms, &terms_map, &params);
+        assert_eq!(statuses["separate"], TermStatus::Expanded);
+        assert_eq!(statuses["divide"], TermStatus::ExcludedByModuleFilter);
+
+        let params = QueryParams::new().with(
+            "",
+            "term_overrides",
+            r#"{"divide": {"include": ["nonexistent"]}}"#.into(),
+        );
+        assert!(matches!(
+            expander.expand_query(&terms, &params),
+            Err(Error::ModuleNotFound(_))
+        ));
+        Ok(())
+    }
+}
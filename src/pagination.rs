@@ -0,0 +1,119 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::globalparams::GlobalParam;
+use crate::{Error, QueryParams, TermExpansions};
+
+/// Maps each term to its total number of expansions before pagination
+pub type Totals = HashMap<String, usize>;
+
+/// A page of the expansions of each term, as requested with the `offset` and `limit` parameters
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Pagination {
+    /// Number of expansions to skip
+    offset: usize,
+    /// Maximum number of expansions to return, all remaining ones if not set
+    limit: Option<usize>,
+}
+
+impl Pagination {
+    pub fn new(offset: usize, limit: Option<usize>) -> Self {
+        Self { offset, limit }
+    }
+
+    /// Reads the global `offset` and `limit` parameters, returns None if neither is passed
+    pub fn from_params(params: &QueryParams) -> Result<Option<Self>, Error> {
+        let offset = parameter(params, GlobalParam::Offset)?;
+        let limit = parameter(params, GlobalParam::Limit)?;
+        if offset.is_none() && limit.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(offset.unwrap_or_default(), limit)))
+    }
+
+    /// Retains only the requested page of the expansions of each term, over all modules in order. Scored
+    /// expansions are ordered by score first (see [`crate::TermExpansion::sort_by_score()`]), so pages are the same
+    /// for identical requests. Returns the total number of expansions of each term.
+    pub fn apply(&self, terms_map: &mut TermExpansions) -> Totals {
+        terms_map
+            .iter_mut()
+            .map(|(term, termexpansions)| {
+                let total = termexpansions.iter().map(|x| x.len()).sum();
+                let mut position = 0;
+                for termexpansion in termexpansions.iter_mut() {
+                    termexpansion.sort_by_score();
+                    termexpansion.retain(|_| {
+                        let on_page = position >= self.offset
+                            && self
+                                .limit
+                                .is_none_or(|limit| position < self.offset + limit);
+                        position += 1;
+                        on_page
+                    });
+                }
+                termexpansions.retain(|termexpansion| !termexpansion.is_empty());
+                (term.clone(), total)
+            })
+            .collect()
+    }
+}
+
+/// Returns a global parameter that must be a non-negative integer, if passed
+fn parameter(params: &QueryParams, param: GlobalParam) -> Result<Option<usize>, Error> {
+    let value = match params.global(param) {
+        None => return Ok(None),
+        Some(Value::Number(value)) => value.as_u64().map(|value| value as usize),
+        Some(Value::String(value)) => value.parse().ok(),
+        Some(_) => None,
+    };
+    value
+        .map(Some)
+        .ok_or_else(|| Error::InvalidParameter(format!("{} must be a non-negative integer", param)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_pagination_params() -> Result<(), Error> {
+        assert_eq!(Pagination::from_params(&QueryParams::new())?, None);
+        assert_eq!(
+            Pagination::from_params(&QueryParams::new().with("", "limit", "10".into()))?,
+            Some(Pagination::new(0, Some(10)))
+        );
+        assert_eq!(
+            Pagination::from_params(&QueryParams::new().with("", "offset", 5.into()))?,
+            Some(Pagination::new(5, None))
+        );
+        assert!(
+            Pagination::from_params(&QueryParams::new().with("", "limit", "-1".into())).is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_pagination_apply() {
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "kaas".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["kaes".into(), "caas".into()]),
+                TermExpansion::default()
+                    .with_expansions(vec!["fromage".into(), "cheese".into()])
+                    .with_scores(vec![0.5, 0.5]),
+            ],
+        );
+        terms_map.insert("wijn".into(), vec![]);
+        let totals = Pagination::new(1, Some(2)).apply(&mut terms_map);
+        assert_eq!(totals["kaas"], 4);
+        assert_eq!(totals["wijn"], 0);
+        assert_eq!(terms_map["kaas"].len(), 2);
+        assert_eq!(terms_map["kaas"][0].expansions(), &vec!["caas".to_string()]);
+        assert_eq!(
+            terms_map["kaas"][1].expansions(),
+            &vec!["cheese".to_string()]
+        );
+    }
+}
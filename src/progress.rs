@@ -0,0 +1,263 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+thread_local! {
+    /// The tracker and the ID of the module being loaded on this thread, if any
+    static CURRENT: RefCell<Option<(Arc<LoadProgress>, String)>> = const { RefCell::new(None) };
+}
+
+/// Modules that read line-based files report their progress every this many lines
+pub const REPORT_INTERVAL: u64 = 10000;
+
+/// The status of a module while the service starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LoadStatus {
+    /// Waiting for other modules to be loaded first
+    Pending,
+    Loading,
+    /// Loaded, or to be loaded on first use (lazy modules)
+    Ready,
+    /// The data could not be loaded, the module is unavailable
+    Failed,
+}
+
+/// The progress of loading a single module
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct ModuleProgress {
+    id: String,
+    status: LoadStatus,
+    /// The number of lines read so far, for modules that read line-based files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lines: Option<u64>,
+    /// The fraction of the data read so far (0 to 1), if the size of the data is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fraction: Option<f64>,
+    /// Why loading failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl ModuleProgress {
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    pub fn status(&self) -> LoadStatus {
+        self.status
+    }
+
+    pub fn lines(&self) -> Option<u64> {
+        self.lines
+    }
+
+    pub fn fraction(&self) -> Option<f64> {
+        self.fraction
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+}
+
+/// Tracks the loading of the modules of a query expander, so the progress of a long startup can be followed while
+/// it happens (see [`crate::QueryExpander::load_shared_with_progress()`])
+#[derive(Debug, Default)]
+pub struct LoadProgress {
+    modules: Mutex<Vec<ModuleProgress>>,
+    ready: AtomicBool,
+}
+
+impl LoadProgress {
+    /// Tracks the modules with the given IDs, all pending
+    pub fn new<'a>(ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let progress = Self::default();
+        for id in ids {
+            progress.set_status(id, LoadStatus::Pending);
+        }
+        progress
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut ModuleProgress)) {
+        let mut modules = self.modules.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(module) = modules.iter_mut().find(|module| module.id == id) {
+            f(module)
+        } else {
+            let mut module = ModuleProgress {
+                id: id.to_owned(),
+                status: LoadStatus::Pending,
+                lines: None,
+                fraction: None,
+                error: None,
+            };
+            f(&mut module);
+            modules.push(module);
+        }
+    }
+
+    /// Marks a module as loading (again) when its data is reloaded, the current version keeps serving meanwhile.
+    /// Returns false if the module is already loading, so the same data is not loaded twice at once.
+    pub fn begin(&self, id: &str) -> bool {
+        let mut begun = false;
+        self.update(id, |module| {
+            if module.status != LoadStatus::Loading {
+                *module = ModuleProgress {
+                    id: module.id.clone(),
+                    status: LoadStatus::Loading,
+                    lines: None,
+                    fraction: None,
+                    error: None,
+                };
+                begun = true;
+            }
+        });
+        begun
+    }
+
+    /// Returns the progress of a single module, if tracked
+    pub fn get(&self, id: &str) -> Option<ModuleProgress> {
+        self.modules
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|module| module.id == id)
+            .cloned()
+    }
+
+    pub fn set_status(&self, id: &str, status: LoadStatus) {
+        self.update(id, |module| module.status = status)
+    }
+
+    /// Marks a module as failed, with the error
+    pub fn fail(&self, id: &str, error: impl Into<String>) {
+        self.update(id, |module| {
+            module.status = LoadStatus::Failed;
+            module.error = Some(error.into());
+        })
+    }
+
+    /// Returns the progress of each module, in the order in which they are loaded
+    pub fn modules(&self) -> Vec<ModuleProgress> {
+        self.modules
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Marks the startup as complete
+    pub fn set_ready(&self) {
+        self.ready.store(true, Ordering::Relaxed)
+    }
+
+    /// Returns whether the startup is complete
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+
+    /// Runs a function (loading a module) with this tracker as the current one for this thread, so the progress
+    /// reported by the module (see [`report()`]) is recorded for it
+    pub fn scope<T>(self: &Arc<Self>, id: &str, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<(Arc<LoadProgress>, String)>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+            }
+        }
+        let _restore = Restore(
+            CURRENT.with(|current| current.borrow_mut().replace((self.clone(), id.to_owned()))),
+        );
+        f()
+    }
+}
+
+/// Reports the progress of reading a line-based file while a module is loaded: the lines and bytes read so far and
+/// the size of the file, if known. Does nothing if no progress is tracked on this thread. Modules should call this
+/// periodically (e.g. every [`REPORT_INTERVAL`] lines) rather than for every line.
+pub fn report(lines: u64, bytes: u64, total: Option<u64>) {
+    CURRENT.with(|current| {
+        if let Some((progress, id)) = current.borrow().as_ref() {
+            progress.update(id, |module| {
+                module.lines = Some(lines);
+                module.fraction = total
+                    .filter(|total| *total > 0)
+                    .map(|total| (bytes as f64 / total as f64).min(1.0));
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Error, QueryExpander};
+
+    #[test]
+    pub fn test001_progress_load() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [[module]]
+            type = "lookup"
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+
+            [[module]]
+            type = "lookup"
+            id = "broken"
+            name = "Broken"
+            file = "test/nonexistent.tsv"
+            required = false
+            "#,
+        )
+        .expect("config must parse");
+        let progress = Arc::new(LoadProgress::new(["lookup", "broken"]));
+        assert!(progress
+            .modules()
+            .iter()
+            .all(|module| module.status() == LoadStatus::Pending));
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load_shared_with_progress(&mut crate::ModulePool::new(), &progress)?;
+        let modules = progress.modules();
+        assert_eq!(modules[0].id(), "lookup");
+        assert_eq!(modules[0].status(), LoadStatus::Ready);
+        assert!(modules[0].lines().is_some_and(|lines| lines > 0));
+        assert_eq!(modules[0].fraction(), Some(1.0));
+        assert_eq!(modules[1].status(), LoadStatus::Failed);
+        assert!(modules[1]
+            .error()
+            .is_some_and(|e| e.contains("nonexistent.tsv")));
+        // the expander does not decide when the service is ready, e.g. warm-up queries may follow
+        assert!(!progress.is_ready());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_progress_report_outside_scope() {
+        // no tracker on this thread: nothing happens
+        report(10, 100, Some(1000));
+        let progress = Arc::new(LoadProgress::default());
+        progress.scope("lookup", || report(10, 100, Some(1000)));
+        report(20, 200, Some(1000));
+        assert_eq!(progress.modules()[0].lines(), Some(10));
+        assert_eq!(progress.modules()[0].fraction(), Some(0.1));
+    }
+
+    #[test]
+    pub fn test003_progress_reload() {
+        let progress = LoadProgress::new(["lookup"]);
+        progress.fail("lookup", "missing file");
+        assert!(progress.begin("lookup"));
+        assert_eq!(progress.get("lookup").and_then(|m| m.error), None);
+        // already reloading
+        assert!(!progress.begin("lookup"));
+        progress.set_status("lookup", LoadStatus::Ready);
+        assert!(progress.begin("lookup"));
+        assert_eq!(progress.get("nonexistent"), None);
+    }
+}
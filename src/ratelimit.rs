@@ -0,0 +1,191 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Configuration for per-client rate limiting using a token bucket
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// Number of requests per second a client may sustain (the refill rate of the bucket)
+    rate: f64,
+
+    /// Maximum number of requests a client may issue in a burst (the capacity of the bucket)
+    burst: u32,
+
+    /// Identify clients by IP address or by API key
+    #[serde(default)]
+    by: RateLimitBy,
+
+    /// Take the client IP from the X-Forwarded-For header (the address the proxy appended last), only enable this
+    /// behind a trusted reverse proxy
+    #[serde(default)]
+    trust_forwarded_for: bool,
+
+    /// Maximum number of clients to keep a bucket for, the least recently seen ones are forgotten beyond that
+    #[serde(default = "default_max_clients")]
+    max_clients: usize,
+}
+
+fn default_max_clients() -> usize {
+    10000
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBy {
+    /// Identify clients by their IP address
+    #[default]
+    Ip,
+    /// Identify clients by their API key, falls back to the IP address for clients without a valid key
+    Key,
+}
+
+impl RateLimitConfig {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            by: RateLimitBy::default(),
+            trust_forwarded_for: false,
+            max_clients: default_max_clients(),
+        }
+    }
+
+    /// Set the maximum number of clients to keep a bucket for
+    pub fn with_max_clients(mut self, max_clients: usize) -> Self {
+        self.max_clients = max_clients.max(1);
+        self
+    }
+
+    pub fn by(&self) -> RateLimitBy {
+        self.by
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.trust_forwarded_for
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    last: Instant,
+}
+
+/// A token-bucket rate limiter keeping one bucket per client
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Check whether the client may issue a request now, consumes a token if so.
+    pub fn check(&self, client: &str) -> bool {
+        self.check_at(client, Instant::now())
+    }
+
+    /// Check whether the client may issue a request at the given time, consumes a token if so.
+    pub fn check_at(&self, client: &str, now: Instant) -> bool {
+        let capacity = self.config.burst as f64;
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= self.config.max_clients && !buckets.contains_key(client) {
+            self.purge(&mut buckets, now);
+        }
+        let bucket = buckets.entry(client.to_owned()).or_insert(Bucket {
+            tokens: capacity,
+            last: now,
+        });
+        let elapsed = now.saturating_duration_since(bucket.last).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.rate).min(capacity);
+        bucket.last = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Makes room for new clients: forgets the buckets that have been refilled completely (nothing is lost by that)
+    /// and, if that is not enough, the least recently seen clients. Frees half of the buckets at once, so a flood of
+    /// new clients only purges once every `max_clients / 2` requests.
+    fn purge(&self, buckets: &mut HashMap<String, Bucket>, now: Instant) {
+        let capacity = self.config.burst as f64;
+        let rate = self.config.rate;
+        buckets.retain(|_, bucket| {
+            bucket.tokens + now.saturating_duration_since(bucket.last).as_secs_f64() * rate
+                < capacity
+        });
+        let keep = self.config.max_clients / 2;
+        if keep == 0 {
+            buckets.clear();
+        } else if buckets.len() > keep {
+            let mut clients: Vec<(Instant, String)> = buckets
+                .iter()
+                .map(|(client, bucket)| (bucket.last, client.clone()))
+                .collect();
+            let forget = clients.len() - keep;
+            clients.select_nth_unstable_by_key(forget - 1, |(last, _)| *last);
+            for (_, client) in &clients[..forget] {
+                buckets.remove(client);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    pub fn test001_ratelimit_burst() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(1.0, 3));
+        let now = Instant::now();
+        assert!(limiter.check_at("a", now));
+        assert!(limiter.check_at("a", now));
+        assert!(limiter.check_at("a", now));
+        assert!(!limiter.check_at("a", now), "bucket must be empty");
+        assert!(limiter.check_at("b", now), "other clients are unaffected");
+    }
+
+    #[test]
+    pub fn test002_ratelimit_refill() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(2.0, 1));
+        let now = Instant::now();
+        assert!(limiter.check_at("a", now));
+        assert!(!limiter.check_at("a", now + Duration::from_millis(100)));
+        assert!(limiter.check_at("a", now + Duration::from_millis(600)));
+    }
+
+    #[test]
+    pub fn test003_ratelimit_max_clients() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(1.0, 2).with_max_clients(4));
+        let now = Instant::now();
+        for (i, client) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            let now = now + Duration::from_millis(i as u64);
+            assert!(limiter.check_at(client, now));
+            assert!(limiter.check_at(client, now));
+        }
+        // "d" was seen last, so it keeps its empty bucket while the oldest clients are forgotten
+        let now = now + Duration::from_millis(10);
+        assert!(limiter.check_at("e", now));
+        assert!(limiter.buckets.lock().unwrap().len() <= 4);
+        assert!(!limiter.check_at("d", now), "bucket must be empty");
+        assert!(limiter.check_at("a", now), "forgotten clients start afresh");
+        for i in 0..1000 {
+            limiter.check_at(&format!("flood{}", i), now);
+            assert!(limiter.buckets.lock().unwrap().len() <= 4);
+        }
+    }
+}
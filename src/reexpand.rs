@@ -0,0 +1,212 @@
+use serde::Deserialize;
+
+use crate::api::ApiResponse;
+use crate::coverage::Coverage;
+use crate::reproducibility::Reproducibility;
+use crate::{Error, QueryContext, QueryExpander, QueryParams, Term, TermExpansions};
+
+/// The parts of an earlier query expansion response that a re-expansion builds on, other fields are ignored
+#[derive(Debug, Clone, Deserialize)]
+struct PreviousExpansion {
+    terms: TermExpansions,
+    original_query: String,
+}
+
+/// Re-runs part of an earlier query expansion: only the given terms are expanded again, only by the given modules,
+/// and the result is merged into the earlier response. This keeps interactive tuning of a single module parameter
+/// cheap, as the other modules and terms need not be consulted again.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Reexpansion {
+    /// The earlier query expansion response
+    response: PreviousExpansion,
+    /// The terms to expand again, all terms of the query if empty
+    #[serde(default)]
+    terms: Vec<String>,
+    /// The modules (by ID) to run again, all selected modules if empty
+    #[serde(default)]
+    modules: Vec<String>,
+}
+
+impl Reexpansion {
+    pub fn new(terms_map: TermExpansions, original_query: impl Into<String>) -> Self {
+        Self {
+            response: PreviousExpansion {
+                terms: terms_map,
+                original_query: original_query.into(),
+            },
+            terms: Vec::new(),
+            modules: Vec::new(),
+        }
+    }
+
+    /// Only expand these terms again (builder pattern)
+    pub fn with_terms(mut self, terms: Vec<String>) -> Self {
+        self.terms = terms;
+        self
+    }
+
+    /// Only run these modules again (builder pattern)
+    pub fn with_modules(mut self, modules: Vec<String>) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// Returns whether a module is run again
+    fn reruns(&self, module_id: &str) -> bool {
+        self.modules.is_empty() || self.modules.iter().any(|id| id == module_id)
+    }
+
+    /// Expands the terms again with the modules and the (new) parameters, and returns the earlier response with
+    /// the expansions of these modules for these terms replaced. The expanded query, the term statuses and other
+    /// derived parts of the response are computed anew from the merged expansions.
+    pub fn apply(
+        &self,
+        expander: &QueryExpander,
+        params: &QueryParams,
+    ) -> Result<ApiResponse, Error> {
+        let querystring = self.response.original_query.as_str();
+        expander.config().limits().check_query(querystring)?;
+        let (terms, _) = Term::extract_from_query(querystring);
+        if let Some(term) = self
+            .terms
+            .iter()
+            .find(|term| !terms.iter().any(|t| t.as_str() == term.as_str()))
+        {
+            return Err(Error::InvalidParameter(format!(
+                "Term {} does not occur in the query",
+                term
+            )));
+        }
+        if let Some(id) = self
+            .modules
+            .iter()
+            .find(|id| expander.get_module(id).is_none())
+        {
+            return Err(Error::ModuleNotFound(format!("No such module: {}", id)));
+        }
+        let terms: Vec<Term> = terms
+            .into_iter()
+            .filter(|term| self.terms.is_empty() || self.terms.iter().any(|t| t == term.as_str()))
+            .collect();
+        let reproducibility = Reproducibility::new(expander, params)?;
+        let coverage = Coverage::from_params(params)?;
+        let context = QueryContext::from_query(querystring);
+        let mut fresh = TermExpansions::new();
+        let warnings =
+            expander.expand_query_filtered(&mut fresh, &terms, &context, params, |module| {
+                self.reruns(module.id())
+            })?;
+        let mut terms_map = self.response.terms.clone();
+        for term in terms.iter() {
+            let expansions = terms_map.entry(term.as_str().to_owned()).or_default();
+            // expansions without a source are kept, unless all modules are run again
+            expansions.retain(|termexpansion| {
+                !termexpansion
+                    .source_id()
+                    .map_or(self.modules.is_empty(), |id| self.reruns(id))
+            });
+            if let Some(moduleexpansions) = fresh.remove(term.as_str()) {
+                expansions.extend(moduleexpansions);
+            }
+            // in the order of the modules, as in a complete expansion
+            expansions.sort_by_key(|termexpansion| {
+                termexpansion
+                    .source_id()
+                    .and_then(|id| expander.modules().position(|module| module.id() == id))
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        Ok(ApiResponse::from_expansions(
+            expander,
+            querystring,
+            terms_map,
+            params,
+            coverage,
+            reproducibility,
+        )?
+        .with_warnings(warnings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::lookup::{LookupConfig, LookupModule};
+    use crate::modules::Module;
+
+    fn lookup(id: &str) -> Result<Box<dyn Module>, Error> {
+        let config: LookupConfig = toml::from_str(&format!(
+            "id = \"{}\"\nname = \"{}\"\nfile = \"{}/test/lookup.tsv\"",
+            id,
+            id,
+            env!("CARGO_MANIFEST_DIR")
+        ))
+        .expect("valid configuration");
+        let mut module = LookupModule::new(config);
+        module.load()?;
+        Ok(Box::new(module))
+    }
+
+    /// Returns the sources of the expansions of a term in a response
+    fn sources(response: &serde_json::Value, term: &str) -> Vec<String> {
+        response["terms"][term]
+            .as_array()
+            .expect("term in response")
+            .iter()
+            .map(|termexpansion| {
+                termexpansion["source_id"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    pub fn test001_reexpand() -> Result<(), Error> {
+        let expander = QueryExpander::new()
+            .with_module(lookup("lookup")?)
+            .with_module(lookup("names")?);
+        let params = QueryParams::new().with("", "include", "lookup".into());
+        let response = ApiResponse::expand(&expander, "separate divide", &params)?;
+        let previous = serde_json::to_value(&response).expect("serializable");
+        assert_eq!(sources(&previous, "divide"), vec!["lookup"]);
+
+        // run all selected modules again for one term only
+        let reexpansion: Reexpansion = serde_json::from_value(serde_json::json!({
+            "response": previous,
+            "terms": ["divide"],
+        }))
+        .expect("valid request");
+        let merged = serde_json::to_value(reexpansion.apply(&expander, &QueryParams::new())?)
+            .expect("serializable");
+        assert_eq!(sources(&merged, "divide"), vec!["lookup", "names"]);
+        assert_eq!(sources(&merged, "separate"), vec!["lookup"]);
+
+        // run one module again, with a module filter that excludes it
+        let reexpansion = Reexpansion::new(
+            serde_json::from_value(merged["terms"].clone()).expect("valid expansions"),
+            "separate divide",
+        )
+        .with_modules(vec!["names".into()]);
+        let params = QueryParams::new().with("", "exclude", "names".into());
+        let merged =
+            serde_json::to_value(reexpansion.apply(&expander, &params)?).expect("serializable");
+        assert_eq!(sources(&merged, "divide"), vec!["lookup"]);
+
+        assert!(matches!(
+            reexpansion
+                .clone()
+                .with_terms(vec!["unite".into()])
+                .apply(&expander, &params),
+            Err(Error::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            reexpansion
+                .with_modules(vec!["nonexistent".into()])
+                .apply(&expander, &params),
+            Err(Error::ModuleNotFound(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use serde_json::{json, Map, Value};
+use std::str::FromStr;
+
+use crate::api::ApiResponse;
+use crate::{Error, Term};
+
+/// Search engines that don't take Lucene syntax, for which the expansions can be rendered as search parameters instead.
+/// Neither supports disjunctions in the query string, so the expansions are rendered as synonyms, which the search engine
+/// applies to the (unexpanded) query.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchEngine {
+    /// Renders `q` and a list of one-way synonym objects (`id`, `root`, `synonyms`) as upserted into a Typesense collection
+    Typesense,
+    /// Renders `q` and a synonyms object (term to variants) as set in the settings of a Meilisearch index
+    Meilisearch,
+}
+
+impl FromStr for SearchEngine {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "typesense" => Ok(Self::Typesense),
+            "meilisearch" | "meili" => Ok(Self::Meilisearch),
+            _ => Err(Error::InvalidParameter(format!(
+                "Unknown search engine: {} (expected typesense or meilisearch)",
+                s
+            ))),
+        }
+    }
+}
+
+impl SearchEngine {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Typesense => "typesense",
+            Self::Meilisearch => "meilisearch",
+        }
+    }
+
+    /// Renders a query expansion response as search parameters for this search engine
+    pub fn render(&self, response: &ApiResponse) -> Result<Value, Error> {
+        let ApiResponse::QueryExpansion {
+            terms,
+            original_query,
+            ..
+        } = response
+        else {
+            return Err(Error::InvalidParameter(
+                "Only query expansion responses can be rendered".into(),
+            ));
+        };
+        let (queryterms, _) = Term::extract_from_query(original_query);
+        let mut q = Vec::new();
+        // term and its variants, in query order, without duplicates
+        let mut synonyms: Vec<(&str, Vec<&str>)> = Vec::new();
+        for term in queryterms.iter() {
+            q.push(match term {
+                Term::Phrase(phrase) => format!("\"{}\"", phrase),
+                term => term.as_str().to_owned(),
+            });
+            if synonyms.iter().any(|(t, _)| *t == term.as_str()) {
+                continue;
+            }
+            let mut variants: Vec<&str> = Vec::new();
+            for variant in terms
+                .get(term.as_str())
+                .into_iter()
+                .flatten()
+                .flat_map(|termexpansion| termexpansion.iter())
+            {
+                if variant != term.as_str() && !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+            }
+            if !variants.is_empty() {
+                synonyms.push((term.as_str(), variants));
+            }
+        }
+        let q = q.join(" ");
+        Ok(match self {
+            Self::Typesense => json!({
+                "q": q,
+                "synonyms": synonyms
+                    .into_iter()
+                    .map(|(term, variants)| json!({
+                        "id": synonym_id(term),
+                        "root": term,
+                        "synonyms": variants,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+            Self::Meilisearch => json!({
+                "q": q,
+                "synonyms": synonyms
+                    .into_iter()
+                    .map(|(term, variants)| (term.to_owned(), Value::from(variants)))
+                    .collect::<Map<_, _>>(),
+            }),
+        })
+    }
+}
+
+/// An identifier for the synonym of a term that is safe to use in a URL path
+fn synonym_id(term: &str) -> String {
+    let mut id = String::from("kweepeer-");
+    for c in term.chars() {
+        if c.is_alphanumeric() {
+            id.extend(c.to_lowercase());
+        } else {
+            id.push('-');
+        }
+    }
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TermExpansion, TermExpansions};
+
+    fn response() -> ApiResponse {
+        let mut terms = TermExpansions::new();
+        terms.insert(
+            "wine".into(),
+            vec![TermExpansion::default().with_expansions(vec!["wijn".into(), "wine".into()])],
+        );
+        terms.insert(
+            "red grape".into(),
+            vec![TermExpansion::default().with_expansions(vec!["blauwe druif".into()])],
+        );
+        terms.insert("cheese".into(), vec![]);
+        ApiResponse::new_queryexpansion(
+            terms,
+            "wine AND \"red grape\" AND cheese",
+            "{{wine}} AND {{red grape}} AND {{cheese}}",
+            "",
+        )
+    }
+
+    #[test]
+    pub fn test001_render_typesense() -> Result<(), Error> {
+        let value = SearchEngine::from_str("typesense")?.render(&response())?;
+        assert_eq!(
+            value,
+            json!({
+                "q": "wine \"red grape\" cheese",
+                "synonyms": [
+                    {"id": "kweepeer-wine", "root": "wine", "synonyms": ["wijn"]},
+                    {"id": "kweepeer-red-grape", "root": "red grape", "synonyms": ["blauwe druif"]},
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_render_meilisearch() -> Result<(), Error> {
+        let value = SearchEngine::Meilisearch.render(&response())?;
+        assert_eq!(
+            value,
+            json!({
+                "q": "wine \"red grape\" cheese",
+                "synonyms": {"wine": ["wijn"], "red grape": ["blauwe druif"]}
+            })
+        );
+        assert!(SearchEngine::from_str("solr").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::globalparams::GlobalParam;
+use crate::{Error, QueryExpander, QueryParams};
+
+/// Everything needed to reproduce an expansion later: the software version, the seed and the version of the data
+/// of each module that was consulted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Reproducibility {
+    /// The version of kweepeer
+    pub version: String,
+
+    /// The seed for modules with nondeterministic results, as passed in the `seed` parameter
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Maps the ID of each consulted module to the version of its data (a SHA-256 checksum)
+    #[serde(default)]
+    pub modules: BTreeMap<String, Option<String>>,
+}
+
+impl Reproducibility {
+    /// Collects the reproducibility information for the modules selected by the parameters
+    pub fn new(expander: &QueryExpander, params: &QueryParams) -> Result<Self, Error> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            seed: seed(params)?,
+            modules: expander
+                .selected_modules(params)
+                .map(|module| {
+                    (
+                        module.id().to_owned(),
+                        module.data_version().map(|version| version.to_owned()),
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Returns the global `seed` parameter, if passed, which must be a non-negative integer
+pub fn seed(params: &QueryParams) -> Result<Option<u64>, Error> {
+    let seed = match params.global(GlobalParam::Seed) {
+        None => return Ok(None),
+        Some(Value::Number(seed)) => seed.as_u64(),
+        Some(Value::String(seed)) => seed.parse().ok(),
+        Some(_) => None,
+    };
+    seed.map(Some)
+        .ok_or_else(|| Error::InvalidParameter("seed must be a non-negative integer".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_reproducibility_seed() -> Result<(), Error> {
+        assert_eq!(seed(&QueryParams::new())?, None);
+        assert_eq!(
+            seed(&QueryParams::new().with("", "seed", "42".into()))?,
+            Some(42)
+        );
+        assert_eq!(
+            seed(&QueryParams::new().with("", "seed", 7.into()))?,
+            Some(7)
+        );
+        assert!(seed(&QueryParams::new().with("", "seed", "-1".into())).is_err());
+        Ok(())
+    }
+}
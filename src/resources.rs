@@ -0,0 +1,245 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// A resource (a file read by a module) that is referenced by URL rather than by a local path.
+/// The URL may carry the expected SHA-256 checksum in its fragment, e.g. `https://example.org/lexicon.tsv#sha256=...`.
+/// Remote resources are downloaded into the cache directory by `kweepeer fetch`, modules only ever read the cached copy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    url: String,
+    checksum: Option<String>,
+}
+
+impl Resource {
+    /// Interprets a configured path as a resource, returns `None` if it is not an http(s) URL
+    pub fn parse(path: &Path) -> Option<Self> {
+        let s = path.to_str()?;
+        if !s.starts_with("http://") && !s.starts_with("https://") {
+            return None;
+        }
+        match s.split_once("#sha256=") {
+            Some((url, checksum)) => Some(Self {
+                url: url.to_owned(),
+                checksum: Some(checksum.to_lowercase()),
+            }),
+            None => Some(Self {
+                url: s.to_owned(),
+                checksum: None,
+            }),
+        }
+    }
+
+    /// The URL to download the resource from (without checksum)
+    pub fn url(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// The expected SHA-256 checksum (hexadecimal), if specified
+    pub fn checksum(&self) -> Option<&str> {
+        self.checksum.as_deref()
+    }
+
+    /// Sets the expected SHA-256 checksum (builder pattern), e.g. from the `sha256` of the module
+    pub fn with_checksum(mut self, checksum: &str) -> Self {
+        self.checksum = Some(checksum.to_lowercase());
+        self
+    }
+
+    /// The path of the resource in the cache directory. The file name is prefixed
+    /// with a hash of the URL so resources with the same name don't clash.
+    pub fn cache_path(&self, cache_dir: &Path) -> PathBuf {
+        let hash = format!("{:x}", Sha256::digest(self.url.as_bytes()));
+        let filename = self
+            .url
+            .split(['?', '#'])
+            .next()
+            .and_then(|url| url.rsplit('/').next())
+            .filter(|filename| !filename.is_empty())
+            .unwrap_or("resource");
+        cache_dir.join(format!("{}-{}", &hash[..16], filename))
+    }
+
+    /// Checks the checksum of a (downloaded) file against the expected checksum, if any.
+    /// Returns the actual checksum.
+    pub fn verify(&self, path: &Path) -> Result<String, Error> {
+        let checksum = sha256_file(path)?;
+        match self.checksum.as_deref() {
+            Some(expected) if expected != checksum => Err(Error::LoadError(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                self.url, expected, checksum
+            ))),
+            _ => Ok(checksum),
+        }
+    }
+}
+
+/// The expected SHA-256 checksums of the data files of a module (`sha256` in its configuration): either a single
+/// checksum for a module that reads only one file, or a table mapping file names to checksums
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum Checksums {
+    Single(String),
+    PerFile(HashMap<String, String>),
+}
+
+impl Checksums {
+    /// Returns the expected checksum of one of the files of a module, if specified
+    pub fn expected(&self, files: &[&Path], file: &Path) -> Option<&str> {
+        match self {
+            Self::Single(checksum) if files.len() == 1 => Some(checksum.as_str()),
+            Self::Single(_) => None,
+            Self::PerFile(checksums) => checksums
+                .iter()
+                .find(|(name, _)| has_name(file, name))
+                .map(|(_, checksum)| checksum.as_str()),
+        }
+    }
+
+    /// Checks that the checksums refer to files the module actually reads, so a typo does not silently disable
+    /// verification
+    pub fn validate(&self, files: &[&Path]) -> Result<(), Error> {
+        match self {
+            Self::Single(_) if files.len() != 1 => Err(Error::InvalidConfig(
+                "sha256 must map file names to checksums for a module that reads several files"
+                    .into(),
+            )),
+            Self::Single(_) => Ok(()),
+            Self::PerFile(checksums) => {
+                for name in checksums.keys() {
+                    if !files.iter().any(|file| has_name(file, name)) {
+                        return Err(Error::InvalidConfig(format!(
+                            "sha256 lists {}, which is not a file of the module",
+                            name
+                        )));
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Verifies a file against its expected checksum, if any. Returns the actual checksum and whether it was
+    /// verified.
+    pub fn verify(&self, files: &[&Path], file: &Path) -> Result<(String, bool), Error> {
+        let checksum = sha256_file(file)?;
+        match self.expected(files, file) {
+            Some(expected) if !expected.eq_ignore_ascii_case(&checksum) => {
+                Err(Error::LoadError(format!(
+                    "Checksum mismatch for {}: expected {}, got {}, the file may be corrupted or truncated",
+                    file.display(),
+                    expected.to_lowercase(),
+                    checksum
+                )))
+            }
+            Some(_) => Ok((checksum, true)),
+            None => Ok((checksum, false)),
+        }
+    }
+}
+
+/// Returns whether a file has the given name, also for the cached copy of a remote resource, whose name is prefixed
+/// (see [`Resource::cache_path()`])
+fn has_name(file: &Path, name: &str) -> bool {
+    file.file_name()
+        .and_then(|filename| filename.to_str())
+        .is_some_and(|filename| {
+            filename == name
+                || filename.split_once('-').is_some_and(|(prefix, filename)| {
+                    prefix.len() == 16
+                        && prefix.bytes().all(|b| b.is_ascii_hexdigit())
+                        && filename == name
+                })
+        })
+}
+
+/// Computes the SHA-256 checksum of a file (hexadecimal)
+pub fn sha256_file(path: &Path) -> Result<String, Error> {
+    let error =
+        |e: std::io::Error| Error::LoadError(format!("Unable to read {}: {}", path.display(), e));
+    let mut reader = BufReader::new(File::open(path).map_err(error)?);
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut reader, &mut hasher).map_err(error)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The default cache directory for downloaded resources: `$XDG_CACHE_HOME/kweepeer` or `$HOME/.cache/kweepeer`
+pub fn default_cache_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(dir).join("kweepeer")
+    } else if let Some(home) = std::env::var_os("HOME").filter(|dir| !dir.is_empty()) {
+        PathBuf::from(home).join(".cache").join("kweepeer")
+    } else {
+        PathBuf::from(".kweepeer-cache")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_resource_parse() {
+        assert_eq!(Resource::parse(Path::new("test/lookup.tsv")), None);
+        let resource = Resource::parse(Path::new(
+            "https://example.org/data/lexicon.tsv#sha256=ABC123",
+        ))
+        .expect("must be a resource");
+        assert_eq!(resource.url(), "https://example.org/data/lexicon.tsv");
+        assert_eq!(resource.checksum(), Some("abc123"));
+        let path = resource.cache_path(Path::new("/cache"));
+        assert!(path.starts_with("/cache"));
+        assert!(path.to_string_lossy().ends_with("-lexicon.tsv"));
+    }
+
+    #[test]
+    pub fn test002_resource_verify() -> Result<(), Error> {
+        let mut testfile = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        testfile.push("test");
+        testfile.push("lookup.tsv");
+        let checksum = sha256_file(&testfile)?;
+        let url = format!("https://example.org/lookup.tsv#sha256={}", checksum);
+        let resource = Resource::parse(Path::new(&url)).expect("must be a resource");
+        assert_eq!(resource.verify(&testfile)?, checksum);
+        let resource = Resource::parse(Path::new("https://example.org/lookup.tsv#sha256=00"))
+            .expect("must be a resource");
+        assert!(resource.verify(&testfile).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_checksums() -> Result<(), Error> {
+        let mut testfile = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        testfile.push("test");
+        testfile.push("lookup.tsv");
+        let checksum = sha256_file(&testfile)?;
+        let files = [testfile.as_path()];
+        let checksums = Checksums::Single(checksum.to_uppercase());
+        checksums.validate(&files)?;
+        assert_eq!(
+            checksums.verify(&files, &testfile)?,
+            (checksum.clone(), true)
+        );
+        assert!(checksums
+            .validate(&[testfile.as_path(), Path::new("other.tsv")])
+            .is_err());
+        let checksums: Checksums =
+            serde_json::from_str(r#"{"lookup.tsv": "00"}"#).expect("valid checksums");
+        checksums.validate(&files)?;
+        assert!(matches!(
+            checksums.verify(&files, &testfile),
+            Err(Error::LoadError(_))
+        ));
+        assert!(checksums.validate(&[Path::new("other.tsv")]).is_err());
+        let resource = Resource::parse(Path::new("https://example.org/lookup.tsv"))
+            .expect("must be a resource");
+        let cached = resource.cache_path(Path::new("/cache"));
+        assert_eq!(checksums.expected(&[cached.as_path()], &cached), Some("00"));
+        Ok(())
+    }
+}
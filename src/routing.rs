@@ -0,0 +1,254 @@
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::lexer;
+use crate::Error;
+
+/// A built-in class of terms that routing rules can match on, see [`TermClass::matches()`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TermClass {
+    /// Digits, possibly with decimal separators, e.g. `1648` or `3,5`
+    Number,
+    /// Four digits, e.g. `1648`
+    Year,
+    /// At least two capitals and nothing else but periods, e.g. `VOC` or `N.V.`
+    Acronym,
+    /// A web address, e.g. `https://www.knaw.nl` or `www.knaw.nl`
+    Url,
+    /// Capitalised words, as names of persons and places, e.g. `Rembrandt` or `Jan Pietersz Coen`
+    Name,
+}
+
+impl TermClass {
+    /// Returns true if the term is of this class
+    pub fn matches(&self, term: &str) -> bool {
+        match self {
+            Self::Number => {
+                term.chars().any(|c| c.is_ascii_digit())
+                    && term
+                        .chars()
+                        .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+            }
+            Self::Year => term.len() == 4 && term.chars().all(|c| c.is_ascii_digit()),
+            Self::Acronym => lexer::is_acronym(term),
+            Self::Url => {
+                let term = term.to_lowercase();
+                term.starts_with("http://")
+                    || term.starts_with("https://")
+                    || term.starts_with("www.")
+            }
+            Self::Name => term.split_whitespace().all(|word| {
+                let mut chars = word.chars();
+                chars.next().is_some_and(|c| c.is_uppercase())
+                    && chars.clone().next().is_some()
+                    && chars.all(|c| c.is_lowercase() || c == '-' || c == '\'')
+            }),
+        }
+    }
+}
+
+/// A routing rule as written in the configuration, see [`Route`]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RouteConfig {
+    #[serde(default)]
+    class: Option<TermClass>,
+    #[serde(default)]
+    pattern: Option<String>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+/// Routes the terms of a class (e.g. years or acronyms) to a subset of the modules, as sending them through modules
+/// that can not do anything sensible with them (e.g. embeddings or fuzzy matchers) only produces noise. Configured as
+/// `[[route]]` blocks, with a built-in `class` and/or a regular expression `pattern` (a term must match both if both
+/// are given) and the modules to `include` and/or `exclude`. The first rule that matches a term applies.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RouteConfig")]
+pub struct Route {
+    class: Option<TermClass>,
+    pattern: Option<Regex>,
+    /// Only these modules expand matching terms (as far as they are selected), all selected modules if empty
+    include: Vec<String>,
+    /// These modules do not expand matching terms
+    exclude: Vec<String>,
+}
+
+impl TryFrom<RouteConfig> for Route {
+    type Error = Error;
+
+    fn try_from(config: RouteConfig) -> Result<Self, Error> {
+        if config.class.is_none() && config.pattern.is_none() {
+            return Err(Error::InvalidConfig(
+                "A route needs a class and/or a pattern".to_owned(),
+            ));
+        }
+        let pattern = config
+            .pattern
+            .map(|pattern| {
+                Regex::new(&pattern).map_err(|e| {
+                    Error::InvalidConfig(format!("Invalid route pattern {}: {}", pattern, e))
+                })
+            })
+            .transpose()?;
+        Ok(Self {
+            class: config.class,
+            pattern,
+            include: config.include,
+            exclude: config.exclude,
+        })
+    }
+}
+
+impl Route {
+    /// Returns true if the rule applies to the term
+    pub fn matches(&self, term: &str) -> bool {
+        self.class.is_none_or(|class| class.matches(term))
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| pattern.is_match(term))
+    }
+
+    /// Returns whether the rule lets a module expand the matching terms
+    pub fn allows(&self, module_id: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|id| id == module_id))
+            && !self.exclude.iter().any(|id| id == module_id)
+    }
+
+    /// Returns the IDs of all modules the rule refers to
+    pub fn module_ids(&self) -> impl Iterator<Item = &str> {
+        self.include
+            .iter()
+            .chain(self.exclude.iter())
+            .map(|id| id.as_str())
+    }
+}
+
+/// The routing rules of a configuration, see [`Route`]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct Routes(Vec<Route>);
+
+impl Routes {
+    /// Returns the first rule that applies to the term, if any
+    pub fn route(&self, term: &str) -> Option<&Route> {
+        self.0.iter().find(|route| route.matches(term))
+    }
+
+    /// Returns whether a module expands a term, given whether the `include` and `exclude` parameters select it.
+    /// The rules only narrow down the selection, they never add modules.
+    pub fn selects(&self, module_id: &str, term: &str, selected: bool) -> bool {
+        selected && self.route(term).is_none_or(|route| route.allows(module_id))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.0.iter()
+    }
+
+    /// Prefixes the module IDs the rules refer to, along with the IDs of the modules (see `id_prefix`)
+    pub(crate) fn prefix_ids(&mut self, prefix: &str) {
+        for route in self.0.iter_mut() {
+            for id in route.include.iter_mut().chain(route.exclude.iter_mut()) {
+                *id = format!("{}{}", prefix, id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, QueryExpander, QueryParams, Term, TermStatus};
+
+    #[test]
+    pub fn test001_routing_classes() {
+        assert!(TermClass::Year.matches("1648"));
+        assert!(!TermClass::Year.matches("16480"));
+        assert!(TermClass::Number.matches("3,5"));
+        assert!(!TermClass::Number.matches("v3"));
+        assert!(TermClass::Acronym.matches("VOC"));
+        assert!(TermClass::Acronym.matches("N.V."));
+        assert!(!TermClass::Acronym.matches("A."));
+        assert!(!TermClass::Acronym.matches("Voc"));
+        assert!(TermClass::Url.matches("https://www.knaw.nl"));
+        assert!(TermClass::Name.matches("Jan Pietersz Coen"));
+        assert!(!TermClass::Name.matches("Jan pietersz"));
+        assert!(!TermClass::Name.matches("VOC"));
+    }
+
+    #[test]
+    pub fn test002_routing_expand() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [[lookup]]
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+
+            [[lookup]]
+            id = "names"
+            name = "Names"
+            file = "test/lookup.tsv"
+
+            [[route]]
+            class = "year"
+            exclude = ["lookup", "names"]
+
+            [[route]]
+            pattern = "^sep"
+            include = ["names"]
+            "#,
+        )
+        .expect("config must parse");
+        assert!(config.validate().is_empty());
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let terms = vec![
+            Term::Singular("seperate"),
+            Term::Singular("divide"),
+            Term::Singular("1648"),
+        ];
+        let params = QueryParams::new();
+        let terms_map = expander.expand_query(&terms, &params)?;
+        let sources = |term: &str| -> Vec<&str> {
+            terms_map[term]
+                .iter()
+                .filter_map(|termexpansion| termexpansion.source_id())
+                .collect()
+        };
+        assert_eq!(sources("seperate"), vec!["names"]);
+        assert_eq!(sources("divide"), vec!["lookup", "names"]);
+        let statuses = expander.term_statuses(&terms, &terms_map, &params);
+        assert_eq!(
+            statuses.get("1648"),
+            Some(&TermStatus::ExcludedByModuleFilter)
+        );
+        // the rules never add modules
+        let params = QueryParams::new().with("", "exclude", "names".into());
+        let terms_map = expander.expand_query(&terms, &params)?;
+        assert!(terms_map["seperate"].is_empty());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_routing_invalid() {
+        for route in [
+            "[[route]]\ninclude = [\"lookup\"]",
+            "[[route]]\npattern = \"(\"",
+            "[[route]]\nclass = \"emoji\"",
+        ] {
+            assert!(toml::from_str::<Config>(route).is_err(), "{}", route);
+        }
+        let config: Config =
+            toml::from_str("[[route]]\nclass = \"year\"\ninclude = [\"nonexistent\"]")
+                .expect("config must parse");
+        assert!(config
+            .validate()
+            .iter()
+            .any(|problem| problem.contains("nonexistent")));
+    }
+}
@@ -0,0 +1,399 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::api::ApiResponse;
+use crate::{Error, QueryExpander, QueryParams, TermExpansions};
+
+/// Configuration for server-side sessions of interactive (step-wise) query expansion
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Sessions that have not been used for this many seconds expire
+    ttl: u64,
+
+    /// Maximum number of sessions
+    capacity: usize,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            ttl: 3600,
+            capacity: 1000,
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn new(ttl: u64, capacity: usize) -> Self {
+        Self { ttl, capacity }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+/// The decision of a user on an expansion
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Accept,
+    Reject,
+}
+
+/// Maps terms to the decisions on their expansions
+pub type Selection = HashMap<String, HashMap<String, Decision>>;
+
+/// A query, its expansions and the decisions of the user on them
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    id: String,
+
+    /// The parameters of the expansion request, including the query itself (`q`)
+    params: HashMap<String, String>,
+
+    /// The expansions of the query
+    expansion: ApiResponse,
+
+    selection: Selection,
+}
+
+impl Session {
+    pub fn new(params: HashMap<String, String>, expansion: ApiResponse) -> Self {
+        Self {
+            id: new_id(),
+            params,
+            expansion,
+            selection: Selection::new(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    pub fn params(&self) -> &HashMap<String, String> {
+        &self.params
+    }
+
+    pub fn expansion(&self) -> &ApiResponse {
+        &self.expansion
+    }
+
+    pub fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// Records decisions, replacing any earlier decisions on the same expansions.
+    /// Returns an error (without recording anything) if a term or expansion is not part of the session.
+    pub fn select(&mut self, selection: Selection) -> Result<(), Error> {
+        let ApiResponse::QueryExpansion { terms, .. } = &self.expansion else {
+            return Err(Error::InvalidParameter(
+                "Session holds no query expansion".into(),
+            ));
+        };
+        for (term, decisions) in selection.iter() {
+            let termexpansions = terms
+                .get(term)
+                .ok_or_else(|| Error::InvalidParameter(format!("Unknown term: {}", term)))?;
+            for expansion in decisions.keys() {
+                if !termexpansions
+                    .iter()
+                    .any(|termexpansion| termexpansion.iter().any(|e| e == expansion))
+                {
+                    return Err(Error::InvalidParameter(format!(
+                        "Unknown expansion of {}: {}",
+                        term, expansion
+                    )));
+                }
+            }
+        }
+        for (term, decisions) in selection {
+            self.selection.entry(term).or_default().extend(decisions);
+        }
+        Ok(())
+    }
+
+    /// Returns the expansions that remain after applying the decisions: rejected expansions are dropped,
+    /// and if any expansion of a term was accepted, only the accepted expansions of that term are kept
+    pub fn selected_expansions(&self) -> TermExpansions {
+        let mut terms_map = match &self.expansion {
+            ApiResponse::QueryExpansion { terms, .. } => terms.clone(),
+            _ => TermExpansions::new(),
+        };
+        for (term, termexpansions) in terms_map.iter_mut() {
+            let Some(decisions) = self.selection.get(term) else {
+                continue;
+            };
+            let accepted_only = decisions.values().any(|d| *d == Decision::Accept);
+            for termexpansion in termexpansions.iter_mut() {
+                termexpansion.retain(|expansion| match decisions.get(expansion) {
+                    Some(Decision::Accept) => true,
+                    Some(Decision::Reject) => false,
+                    None => !accepted_only,
+                });
+            }
+            termexpansions.retain(|termexpansion| !termexpansion.is_empty());
+        }
+        terms_map
+    }
+
+    /// Returns the query expansion with only the selected expansions, including the resulting expanded query
+    pub fn query(&self, expander: &QueryExpander) -> Result<ApiResponse, Error> {
+        let params: QueryParams = (&self.params).into();
+        self.expansion
+            .clone()
+            .with_expansions(expander, self.selected_expansions(), &params)
+    }
+}
+
+/// Generates an unguessable session ID, which is all that is needed to access a session
+fn new_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = Sha256::new();
+    // std seeds each RandomState from a random source
+    hasher.update(
+        std::collections::hash_map::RandomState::new()
+            .hash_one(COUNTER.fetch_add(1, Ordering::Relaxed))
+            .to_le_bytes(),
+    );
+    hasher.update(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_le_bytes(),
+    );
+    hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Holds the sessions in memory, sessions expire when they have not been used for a while
+pub struct SessionStore {
+    config: SessionConfig,
+    sessions: Mutex<HashMap<String, (Instant, Session)>>,
+}
+
+impl SessionStore {
+    pub fn new(config: SessionConfig) -> Self {
+        Self {
+            config,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Adds a session, evicts expired (or otherwise the least recently used) sessions if the store is full (see
+    /// [`Self::purge()`])
+    pub fn insert(&self, session: Session) {
+        self.insert_at(session, Instant::now())
+    }
+
+    fn insert_at(&self, session: Session, now: Instant) {
+        if self.config.capacity == 0 {
+            return;
+        }
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if sessions.len() >= self.config.capacity {
+            self.purge(&mut sessions, now);
+        }
+        sessions.insert(session.id.clone(), (now, session));
+    }
+
+    /// Makes room for new sessions: evicts the expired sessions and, if that is not enough, the least recently used
+    /// ones. Frees half of the store at once, so a flood of new sessions only purges once every `capacity / 2`
+    /// sessions.
+    fn purge(&self, sessions: &mut HashMap<String, (Instant, Session)>, now: Instant) {
+        let ttl = self.config.ttl();
+        sessions.retain(|_, (used, _)| now.saturating_duration_since(*used) < ttl);
+        let keep = self.config.capacity / 2;
+        if keep == 0 {
+            sessions.clear();
+        } else if sessions.len() > keep {
+            let mut ids: Vec<(Instant, String)> = sessions
+                .iter()
+                .map(|(id, (used, _))| (*used, id.clone()))
+                .collect();
+            let evict = ids.len() - keep;
+            ids.select_nth_unstable_by_key(evict - 1, |(used, _)| *used);
+            for (_, id) in &ids[..evict] {
+                sessions.remove(id);
+            }
+        }
+    }
+
+    /// Calls the function on a session if it exists and has not expired
+    pub fn with_session<T>(&self, id: &str, f: impl FnOnce(&mut Session) -> T) -> Option<T> {
+        self.with_session_at(id, f, Instant::now())
+    }
+
+    fn with_session_at<T>(
+        &self,
+        id: &str,
+        f: impl FnOnce(&mut Session) -> T,
+        now: Instant,
+    ) -> Option<T> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        match sessions.get_mut(id) {
+            Some((used, session)) if now.duration_since(*used) < self.config.ttl() => {
+                *used = now;
+                Some(f(session))
+            }
+            Some(_) => {
+                sessions.remove(id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Returns a copy of a session if it exists and has not expired
+    pub fn get(&self, id: &str) -> Option<Session> {
+        self.with_session(id, |session| session.clone())
+    }
+
+    /// Removes a session, returns false if it does not exist
+    pub fn remove(&self, id: &str) -> bool {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(id)
+            .is_some()
+    }
+
+    /// Number of sessions currently held (including expired ones not yet evicted)
+    pub fn len(&self) -> usize {
+        self.sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{TermExpansion, TermStatus, TermStatuses};
+
+    fn session() -> Session {
+        let mut terms = TermExpansions::new();
+        terms.insert(
+            "wijn".into(),
+            vec![
+                TermExpansion::default().with_expansions(vec!["wyn".into(), "wijne".into()]),
+                TermExpansion::default().with_expansions(vec!["vinum".into()]),
+            ],
+        );
+        terms.insert(
+            "kaas".into(),
+            vec![TermExpansion::default().with_expansions(vec!["kees".into(), "caes".into()])],
+        );
+        let mut statuses = TermStatuses::new();
+        statuses.insert("wijn".into(), TermStatus::Expanded);
+        statuses.insert("kaas".into(), TermStatus::Expanded);
+        let response = ApiResponse::new_queryexpansion(terms, "wijn kaas", "{{wijn}} {{kaas}}", "")
+            .with_term_status(statuses);
+        Session::new(
+            [("q".to_string(), "wijn kaas".to_string())].into(),
+            response,
+        )
+    }
+
+    #[test]
+    pub fn test001_session_select() -> Result<(), Error> {
+        let mut session = session();
+        let selection: Selection =
+            serde_json::from_str(r#"{"wijn": {"wyn": "reject"}, "kaas": {"kees": "accept"}}"#)
+                .expect("valid selection");
+        session.select(selection)?;
+        let terms_map = session.selected_expansions();
+        assert_eq!(terms_map["wijn"].len(), 2);
+        assert_eq!(
+            terms_map["wijn"][0].expansions(),
+            &vec!["wijne".to_string()]
+        );
+        // only the accepted expansion remains
+        assert_eq!(terms_map["kaas"][0].expansions(), &vec!["kees".to_string()]);
+
+        // a later decision replaces an earlier one
+        let selection: Selection =
+            serde_json::from_str(r#"{"wijn": {"wyn": "accept"}}"#).expect("valid selection");
+        session.select(selection)?;
+        let terms_map = session.selected_expansions();
+        assert_eq!(terms_map["wijn"].len(), 1);
+        assert_eq!(terms_map["wijn"][0].expansions(), &vec!["wyn".to_string()]);
+
+        let selection: Selection =
+            serde_json::from_str(r#"{"wijn": {"bier": "accept"}}"#).expect("valid selection");
+        assert!(session.select(selection).is_err());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_session_store() {
+        let store = SessionStore::new(SessionConfig::new(10, 2));
+        let now = Instant::now();
+        let a = session();
+        let id = a.id().to_owned();
+        store.insert_at(a, now);
+        let b = session();
+        assert_ne!(b.id(), id);
+        store.insert_at(b, now + Duration::from_secs(1));
+        // using a session keeps it alive
+        assert!(store
+            .with_session_at(&id, |_| (), now + Duration::from_secs(8))
+            .is_some());
+        store.insert_at(session(), now + Duration::from_secs(9));
+        assert_eq!(store.len(), 2);
+        assert!(store
+            .with_session_at(&id, |_| (), now + Duration::from_secs(9))
+            .is_some());
+        assert!(store
+            .with_session_at(&id, |_| (), now + Duration::from_secs(20))
+            .is_none());
+    }
+
+    #[test]
+    pub fn test003_session_store_purge() {
+        let store = SessionStore::new(SessionConfig::new(10, 4));
+        let now = Instant::now();
+        let mut ids = Vec::new();
+        for i in 0..4 {
+            let session = session();
+            ids.push(session.id().to_owned());
+            store.insert_at(session, now + Duration::from_secs(i));
+        }
+        // the first session is used again, so the second and third are the least recently used
+        assert!(store
+            .with_session_at(&ids[0], |_| (), now + Duration::from_secs(4))
+            .is_some());
+        store.insert_at(session(), now + Duration::from_secs(5));
+        assert_eq!(store.len(), 3);
+        for (id, kept) in ids.iter().zip([true, false, false, true]) {
+            assert_eq!(
+                store
+                    .with_session_at(id, |_| (), now + Duration::from_secs(5))
+                    .is_some(),
+                kept
+            );
+        }
+    }
+}
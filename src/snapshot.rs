@@ -0,0 +1,470 @@
+use memmap2::Mmap;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::modules::{self, DataVersion, FileChecksum, FileStamp, Module, ModuleConfig};
+use crate::{Error, QueryExpander};
+
+/// Identifies a snapshot file and the version of its format
+const MAGIC: &[u8; 8] = b"KWPSNAP2";
+
+/// The loaded data of modules, serialised so a restart can restore it rather than parse all data files again.
+/// Only modules that implement [`Module::snapshot()`] (lookup and fst) are included, the others are loaded as usual.
+/// The snapshot of a module is only restored if neither the options that determine its data (see
+/// [`modules::data_key()`]) nor its data files changed since.
+///
+/// The snapshot starts with an index of the modules and the offsets of their data. A snapshot file is memory-mapped
+/// (see [`Self::from_file()`]), so only the data of the modules that are restored is read, and data that modules use
+/// as is (the FSTs) is not copied.
+#[derive(Default)]
+pub struct Snapshot {
+    modules: Vec<SnapshotEntry>,
+}
+
+/// The serialised data of a single module
+struct SnapshotEntry {
+    id: String,
+    kind: String,
+    data: SharedBytes,
+}
+
+/// Bytes shared by reference counting: a buffer in memory, or (part of) a memory-mapped snapshot file
+#[derive(Clone)]
+pub struct SharedBytes {
+    buffer: Arc<Buffer>,
+    range: Range<usize>,
+}
+
+enum Buffer {
+    Owned(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl SharedBytes {
+    /// Returns part of the bytes. Will panic if the range is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Self {
+        assert!(range.start <= range.end && range.end <= self.len());
+        Self {
+            buffer: self.buffer.clone(),
+            range: self.range.start + range.start..self.range.start + range.end,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self {
+            range: 0..bytes.len(),
+            buffer: Arc::new(Buffer::Owned(bytes)),
+        }
+    }
+}
+
+impl AsRef<[u8]> for SharedBytes {
+    fn as_ref(&self) -> &[u8] {
+        let buffer = match self.buffer.as_ref() {
+            Buffer::Owned(bytes) => bytes.as_slice(),
+            Buffer::Mapped(mmap) => mmap.as_ref(),
+        };
+        &buffer[self.range.clone()]
+    }
+}
+
+impl Snapshot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the data of all loaded modules of the query expander that support snapshots. The data of a module that is
+    /// already in the snapshot (e.g. one shared by tenants, also under another ID) is only added once.
+    pub fn add(&mut self, expander: &QueryExpander) -> Result<(), Error> {
+        for module in expander.modules().filter(|module| module.is_loaded()) {
+            let mut data = Vec::new();
+            if !module.snapshot(&mut data)? {
+                info!(
+                    "Module {} ({}) does not support snapshots, it will be loaded as usual",
+                    module.id(),
+                    module.kind()
+                );
+                continue;
+            }
+            if !self
+                .modules
+                .iter()
+                .any(|entry| entry.kind == module.kind() && entry.data.as_ref() == data)
+            {
+                self.modules.push(SnapshotEntry {
+                    id: module.id().to_owned(),
+                    kind: module.kind().to_owned(),
+                    data: data.into(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of modules in the snapshot
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Returns the IDs of the modules in the snapshot
+    pub fn module_ids(&self) -> impl Iterator<Item = &str> {
+        self.modules.iter().map(|entry| entry.id.as_str())
+    }
+
+    /// Restores the data of a (not yet loaded) module from the snapshot. Returns false if the snapshot holds no up to
+    /// date data for it, the module should then be loaded as usual. The data may have been stored for a module with
+    /// another ID that loads the same data (see [`modules::data_key()`]).
+    pub fn restore(&self, module: &mut dyn Module) -> Result<bool, Error> {
+        let kind = module.kind();
+        // the entry with the same ID is the most likely match
+        let mut entries: Vec<&SnapshotEntry> = self
+            .modules
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .collect();
+        entries.sort_by_key(|entry| entry.id != module.id());
+        for entry in entries.iter() {
+            if module.load_snapshot(&entry.data)? {
+                info!("Restored module {} from snapshot", module.id());
+                return Ok(true);
+            }
+        }
+        if entries.iter().any(|entry| entry.id == module.id()) {
+            warn!(
+                "The configuration or data of module {} changed since the snapshot was taken, it is loaded as usual",
+                module.id()
+            );
+        }
+        Ok(false)
+    }
+
+    /// Writes the snapshot
+    pub fn write(&self, writer: &mut dyn Write) -> Result<(), Error> {
+        writer.write_all(MAGIC)?;
+        write_u64(writer, self.modules.len() as u64)?;
+        let index_len: usize = self
+            .modules
+            .iter()
+            .map(|entry| 8 + entry.id.len() + 8 + entry.kind.len() + 16)
+            .sum();
+        let mut offset = (MAGIC.len() + 8 + index_len) as u64;
+        for entry in self.modules.iter() {
+            write_str(writer, &entry.id)?;
+            write_str(writer, &entry.kind)?;
+            write_u64(writer, offset)?;
+            write_u64(writer, entry.data.len() as u64)?;
+            offset += entry.data.len() as u64;
+        }
+        for entry in self.modules.iter() {
+            writer.write_all(entry.data.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot written by [`Self::write()`] from a buffer, only the index is parsed
+    pub fn from_bytes(bytes: impl Into<SharedBytes>) -> Result<Self, Error> {
+        let bytes = bytes.into();
+        let reader = &mut bytes.as_ref();
+        let mut magic = [0; 8];
+        reader
+            .read_exact(&mut magic)
+            .map_err(|_| Error::LoadError("Not a kweepeer snapshot".into()))?;
+        if &magic != MAGIC {
+            return Err(Error::LoadError(
+                "Not a kweepeer snapshot, or one written by an incompatible version".into(),
+            ));
+        }
+        let count = read_u64(reader)?;
+        let mut modules = Vec::new();
+        for _ in 0..count {
+            let id = read_string(reader)?;
+            let kind = read_string(reader)?;
+            let (offset, len) = (read_u64(reader)?, read_u64(reader)?);
+            let end = offset
+                .checked_add(len)
+                .filter(|end| *end <= bytes.len() as u64);
+            let Some(end) = end else {
+                return Err(truncated(std::io::ErrorKind::UnexpectedEof.into()));
+            };
+            modules.push(SnapshotEntry {
+                id,
+                kind,
+                data: bytes.slice(offset as usize..end as usize),
+            });
+        }
+        Ok(Self { modules })
+    }
+
+    /// Reads a snapshot from file. The file is memory-mapped rather than read: it must not be changed while the
+    /// snapshot is in use (a new snapshot written with [`Self::save()`] replaces it instead).
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        info!("Reading snapshot {}", path.display());
+        let error = |e: std::io::Error| {
+            Error::LoadError(format!("Unable to open snapshot {}: {}", path.display(), e))
+        };
+        let file = File::open(path).map_err(error)?;
+        // Safety: the snapshot is not changed in place, see above
+        let mmap = unsafe { Mmap::map(&file) }.map_err(error)?;
+        Self::from_bytes(SharedBytes {
+            range: 0..mmap.len(),
+            buffer: Arc::new(Buffer::Mapped(mmap)),
+        })
+    }
+
+    /// Writes the snapshot to file. It is written to a temporary file first that then replaces the file, so a
+    /// snapshot that is still mapped into memory is not changed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        self.write(&mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Writes the header of the snapshot of a module: the key of its data (see [`modules::data_key()`]) and the checksums,
+/// sizes and modification times of its data files, which [`read_header()`] checks to see whether the snapshot is
+/// still up to date
+pub(crate) fn write_header(
+    writer: &mut dyn Write,
+    config: &dyn ModuleConfig,
+    data_version: Option<&DataVersion>,
+) -> Result<(), Error> {
+    write_str(writer, &modules::data_key(config))?;
+    let files = data_version
+        .map(|version| version.files())
+        .unwrap_or_default();
+    write_u64(writer, files.len() as u64)?;
+    for file in files {
+        write_str(writer, file.sha256())?;
+        writer.write_all(&[file.verified() as u8])?;
+        // a file without a stamp never matches, the snapshot can then only be restored after loading as usual
+        let stamp = file.stamp().unwrap_or(FileStamp {
+            size: u64::MAX,
+            modified: 0,
+        });
+        write_u64(writer, stamp.size)?;
+        write_u64(writer, stamp.modified)?;
+    }
+    Ok(())
+}
+
+/// Reads the header written by [`write_header()`]. Returns the version of the data files if the snapshot was taken
+/// with the same data key and data files, `None` if it is out of date. The data files are considered unchanged if
+/// their size and modification time are, so their checksums are not computed again.
+pub(crate) fn read_header(
+    reader: &mut dyn Read,
+    config: &dyn ModuleConfig,
+) -> Result<Option<DataVersion>, Error> {
+    if read_string(reader)? != modules::data_key(config) {
+        return Ok(None);
+    }
+    let files = config.files();
+    if read_u64(reader)? != files.len() as u64 {
+        return Ok(None);
+    }
+    let mut checksums = Vec::with_capacity(files.len());
+    for file in files {
+        let sha256 = read_string(reader)?;
+        let mut verified = [0];
+        reader.read_exact(&mut verified).map_err(truncated)?;
+        let stamp = FileStamp {
+            size: read_u64(reader)?,
+            modified: read_u64(reader)?,
+        };
+        if FileStamp::of(file).ok() != Some(stamp) {
+            return Ok(None);
+        }
+        checksums.push(FileChecksum::new(
+            file.to_path_buf(),
+            sha256,
+            verified[0] != 0,
+            stamp,
+        ));
+    }
+    Ok(Some(DataVersion::new(checksums)))
+}
+
+pub(crate) fn write_u64(writer: &mut dyn Write, value: u64) -> Result<(), Error> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn write_bytes(writer: &mut dyn Write, bytes: &[u8]) -> Result<(), Error> {
+    write_u64(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+pub(crate) fn write_str(writer: &mut dyn Write, s: &str) -> Result<(), Error> {
+    write_bytes(writer, s.as_bytes())
+}
+
+pub(crate) fn read_u64(reader: &mut dyn Read) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes).map_err(truncated)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(crate) fn read_bytes(reader: &mut dyn Read) -> Result<Vec<u8>, Error> {
+    let len = read_u64(reader)?;
+    let mut bytes = Vec::new();
+    // the length is not trusted for the allocation, a corrupt one just makes the snapshot truncated
+    reader.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(truncated(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(bytes)
+}
+
+/// Reads bytes written by [`write_bytes()`] from the data of a module without copying them: returns them as part of
+/// the data, which the reader is a slice of
+#[cfg_attr(not(feature = "fst"), allow(dead_code))]
+pub(crate) fn read_shared(data: &SharedBytes, reader: &mut &[u8]) -> Result<SharedBytes, Error> {
+    let len = read_u64(reader)?;
+    if len > reader.len() as u64 {
+        return Err(truncated(std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    let start = data.len() - reader.len();
+    *reader = &reader[len as usize..];
+    Ok(data.slice(start..start + len as usize))
+}
+
+pub(crate) fn read_string(reader: &mut dyn Read) -> Result<String, Error> {
+    String::from_utf8(read_bytes(reader)?)
+        .map_err(|_| Error::LoadError("Snapshot is corrupt: invalid UTF-8".into()))
+}
+
+fn truncated(e: std::io::Error) -> Error {
+    Error::LoadError(format!("Snapshot is truncated: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Term;
+    use crate::modules::lookup::{LookupConfig, LookupModule};
+    use crate::testutil::TestDir;
+    use crate::{Config, QueryParams};
+
+    fn lookup_config(dir: &TestDir, casesensitive: bool) -> LookupConfig {
+        // not written again if it exists, that changes its modification time and makes a snapshot out of date
+        let mut file = dir.join("lookup.tsv");
+        if !file.exists() {
+            file = dir.write(
+                "lookup.tsv",
+                "separate\tseperate\tsepparate\nvoc\tcompagnie\n",
+            );
+        }
+        toml::from_str(&format!(
+            "id = \"lookup\"\nname = \"lookup\"\nfile = \"{}\"\nfuzzy = true\ncasesensitive = {}",
+            file.display(),
+            casesensitive
+        ))
+        .expect("config must parse")
+    }
+
+    #[test]
+    pub fn test001_snapshot_roundtrip() -> Result<(), Error> {
+        let dir = TestDir::new("snapshot-roundtrip");
+        let file = dir.write(
+            "lookup.tsv",
+            "separate\tseperate\tsepparate\nvoc\tcompagnie\n",
+        );
+        let config: Config = toml::from_str(&format!(
+            "[[lookup]]\nid = \"lookup\"\nname = \"lookup\"\nfile = \"{}\"\nfuzzy = true",
+            file.display()
+        ))
+        .expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let mut snapshot = Snapshot::new();
+        snapshot.add(&expander)?;
+        // adding the same modules again does not duplicate them
+        snapshot.add(&expander)?;
+        assert_eq!(snapshot.module_ids().collect::<Vec<_>>(), vec!["lookup"]);
+        snapshot.save(dir.join("kweepeer.snapshot"))?;
+
+        let snapshot = Snapshot::from_file(dir.join("kweepeer.snapshot"))?;
+        let mut module = LookupModule::new(lookup_config(&dir, false));
+        assert!(snapshot.restore(&mut module)?);
+        let terms = vec![Term::Singular("Séparate")];
+        let expansions = module.expand_query(&terms, &QueryParams::new())?;
+        assert_eq!(
+            expansions["Séparate"][0].iter().collect::<Vec<_>>(),
+            vec!["separate", "seperate", "sepparate"]
+        );
+        assert_eq!(module.entries(), Some(2));
+        assert_eq!(
+            module.data_version(),
+            expander.get_module("lookup").unwrap().data_version()
+        );
+        // the data is restored for a module with another ID that loads the same data
+        let config = toml::from_str(&format!(
+            "id = \"lookup2\"\nname = \"lookup\"\nfile = \"{}\"\nfuzzy = true\npriority = 2",
+            dir.join("lookup.tsv").display()
+        ))
+        .expect("config must parse");
+        assert!(snapshot.restore(&mut LookupModule::new(config))?);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_snapshot_out_of_date() -> Result<(), Error> {
+        let dir = TestDir::new("snapshot-out-of-date");
+        let mut module = LookupModule::new(lookup_config(&dir, false));
+        module.load()?;
+        let expander = QueryExpander::new().with_module(Box::new(module));
+        let mut snapshot = Snapshot::new();
+        snapshot.add(&expander)?;
+
+        // another configuration
+        let mut module = LookupModule::new(lookup_config(&dir, true));
+        assert!(!snapshot.restore(&mut module)?);
+        // other data
+        let mut module = LookupModule::new(lookup_config(&dir, false));
+        dir.write("lookup.tsv", "separate\tseperate\n");
+        assert!(!snapshot.restore(&mut module)?);
+        Ok(())
+    }
+
+    #[test]
+    pub fn test003_snapshot_corrupt() {
+        assert!(Snapshot::from_bytes(b"KWPSNAP0".to_vec()).is_err());
+        let mut data = Vec::new();
+        Snapshot {
+            modules: vec![SnapshotEntry {
+                id: "lookup".into(),
+                kind: "lookup".into(),
+                data: vec![0; 16].into(),
+            }],
+        }
+        .write(&mut data)
+        .expect("snapshot must be written");
+        data.truncate(data.len() - 1);
+        assert!(Snapshot::from_bytes(data).is_err());
+    }
+}
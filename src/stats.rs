@@ -0,0 +1,186 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Runtime statistics on module usage, collected during query expansion
+#[derive(Default)]
+pub struct ExpansionStats {
+    modules: Mutex<BTreeMap<String, ModuleStats>>,
+
+    /// Time it took to load the data of each module
+    load_times: Mutex<BTreeMap<String, Duration>>,
+}
+
+/// Statistics for a single module
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ModuleStats {
+    /// Number of times the module was called
+    calls: u64,
+
+    /// Number of calls that resulted in an error
+    errors: u64,
+
+    /// Total time spent in the module, in microseconds
+    total_time_us: u64,
+
+    /// Longest time spent in a single call, in microseconds
+    max_time_us: u64,
+}
+
+impl ModuleStats {
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Average time per call, in microseconds
+    pub fn avg_time_us(&self) -> u64 {
+        self.total_time_us.checked_div(self.calls).unwrap_or(0)
+    }
+
+    pub fn max_time_us(&self) -> u64 {
+        self.max_time_us
+    }
+}
+
+impl ExpansionStats {
+    /// Record a call to a module
+    pub fn record(&self, module_id: &str, duration: Duration, success: bool) {
+        let mut modules = self.modules.lock().unwrap();
+        let stats = if let Some(stats) = modules.get_mut(module_id) {
+            stats
+        } else {
+            modules.entry(module_id.to_owned()).or_default()
+        };
+        let duration = duration.as_micros() as u64;
+        stats.calls += 1;
+        if !success {
+            stats.errors += 1;
+        }
+        stats.total_time_us += duration;
+        stats.max_time_us = stats.max_time_us.max(duration);
+    }
+
+    /// Returns a snapshot of the statistics for all modules that have been called so far
+    pub fn modules(&self) -> BTreeMap<String, ModuleStats> {
+        self.modules.lock().unwrap().clone()
+    }
+
+    /// Record the time it took to load the data of a module
+    pub fn record_load(&self, module_id: &str, duration: Duration) {
+        self.load_times
+            .lock()
+            .unwrap()
+            .insert(module_id.to_owned(), duration);
+    }
+
+    /// Returns the time it took to load the data of a module, if it was recorded
+    pub fn load_time(&self, module_id: &str) -> Option<Duration> {
+        self.load_times.lock().unwrap().get(module_id).copied()
+    }
+}
+
+/// A collection of latency samples, used for benchmarking
+#[derive(Debug, Default, Clone)]
+pub struct Latencies {
+    samples: Vec<Duration>,
+    sorted: bool,
+}
+
+impl Latencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, duration: Duration) {
+        self.samples.push(duration);
+        self.sorted = false;
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Total of all samples
+    pub fn total(&self) -> Duration {
+        self.samples.iter().sum()
+    }
+
+    /// Returns the given percentile (0-100) using the nearest-rank method
+    pub fn percentile(&mut self, percentile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        if !self.sorted {
+            self.samples.sort();
+            self.sorted = true;
+        }
+        let rank = ((percentile / 100.0) * self.samples.len() as f64).ceil() as usize;
+        self.samples[rank.clamp(1, self.samples.len()) - 1]
+    }
+
+    /// Number of samples per second
+    pub fn throughput(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total > 0.0 {
+            self.samples.len() as f64 / total
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Returns the resident memory usage of the current process in bytes, if it can be determined (Linux only)
+pub fn memory_usage() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_stats_record() {
+        let stats = ExpansionStats::default();
+        stats.record("lookup", Duration::from_micros(100), true);
+        stats.record("lookup", Duration::from_micros(300), false);
+        let modules = stats.modules();
+        let lookup = modules.get("lookup").expect("module must exist");
+        assert_eq!(lookup.calls(), 2);
+        assert_eq!(lookup.errors(), 1);
+        assert_eq!(lookup.avg_time_us(), 200);
+        assert_eq!(lookup.max_time_us(), 300);
+        assert_eq!(stats.load_time("lookup"), None);
+        stats.record_load("lookup", Duration::from_millis(5));
+        assert_eq!(stats.load_time("lookup"), Some(Duration::from_millis(5)));
+    }
+
+    #[test]
+    pub fn test002_latencies_percentile() {
+        let mut latencies = Latencies::new();
+        for ms in (1..=100).rev() {
+            latencies.add(Duration::from_millis(ms));
+        }
+        assert_eq!(latencies.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(latencies.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(latencies.percentile(100.0), Duration::from_millis(100));
+        assert_eq!(latencies.percentile(0.0), Duration::from_millis(1));
+    }
+}
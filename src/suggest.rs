@@ -0,0 +1,152 @@
+use serde::Serialize;
+use std::collections::HashMap;
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::distance;
+use crate::lexer::Term;
+use crate::TermExpansions;
+
+/// The best spelling correction for a term
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct Suggestion {
+    /// The corrected term
+    suggestion: String,
+    /// The score of the correction as provided by the module or, if the module provides no scores, the similarity
+    /// of the correction to the term (between 0 and 1)
+    score: f64,
+    /// The ID of the module that provided the correction
+    source_id: Option<String>,
+}
+
+impl Suggestion {
+    pub fn suggestion(&self) -> &str {
+        self.suggestion.as_str()
+    }
+
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    pub fn source_id(&self) -> Option<&str> {
+        self.source_id.as_deref()
+    }
+}
+
+/// Maps terms to their best correction, terms that need no correction are absent
+pub type Suggestions = HashMap<String, Suggestion>;
+
+/// Picks the best correction for each term from the expansions of spelling-oriented modules: the variant with the
+/// highest score. Terms whose best variant is the term itself (ignoring case) are considered correctly spelled.
+pub fn best_corrections(terms_map: &TermExpansions) -> Suggestions {
+    let mut suggestions = Suggestions::new();
+    for (term, termexpansions) in terms_map.iter() {
+        let mut best: Option<Suggestion> = None;
+        for termexpansion in termexpansions.iter() {
+            let scored = termexpansion.scores().len() == termexpansion.len();
+            for (i, expansion) in termexpansion.iter().enumerate() {
+                let score = if scored {
+                    termexpansion.scores()[i]
+                } else {
+                    similarity(term, expansion)
+                };
+                // on equal scores, the alphabetically first variant wins, so identical requests get the same suggestion
+                if best.as_ref().is_none_or(|best| {
+                    score > best.score
+                        || (score == best.score && expansion < best.suggestion.as_str())
+                }) {
+                    best = Some(Suggestion {
+                        suggestion: expansion.to_owned(),
+                        score,
+                        source_id: termexpansion.source_id().map(|s| s.to_owned()),
+                    });
+                }
+            }
+        }
+        if let Some(best) = best {
+            if best.suggestion.to_lowercase() != term.to_lowercase() {
+                suggestions.insert(term.clone(), best);
+            }
+        }
+    }
+    suggestions
+}
+
+/// Fills in a query template (see [`Term::extract_from_query()`]) with the corrected terms, phrases remain quoted
+pub fn corrected_query(query_template: &str, terms: &[Term], suggestions: &Suggestions) -> String {
+    let mut query = String::with_capacity(query_template.len());
+    let mut rest = query_template;
+    for term in terms.iter() {
+        let placeholder = format!("{{{{{}}}}}", term.as_str());
+        let Some(begin) = rest.find(placeholder.as_str()) else {
+            break;
+        };
+        query += &rest[..begin];
+        let corrected = suggestions
+            .get(term.as_str())
+            .map(|suggestion| suggestion.suggestion())
+            .unwrap_or(term.as_str());
+        if matches!(term, Term::Phrase(_)) || corrected.contains(char::is_whitespace) {
+            query.push('"');
+            query += corrected;
+            query.push('"');
+        } else {
+            query += corrected;
+        }
+        rest = &rest[begin + placeholder.len()..];
+    }
+    query += rest;
+    query
+}
+
+/// Similarity of two strings based on their Levenshtein distance, 1.0 if they are equal
+fn similarity(a: &str, b: &str) -> f64 {
+    let maxlen = a.chars().count().max(b.chars().count());
+    if maxlen == 0 {
+        return 1.0;
+    }
+    1.0 - distance::levenshtein(a, b) as f64 / maxlen as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TermExpansion;
+
+    #[test]
+    pub fn test001_suggest_best() {
+        let mut terms_map = TermExpansions::new();
+        terms_map.insert(
+            "wijm".into(),
+            vec![
+                TermExpansion::default()
+                    .with_expansions(vec!["wijn".into(), "wim".into()])
+                    .with_scores(vec![0.9, 0.6]),
+                TermExpansion::default().with_expansions(vec!["wijnen".into()]),
+            ],
+        );
+        terms_map.insert(
+            "kaas".into(),
+            vec![TermExpansion::default().with_expansions(vec!["kaas".into(), "kas".into()])],
+        );
+        terms_map.insert("brood".into(), vec![]);
+        let suggestions = best_corrections(&terms_map);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions["wijm"].suggestion(), "wijn");
+        assert_eq!(suggestions["wijm"].score(), 0.9);
+
+        let (terms, template) = Term::extract_from_query("wijm AND \"oude kaas\" OR brood");
+        assert_eq!(
+            corrected_query(&template, &terms, &suggestions),
+            "wijn AND \"oude kaas\" OR brood"
+        );
+    }
+
+    #[test]
+    pub fn test002_suggest_similarity() {
+        assert_eq!(similarity("kaas", "kaas"), 1.0);
+        assert_eq!(similarity("kaas", "kas"), 0.75);
+        assert_eq!(similarity("", "ab"), 0.0);
+    }
+}
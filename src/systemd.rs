@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use crate::Error;
+
+/// Sends a state notification (e.g. `READY=1`) to the service manager, like `sd_notify(3)`.
+/// Returns false if no service manager expects notifications (`$NOTIFY_SOCKET` is not set).
+pub fn notify(state: &str) -> Result<bool, Error> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) if !path.is_empty() => {
+            notify_socket(Path::new(&path), state)?;
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+#[cfg(unix)]
+fn notify_socket(path: &Path, state: &str) -> Result<(), Error> {
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::UnixDatagram;
+
+    let socket = UnixDatagram::unbound()?;
+    if let Some(name) = path.as_os_str().as_bytes().strip_prefix(b"@") {
+        // socket in the abstract namespace
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &addr)?;
+            return Ok(());
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            let _ = name;
+            return Err(Error::LoadError(
+                "Abstract notification sockets are not supported on this platform".into(),
+            ));
+        }
+    }
+    socket.send_to(state.as_bytes(), path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn notify_socket(_path: &Path, _state: &str) -> Result<(), Error> {
+    Err(Error::LoadError(
+        "Service manager notifications are not supported on this platform".into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::testutil::TestDir;
+
+    #[test]
+    #[cfg(unix)]
+    pub fn test001_notify_socket() -> Result<(), Error> {
+        let dir = TestDir::new("notify");
+        let path = dir.join("notify");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&path)?;
+        notify_socket(&path, "READY=1")?;
+        let mut buffer = [0; 64];
+        let len = receiver.recv(&mut buffer)?;
+        assert_eq!(&buffer[..len], b"READY=1");
+        Ok(())
+    }
+}
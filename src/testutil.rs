@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+/// A temporary directory for a single test, removed again when dropped, so also when the test fails
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates an empty directory, named after the test and the process so tests that run at the same time don't
+    /// get in each other's way
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("kweepeer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).expect("temporary directory must be created");
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        self.0.as_path()
+    }
+
+    /// Returns the path of a file in the directory
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+
+    /// Writes a file in the directory, creating parent directories as needed, and returns its path
+    pub fn write(&self, path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("directory must be created");
+        }
+        std::fs::write(&path, contents).expect("file must be written");
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,113 @@
+use serde::Deserialize;
+#[cfg(feature = "parallel")]
+use tracing::{info, warn};
+
+#[cfg(feature = "parallel")]
+use crate::Error;
+
+/// Configuration for a dedicated thread pool for CPU-intensive query expansion, isolating it from the threads that
+/// serve requests
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ThreadPoolConfig {
+    /// Number of threads, defaults to the number of CPUs in `cpus` or, if not set, to the number of available CPUs
+    #[serde(default)]
+    threads: Option<usize>,
+
+    /// IDs of the CPUs to pin the threads to (round-robin), e.g. all CPUs of a single NUMA node. Threads are not
+    /// pinned if not set.
+    #[serde(default)]
+    cpus: Vec<usize>,
+}
+
+impl ThreadPoolConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    pub fn with_cpus(mut self, cpus: Vec<usize>) -> Self {
+        self.cpus = cpus;
+        self
+    }
+
+    pub fn threads(&self) -> Option<usize> {
+        self.threads
+    }
+
+    pub fn cpus(&self) -> &[usize] {
+        &self.cpus
+    }
+
+    /// Builds the thread pool. Parallel work started from within the pool (such as the searches of modules that use
+    /// multiple threads) stays in the pool.
+    #[cfg(feature = "parallel")]
+    pub fn build(&self) -> Result<rayon::ThreadPool, Error> {
+        if self.threads == Some(0) {
+            return Err(Error::InvalidConfig(
+                "The thread pool needs at least one thread".into(),
+            ));
+        }
+        let cores = if self.cpus.is_empty() {
+            Vec::new()
+        } else {
+            let available = core_affinity::get_core_ids().unwrap_or_default();
+            self.cpus
+                .iter()
+                .map(|cpu| {
+                    available
+                        .iter()
+                        .find(|core| core.id == *cpu)
+                        .copied()
+                        .ok_or_else(|| {
+                            Error::InvalidConfig(format!(
+                                "CPU {} is not available for the thread pool",
+                                cpu
+                            ))
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let threads = self.threads.unwrap_or(cores.len());
+        let mut builder = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("kweepeer-cpu-{}", i));
+        if !cores.is_empty() {
+            builder = builder.start_handler(move |i| {
+                if !core_affinity::set_for_current(cores[i % cores.len()]) {
+                    warn!("Unable to pin thread {} of the thread pool to a CPU", i);
+                }
+            });
+        }
+        let pool = builder.build().map_err(|e| {
+            Error::InvalidConfig(format!("Unable to create the thread pool: {}", e))
+        })?;
+        info!(
+            "Created a thread pool of {} threads for CPU-intensive modules",
+            pool.current_num_threads()
+        );
+        Ok(pool)
+    }
+}
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test001_threadpool_build() -> Result<(), Error> {
+        let pool = ThreadPoolConfig::new().with_threads(2).build()?;
+        assert_eq!(pool.current_num_threads(), 2);
+        let name = pool.install(|| std::thread::current().name().map(|name| name.to_owned()));
+        assert!(name.is_some_and(|name| name.starts_with("kweepeer-cpu-")));
+        assert!(ThreadPoolConfig::new().with_threads(0).build().is_err());
+        assert!(ThreadPoolConfig::new()
+            .with_cpus(vec![usize::MAX])
+            .build()
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "server")]
+use utoipa::ToSchema;
+
+use crate::{TermStatus, TermStatuses};
+
+/// Why a module was skipped during query expansion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// The time for expanding the query (`limits.timeout`) ran out before the module was consulted
+    Timeout,
+    /// The data of the module could not be loaded (e.g. a lazy module with a missing file)
+    LoadFailed,
+}
+
+/// Reports a module that was skipped during query expansion, so the response holds fewer expansions than it
+/// otherwise would. Returned in the `warnings` of a query expansion response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "server", derive(ToSchema))]
+pub struct ModuleWarning {
+    /// The ID of the skipped module
+    module: String,
+    reason: SkipReason,
+    /// Human-readable details
+    message: String,
+}
+
+impl ModuleWarning {
+    pub fn new(module: impl Into<String>, reason: SkipReason, message: impl Into<String>) -> Self {
+        Self {
+            module: module.into(),
+            reason,
+            message: message.into(),
+        }
+    }
+
+    pub fn module(&self) -> &str {
+        self.module.as_str()
+    }
+
+    pub fn reason(&self) -> SkipReason {
+        self.reason
+    }
+
+    pub fn message(&self) -> &str {
+        self.message.as_str()
+    }
+}
+
+/// The modules that were skipped during query expansion
+pub type Warnings = Vec<ModuleWarning>;
+
+/// Terms without matches get status [`TermStatus::Timeout`] if any module was skipped because time ran out, as the
+/// skipped modules might have expanded them
+pub fn apply_to_statuses(warnings: &[ModuleWarning], statuses: &mut TermStatuses) {
+    if warnings
+        .iter()
+        .any(|warning| warning.reason == SkipReason::Timeout)
+    {
+        for status in statuses.values_mut() {
+            if *status == TermStatus::NoMatches {
+                *status = TermStatus::Timeout;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::api::ApiResponse;
+    use crate::{Config, Error, QueryExpander, QueryParams};
+
+    #[test]
+    pub fn test001_warnings_degrade() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [[module]]
+            type = "lookup"
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+
+            [[module]]
+            type = "lookup"
+            id = "broken"
+            name = "Broken"
+            file = "test/nonexistent.tsv"
+            lazy = true
+            "#,
+        )
+        .expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let response = serde_json::to_value(ApiResponse::expand(
+            &expander,
+            "separate",
+            &QueryParams::new(),
+        )?)
+        .expect("serializable");
+        assert_eq!(response["term_status"]["separate"], "expanded");
+        assert_eq!(response["warnings"][0]["module"], "broken");
+        assert_eq!(response["warnings"][0]["reason"], "load_failed");
+        assert_eq!(response["warnings"].as_array().map(|w| w.len()), Some(1));
+        let response = serde_json::to_value(ApiResponse::expand(
+            &expander,
+            "separate",
+            &QueryParams::new().with("", "include", "lookup".into()),
+        )?)
+        .expect("serializable");
+        assert!(response.get("warnings").is_none());
+        Ok(())
+    }
+
+    #[test]
+    pub fn test002_warnings_timeout() -> Result<(), Error> {
+        let config: Config = toml::from_str(
+            r#"
+            [limits]
+            timeout = 0
+
+            [[module]]
+            type = "lookup"
+            id = "lookup"
+            name = "Lookup"
+            file = "test/lookup.tsv"
+            "#,
+        )
+        .expect("config must parse");
+        let mut expander = QueryExpander::new().with_config(config);
+        expander.load()?;
+        let response = serde_json::to_value(ApiResponse::expand(
+            &expander,
+            "separate",
+            &QueryParams::new(),
+        )?)
+        .expect("serializable");
+        assert_eq!(response["term_status"]["separate"], "timeout");
+        assert_eq!(response["warnings"][0]["reason"], "timeout");
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::lexer::Term;
+use crate::Error;
+
+/// Configuration for weighting the terms of a query by their informativeness (inverse document frequency)
+#[derive(Debug, Deserialize, Clone)]
+pub struct WeightsConfig {
+    /// Tab-separated file with a term and its document frequency on each line
+    file: PathBuf,
+
+    /// Total number of documents in the corpus, defaults to the highest document frequency in the file
+    #[serde(default)]
+    documents: Option<u64>,
+
+    /// Compare terms case-sensitively
+    #[serde(default)]
+    casesensitive: bool,
+}
+
+impl WeightsConfig {
+    pub fn new(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: file.into(),
+            documents: None,
+            casesensitive: false,
+        }
+    }
+
+    pub fn with_documents(mut self, documents: u64) -> Self {
+        self.documents = Some(documents);
+        self
+    }
+
+    pub fn file(&self) -> &Path {
+        self.file.as_path()
+    }
+
+    pub(crate) fn file_mut(&mut self) -> &mut PathBuf {
+        &mut self.file
+    }
+}
+
+/// Document frequencies of a corpus, to compute the inverse document frequency (IDF) of terms
+#[derive(Debug, Default)]
+pub struct TermWeights {
+    frequencies: HashMap<String, u64>,
+    documents: u64,
+    casesensitive: bool,
+}
+
+impl TermWeights {
+    /// Loads the document frequencies from the configured file
+    pub fn load(config: &WeightsConfig) -> Result<Self, Error> {
+        let file = File::open(&config.file).map_err(|e| {
+            Error::LoadError(format!(
+                "Unable to read frequency file {}: {}",
+                config.file.display(),
+                e
+            ))
+        })?;
+        let mut frequencies = HashMap::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (term, frequency) = line
+                .split_once('\t')
+                .and_then(|(term, frequency)| {
+                    frequency
+                        .split('\t')
+                        .next()
+                        .and_then(|frequency| frequency.trim().parse::<u64>().ok())
+                        .map(|frequency| (term, frequency))
+                })
+                .ok_or_else(|| {
+                    Error::LoadError(format!(
+                        "Invalid line {} in frequency file {}, expected a term and a frequency",
+                        i + 1,
+                        config.file.display()
+                    ))
+                })?;
+            let term = if config.casesensitive {
+                term.to_owned()
+            } else {
+                term.to_lowercase()
+            };
+            *frequencies.entry(term).or_insert(0) += frequency;
+        }
+        let documents = config
+            .documents
+            .unwrap_or_else(|| frequencies.values().copied().max().unwrap_or_default());
+        info!("Loaded document frequencies of {} terms", frequencies.len());
+        Ok(Self {
+            frequencies,
+            documents,
+            casesensitive: config.casesensitive,
+        })
+    }
+
+    /// Returns the inverse document frequency of a term, `ln((N + 1) / (df + 1))`, where N is the number of documents
+    /// and df the document frequency of the term. Common terms get a weight near zero, unknown terms the highest weight.
+    pub fn idf(&self, term: &str) -> f64 {
+        let frequency = if self.casesensitive {
+            self.frequencies.get(term)
+        } else {
+            self.frequencies.get(&term.to_lowercase())
+        }
+        .copied()
+        .unwrap_or_default()
+        .min(self.documents);
+        ((self.documents as f64 + 1.0) / (frequency as f64 + 1.0)).ln()
+    }
+
+    /// Returns the weight of each of the terms
+    pub fn weigh(&self, terms: &[Term]) -> HashMap<String, f64> {
+        terms
+            .iter()
+            .map(|term| (term.as_str().to_owned(), self.idf(term.as_str())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TestDir;
+
+    #[test]
+    pub fn test001_weights_idf() -> Result<(), Error> {
+        let dir = TestDir::new("frequencies");
+        let file = dir.write("frequencies.tsv", "de\t999\nkaas\t9\nWijn\t99\n");
+        let weights = TermWeights::load(&WeightsConfig::new(&file).with_documents(999))?;
+        assert_eq!(weights.idf("de"), 0.0);
+        assert_eq!(weights.idf("kaas"), 100f64.ln());
+        assert_eq!(weights.idf("wijn"), 10f64.ln());
+        assert_eq!(weights.idf("bier"), 1000f64.ln());
+        let (terms, _) = Term::extract_from_query("de kaas");
+        assert_eq!(weights.weigh(&terms).len(), 2);
+        Ok(())
+    }
+}